@@ -1,4 +1,87 @@
 use crate::*;
 
 pub type ColorRGBA32 = Vector4<f32>;
-pub type ColorRGB32 = Vector3<f32>;
\ No newline at end of file
+pub type ColorRGB32 = Vector3<f32>;
+
+/// Converts a single sRGB encoded channel in `0..=1` into linear space.
+pub fn srgb_channel_to_linear(value: f32) -> f32 {
+  if value <= 0.04045 {
+    value / 12.92
+  } else {
+    ((value + 0.055) / 1.055).powf(2.4)
+  }
+}
+
+/// Converts a single linear channel in `0..=1` into sRGB encoding.
+pub fn linear_channel_to_srgb(value: f32) -> f32 {
+  if value <= 0.003_130_8 {
+    value * 12.92
+  } else {
+    1.055 * value.powf(1.0 / 2.4) - 0.055
+  }
+}
+
+/// Colors whose rgb channels can be converted between color spaces, alpha is always kept linear.
+pub trait ColorChannels: Sized {
+  fn map_rgb(self, f: impl Fn(f32) -> f32) -> Self;
+}
+
+impl ColorChannels for Vector3<f32> {
+  fn map_rgb(self, f: impl Fn(f32) -> f32) -> Self {
+    self.map(f)
+  }
+}
+
+impl ColorChannels for Vector4<f32> {
+  fn map_rgb(self, f: impl Fn(f32) -> f32) -> Self {
+    Vector4::new(f(self.x), f(self.y), f(self.z), self.w)
+  }
+}
+
+/// Converts an sRGB color, e.g. picked in an image editor, into linear space.
+/// Shaders, clear colors and blending work in linear space, sRGB targets encode on write.
+pub fn srgb_to_linear<C: ColorChannels>(color: C) -> C {
+  color.map_rgb(srgb_channel_to_linear)
+}
+
+/// Converts a linear color into sRGB encoding, e.g. to write it into a non sRGB target.
+pub fn linear_to_srgb<C: ColorChannels>(color: C) -> C {
+  color.map_rgb(linear_channel_to_srgb)
+}
+
+/// Converts hue, saturation and value, all within `0..=1`, into rgb.
+pub fn hsv_to_rgb(hsv: Vector3<f32>) -> ColorRGB32 {
+  let hue = (hsv.x - hsv.x.floor()) * 6.0;
+  let chroma = hsv.z * hsv.y;
+  let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+  let (r, g, b) = match hue as u32 {
+    0 => (chroma, x, 0.0),
+    1 => (x, chroma, 0.0),
+    2 => (0.0, chroma, x),
+    3 => (0.0, x, chroma),
+    4 => (x, 0.0, chroma),
+    _ => (chroma, 0.0, x),
+  };
+  let m = hsv.z - chroma;
+  Vector3::new(r + m, g + m, b + m)
+}
+
+/// Converts rgb into hue, saturation and value, all within `0..=1`.
+pub fn rgb_to_hsv(rgb: ColorRGB32) -> Vector3<f32> {
+  let max = rgb.x.max(rgb.y).max(rgb.z);
+  let min = rgb.x.min(rgb.y).min(rgb.z);
+  let delta = max - min;
+
+  let hue = if delta <= 0.0 {
+    0.0
+  } else if max == rgb.x {
+    ((rgb.y - rgb.z) / delta).rem_euclid(6.0)
+  } else if max == rgb.y {
+    (rgb.z - rgb.x) / delta + 2.0
+  } else {
+    (rgb.x - rgb.y) / delta + 4.0
+  };
+  let saturation = if max <= 0.0 { 0.0 } else { delta / max };
+
+  Vector3::new(hue / 6.0, saturation, max)
+}
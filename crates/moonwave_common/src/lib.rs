@@ -18,6 +18,9 @@ pub use cgmath::{
 mod color;
 pub use color::*;
 
+mod packed;
+pub use packed::*;
+
 pub use bytemuck;
 
 pub mod atomics;
@@ -52,3 +55,28 @@ pub fn correct_angle(rad: Rad<f32>) -> Rad<f32> {
 pub fn inv_lerp(a: f32, b: f32, v: f32) -> f32 {
   (v - a) / (b - a)
 }
+
+/// Rotation that turns the local -Z axis towards `forward` while keeping local +Y as close to `up`
+/// as possible. Follows the same right handed convention as `look_at`.
+pub fn look_rotation(forward: Vector3<f32>, up: Vector3<f32>) -> Quaternion<f32> {
+  let forward = forward.normalize();
+  let mut right = forward.cross(up);
+  if right.magnitude2() < 1e-8 {
+    // Up is parallel to forward, any perpendicular axis does the job.
+    let fallback = if forward.x.abs() < 0.9 {
+      Vector3::unit_x()
+    } else {
+      Vector3::unit_z()
+    };
+    right = fallback.cross(forward);
+  }
+  let right = right.normalize();
+  let up = right.cross(forward);
+
+  Quaternion::from(Matrix3::from_cols(right, up, -forward))
+}
+
+/// Right handed view matrix of an eye looking at `target`.
+pub fn look_at(eye: Vector3<f32>, target: Vector3<f32>, up: Vector3<f32>) -> Matrix4<f32> {
+  Matrix4::look_at_rh(Point3::from_vec(eye), Point3::from_vec(target), up)
+}
@@ -0,0 +1,114 @@
+use bytemuck::{Pod, Zeroable};
+
+use crate::{Vector2, Vector4};
+
+/// Four bytes read as normalized `vec4` within shaders, e.g. vertex colors at a quarter of the size.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Unorm8x4(pub [u8; 4]);
+
+impl Unorm8x4 {
+  /// Packs components in `0..=1`, values outside are clamped.
+  pub fn from_vector(value: Vector4<f32>) -> Self {
+    let pack = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Self([pack(value.x), pack(value.y), pack(value.z), pack(value.w)])
+  }
+
+  pub fn to_vector(&self) -> Vector4<f32> {
+    let [x, y, z, w] = self.0;
+    Vector4::new(x as f32, y as f32, z as f32, w as f32) / 255.0
+  }
+}
+
+/// Two half precision floats read as `vec2` within shaders, e.g. uvs at half of the size.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Half2(pub [u16; 2]);
+
+impl Half2 {
+  pub fn from_vector(value: Vector2<f32>) -> Self {
+    Self([f32_to_f16_bits(value.x), f32_to_f16_bits(value.y)])
+  }
+
+  pub fn to_vector(&self) -> Vector2<f32> {
+    Vector2::new(f16_bits_to_f32(self.0[0]), f16_bits_to_f32(self.0[1]))
+  }
+}
+
+/// Four half precision floats read as `vec4` within shaders.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Half4(pub [u16; 4]);
+
+impl Half4 {
+  pub fn from_vector(value: Vector4<f32>) -> Self {
+    Self([
+      f32_to_f16_bits(value.x),
+      f32_to_f16_bits(value.y),
+      f32_to_f16_bits(value.z),
+      f32_to_f16_bits(value.w),
+    ])
+  }
+
+  pub fn to_vector(&self) -> Vector4<f32> {
+    let [x, y, z, w] = self.0;
+    Vector4::new(
+      f16_bits_to_f32(x),
+      f16_bits_to_f32(y),
+      f16_bits_to_f32(z),
+      f16_bits_to_f32(w),
+    )
+  }
+}
+
+unsafe impl Pod for Unorm8x4 {}
+unsafe impl Zeroable for Unorm8x4 {}
+unsafe impl Pod for Half2 {}
+unsafe impl Zeroable for Half2 {}
+unsafe impl Pod for Half4 {}
+unsafe impl Zeroable for Half4 {}
+
+/// Converts to IEEE 754 half precision bits rounding to the nearest value.
+/// Values out of range become infinity, tiny values end up as subnormals or zero.
+pub fn f32_to_f16_bits(value: f32) -> u16 {
+  let bits = value.to_bits();
+  let sign = ((bits >> 16) & 0x8000) as u16;
+  let exponent = ((bits >> 23) & 0xff) as i32;
+  let mantissa = bits & 0x7f_ffff;
+
+  // Infinity and NaN, NaN keeps a mantissa bit set.
+  if exponent == 0xff {
+    return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+  }
+
+  let exponent = exponent - 127 + 15;
+  if exponent >= 0x1f {
+    return sign | 0x7c00;
+  }
+  if exponent <= 0 {
+    if exponent < -10 {
+      return sign;
+    }
+    let mantissa = mantissa | 0x80_0000;
+    let shift = (14 - exponent) as u32;
+    let round = (mantissa >> (shift - 1)) & 1;
+    return sign | ((mantissa >> shift) + round) as u16;
+  }
+
+  // A carry of the rounding correctly moves into the exponent.
+  let half = ((exponent as u32) << 10) | (mantissa >> 13);
+  let round = (mantissa >> 12) & 1;
+  sign | (half + round) as u16
+}
+
+pub fn f16_bits_to_f32(bits: u16) -> f32 {
+  let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+  let exponent = ((bits >> 10) & 0x1f) as i32;
+  let mantissa = (bits & 0x3ff) as f32;
+  match exponent {
+    0 => sign * mantissa * 2f32.powi(-24),
+    0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+    0x1f => f32::NAN,
+    _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+  }
+}
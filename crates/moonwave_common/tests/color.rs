@@ -0,0 +1,65 @@
+use moonwave_common::*;
+
+fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+  assert!((a - b).magnitude() < 1e-4, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn srgb_round_trip_test() {
+  for i in 0..=100 {
+    let value = i as f32 / 100.0;
+    let round_trip = linear_channel_to_srgb(srgb_channel_to_linear(value));
+    assert!((round_trip - value).abs() < 1e-4);
+  }
+}
+
+#[test]
+fn srgb_known_values_test() {
+  assert_eq!(srgb_channel_to_linear(0.0), 0.0);
+  assert!((srgb_channel_to_linear(1.0) - 1.0).abs() < 1e-6);
+  assert!((srgb_channel_to_linear(0.5) - 0.214_04).abs() < 1e-4);
+  assert!((linear_channel_to_srgb(0.5) - 0.735_36).abs() < 1e-4);
+}
+
+#[test]
+fn srgb_keeps_alpha_test() {
+  let color = srgb_to_linear(Vector4::new(0.5, 0.5, 0.5, 0.5));
+  assert!((color.x - 0.214_04).abs() < 1e-4);
+  assert_eq!(color.w, 0.5);
+
+  let color = linear_to_srgb(Vector3::new(0.214_04, 0.0, 1.0));
+  assert_close(color, Vector3::new(0.5, 0.0, 1.0));
+}
+
+#[test]
+fn hsv_primaries_test() {
+  assert_close(
+    hsv_to_rgb(Vector3::new(0.0, 1.0, 1.0)),
+    Vector3::new(1.0, 0.0, 0.0),
+  );
+  assert_close(
+    hsv_to_rgb(Vector3::new(1.0 / 3.0, 1.0, 1.0)),
+    Vector3::new(0.0, 1.0, 0.0),
+  );
+  assert_close(
+    hsv_to_rgb(Vector3::new(2.0 / 3.0, 1.0, 1.0)),
+    Vector3::new(0.0, 0.0, 1.0),
+  );
+  assert_close(
+    hsv_to_rgb(Vector3::new(0.5, 0.0, 0.25)),
+    Vector3::new(0.25, 0.25, 0.25),
+  );
+}
+
+#[test]
+fn hsv_round_trip_test() {
+  let colors = [
+    Vector3::new(0.2, 0.4, 0.6),
+    Vector3::new(0.9, 0.1, 0.3),
+    Vector3::new(0.5, 0.5, 0.1),
+    Vector3::new(0.0, 0.0, 0.0),
+  ];
+  for color in colors.iter() {
+    assert_close(hsv_to_rgb(rgb_to_hsv(*color)), *color);
+  }
+}
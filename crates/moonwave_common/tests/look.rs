@@ -0,0 +1,58 @@
+use moonwave_common::*;
+
+fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+  assert!((a - b).magnitude() < 1e-4, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn look_rotation_turns_forward_test() {
+  let forward = Vector3::new(1.0, 2.0, -3.0).normalize();
+  let rotation = look_rotation(forward, Vector3::unit_y());
+
+  assert_close(rotation.rotate_vector(-Vector3::unit_z()), forward);
+  // Up stays within the plane spanned by forward and the world up.
+  let up = rotation.rotate_vector(Vector3::unit_y());
+  assert!(up.y > 0.0);
+  assert!(up.dot(forward).abs() < 1e-4);
+}
+
+#[test]
+fn look_rotation_identity_test() {
+  let rotation = look_rotation(-Vector3::unit_z(), Vector3::unit_y());
+  assert_close(rotation.rotate_vector(Vector3::unit_x()), Vector3::unit_x());
+  assert_close(rotation.rotate_vector(Vector3::unit_y()), Vector3::unit_y());
+}
+
+#[test]
+fn look_rotation_parallel_up_test() {
+  let rotation = look_rotation(Vector3::unit_y(), Vector3::unit_y());
+  assert_close(
+    rotation.rotate_vector(-Vector3::unit_z()),
+    Vector3::unit_y(),
+  );
+}
+
+#[test]
+fn look_at_matches_look_rotation_test() {
+  let eye = Vector3::new(4.0, 3.0, 2.0);
+  let target = Vector3::new(-1.0, 0.0, 5.0);
+  let view = look_at(eye, target, Vector3::unit_y());
+
+  assert_close(
+    view.transform_point(Point3::from_vec(eye)).to_vec(),
+    Vector3::zero(),
+  );
+  let distance = (target - eye).magnitude();
+  assert_close(
+    view.transform_point(Point3::from_vec(target)).to_vec(),
+    Vector3::new(0.0, 0.0, -distance),
+  );
+
+  // The view rotates the world back into the orientation of the eye.
+  let rotation = look_rotation(target - eye, Vector3::unit_y());
+  let direction = Vector3::new(0.3, -0.2, 0.9);
+  assert_close(
+    view.transform_vector(rotation.rotate_vector(direction)),
+    direction,
+  );
+}
@@ -0,0 +1,39 @@
+use moonwave_common::{f16_bits_to_f32, f32_to_f16_bits, Half2, Half4, Unorm8x4, Vector2, Vector4};
+
+#[test]
+fn half_conversion_test() {
+  assert_eq!(f32_to_f16_bits(0.0), 0x0000);
+  assert_eq!(f32_to_f16_bits(-0.0), 0x8000);
+  assert_eq!(f32_to_f16_bits(1.0), 0x3c00);
+  assert_eq!(f32_to_f16_bits(-2.0), 0xc000);
+  assert_eq!(f32_to_f16_bits(0.5), 0x3800);
+  assert_eq!(f32_to_f16_bits(65504.0), 0x7bff);
+
+  // Out of range values become infinity or zero.
+  assert_eq!(f32_to_f16_bits(1.0e6), 0x7c00);
+  assert_eq!(f32_to_f16_bits(f32::NEG_INFINITY), 0xfc00);
+  assert_eq!(f32_to_f16_bits(1.0e-10), 0x0000);
+  assert!(f16_bits_to_f32(f32_to_f16_bits(f32::NAN)).is_nan());
+
+  // Smallest subnormal.
+  assert_eq!(f32_to_f16_bits(2f32.powi(-24)), 0x0001);
+  assert_eq!(f16_bits_to_f32(0x0001), 2f32.powi(-24));
+
+  for value in [0.25f32, 1.5, -3.75, 0.1, 1000.0].iter() {
+    let roundtrip = f16_bits_to_f32(f32_to_f16_bits(*value));
+    assert!((roundtrip - value).abs() <= value.abs() / 1024.0);
+  }
+}
+
+#[test]
+fn packed_vector_test() {
+  let uv = Half2::from_vector(Vector2::new(0.5, 0.25));
+  assert_eq!(uv.to_vector(), Vector2::new(0.5, 0.25));
+  let value = Half4::from_vector(Vector4::new(1.0, -1.0, 2.0, 0.0));
+  assert_eq!(value.to_vector(), Vector4::new(1.0, -1.0, 2.0, 0.0));
+
+  let color = Unorm8x4::from_vector(Vector4::new(1.0, 0.5, 0.0, 2.0));
+  assert_eq!(color, Unorm8x4([255, 128, 0, 255]));
+  assert_eq!(color.to_vector().x, 1.0);
+  assert_eq!(Unorm8x4([0, 51, 0, 0]).to_vector().y, 0.2);
+}
@@ -1,6 +1,6 @@
 use heck::*;
 use proc_macro::*;
-use proc_macro2::{Span, TokenStream as TokenStream2};
+use proc_macro2::TokenStream as TokenStream2;
 use quote::{format_ident, quote};
 use syn::{
   parenthesized,
@@ -50,8 +50,11 @@ impl Item {
       }
       Item::Impl(im) => {
         let ident = match &*im.self_ty {
-          Type::Path(path) => path.path.get_ident().unwrap(),
-          _ => panic!("Unknown implementation ident"),
+          Type::Path(path) if path.path.get_ident().is_some() => path.path.get_ident().unwrap(),
+          ty => {
+            return syn::Error::new_spanned(ty, "Actors must be implemented for a plain type name")
+              .to_compile_error()
+          }
         };
         let self_ident_var = format_ident!("{}", ident.to_string().to_snake_case());
 
@@ -61,7 +64,7 @@ impl Item {
         let mut spawns = Vec::new();
         // Items are "normal" actor methods.
         let mut items = Vec::new();
-        // Event receivers that are hooked for this actor.
+        // Event receivers that are hooked for this actor, messages are received through a mailbox instead.
         let mut event_receiver = Vec::new();
 
         'outer: for item in &im.items {
@@ -97,19 +100,36 @@ impl Item {
                     continue 'outer;
                   }
                   "actor_tick" => {
-                    let x = attr.tokens.clone().into_iter().next().unwrap();
-                    if let proc_macro2::TokenTree::Group(g) = x {
-                      let ty = parse2::<TickType>(g.stream()).unwrap();
-                      timers.push(Tick {
-                        ty,
-                        method: ActorMethod::new(ident.clone(), method),
-                      });
-                      let mut regular = method.clone();
-                      regular.attrs.clear();
-                      items.push(ImplItem::Method(regular));
-                    }
+                    let group = match attr.tokens.clone().into_iter().next() {
+                      Some(proc_macro2::TokenTree::Group(group)) => group,
+                      _ => {
+                        return syn::Error::new_spanned(
+                          attr,
+                          "Expected a tick type, e.g. `#[actor_tick(real)]` or `#[actor_tick(timer(1s))]`",
+                        )
+                        .to_compile_error()
+                      }
+                    };
+                    let TickAttribute { ty, thread_local } =
+                      match parse2::<TickAttribute>(group.stream()) {
+                        Ok(tick) => tick,
+                        Err(err) => return err.to_compile_error(),
+                      };
+                    timers.push(Tick {
+                      ty,
+                      thread_local,
+                      method: ActorMethod::new(ident.clone(), method),
+                    });
+                    let mut regular = method.clone();
+                    regular.attrs.clear();
+                    items.push(ImplItem::Method(regular));
                   }
-                  "actor_event" => {
+                  "actor_event" | "actor_message" => {
+                    let receiver = if name == "actor_message" {
+                      format_ident!("Mailbox")
+                    } else {
+                      format_ident!("EventReceiver")
+                    };
                     let has_attributes = attr.tokens.clone().into_iter().next().is_some();
                     let spawn_type = if has_attributes {
                       SpawnType::Background
@@ -118,14 +138,19 @@ impl Item {
                     };
 
                     let mut item = method.clone();
-                    let event_data_input = item.sig.inputs.pop().unwrap();
-                    let ty = match event_data_input.value() {
-                      FnArg::Typed(ty) => ty.clone(),
-                      _ => panic!("Expected event data as last parameter for event receiver."),
+                    let ty = match item.sig.inputs.pop().map(|input| input.into_value()) {
+                      Some(FnArg::Typed(ty)) => ty,
+                      _ => {
+                        return syn::Error::new_spanned(
+                          &method.sig,
+                          "Expected event data as last parameter for event receiver",
+                        )
+                        .to_compile_error()
+                      }
                     };
                     let actor_method = ActorMethod::new(ident.clone(), &item);
 
-                    event_receiver.push((item, actor_method, ty, spawn_type));
+                    event_receiver.push((item, actor_method, ty, spawn_type, receiver));
 
                     let mut regular = method.clone();
                     regular.attrs.clear();
@@ -145,19 +170,33 @@ impl Item {
           }
         }
 
+        // Validate that all actor methods agree on how components are accessed.
+        let validated = ActorMethod::validate_access(
+          timers
+            .iter()
+            .map(|t| &t.method)
+            .chain(spawns.iter().map(|(_, s)| s))
+            .chain(event_receiver.iter().map(|e| &e.1)),
+        );
+        if let Err(err) = validated {
+          return err.to_compile_error();
+        }
+
         // Create event receiver
         let (event_receiver_spawn, event_receiver_impl) = if !event_receiver.is_empty() {
           let components = event_receiver.iter().map(|recv| {
             let event_data_type = &recv.2.ty;
+            let receiver = &recv.4;
             quote! {
-              cmd.add_component(entity, moonwave_core::EventReceiver::<#event_data_type>::new());
+              cmd.add_component(entity, moonwave_core::#receiver::<#event_data_type>::new());
             }
           });
 
           let drains = event_receiver.iter().map(|recv| {
             let event_data_type = &recv.2.ty;
             let event_data_var = format_ident!(
-              "moonwave_core_event_receiver_{}",
+              "moonwave_core_{}_{}",
+              recv.4.to_string().to_snake_case(),
               get_ident_of_type(&event_data_type)
                 .to_string()
                 .to_snake_case(),
@@ -344,16 +383,24 @@ impl Item {
 
           let repeated = (0..8).map(|_| quote! { std::sync::Once::new() });
 
+          // A single thread local tick forces the whole tick system onto the world thread.
+          let add_system_fn = if timers.iter().any(|t| t.thread_local) {
+            format_ident!("add_thread_local_system_to_stage")
+          } else {
+            format_ident!("add_system_to_stage")
+          };
+
           // Collect all used components.
           let event_componentns = event_receiver.iter().map(|e| e.1.clone());
           let event_recv_components = event_receiver.iter().map(|e| {
             let component = get_path_of_type(&e.2.ty);
+            let receiver = &e.4;
             ActorMethod {
               usages: vec![ComponentUsage {
                 reader: false,
                 writer: false,
                 name: get_ident_of_pat(&e.2.pat),
-                component: parse_quote! { moonwave_core::EventReceiver<#component> },
+                component: parse_quote! { moonwave_core::#receiver<#component> },
                 mutable: true,
               }],
               method: event_receiver[0].1.method.clone(),
@@ -412,15 +459,21 @@ impl Item {
                   #event_receiver_impl
                 }
               }
-            }),
-            Some(quote! {
-              #tick_system_ident_register [level].call_once(|| {
-                moonwave_core::Core::get_instance()
-                  .get_world()
-                  .add_system_to_stage(
+
+              impl #ident {
+                /// Adds the tick system of the given level, done once per level on the first spawn.
+                #[doc(hidden)]
+                pub fn register_tick_system(world: &moonwave_core::World, level: usize) {
+                  world.#add_system_fn(
                     Box::new(move || -> Box<dyn legion::systems::ParallelRunnable> { Box::new(#tick_system_create_ident(level) ) } ),
                     moonwave_core::SystemStage::Application(level as u8)
                   );
+                }
+              }
+            }),
+            Some(quote! {
+              #tick_system_ident_register [level].call_once(|| {
+                Self::register_tick_system(moonwave_core::Core::get_instance().get_world(), level);
               });
             }),
           )
@@ -430,6 +483,59 @@ impl Item {
 
         let optick_spawn = format!("Actor::{}::spawn", ident.to_string());
 
+        // Component access summary
+        let access_summary = {
+          let mut reads = Vec::new();
+          let mut writes = Vec::new();
+          if !timers.is_empty() || !event_receiver.is_empty() {
+            writes.push("moonwave_core::Actor".to_string());
+          } else if !spawns.is_empty() {
+            reads.push("moonwave_core::Actor".to_string());
+          }
+          let methods = timers
+            .iter()
+            .map(|t| t.method.clone())
+            .chain(spawns.iter().map(|(_, s)| s.clone()))
+            .chain(event_receiver.iter().map(|e| e.1.clone()))
+            .collect::<Vec<_>>();
+          if !methods.is_empty() {
+            let (self_usage, components) = ActorMethod::combined_usages(&methods);
+            for usage in std::iter::once(&self_usage).chain(components.iter()) {
+              let name = path_to_display(&usage.component);
+              if usage.mutable {
+                writes.push(name);
+              } else {
+                reads.push(name);
+              }
+            }
+          }
+          for recv in &event_receiver {
+            let event_data_type = &recv.2.ty;
+            writes.push(
+              format!("moonwave_core::{}<{}>", recv.4, quote!(#event_data_type)).replace(' ', ""),
+            );
+          }
+          writes.sort();
+          writes.dedup();
+          reads.sort();
+          reads.dedup();
+          reads.retain(|r| !writes.contains(r));
+          let actor_name = ident.to_string();
+
+          quote! {
+            impl #ident {
+              /// Returns the components read and written by the systems generated for this actor.
+              pub fn component_access() -> moonwave_core::ComponentAccessSummary {
+                moonwave_core::ComponentAccessSummary {
+                  actor: #actor_name,
+                  reads: vec![#(#reads),*],
+                  writes: vec![#(#writes),*],
+                }
+              }
+            }
+          }
+        };
+
         quote! {
           #spawn_system
 
@@ -439,6 +545,8 @@ impl Item {
             #(#items)*
           }
 
+          #access_summary
+
           impl moonwave_core::Spawnable for #ident {
             fn spawn(
               self,
@@ -475,6 +583,8 @@ impl Item {
   }
 }
 
+/// Generates the spawn, tick and event systems of an actor.
+/// All actor methods must agree on the mutability of shared components, see `moonwave_core/tests/ui`.
 #[proc_macro_attribute]
 pub fn actor(_attr: TokenStream, item: TokenStream) -> TokenStream {
   let item = parse_macro_input!(item as Item);
@@ -495,9 +605,15 @@ enum TickType {
 
 struct Tick {
   ty: TickType,
+  thread_local: bool,
   method: ActorMethod,
 }
 
+struct TickAttribute {
+  ty: TickType,
+  thread_local: bool,
+}
+
 #[derive(Clone, Debug)]
 struct ActorMethod {
   method: ImplItemMethod,
@@ -548,7 +664,7 @@ impl ComponentUsage {
 
 fn get_ident_of_type(ty: &syn::Type) -> syn::Ident {
   match ty {
-    syn::Type::Path(path) => path.path.get_ident().unwrap().clone(),
+    syn::Type::Path(path) => format_ident!("{}", path_to_string_ident(&path.path)),
     _ => panic!("Not a ident type"),
   }
 }
@@ -568,18 +684,26 @@ fn get_ident_of_pat(pat: &syn::Pat) -> syn::Ident {
   }
 }
 
+fn path_to_display(path: &Path) -> String {
+  quote!(#path).to_string().replace(' ', "")
+}
+
 fn path_to_string_ident(path: &Path) -> String {
   path
     .segments
     .iter()
     .map(|segment| {
       let postfix = match &segment.arguments {
-        PathArguments::AngleBracketed(x) => match x.args.first().as_ref().unwrap() {
-          GenericArgument::Type(Type::Path(path)) => {
-            format!("_{}_", path_to_string_ident(&path.path))
-          }
-          _ => "".to_string(),
-        },
+        PathArguments::AngleBracketed(x) => x
+          .args
+          .iter()
+          .map(|arg| match arg {
+            GenericArgument::Type(Type::Path(path)) => {
+              format!("_{}_", path_to_string_ident(&path.path))
+            }
+            _ => "".to_string(),
+          })
+          .collect::<String>(),
         _ => "".to_string(),
       };
       format!("{}{}", segment.ident.to_string(), postfix)
@@ -678,28 +802,64 @@ impl ActorMethod {
     }
   }
 
-  pub fn combined(
-    inputs: &[ActorMethod],
-  ) -> (TokenStream2, TokenStream2, TokenStream2, TokenStream2) {
-    let self_mutable = inputs.iter().any(|t| t.self_usage.mutable);
+  /// Ensures that no component is accessed mutably by one actor method and immutably by another.
+  pub fn validate_access<'a>(methods: impl Iterator<Item = &'a ActorMethod>) -> Result<()> {
+    let mut seen: Vec<(String, bool, &syn::Ident)> = Vec::new();
+    for method in methods {
+      let method_ident = &method.method.sig.ident;
+      for usage in &method.usages {
+        let component = path_to_string_ident(&usage.component);
+        let conflict = seen
+          .iter()
+          .find(|(name, mutable, _)| name == &component && *mutable != usage.mutable);
+        if let Some((_, mutable, other)) = conflict {
+          let access = |mutable: bool| if mutable { "mutably" } else { "immutably" };
+          return Err(syn::Error::new(
+            method_ident.span(),
+            format!(
+              "Actor method `{}` accesses component `{}` {} while `{}` accesses it {}, all actor methods must agree on the component access",
+              method_ident,
+              component,
+              access(usage.mutable),
+              other,
+              access(*mutable),
+            ),
+          ));
+        }
+        seen.push((component, usage.mutable, method_ident));
+      }
+    }
+    Ok(())
+  }
+
+  /// Access of the actor itself, mutable if any method needs it, and the deduplicated component
+  /// usages of all given methods. `inputs` must not be empty.
+  fn combined_usages(inputs: &[ActorMethod]) -> (ComponentUsage, Vec<ComponentUsage>) {
+    let self_usage = ComponentUsage {
+      reader: false,
+      writer: false,
+      mutable: inputs.iter().any(|t| t.self_usage.mutable),
+      name: format_ident!("self"),
+      component: inputs[0].self_usage.component.clone(),
+    };
     let mut components = inputs
       .iter()
       .flat_map(|t| t.usages.clone())
       .collect::<Vec<_>>();
     components.sort_unstable_by_key(|u| path_to_string_ident(&u.component));
     components.dedup_by_key(|u| u.component.clone());
+    (self_usage, components)
+  }
+
+  pub fn combined(
+    inputs: &[ActorMethod],
+  ) -> (TokenStream2, TokenStream2, TokenStream2, TokenStream2) {
+    let (self_usage, components) = Self::combined_usages(inputs);
 
     // Build stream for component refs
     let component_streams = {
       let mut stream = TokenStream2::new();
-      ComponentUsage {
-        reader: false,
-        writer: false,
-        mutable: self_mutable,
-        name: format_ident!("self"),
-        component: inputs[0].self_usage.component.clone(),
-      }
-      .extend_stream(&mut stream);
+      self_usage.extend_stream(&mut stream);
       for usage in &components {
         usage.extend_stream(&mut stream);
       }
@@ -708,15 +868,7 @@ impl ActorMethod {
 
     // Build stream for the query
     let query_stream = {
-      let all = [ComponentUsage {
-        reader: false,
-        mutable: self_mutable,
-        writer: false,
-        name: format_ident!("self"),
-        component: inputs[0].self_usage.component.clone(),
-      }];
-      let usages = all
-        .iter()
+      let usages = std::iter::once(&self_usage)
         .chain(components.iter().filter(|c| !c.reader && !c.writer))
         .map(|m| m.query_type());
 
@@ -727,15 +879,7 @@ impl ActorMethod {
 
     // Build stream for the query
     let names = {
-      let all = [ComponentUsage {
-        reader: false,
-        writer: false,
-        mutable: self_mutable,
-        name: format_ident!("self"),
-        component: inputs[0].self_usage.component.clone(),
-      }];
-      let usages = all
-        .iter()
+      let usages = std::iter::once(&self_usage)
         .chain(components.iter().filter(|c| !c.reader && !c.writer))
         .map(|m| m.name());
 
@@ -778,26 +922,46 @@ impl Parse for TickType {
         parenthesized!(content in input);
         let value = content.parse::<LitInt>()?;
         match value.suffix() {
-          "s" => Ok(TickType::Timer(TimerValue::Seconds(
-            value.base10_parse().unwrap(),
-          ))),
+          "s" => Ok(TickType::Timer(TimerValue::Seconds(value.base10_parse()?))),
           "ms" => Ok(TickType::Timer(TimerValue::Milliseconds(
-            value.base10_parse().unwrap(),
+            value.base10_parse()?,
           ))),
           _ => Err(syn::Error::new(
-            Span::call_site(),
+            value.span(),
             "Unexpected timer value variant (only ms and s allowed)",
           )),
         }
       }
       _ => Err(syn::Error::new(
-        Span::call_site(),
+        ident.span(),
         "Unexpected timer variant (only 'real' and 'timer' are allowed)",
       )),
     }
   }
 }
 
+impl Parse for TickAttribute {
+  fn parse(input: ParseStream) -> Result<Self> {
+    let ty = input.parse::<TickType>()?;
+    let mut thread_local = false;
+    while input.peek(Token![,]) {
+      input.parse::<Token![,]>()?;
+      let option = input.parse::<syn::Ident>()?;
+      match option.to_string().as_str() {
+        "thread_local" => thread_local = true,
+        _ => {
+          return Err(syn::Error::new(
+            option.span(),
+            "Unexpected tick option (only 'thread_local' is allowed)",
+          ))
+        }
+      }
+    }
+
+    Ok(TickAttribute { ty, thread_local })
+  }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum SpawnType {
   Blocking,
@@ -855,7 +1019,7 @@ fn service_trait_logger_items(
 
         quote! {
           #sig {
-            moonwave_core::debug!(#log_msg);
+            moonwave_core::debug!(target: moonwave_core::SERVICE_LOG_TARGET, #log_msg);
             self.0.#name(#(#sig_args),*)
           }
         }
@@ -881,11 +1045,17 @@ fn service_trait_bench_items(
         let name = method.sig.ident.clone();
         let name_str = name.to_string();
         let event_name = format!("Service::{}::{}", ident.to_string(), name_str);
+        let timing_name = format!("{}::{}", ident.to_string(), name_str);
 
         quote! {
           #sig {
             moonwave_core::optick::event!(#event_name);
-            self.0.#name(#(#sig_args),*)
+            let start = std::time::Instant::now();
+            let result = self.0.#name(#(#sig_args),*);
+            if let Some(timings) = self.1.get() {
+              timings.record(#timing_name, start.elapsed());
+            }
+            result
           }
         }
       }
@@ -894,19 +1064,37 @@ fn service_trait_bench_items(
     .collect::<Vec<_>>()
 }
 
+/// Timed extensions additionally hold the timings of the locator they are registered with.
+/// Timings are forwarded to the wrapped service, so extensions nested in any order are measured.
 fn generate_extension_tree(
   host: &proc_macro2::Ident,
   org: &proc_macro2::Ident,
   ext: &proc_macro2::Ident,
   items: &[TokenStream2],
+  timed: bool,
 ) -> TokenStream2 {
   let ext_into = format_ident!("{}{}Into", ext, host);
+  let (timings_field, timings_init, timings_set) = if timed {
+    (
+      quote! { , moonwave_core::OnceCell<std::sync::Arc<moonwave_core::ServiceTimings>> },
+      quote! { , moonwave_core::OnceCell::new() },
+      quote! { let _ = self.1.set(timings.clone()); },
+    )
+  } else {
+    (quote! {}, quote! {}, quote! {})
+  };
 
   quote! {
         #[doc(hidden)]
-        pub struct #ext (#host);
+        #[allow(non_camel_case_types)]
+        pub struct #ext (#host #timings_field);
         impl #org for #ext {
           #(#items)*
+
+          fn attach_service_timings(&self, timings: &std::sync::Arc<moonwave_core::ServiceTimings>) {
+            #timings_set
+            self.0.attach_service_timings(timings);
+          }
         }
         impl moonwave_core::ServiceSafeType for #ext {}
         impl moonwave_core::TypedServiceIntoHost for #ext {
@@ -916,13 +1104,17 @@ fn generate_extension_tree(
               inner: std::sync::Arc::new(self),
             }
           }
+          fn attach_timings(&mut self, timings: &std::sync::Arc<moonwave_core::ServiceTimings>) {
+            #org::attach_service_timings(self, timings);
+          }
         }
+        #[allow(non_camel_case_types)]
         pub trait #ext_into {
           fn #ext (self) -> #ext;
         }
         impl<T: moonwave_core::TypedServiceIntoHost<Host = #host>> #ext_into for T {
           fn #ext (self) -> #ext {
-            #ext (self.into_host())
+            #ext (self.into_host() #timings_init)
           }
         }
   }
@@ -946,12 +1138,14 @@ pub fn service_trait(_attr: TokenStream, item: TokenStream) -> TokenStream {
         &renamed.ident,
         &format_ident!("logged"),
         &logged_items,
+        false,
       );
       let benched_ext = generate_extension_tree(
         &name,
         &renamed.ident,
         &format_ident!("benched"),
         &benched_items,
+        true,
       );
 
       let items = renamed
@@ -976,6 +1170,12 @@ pub fn service_trait(_attr: TokenStream, item: TokenStream) -> TokenStream {
         })
         .collect::<Vec<_>>();
 
+      // Extensions hand the timings of their locator down to the services they wrap.
+      renamed.items.push(parse_quote! {
+        #[doc(hidden)]
+        fn attach_service_timings(&self, _timings: &std::sync::Arc<moonwave_core::ServiceTimings>) {}
+      });
+
       TokenStream::from(quote! {
         #renamed
 
@@ -986,6 +1186,11 @@ pub fn service_trait(_attr: TokenStream, item: TokenStream) -> TokenStream {
 
         impl #name {
           #(#items)*
+
+          #[doc(hidden)]
+          pub fn attach_service_timings(&self, timings: &std::sync::Arc<moonwave_core::ServiceTimings>) {
+            self.inner.attach_service_timings(timings);
+          }
         }
 
         impl moonwave_core::TypedServiceTrait for #name {
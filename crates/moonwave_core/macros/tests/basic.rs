@@ -1,6 +1,10 @@
 #![feature(arbitrary_self_types)]
 
+use legion::{IntoQuery, SystemBuilder};
+use moonwave_core::{ActorRc, Entity, Mailbox, World};
 use moonwave_core_macro::*;
+use std::sync::{Arc, Mutex};
+use std::thread::ThreadId;
 
 #[actor]
 struct MyTestActor {
@@ -25,6 +29,51 @@ impl MyTestActor {
   }
 }
 
+struct MyThreadLocalActor {
+  ticks: usize,
+}
+
+#[actor]
+impl MyThreadLocalActor {
+  #[actor_tick(real, thread_local)]
+  fn tick(&mut self) {
+    self.ticks += 1;
+    THREAD_LOCAL_TICKS
+      .lock()
+      .unwrap()
+      .push(std::thread::current().id());
+  }
+}
+
+struct Transform {
+  x: f32,
+}
+
+struct MyMovingActor;
+
+#[actor]
+impl MyMovingActor {
+  #[actor_tick(real)]
+  fn tick(&self, transform: &mut Transform) {
+    transform.x += 1.0;
+  }
+}
+
+#[derive(Clone)]
+struct Damage(u32);
+
+struct MyEnemyActor {
+  health: u32,
+}
+
+#[actor]
+impl MyEnemyActor {
+  #[actor_message]
+  fn on_damage(&mut self, damage: Damage) {
+    self.health = self.health.saturating_sub(damage.0);
+  }
+}
+
 struct MyOtherActor;
 
 #[actor]
@@ -39,3 +88,98 @@ pub fn basic_test() {
   let x = 1usize.min(2);
   assert!(x >= 1);
 }
+
+#[test]
+pub fn component_access_test() {
+  let access = MyMovingActor::component_access();
+  assert!(access.writes.contains(&"Transform"));
+  assert!(!access.reads.contains(&"Transform"));
+  assert!(access.reads.contains(&"MyMovingActor"));
+}
+
+#[test]
+pub fn message_access_test() {
+  let access = MyEnemyActor::component_access();
+  assert!(access.writes.contains(&"moonwave_core::Mailbox<Damage>"));
+  assert!(!access
+    .writes
+    .contains(&"moonwave_core::EventReceiver<Damage>"));
+}
+
+static THREAD_LOCAL_TICKS: Mutex<Vec<ThreadId>> = Mutex::new(Vec::new());
+
+#[test]
+pub fn thread_local_tick_test() {
+  let mut world = World::new();
+  MyThreadLocalActor::register_tick_system(&world, 0);
+
+  // Actors are only spawned through the core, so the actor is pushed manually.
+  world.add_temp_system(Box::new(
+    SystemBuilder::new("spawn_thread_local_actor").build(|cmd, _, _, _| {
+      let actor = ActorRc::new(cmd, MyThreadLocalActor { ticks: 0 }, None, 0, Vec::new());
+      // Dropping the handle would despawn the actor through the core.
+      std::mem::forget(actor);
+    }),
+  ));
+
+  let pool = moonwave_core::rayon::ThreadPoolBuilder::new()
+    .num_threads(4)
+    .build()
+    .unwrap();
+  for _ in 0..16 {
+    world.tick(0, &pool);
+  }
+
+  // The actor exists from the second tick on.
+  let ticks = THREAD_LOCAL_TICKS.lock().unwrap();
+  assert_eq!(ticks.len(), 15);
+  assert!(ticks.iter().all(|id| *id == std::thread::current().id()));
+}
+
+#[test]
+pub fn actor_message_test() {
+  let mut world = World::new();
+  MyEnemyActor::register_tick_system(&world, 0);
+
+  // Same as `Spawnable::spawn` without going through the core.
+  let enemies = Arc::new(Mutex::new(Vec::<Entity>::new()));
+  let enemies_cloned = enemies.clone();
+  world.add_temp_system(Box::new(SystemBuilder::new("spawn_enemies").build(
+    move |cmd, _, _, _| {
+      for _ in 0..2 {
+        let actor = ActorRc::new(cmd, MyEnemyActor { health: 100 }, None, 0, Vec::new());
+        cmd.add_component(actor.get_entity(), Mailbox::<Damage>::new());
+        enemies_cloned.lock().unwrap().push(actor.get_entity());
+        std::mem::forget(actor);
+      }
+    },
+  )));
+
+  let pool = moonwave_core::rayon::ThreadPoolBuilder::new()
+    .num_threads(2)
+    .build()
+    .unwrap();
+  world.tick(0, &pool);
+
+  let enemies = enemies.lock().unwrap().clone();
+  world.send_to(enemies[1], Damage(30));
+  world.send_to(enemies[1], Damage(20));
+  world.tick(0, &pool);
+
+  let health = Arc::new(Mutex::new(Vec::new()));
+  let health_cloned = health.clone();
+  world.add_temp_system(Box::new(
+    SystemBuilder::new("read_health")
+      .with_query(<(Entity, &MyEnemyActor)>::query())
+      .build(move |_, world, _, query| {
+        for (entity, enemy) in query.iter(world) {
+          health_cloned.lock().unwrap().push((*entity, enemy.health));
+        }
+      }),
+  ));
+  world.tick(0, &pool);
+
+  let mut health = health.lock().unwrap().clone();
+  health.sort_by_key(|(entity, _)| enemies.iter().position(|e| e == entity));
+  assert_eq!(health, vec![(enemies[0], 100), (enemies[1], 50)]);
+}
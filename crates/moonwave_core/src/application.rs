@@ -1,4 +1,6 @@
-use crate::{base::Core, logger::init, ActorRc, Extension, Spawnable, TypedServiceIntoHost};
+use crate::{
+  base::Core, logger::init, ActorRc, CoreConfig, Extension, Spawnable, TypedServiceIntoHost,
+};
 use legion::{systems::CommandBuffer, Resources};
 use log::debug;
 use wgpu::SwapChainError;
@@ -20,8 +22,12 @@ pub struct Application {
 
 impl Application {
   pub fn new() -> Self {
+    Self::new_with_config(CoreConfig::new())
+  }
+
+  pub fn new_with_config(config: CoreConfig) -> Self {
     // Initialize core logging systems.
-    init();
+    init(config.log_filter.clone());
 
     // Render doc support
     #[cfg(feature = "renderdochost")]
@@ -48,12 +54,11 @@ impl Application {
         .request_device(
           &wgpu::DeviceDescriptor {
             label: Some("Render Device"),
-            features: wgpu::Features::NON_FILL_POLYGON_MODE
-              | wgpu::Features::SAMPLED_TEXTURE_BINDING_ARRAY,
-            limits: wgpu::Limits {
-              max_sampled_textures_per_shader_stage: 128,
-              ..wgpu::Limits::default()
-            },
+            features: config
+              .device
+              .features(adapter.features())
+              .unwrap_or_else(|err| panic!("{}", err)),
+            limits: config.device.limits(&adapter.limits()),
           },
           None, // Trace path
         )
@@ -74,14 +79,14 @@ impl Application {
         format: sc_format.unwrap(),
         width: win_size.width,
         height: win_size.height,
-        present_mode: wgpu::PresentMode::Mailbox,
+        present_mode: config.present_mode,
       };
       let swap_chain = device.create_swap_chain(&surface, &sc_desc);
 
       (surface, device, queue, swap_chain, sc_desc)
     });
 
-    Core::initialize(device, queue, swap_chain, sc_desc, surface);
+    Core::initialize(device, queue, swap_chain, sc_desc, surface, config);
 
     Self {
       #[cfg(feature = "renderdochost")]
@@ -126,7 +131,7 @@ impl Application {
     let rc = actor.spawn(None, 0, &mut cmd);
     Core::get_instance()
       .get_world()
-      .add_command_buffer(cmd, false, None);
+      .add_command_buffer(cmd, 0, None);
     rc
   }
 
@@ -166,6 +171,25 @@ impl Application {
           self.handle_update_size();
         }
         WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+        WindowEvent::CursorMoved { position, .. } => {
+          let input = WindowInput::CursorMoved(position.x as f32, position.y as f32);
+          Core::get_instance().dispatch_input(&input);
+        }
+        WindowEvent::CursorLeft { .. } => {
+          Core::get_instance().dispatch_input(&WindowInput::CursorLeft);
+        }
+        WindowEvent::MouseInput { state, button, .. } => {
+          let input = WindowInput::MouseButton(*button, *state);
+          Core::get_instance().dispatch_input(&input);
+        }
+        WindowEvent::MouseWheel { delta, .. } => {
+          let (x, y) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (x * SCROLL_LINE_PIXELS, y * SCROLL_LINE_PIXELS),
+            MouseScrollDelta::PixelDelta(position) => (position.x as f32, position.y as f32),
+          };
+          // Winit reports right as positive while input scrolls left with positive values.
+          Core::get_instance().dispatch_input(&WindowInput::Scrolled(-x, y));
+        }
         WindowEvent::KeyboardInput { input, .. } => {
           #[cfg(feature = "renderdochost")]
           if input.virtual_keycode == Some(VirtualKeyCode::F10)
@@ -179,7 +203,11 @@ impl Application {
             state: input.state,
           };
 
-          Core::get_instance().get_world().publish_event(event);
+          // Keys consumed by an extension, e.g. a focused text field, don't reach the world.
+          let core = Core::get_instance();
+          if !core.dispatch_input(&WindowInput::Keyboard(event.clone())) {
+            core.get_world().publish_event(event);
+          }
         }
         _ => {}
       },
@@ -200,10 +228,25 @@ impl Application {
   }
 }
 
-pub use winit::event::{DeviceEvent, ElementState, VirtualKeyCode};
+pub use winit::event::{DeviceEvent, ElementState, MouseButton, VirtualKeyCode};
+
+/// Pixels scrolled per line of mouse wheels that report their scrolling in lines.
+const SCROLL_LINE_PIXELS: f32 = 20.0;
 
 #[derive(Clone)]
 pub struct KeyboardEvent {
   pub key: Option<VirtualKeyCode>,
   pub state: ElementState,
 }
+
+/// Window input routed into extensions, see `Extension::on_input`.
+#[derive(Clone)]
+pub enum WindowInput {
+  /// Cursor position in pixels starting at the top left of the window.
+  CursorMoved(f32, f32),
+  CursorLeft,
+  MouseButton(MouseButton, ElementState),
+  /// Mouse wheel or touchpad scrolling in pixels, positive values scroll up and left.
+  Scrolled(f32, f32),
+  Keyboard(KeyboardEvent),
+}
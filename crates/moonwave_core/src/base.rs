@@ -1,13 +1,17 @@
-use itertools::Itertools;
 use lazy_static::__Deref;
-use moonwave_common::Vector2;
-use moonwave_render::{CommandEncoder, DeviceHost, FrameGraph};
+use legion::storage::Component;
+use moonwave_common::{Vector2, Vector4};
+use moonwave_render::{
+  execute_wgpu_async, CommandEncoder, DeviceHost, FrameGraph, FrameTarget, UploadQueue,
+};
 use parking_lot::Mutex;
 use std::{
   collections::HashMap,
+  hash::{Hash, Hasher},
   num::NonZeroU32,
+  panic::Location,
   sync::{
-    atomic::{AtomicU64, Ordering},
+    atomic::{AtomicBool, AtomicU64, Ordering},
     Arc, RwLock,
   },
   time::Instant,
@@ -23,7 +27,9 @@ use wgpu::{
 };
 
 use crate::{
-  execution::Execution, warn, Extension, ExtensionHost, PresentToScreen, ServiceLocator, World,
+  add_debug_text_pass, catch_background_panic, execution::Execution, warn, CapturedError,
+  CoreConfig, DebugTextQueue, Entity, ErrorScopeCapture, Extension, ExtensionHost, PresentToScreen,
+  PresentToTexture, ResourceCache, ServiceLocator, WindowInput, World,
 };
 
 use moonwave_resources::*;
@@ -33,9 +39,10 @@ static mut CORE: Option<Core> = None;
 pub struct Core {
   pub(crate) device: Device,
   queue: Queue,
-  swap_chain: SwapChain,
+  /// Surface and swap chain of the window, `None` when running headless.
+  presentation: Option<(Surface, SwapChain)>,
   sc_desc: SwapChainDescriptor,
-  surface: Surface,
+  headless_target: Option<SampledTexture>,
   resources: ResourceStorage,
   extension_host: RwLock<ExtensionHost>,
   mip_generator: RecommendedMipmapGenerator,
@@ -46,25 +53,43 @@ pub struct Core {
   service_locator: ServiceLocator,
   execution: Execution,
   gp_resources: Option<GPResources>,
+  config: CoreConfig,
+  error_capture: ErrorScopeCapture,
+  /// Whether the frame graph currently executes within the error scopes of the frame.
+  frame_scoped: AtomicBool,
+  wireframe: AtomicBool,
+  over_memory_budget: AtomicBool,
+  shader_cache: ResourceCache<ResourceRc<Shader>>,
+  pipeline_cache: ResourceCache<ResourceRc<RenderPipeline>>,
+  texture_target: Mutex<Option<(SampledTexture, TextureFormat)>>,
+  /// Present mode requested through `set_present_mode`, applied before the next frame.
+  pending_present_mode: Mutex<Option<wgpu::PresentMode>>,
+  debug_text: DebugTextQueue,
+  upload_queue: Arc<UploadQueue>,
 }
 
 impl Core {
   fn new(
     device: Device,
     queue: Queue,
-    swap_chain: SwapChain,
+    presentation: Option<(Surface, SwapChain)>,
     sc_desc: SwapChainDescriptor,
-    surface: Surface,
+    config: CoreConfig,
   ) -> Self {
+    // Wireframe mode needs line polygon mode, see `set_wireframe`.
+    let wireframe = config.wireframe
+      && device
+        .features()
+        .contains(wgpu::Features::NON_FILL_POLYGON_MODE);
     Self {
       mip_generator: RecommendedMipmapGenerator::new(&device),
       last_frame: Instant::now(),
       elapsed_time: 0,
-      swap_chain,
+      presentation,
       sc_desc,
+      headless_target: None,
       device,
       queue,
-      surface,
       graph: None,
       gp_resources: None,
       resources: ResourceStorage::new(),
@@ -72,6 +97,17 @@ impl Core {
       service_locator: ServiceLocator::new(),
       execution: Execution::new(8),
       world: World::new(),
+      error_capture: ErrorScopeCapture::new(),
+      frame_scoped: AtomicBool::new(false),
+      wireframe: AtomicBool::new(wireframe),
+      over_memory_budget: AtomicBool::new(false),
+      shader_cache: ResourceCache::new(),
+      pipeline_cache: ResourceCache::new(),
+      texture_target: Mutex::new(None),
+      pending_present_mode: Mutex::new(None),
+      debug_text: DebugTextQueue::new(),
+      upload_queue: Arc::new(UploadQueue::new()),
+      config,
     }
   }
 
@@ -81,10 +117,79 @@ impl Core {
     swap_chain: SwapChain,
     sc_desc: SwapChainDescriptor,
     surface: Surface,
+    config: CoreConfig,
+  ) {
+    Self::initialize_with(device, queue, Some((surface, swap_chain)), sc_desc, config);
+  }
+
+  /// Initializes the core without a window, e.g. for tests or server side rendering.
+  /// Frames are rendered into an offscreen texture of `CoreConfig::headless_size`, see
+  /// `get_headless_target`, and are executed through `run_headless_frame`.
+  pub fn initialize_headless(device: Device, queue: Queue, config: CoreConfig) {
+    let sc_desc = SwapChainDescriptor {
+      usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+      format: PresentToScreen::FORMAT,
+      width: config.headless_size.x,
+      height: config.headless_size.y,
+      present_mode: wgpu::PresentMode::Fifo,
+    };
+    Self::initialize_with(device, queue, None, sc_desc, config);
+
+    let core = Self::get_instance_mut_unstable();
+    core.headless_target = Some(core.create_headless_target());
+  }
+
+  /// Requests a device without a surface and initializes the core headless with it.
+  /// Returns `false` if there is no usable adapter, e.g. for tests on machines without a GPU,
+  /// or the adapter lacks features required by `CoreConfig::device`.
+  pub fn try_initialize_headless(config: CoreConfig) -> bool {
+    let device = futures::executor::block_on(async {
+      let instance = wgpu::Instance::new(wgpu::BackendBit::all());
+      let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+          power_preference: wgpu::PowerPreference::default(),
+          compatible_surface: None,
+        })
+        .await?;
+      let features = match config.device.features(adapter.features()) {
+        Ok(features) => features,
+        Err(err) => {
+          warn!("Adapter is not usable: {}", err);
+          return None;
+        }
+      };
+      adapter
+        .request_device(
+          &wgpu::DeviceDescriptor {
+            label: Some("Headless Render Device"),
+            features,
+            limits: config.device.limits(&adapter.limits()),
+          },
+          None,
+        )
+        .await
+        .ok()
+    });
+
+    match device {
+      Some((device, queue)) => {
+        Self::initialize_headless(device, queue, config);
+        true
+      }
+      None => false,
+    }
+  }
+
+  fn initialize_with(
+    device: Device,
+    queue: Queue,
+    presentation: Option<(Surface, SwapChain)>,
+    sc_desc: SwapChainDescriptor,
+    config: CoreConfig,
   ) {
     // Build static core and create new framegraph.
     unsafe {
-      CORE = Some(Core::new(device, queue, swap_chain, sc_desc, surface));
+      CORE = Some(Core::new(device, queue, presentation, sc_desc, config));
     }
 
     let core = Self::get_instance();
@@ -101,7 +206,8 @@ impl Core {
         sampled_texture_bind_group_layout,
         sampled_texture_array_bind_group_layout: Mutex::new(HashMap::new()),
       });
-      CORE.as_mut().unwrap().graph = Some(FrameGraph::new(PresentToScreen::new()));
+      CORE.as_mut().unwrap().graph =
+        Some(FrameGraph::new(PresentToScreen::new()).with_upload_queue(core.upload_queue.clone()));
     }
   }
 
@@ -128,32 +234,209 @@ impl Core {
     self.elapsed_time
   }
 
+  /// Returns the number of frames executed so far.
+  #[inline]
+  pub fn get_current_frame(&self) -> u64 {
+    CURRENT_FRAME.load(Ordering::Relaxed)
+  }
+
+  #[inline]
+  pub fn get_config(&self) -> &CoreConfig {
+    &self.config
+  }
+
+  /// Enables or disables wireframe rendering, pipelines that respect it are rebuilt on next use.
+  /// Stays disabled with a warning if the device doesn't support line polygon mode,
+  /// see `DeviceConfig::polygon_mode_line`.
+  pub fn set_wireframe(&self, enabled: bool) {
+    if enabled && !self.supports_wireframe() {
+      warn!("Wireframe rendering is not supported by the device");
+      return;
+    }
+    self.wireframe.store(enabled, Ordering::Relaxed);
+  }
+
+  /// Whether pipelines can be drawn as lines on the current device.
+  pub fn supports_wireframe(&self) -> bool {
+    self
+      .device
+      .features()
+      .contains(wgpu::Features::NON_FILL_POLYGON_MODE)
+  }
+
+  /// Whether render pipelines should be built with line polygon mode for debugging.
+  #[inline]
+  pub fn is_wireframe(&self) -> bool {
+    self.wireframe.load(Ordering::Relaxed)
+  }
+
+  /// Approximate amount of bytes currently allocated by buffers and textures.
+  pub fn gpu_memory_estimate(&self) -> u64 {
+    self.resources.allocated_bytes()
+  }
+
+  /// Warns once whenever the memory estimate goes above the configured budget.
+  fn check_memory_budget(&self) {
+    if let Some(budget) = self.config.gpu_memory_budget {
+      let estimate = self.gpu_memory_estimate();
+      let over_budget = estimate > budget;
+      if over_budget && !self.over_memory_budget.swap(true, Ordering::Relaxed) {
+        warn!(
+          "Estimated GPU memory usage of {} bytes exceeds the budget of {} bytes",
+          estimate, budget
+        );
+      } else if !over_budget {
+        self.over_memory_budget.store(false, Ordering::Relaxed);
+      }
+    }
+  }
+
+  /// Executes the given function within gpu error scopes if enabled in the core config.
+  fn scoped<R, F: FnOnce() -> R>(&self, context: &'static str, f: F) -> R {
+    if !self.config.error_scopes {
+      return f();
+    }
+
+    // Frame graph nodes run on the frame thread pool while the frame holds the scopes,
+    // their device work is part of the frame and waiting for the scopes would deadlock.
+    let in_frame = self.frame_scoped.load(Ordering::Acquire)
+      && self
+        .execution
+        .get_frame_thread_pool()
+        .current_thread_index()
+        .is_some();
+    if in_frame {
+      f()
+    } else {
+      self.error_capture.capture(&self.device, context, f)
+    }
+  }
+
+  /// Returns and clears all gpu errors captured by error scopes.
+  pub fn drain_captured_errors(&self) -> Vec<CapturedError> {
+    self.error_capture.drain()
+  }
+
   pub(crate) fn recreate_swap_chain(&mut self, width: u32, height: u32) {
     self.sc_desc.width = width;
     self.sc_desc.height = height;
-    self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+    if let Some((surface, swap_chain)) = &mut self.presentation {
+      *swap_chain = self.device.create_swap_chain(surface, &self.sc_desc);
+    } else {
+      self.headless_target = Some(self.create_headless_target());
+    }
   }
 
+  /// Changes how frames are presented, the swap chain is recreated before the next frame.
+  /// `Fifo` is vsynced, `Mailbox` avoids tearing with lower latency and `Immediate` is uncapped
+  /// but may tear. Unsupported modes fall back to `Fifo` by the backend.
+  pub fn set_present_mode(&self, mode: wgpu::PresentMode) {
+    *self.pending_present_mode.lock() = Some(mode);
+  }
+
+  /// The present mode used for the next frame.
+  pub fn get_present_mode(&self) -> wgpu::PresentMode {
+    self
+      .pending_present_mode
+      .lock()
+      .unwrap_or(self.sc_desc.present_mode)
+  }
+
+  /// Size of the window, or of the offscreen target when running headless.
   pub fn get_swap_chain_size(&self) -> Vector2<u32> {
     Vector2::new(self.sc_desc.width, self.sc_desc.height)
   }
 
+  /// Whether the core has been initialized without a window, see `initialize_headless`.
+  pub fn is_headless(&self) -> bool {
+    self.presentation.is_none()
+  }
+
+  /// Texture holding the last frame when running headless.
+  pub fn get_headless_target(&self) -> Option<&SampledTexture> {
+    self.headless_target.as_ref()
+  }
+
+  fn create_headless_target(&self) -> SampledTexture {
+    self.create_sampled_texture(
+      Some("HeadlessTarget"),
+      TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED | TextureUsage::COPY_SRC,
+      self.sc_desc.format,
+      self.get_swap_chain_size(),
+      1,
+    )
+  }
+
+  /// Executes a single frame of a core initialized with `initialize_headless`.
+  /// Extensions are initialized before the first frame.
+  pub fn run_headless_frame() -> Result<(), SwapChainError> {
+    let core = Self::get_instance_mut_unstable();
+    assert!(
+      core.is_headless(),
+      "Headless frames require a core initialized with Core::initialize_headless"
+    );
+    if core.get_current_frame() == 0 {
+      core.before_run();
+    }
+    core.frame()
+  }
+
   pub(crate) fn before_run(&self) {
     optick::event!("Core::extensions::init");
     let mut ext_host = self.extension_host.write().unwrap();
     ext_host.init();
   }
 
+  /// Acquires the next swap chain frame, recreating the swap chain once if it became unusable.
+  /// Returns `None` if the frame should be skipped.
+  fn acquire_frame_target(&mut self) -> Result<Option<FrameTarget>, SwapChainError> {
+    let pending_present_mode = self.pending_present_mode.lock().take();
+    if let Some(mode) = pending_present_mode {
+      if mode != self.sc_desc.present_mode {
+        self.sc_desc.present_mode = mode;
+        self.recreate_swap_chain(self.sc_desc.width, self.sc_desc.height);
+      }
+    }
+
+    let mut recreated = false;
+    loop {
+      let swap_chain = match &self.presentation {
+        Some((_, swap_chain)) => swap_chain,
+        None => {
+          let target = self.headless_target.as_ref().unwrap();
+          return Ok(Some(FrameTarget::Offscreen(target.view.clone())));
+        }
+      };
+      let err = match swap_chain.get_current_frame() {
+        Ok(frame) => return Ok(Some(FrameTarget::SwapChain(frame))),
+        Err(err) => err,
+      };
+
+      match swap_chain_recovery(&err) {
+        SwapChainRecovery::Recreate if !recreated => {
+          warn!("Swap chain is {:?}, recreating it", err);
+          self.recreate_swap_chain(self.sc_desc.width, self.sc_desc.height);
+          recreated = true;
+        }
+        SwapChainRecovery::Skip => return Ok(None),
+        _ => return Err(err),
+      }
+    }
+  }
+
   pub(crate) fn frame(&mut self) -> Result<(), SwapChainError> {
+    // Next frame.
+    let frame_target = match self.acquire_frame_target()? {
+      Some(frame) => Arc::new(frame),
+      None => return Ok(()),
+    };
+
     // Timing
     let time = Instant::now();
     let duration = time - self.last_frame;
     self.last_frame = time;
     self.elapsed_time = duration.as_micros() as u64;
 
-    // Next frame.
-    let swap_frame = Arc::new(self.swap_chain.get_current_frame()?);
-
     // Execute extensions
     {
       optick::event!("Core::extensions::before_tick");
@@ -169,24 +452,45 @@ impl Core {
         .tick(self.elapsed_time, self.execution.get_frame_thread_pool());
     }
 
+    // Debug texts queued until now are drawn with this frame.
+    add_debug_text_pass(self, self.graph.as_ref().unwrap(), &self.debug_text);
+
     // Execute graph
     {
       optick::event!("Core::frame::execute_graph");
-      self.graph.as_mut().unwrap().execute(
-        swap_frame.clone(),
-        Core::get_instance(),
-        self.execution.get_frame_thread_pool(),
-      );
+      let graph = self.graph.as_mut().unwrap();
+      let pool = self.execution.get_frame_thread_pool();
+
+      // Redirect this frame into a texture if requested.
+      let screen_end_node = self.texture_target.lock().take().map(|(target, format)| {
+        graph.replace_end_node(Arc::new(PresentToTexture::new(target, format)))
+      });
+
+      let mut execute = || graph.execute(frame_target.clone(), Core::get_instance(), pool);
+      if self.config.error_scopes {
+        let frame_scoped = &self.frame_scoped;
+        self.error_capture.capture(&self.device, "Core::frame", || {
+          frame_scoped.store(true, Ordering::Release);
+          execute();
+          frame_scoped.store(false, Ordering::Release);
+        });
+      } else {
+        execute();
+      }
+
+      if let Some(end_node) = screen_end_node {
+        self.graph.as_ref().unwrap().replace_end_node(end_node);
+      }
     }
 
     {
       optick::event!("Core::frame::swapchain_drop");
       assert_eq!(
         1,
-        Arc::strong_count(&swap_frame),
-        "Reference to Swapchain frame has not been dropped in frame graph"
+        Arc::strong_count(&frame_target),
+        "Reference to frame target has not been dropped in frame graph"
       );
-      drop(swap_frame);
+      drop(frame_target);
     }
 
     CURRENT_FRAME.fetch_add(1, Ordering::Relaxed);
@@ -194,29 +498,53 @@ impl Core {
     Ok(())
   }
 
+  /// Draws the text at the given pixel position for the current frame only, call it every frame
+  /// to keep it visible. Meant for quick debug prints without setting up any UI.
+  pub fn debug_text(&self, text: &str, x: f32, y: f32, color: Vector4<f32>) {
+    self.debug_text.push(text, Vector2::new(x, y), color);
+  }
+
   /// Registers a core extension
   pub fn add_extension<T: Extension>(&self, extension: T) {
     let mut host = self.extension_host.write().unwrap();
     host.add(extension);
   }
 
+  /// Offers window input to the registered extensions until one consumes it, returns whether one did.
+  /// `Application` calls this on the main thread for every cursor, mouse button, wheel and keyboard event.
+  pub fn dispatch_input(&self, input: &WindowInput) -> bool {
+    let mut host = self.extension_host.write().unwrap();
+    host.on_input(input)
+  }
+
   /// Returns the ecs systems world container.
   #[inline]
   pub fn get_world(&self) -> &World {
     &self.world
   }
 
+  /// Sends a message to the mailbox of a single actor, see `World::send_to`.
+  pub fn send_to<T: Component + Sized + 'static>(&self, entity: Entity, message: T) {
+    self.world.send_to(entity, message);
+  }
+
   /// Returns the ecs systems world container.
   pub fn get_world_mut(&mut self) -> &mut World {
     &mut self.world
   }
 
   /// Spawns a background task without waiting for it.
+  /// Panics of the task are logged with the call site and passed to the configured panic handler.
+  #[track_caller]
   pub fn spawn_background_task<OP>(&self, op: OP)
   where
     OP: FnOnce() + Send + 'static,
   {
-    self.execution.get_background_thread_pool().spawn(op);
+    let location = Location::caller();
+    let handler = self.config.background_panic_handler.clone();
+    self.execution.get_background_thread_pool().spawn(move || {
+      catch_background_panic(op, location, handler.as_ref());
+    });
   }
 
   /// Install a task into the background thread pool and returns when its done.
@@ -228,11 +556,58 @@ impl Core {
     self.execution.get_background_thread_pool().install(op)
   }
 
+  /// Renders the next frame into a new texture of the given size and format instead of only the screen.
+  /// The returned texture holds the frame once the next frame has been executed.
+  pub fn render_to_texture(&self, size: Vector2<u32>, format: TextureFormat) -> SampledTexture {
+    let target = self.create_sampled_texture(
+      Some("RenderToTexture"),
+      TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED | TextureUsage::COPY_SRC,
+      format,
+      size,
+      1,
+    );
+    *self.texture_target.lock() = Some((target.clone(), format));
+    target
+  }
+
   #[inline]
   pub fn get_frame_graph(&self) -> &FrameGraph {
     &self.graph.as_ref().unwrap()
   }
 
+  /// Whether the scene is rendered into HDR targets, see `CoreConfig::hdr`.
+  pub fn is_hdr(&self) -> bool {
+    self.config.hdr
+  }
+
+  /// Format of the main scene color target that materials render into.
+  pub fn get_color_target_format(&self) -> TextureFormat {
+    self.config.color_target_format()
+  }
+
+  /// Whether block compressed textures can be sampled on the current device.
+  pub fn supports_texture_compression_bc(&self) -> bool {
+    self
+      .device
+      .features()
+      .contains(wgpu::Features::TEXTURE_COMPRESSION_BC)
+  }
+
+  /// Format of the main scene depth target that materials render into.
+  pub fn get_depth_target_format(&self) -> TextureFormat {
+    self.config.depth_format
+  }
+
+  /// Default depth mode of cameras and materials, see `CoreConfig::depth_mode`.
+  pub fn get_depth_mode(&self) -> DepthMode {
+    self.config.depth_mode
+  }
+
+  /// Buffer writes queued here are uploaded in one batch before the next frame graph level.
+  pub fn get_upload_queue(&self) -> &UploadQueue {
+    &self.upload_queue
+  }
+
   #[inline]
   pub fn get_service_locator(&self) -> &ServiceLocator {
     &self.service_locator
@@ -248,16 +623,20 @@ impl Core {
     optick::event!("Core::create_inited_buffer");
 
     // Create inited data.
-    let buffer = self
-      .device
-      .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label,
-        usage: wgpu::BufferUsage::from_bits(usage.bits()).unwrap(),
-        contents: &data,
-      });
+    let buffer = self.scoped("Core::create_inited_buffer", || {
+      self
+        .device
+        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+          label,
+          usage: wgpu::BufferUsage::from_bits(usage.bits()).unwrap(),
+          contents: &data,
+        })
+    });
 
     // Create proxy
-    self.resources.create_proxy(buffer)
+    let buffer = self.resources.create_sized_proxy(buffer, data.len() as u64);
+    self.check_memory_budget();
+    buffer
   }
 
   /// Creates a new memory buffer on the GPU.
@@ -271,15 +650,19 @@ impl Core {
     // Create raw device buffer.
     optick::event!("Core::create_buffer");
 
-    let buffer = self.device.create_buffer(&BufferDescriptor {
-      label,
-      mapped_at_creation,
-      size,
-      usage: wgpu::BufferUsage::from_bits(usage.bits()).unwrap(),
+    let buffer = self.scoped("Core::create_buffer", || {
+      self.device.create_buffer(&BufferDescriptor {
+        label,
+        mapped_at_creation,
+        size,
+        usage: wgpu::BufferUsage::from_bits(usage.bits()).unwrap(),
+      })
     });
 
     // Create proxy
-    self.resources.create_proxy(buffer)
+    let buffer = self.resources.create_sized_proxy(buffer, size);
+    self.check_memory_budget();
+    buffer
   }
 
   /// Creates a new empty texture
@@ -293,22 +676,29 @@ impl Core {
   ) -> ResourceRc<Texture> {
     // Create raw device buffer.
     optick::event!("Core::create_texture");
-    let raw = self.device.create_texture(&wgpu::TextureDescriptor {
-      label,
-      mip_level_count: mips,
-      sample_count: 1,
-      dimension: wgpu::TextureDimension::D2,
-      size: wgpu::Extent3d {
-        width: size.x,
-        height: size.y,
-        depth_or_array_layers: 1,
-      },
-      usage,
-      format,
+    let raw = self.scoped("Core::create_texture", || {
+      self.device.create_texture(&wgpu::TextureDescriptor {
+        label,
+        mip_level_count: mips,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        size: wgpu::Extent3d {
+          width: size.x,
+          height: size.y,
+          depth_or_array_layers: 1,
+        },
+        usage,
+        format,
+      })
     });
 
     // Create proxy
-    self.resources.create_proxy(raw)
+    let texture = self
+      .resources
+      .create_sized_proxy(raw, texture_byte_size(format, size.x, size.y, 1, mips))
+      .with_formats(vec![format]);
+    self.check_memory_budget();
+    texture
   }
 
   pub fn exec_with_encoder<'a, F: FnOnce(&mut CommandEncoder<'a>)>(&'a self, f: F) {
@@ -373,6 +763,62 @@ impl Core {
     self.queue.submit(std::iter::once(encoder.finish()));
   }
 
+  /// Copies the first mip of a texture created with `COPY_SRC` back and blocks until it arrived,
+  /// e.g. for screenshots or tests. Rows are tightly packed in the returned bytes.
+  pub fn read_texture(
+    &self,
+    texture: &ResourceRc<Texture>,
+    format: TextureFormat,
+    size: Vector2<u32>,
+  ) -> Vec<u8> {
+    optick::event!("Core::read_texture");
+    let row_size = size.x * format.describe().block_size as u32;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_row_size = (row_size + align - 1) / align * align;
+    let buffer = self.device.create_buffer(&BufferDescriptor {
+      label: Some("ReadTexture"),
+      size: padded_row_size as u64 * size.y as u64,
+      usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+      mapped_at_creation: false,
+    });
+
+    let mut encoder = self.device.create_command_encoder(&Default::default());
+    encoder.copy_texture_to_buffer(
+      wgpu::ImageCopyTexture {
+        texture: &*texture.get_raw(),
+        mip_level: 0,
+        origin: wgpu::Origin3d::ZERO,
+      },
+      wgpu::ImageCopyBuffer {
+        buffer: &buffer,
+        layout: wgpu::ImageDataLayout {
+          offset: 0,
+          bytes_per_row: NonZeroU32::new(padded_row_size),
+          rows_per_image: NonZeroU32::new(size.y),
+        },
+      },
+      wgpu::Extent3d {
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: 1,
+      },
+    );
+    self.queue.submit(std::iter::once(encoder.finish()));
+
+    // Strip the row padding required by the copy.
+    let slice = buffer.slice(..);
+    execute_wgpu_async(&self.device, async {
+      slice.map_async(wgpu::MapMode::Read).await.unwrap();
+    });
+    let bytes = slice
+      .get_mapped_range()
+      .chunks(padded_row_size as usize)
+      .flat_map(|row| row[..row_size as usize].iter().copied())
+      .collect::<Vec<_>>();
+    buffer.unmap();
+    bytes
+  }
+
   pub fn create_inited_sampled_texture(
     &self,
     label: Option<&str>,
@@ -433,7 +879,89 @@ impl Core {
     self.queue.submit(std::iter::once(encoder.finish()));
 
     // Create proxy
-    let texture = self.resources.create_proxy(raw);
+    let texture = self
+      .resources
+      .create_sized_proxy(raw, texture_byte_size(format, size.x, size.y, 1, mips + 1))
+      .with_formats(vec![format]);
+    self.check_memory_budget();
+
+    // Create sampling
+    let gp_resources = self.get_gp_resources();
+    let view = self.create_texture_view(texture.clone());
+    let sampler = self.create_sampler();
+    let bind_group = self.create_bind_group(
+      BindGroupDescriptor::new(gp_resources.sampled_texture_bind_group_layout.clone())
+        .add_texture_binding(0, view.clone())
+        .add_sampler_binding(1, sampler.clone()),
+    );
+
+    SampledTexture {
+      view,
+      texture,
+      sampler,
+      bind_group,
+    }
+  }
+
+  /// Creates a sampled texture from pre-compressed data, e.g. BC1/BC3/BC7 read from a DDS file.
+  /// Every entry of `mips` holds one tightly packed mip level, no mips are generated.
+  pub fn create_compressed_sampled_texture(
+    &self,
+    label: Option<&str>,
+    usage: TextureUsage,
+    format: TextureFormat,
+    size: Vector2<u32>,
+    mips: &[Vec<u8>],
+  ) -> SampledTexture {
+    optick::event!("Core::create_compressed_sampled_texture");
+    let mip_count = mips.len().max(1) as u32;
+
+    // Create empty texture, compressed formats can't be rendered to.
+    let desc = wgpu::TextureDescriptor {
+      label,
+      mip_level_count: mip_count,
+      sample_count: 1,
+      dimension: wgpu::TextureDimension::D2,
+      size: wgpu::Extent3d {
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: 1,
+      },
+      usage: wgpu::TextureUsage::COPY_DST | usage,
+      format,
+    };
+    let raw = self.device.create_texture(&desc);
+
+    // Fill every mip level with its block compressed rows.
+    let block_height = format.describe().block_dimensions.1 as u32;
+    for (mip, data) in mips.iter().enumerate() {
+      let (width, height) = texture_mip_physical_size(format, size.x, size.y, mip as u32);
+      self.queue.write_texture(
+        wgpu::ImageCopyTexture {
+          texture: &raw,
+          mip_level: mip as u32,
+          origin: wgpu::Origin3d::ZERO,
+        },
+        data,
+        wgpu::ImageDataLayout {
+          bytes_per_row: NonZeroU32::new(texture_bytes_per_row(format, width)),
+          offset: 0,
+          rows_per_image: NonZeroU32::new(height / block_height),
+        },
+        wgpu::Extent3d {
+          width,
+          height,
+          depth_or_array_layers: 1,
+        },
+      );
+    }
+
+    // Create proxy
+    let texture = self
+      .resources
+      .create_sized_proxy(raw, texture_byte_size(format, size.x, size.y, 1, mip_count))
+      .with_formats(vec![format]);
+    self.check_memory_budget();
 
     // Create sampling
     let gp_resources = self.get_gp_resources();
@@ -461,15 +989,54 @@ impl Core {
       .get_raw()
       .create_view(&wgpu::TextureViewDescriptor::default());
 
-    // Create proxy
-    self.resources.create_proxy(raw)
+    // Create proxy, views share the format of their texture.
+    let view = self.resources.create_proxy(raw);
+    match texture.get_formats() {
+      Some(formats) => view.with_formats(formats),
+      None => view,
+    }
   }
 
   /// Creates a new texture sampler.
   pub fn create_sampler(&self) -> ResourceRc<Sampler> {
+    self.create_filtered_sampler(FilterMode::Nearest)
+  }
+
+  /// Creates a new texture sampler using the given filter, linear filtering requires filterable texture bindings.
+  pub fn create_filtered_sampler(&self, filter: FilterMode) -> ResourceRc<Sampler> {
+    self.create_configured_sampler(SamplerConfig::new().with_filter(filter))
+  }
+
+  /// Creates a new texture sampler, e.g. with anisotropic filtering for surfaces seen at grazing angles.
+  /// Anisotropy is part of core wgpu so no device feature is needed.
+  pub fn create_configured_sampler(&self, mut config: SamplerConfig) -> ResourceRc<Sampler> {
+    if let Err(err) = config.validate() {
+      warn!("Invalid sampler config, anisotropy disabled: {}", err);
+      config.anisotropy_clamp = None;
+    }
+
+    let raw = self.device.create_sampler(&wgpu::SamplerDescriptor {
+      address_mode_u: config.address_mode,
+      address_mode_v: config.address_mode,
+      mag_filter: config.filter,
+      min_filter: config.filter,
+      mipmap_filter: if config.anisotropy_clamp.is_some() {
+        FilterMode::Linear
+      } else {
+        FilterMode::Nearest
+      },
+      anisotropy_clamp: config.anisotropy_clamp,
+      ..Default::default()
+    });
+    self.resources.create_proxy(raw)
+  }
+
+  /// Creates a new depth comparison sampler, used together with `ComparisonSampler` bindings.
+  pub fn create_comparison_sampler(&self) -> ResourceRc<Sampler> {
     let raw = self.device.create_sampler(&wgpu::SamplerDescriptor {
-      address_mode_u: wgpu::AddressMode::Repeat,
-      address_mode_v: wgpu::AddressMode::Repeat,
+      address_mode_u: wgpu::AddressMode::ClampToEdge,
+      address_mode_v: wgpu::AddressMode::ClampToEdge,
+      compare: Some(wgpu::CompareFunction::Less),
       ..Default::default()
     });
     self.resources.create_proxy(raw)
@@ -509,6 +1076,10 @@ impl Core {
   ) -> ResourceRc<BindGroupLayout> {
     optick::event!("Core::create_bind_group_layout");
 
+    if let Err(err) = desc.validate() {
+      warn!("Invalid bind group layout: {}", err);
+    }
+
     let entries = desc
       .entries
       .iter()
@@ -529,28 +1100,38 @@ impl Core {
           },
           BindGroupLayoutEntryType::Sampler => wgpu::BindingType::Sampler {
             comparison: false,
-            filtering: true,
+            filtering: entry.filterable,
+          },
+          BindGroupLayoutEntryType::ComparisonSampler => wgpu::BindingType::Sampler {
+            comparison: true,
+            filtering: false,
           },
           BindGroupLayoutEntryType::SingleTexture => wgpu::BindingType::Texture {
             multisampled: false,
-            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            sample_type: wgpu::TextureSampleType::Float {
+              filterable: entry.filterable,
+            },
             view_dimension: wgpu::TextureViewDimension::D2,
           },
           BindGroupLayoutEntryType::ArrayTexture(_) => wgpu::BindingType::Texture {
             multisampled: false,
-            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            sample_type: wgpu::TextureSampleType::Float {
+              filterable: entry.filterable,
+            },
             view_dimension: wgpu::TextureViewDimension::D2Array,
           },
         },
       })
       .collect::<Vec<_>>();
 
-    let raw = self
-      .device
-      .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: None,
-        entries: &entries,
-      });
+    let raw = self.scoped("Core::create_bind_group_layout", || {
+      self
+        .device
+        .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+          label: None,
+          entries: &entries,
+        })
+    });
 
     self.resources.create_proxy(raw)
   }
@@ -569,16 +1150,18 @@ impl Core {
         .map(|binding| binding.get_raw())
         .collect::<Vec<_>>();
 
-      self
-        .device
-        .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-          label: None,
-          bind_group_layouts: &bindings
-            .iter()
-            .map(|binding| &**binding)
-            .collect::<Vec<_>>(),
-          push_constant_ranges: &[],
-        })
+      self.scoped("Core::create_pipeline_layout", || {
+        self
+          .device
+          .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &bindings
+              .iter()
+              .map(|binding| &**binding)
+              .collect::<Vec<_>>(),
+            push_constant_ranges: &[],
+          })
+      })
     };
 
     self.resources.create_proxy(raw)
@@ -589,22 +1172,21 @@ impl Core {
     optick::event!("Core::create_bind_group");
 
     let raw = {
-      let views_raw = Vec::new();
-
-      // Validate to ensure unsafe block below is valid
-      std::assert!(
-        desc
-          .entries
-          .iter()
-          .filter(|(_, entry)| matches!(entry, BindGroupEntry::TextureArray(_)))
-          .collect_vec()
-          .len()
-          <= 1
-      );
+      // Texture array entries only borrow their views, so they are collected upfront to outlive the entries.
+      let array_views = desc
+        .entries
+        .iter()
+        .map(|(_, entry)| match entry {
+          BindGroupEntry::TextureArray(textures) => {
+            textures.iter().map(|t| t.get_raw()).collect::<Vec<_>>()
+          }
+          _ => Vec::new(),
+        })
+        .collect::<Vec<_>>();
 
       // Create bind group entry
       let mut wgpu_entries = Vec::with_capacity(desc.entries.len());
-      for (binding, entry) in &desc.entries {
+      for ((binding, entry), views) in desc.entries.iter().zip(array_views.iter()) {
         let raw_entry = match entry {
           BindGroupEntry::Buffer(buffer) => wgpu::BindingResource::Buffer(wgpu::BufferBinding {
             buffer: buffer.get_raw(),
@@ -612,124 +1194,151 @@ impl Core {
             size: None,
           }),
           BindGroupEntry::Texture(texture) => wgpu::BindingResource::TextureView(texture.get_raw()),
-          BindGroupEntry::TextureArray(textures) => {
-            unsafe {
-              let ptr = &views_raw as *const Vec<_>;
-              let views_raw = ptr as *mut Vec<_>;
-              (*views_raw).extend(textures.iter().map(|t| t.get_raw()));
-            }
-            wgpu::BindingResource::TextureViewArray(&views_raw)
-          }
+          BindGroupEntry::TextureArray(_) => wgpu::BindingResource::TextureViewArray(views),
           BindGroupEntry::Sampler(sampler) => wgpu::BindingResource::Sampler(sampler.get_raw()),
         };
         wgpu_entries.push((*binding, raw_entry));
       }
 
       // Bind group device
-      self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-        label: None,
-        layout: &*desc.layout.get_raw(),
-        entries: &wgpu_entries
-          .into_iter()
-          .map(|(binding, resource)| wgpu::BindGroupEntry { binding, resource })
-          .collect::<Vec<_>>(),
+      self.scoped("Core::create_bind_group", || {
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+          label: None,
+          layout: &*desc.layout.get_raw(),
+          entries: &wgpu_entries
+            .into_iter()
+            .map(|(binding, resource)| wgpu::BindGroupEntry { binding, resource })
+            .collect::<Vec<_>>(),
+        })
       })
     };
 
     self.resources.create_proxy(raw)
   }
 
-  /// Creates a new bind group.
+  /// Creates a new render pipeline, fails if multiple vertex attributes feed the same shader location.
   pub fn create_render_pipeline(
     &self,
     desc: RenderPipelineDescriptor,
-  ) -> ResourceRc<RenderPipeline> {
+  ) -> Result<ResourceRc<RenderPipeline>, VertexBufferLayoutError> {
     optick::event!("Core::create_render_pipeline");
+    validate_vertex_buffers(&desc.vertex_buffers)?;
 
     let raw = {
       let vs = desc.vertex_shader.get_raw();
       let fs = desc.fragment_shader.get_raw();
 
-      let attributes = desc.vertex_desc.clone().map(|vertex_desc| {
-        vertex_desc
-          .attributes
-          .iter()
-          .map(|attr| wgpu::VertexAttribute {
-            shader_location: attr.location as u32,
-            offset: attr.offset,
-            format: attr.format.to_wgpu(),
+      let attributes = desc
+        .vertex_buffers
+        .iter()
+        .map(|buffer| {
+          buffer
+            .layout
+            .attributes
+            .iter()
+            .map(|attr| wgpu::VertexAttribute {
+              shader_location: attr.location as u32,
+              offset: attr.offset,
+              format: attr.format.to_wgpu(),
+            })
+            .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+      let buffers = desc
+        .vertex_buffers
+        .iter()
+        .zip(attributes.iter())
+        .map(|(buffer, attributes)| wgpu::VertexBufferLayout {
+          array_stride: buffer.layout.stride,
+          step_mode: buffer.step_mode,
+          attributes,
+        })
+        .collect::<Vec<_>>();
+
+      let create = || {
+        self
+          .device
+          .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&*desc.layout.get_raw()),
+            multisample: wgpu::MultisampleState::default(),
+            vertex: wgpu::VertexState {
+              module: &*vs,
+              entry_point: "main",
+              buffers: &buffers,
+            },
+            primitive: wgpu::PrimitiveState {
+              front_face: desc.front_face,
+              cull_mode: desc.cull_mode,
+              polygon_mode: desc.polygon_mode,
+              topology: desc.topology,
+              strip_index_format: None,
+              clamp_depth: false,
+              conservative: false,
+            },
+            depth_stencil: desc.depth.as_ref().map(|depth| wgpu::DepthStencilState {
+              bias: wgpu::DepthBiasState::default(),
+              stencil: wgpu::StencilState::default(),
+              format: depth.format,
+              depth_compare: desc.depth_mode.compare(depth.compare),
+              depth_write_enabled: depth.write_enabled,
+            }),
+            fragment: Some(wgpu::FragmentState {
+              module: &*fs,
+              entry_point: "main",
+              targets: &desc
+                .outputs
+                .iter()
+                .map(|output| wgpu::ColorTargetState {
+                  format: output.format,
+                  blend: output.blend,
+                  write_mask: wgpu::ColorWrite::all(),
+                })
+                .collect::<Vec<_>>(),
+            }),
           })
-          .collect::<Vec<_>>()
-      });
+      };
+      self.scoped("Core::create_render_pipeline", create)
+    };
 
-      let vs_buffer = desc
-        .vertex_desc
-        .map(|vertex_desc| wgpu::VertexBufferLayout {
-          array_stride: vertex_desc.stride,
-          step_mode: wgpu::InputStepMode::Vertex,
-          attributes: attributes.as_ref().unwrap(),
-        });
+    let formats = desc.outputs.iter().map(|output| output.format).collect();
+    Ok(self.resources.create_proxy(raw).with_formats(formats))
+  }
 
-      let mut buffers = Vec::with_capacity(1);
-      if let Some(vs) = vs_buffer {
-        buffers.push(vs);
-      }
+  /// Creates a render pipeline or returns the one created earlier with the same key.
+  /// The key has to cover everything that ends up in the descriptor, usually the shader sources and render states.
+  pub fn create_cached_render_pipeline<F: FnOnce() -> RenderPipelineDescriptor>(
+    &self,
+    key: u64,
+    desc: F,
+  ) -> Result<ResourceRc<RenderPipeline>, VertexBufferLayoutError> {
+    self
+      .pipeline_cache
+      .get_or_try_insert_with(key, || self.create_render_pipeline(desc()))
+  }
 
-      self
-        .device
-        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-          label: None,
-          layout: Some(&*desc.layout.get_raw()),
-          multisample: wgpu::MultisampleState::default(),
-          vertex: wgpu::VertexState {
-            module: &*vs,
-            entry_point: "main",
-            buffers: &buffers,
-          },
-          primitive: wgpu::PrimitiveState {
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            polygon_mode: wgpu::PolygonMode::Fill,
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            clamp_depth: false,
-            conservative: false,
-          },
-          depth_stencil: desc.depth.map(|depth| wgpu::DepthStencilState {
-            bias: wgpu::DepthBiasState::default(),
-            stencil: wgpu::StencilState::default(),
-            format: depth,
-            depth_compare: wgpu::CompareFunction::Less,
-            depth_write_enabled: true,
-          }),
-          fragment: Some(wgpu::FragmentState {
-            module: &*fs,
-            entry_point: "main",
-            targets: &desc
-              .outputs
-              .iter()
-              .map(|output| wgpu::ColorTargetState {
-                format: output.format,
-                blend: Some(wgpu::BlendState {
-                  color: wgpu::BlendComponent {
-                    src_factor: wgpu::BlendFactor::SrcAlpha,
-                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                    operation: wgpu::BlendOperation::Add,
-                  },
-                  alpha: wgpu::BlendComponent {
-                    src_factor: wgpu::BlendFactor::SrcAlpha,
-                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                    operation: wgpu::BlendOperation::Add,
-                  },
-                }),
-                write_mask: wgpu::ColorWrite::all(),
-              })
-              .collect::<Vec<_>>(),
-          }),
-        })
-    };
+  /// Cache of all pipelines created through `create_cached_render_pipeline`.
+  pub fn get_pipeline_cache(&self) -> &ResourceCache<ResourceRc<RenderPipeline>> {
+    &self.pipeline_cache
+  }
 
-    self.resources.create_proxy(raw)
+  /// Like `create_shader_from_glsl` but identical sources share the same shader module.
+  pub fn create_cached_shader_from_glsl(
+    &self,
+    source: &str,
+    name: &str,
+    kind: ShaderKind,
+  ) -> Result<ResourceRc<Shader>, ShaderError> {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    source.hash(&mut hasher);
+    (kind as u32).hash(&mut hasher);
+
+    self
+      .shader_cache
+      .get_or_try_insert_with(hasher.finish(), || {
+        self.create_shader_from_glsl(source, name, kind)
+      })
   }
 
   /// Creates a raw shader from vulkan compatible glsl.
@@ -740,24 +1349,10 @@ impl Core {
     kind: ShaderKind,
   ) -> Result<ResourceRc<Shader>, ShaderError> {
     optick::event!("Core::create_shader");
-    let mut compiler = Compiler::new().unwrap();
-
-    //println!("=============\n{}\n=========\n", source);
-    // Compile to spir-v
-    let spirv = compiler
-      .compile_into_spirv(source, kind, name, "main", None)
-      .map_err(|err| ShaderError::SpirVCompilationFailed(err.to_string(), source.to_string()))?;
-
-    if spirv.get_num_warnings() > 0 {
-      warn!(
-        "Shader compilation warning: {}",
-        spirv.get_warning_messages()
-      );
-    }
-    let spirv = spirv.as_binary_u8().to_vec();
+    let spirv = compile_glsl(source, name, kind)?;
 
     // Create raw resource
-    let module = {
+    let module = self.scoped("Core::create_shader_from_glsl", || {
       self
         .device
         .create_shader_module(&wgpu::ShaderModuleDescriptor {
@@ -765,13 +1360,32 @@ impl Core {
           source: wgpu::util::make_spirv(&spirv),
           flags: wgpu::ShaderFlags::empty(),
         })
-    };
+    });
 
     // Create proxy
     Ok(self.resources.create_proxy(module))
   }
 }
 
+/// Compiles vulkan compatible glsl to spir-v without requiring a device.
+pub fn compile_glsl(source: &str, name: &str, kind: ShaderKind) -> Result<Vec<u8>, ShaderError> {
+  let mut compiler = Compiler::new().unwrap();
+
+  //println!("=============\n{}\n=========\n", source);
+  // Compile to spir-v
+  let spirv = compiler
+    .compile_into_spirv(source, kind, name, "main", None)
+    .map_err(|err| ShaderError::SpirVCompilationFailed(err.to_string(), source.to_string()))?;
+
+  if spirv.get_num_warnings() > 0 {
+    warn!(
+      "Shader compilation warning: {}",
+      spirv.get_warning_messages()
+    );
+  }
+  Ok(spirv.as_binary_u8().to_vec())
+}
+
 pub struct GPResources {
   /// A bind group used for simple single texture binding.
   pub sampled_texture_bind_group_layout: ResourceRc<BindGroupLayout>,
@@ -798,12 +1412,47 @@ impl GPResources {
   }
 }
 
+/// How a frame reacts to a failure to acquire the next swap chain frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapChainRecovery {
+  /// The swap chain has to be recreated before retrying, e.g. after a resize.
+  Recreate,
+  /// The frame is skipped and the next one tries again.
+  Skip,
+  /// The error can't be recovered from.
+  Fail,
+}
+
+pub fn swap_chain_recovery(err: &SwapChainError) -> SwapChainRecovery {
+  match err {
+    SwapChainError::Outdated | SwapChainError::Lost => SwapChainRecovery::Recreate,
+    SwapChainError::Timeout => SwapChainRecovery::Skip,
+    SwapChainError::OutOfMemory => SwapChainRecovery::Fail,
+  }
+}
+
 #[derive(Error, Debug)]
 pub enum ShaderError {
   #[error("Failed to compile glsl shader to spir-v: {0}\n\nCode: {1}")]
   SpirVCompilationFailed(String, String),
 }
 
+impl ShaderError {
+  /// Compiler output without the source, e.g. to show it within an editor.
+  pub fn message(&self) -> &str {
+    match self {
+      ShaderError::SpirVCompilationFailed(message, _) => message,
+    }
+  }
+
+  /// Generated source code that failed to compile.
+  pub fn source_code(&self) -> &str {
+    match self {
+      ShaderError::SpirVCompilationFailed(_, source) => source,
+    }
+  }
+}
+
 pub enum TaskKind {
   Background,
 }
@@ -834,10 +1483,19 @@ impl OnceInFrame {
   }
 
   pub fn once<F: Fn()>(&self, f: F) {
-    let last_execution = self.last_execution.load(Ordering::Relaxed);
-    let current = CURRENT_FRAME.load(Ordering::Relaxed);
-    if current > last_execution {
-      self.last_execution.store(last_execution, Ordering::Relaxed);
+    // Frames are stored offset by one so that zero means never executed.
+    let current = CURRENT_FRAME.load(Ordering::Acquire) + 1;
+    let last_execution = self.last_execution.load(Ordering::Acquire);
+    if current <= last_execution {
+      return;
+    }
+
+    // Only the caller that wins the exchange is allowed to execute.
+    if self
+      .last_execution
+      .compare_exchange(last_execution, current, Ordering::AcqRel, Ordering::Acquire)
+      .is_ok()
+    {
       f();
     }
   }
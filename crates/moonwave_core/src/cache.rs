@@ -0,0 +1,67 @@
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Shares resources between all users that request them with the same key,
+/// e.g. pipelines of materials that compile to identical shader source.
+pub struct ResourceCache<T: Clone> {
+  entries: Mutex<HashMap<u64, T>>,
+  hits: AtomicUsize,
+}
+
+impl<T: Clone> ResourceCache<T> {
+  pub fn new() -> Self {
+    Self {
+      entries: Mutex::new(HashMap::new()),
+      hits: AtomicUsize::new(0),
+    }
+  }
+
+  /// Returns the cached resource or creates and caches a new one.
+  pub fn get_or_insert_with<F: FnOnce() -> T>(&self, key: u64, f: F) -> T {
+    let result: Result<T, ()> = self.get_or_try_insert_with(key, || Ok(f()));
+    result.ok().unwrap()
+  }
+
+  /// Like `get_or_insert_with` but failed creations are not cached.
+  pub fn get_or_try_insert_with<E, F: FnOnce() -> Result<T, E>>(
+    &self,
+    key: u64,
+    f: F,
+  ) -> Result<T, E> {
+    let mut entries = self.entries.lock();
+    if let Some(entry) = entries.get(&key) {
+      self.hits.fetch_add(1, Ordering::Relaxed);
+      return Ok(entry.clone());
+    }
+
+    let entry = f()?;
+    entries.insert(key, entry.clone());
+    Ok(entry)
+  }
+
+  /// Amount of distinct resources in the cache.
+  pub fn len(&self) -> usize {
+    self.entries.lock().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.lock().is_empty()
+  }
+
+  /// How many requests have been served by an already cached resource.
+  pub fn hits(&self) -> usize {
+    self.hits.load(Ordering::Relaxed)
+  }
+
+  /// Drops all cached resources, resources still in use stay alive.
+  pub fn clear(&self) {
+    self.entries.lock().clear();
+  }
+}
+
+impl<T: Clone> Default for ResourceCache<T> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
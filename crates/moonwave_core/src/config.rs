@@ -0,0 +1,281 @@
+use log::warn;
+use moonwave_common::Vector2;
+use moonwave_resources::DepthMode;
+use thiserror::Error;
+
+use crate::{BackgroundPanicHandler, BackgroundTaskPanic, LogFilter};
+
+/// Configuration used when initializing the core.
+#[derive(Debug, Clone)]
+pub struct CoreConfig {
+  /// Wraps resource creation and frame submission in wgpu error scopes and logs captured errors.
+  pub error_scopes: bool,
+  /// Initial state of the wireframe debug mode, see `Core::set_wireframe`.
+  pub wireframe: bool,
+  /// Soft limit in bytes for the estimated GPU memory usage, exceeding it logs a warning.
+  pub gpu_memory_budget: Option<u64>,
+  /// Features and limits requested when creating the render device.
+  pub device: DeviceConfig,
+  /// Called for every panicking background task after it has been logged.
+  pub background_panic_handler: Option<BackgroundPanicHandler>,
+  /// Renders the scene into `Rgba16Float` targets that are tonemapped when presented.
+  pub hdr: bool,
+  /// Size of the offscreen target when initialized through `Core::initialize_headless`.
+  pub headless_size: Vector2<u32>,
+  /// Log levels per target, the `MOONWAVE_LOG` environment variable is applied on top.
+  pub log_filter: LogFilter,
+  /// Presentation of the swap chain, trades latency against tearing and can be changed at
+  /// runtime through `Core::set_present_mode`.
+  pub present_mode: wgpu::PresentMode,
+  /// Format of the main scene depth target.
+  pub depth_format: wgpu::TextureFormat,
+  /// Depth mode cameras and materials use unless they override it,
+  /// reverse-Z greatly improves the depth precision of distant geometry.
+  pub depth_mode: DepthMode,
+}
+
+impl Default for CoreConfig {
+  fn default() -> Self {
+    Self {
+      error_scopes: false,
+      wireframe: false,
+      gpu_memory_budget: None,
+      device: DeviceConfig::default(),
+      background_panic_handler: None,
+      hdr: false,
+      headless_size: Vector2::new(1280, 720),
+      log_filter: LogFilter::default(),
+      present_mode: wgpu::PresentMode::Mailbox,
+      depth_format: wgpu::TextureFormat::Depth32Float,
+      depth_mode: DepthMode::Standard,
+    }
+  }
+}
+
+impl CoreConfig {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_error_scopes(mut self, enabled: bool) -> Self {
+    self.error_scopes = enabled;
+    self
+  }
+
+  pub fn with_wireframe(mut self, enabled: bool) -> Self {
+    self.wireframe = enabled;
+    self
+  }
+
+  pub fn with_gpu_memory_budget(mut self, bytes: u64) -> Self {
+    self.gpu_memory_budget = Some(bytes);
+    self
+  }
+
+  pub fn with_hdr(mut self, enabled: bool) -> Self {
+    self.hdr = enabled;
+    self
+  }
+
+  pub fn with_headless_size(mut self, size: Vector2<u32>) -> Self {
+    self.headless_size = size;
+    self
+  }
+
+  pub fn with_log_filter(mut self, filter: LogFilter) -> Self {
+    self.log_filter = filter;
+    self
+  }
+
+  pub fn with_present_mode(mut self, mode: wgpu::PresentMode) -> Self {
+    self.present_mode = mode;
+    self
+  }
+
+  pub fn with_depth_format(mut self, format: wgpu::TextureFormat) -> Self {
+    self.depth_format = format;
+    self
+  }
+
+  pub fn with_depth_mode(mut self, mode: DepthMode) -> Self {
+    self.depth_mode = mode;
+    self
+  }
+
+  /// Format of the main scene color target, the swap chain itself always stays sRGB.
+  pub fn color_target_format(&self) -> wgpu::TextureFormat {
+    if self.hdr {
+      wgpu::TextureFormat::Rgba16Float
+    } else {
+      wgpu::TextureFormat::Bgra8UnormSrgb
+    }
+  }
+
+  pub fn with_device(mut self, device: DeviceConfig) -> Self {
+    self.device = device;
+    self
+  }
+
+  pub fn with_background_panic_handler<F: Fn(&BackgroundTaskPanic) + Send + Sync + 'static>(
+    mut self,
+    handler: F,
+  ) -> Self {
+    self.background_panic_handler = Some(BackgroundPanicHandler::new(handler));
+    self
+  }
+}
+
+/// Device features and limits requested from the adapter.
+/// Features are enabled where supported by default, those enabled through one of the `with_*`
+/// builders are required and fail device creation if the adapter lacks them.
+/// Anisotropic filtering needs no device feature with the used wgpu version, see `SamplerConfig`.
+#[derive(Debug, Clone)]
+pub struct DeviceConfig {
+  /// Allows drawing polygons as lines, required by the wireframe mode.
+  pub polygon_mode_line: bool,
+  /// Allows binding arrays of sampled textures, required by texture arrays.
+  pub texture_binding_array: bool,
+  /// Timestamp queries used for GPU timings of frame graph nodes.
+  pub timestamp_query: bool,
+  /// Allows sampling BC1-BC7 compressed textures.
+  pub texture_compression_bc: bool,
+  /// Allows issuing many indirect draws with a single call, see `multi_draw_indexed_indirect`.
+  pub multi_draw_indirect: bool,
+  /// Maximum amount of bind groups a single pipeline can use.
+  pub max_bind_groups: u32,
+  /// Maximum amount of textures within a single texture array binding.
+  pub max_texture_array_size: u32,
+  /// Features that have to be supported, see `DeviceConfig::features`.
+  pub required_features: wgpu::Features,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum DeviceConfigError {
+  #[error("Required device features are not supported: {0:?}")]
+  UnsupportedFeatures(wgpu::Features),
+}
+
+impl Default for DeviceConfig {
+  fn default() -> Self {
+    Self {
+      polygon_mode_line: true,
+      texture_binding_array: true,
+      timestamp_query: true,
+      texture_compression_bc: true,
+      multi_draw_indirect: true,
+      max_bind_groups: wgpu::Limits::default().max_bind_groups,
+      max_texture_array_size: 128,
+      required_features: wgpu::Features::empty(),
+    }
+  }
+}
+
+impl DeviceConfig {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_polygon_mode_line(mut self, enabled: bool) -> Self {
+    self.polygon_mode_line = enabled;
+    self.require(wgpu::Features::NON_FILL_POLYGON_MODE, enabled)
+  }
+
+  pub fn with_texture_binding_array(mut self, enabled: bool) -> Self {
+    self.texture_binding_array = enabled;
+    self.require(wgpu::Features::SAMPLED_TEXTURE_BINDING_ARRAY, enabled)
+  }
+
+  pub fn with_timestamp_query(mut self, enabled: bool) -> Self {
+    self.timestamp_query = enabled;
+    self.require(wgpu::Features::TIMESTAMP_QUERY, enabled)
+  }
+
+  pub fn with_texture_compression_bc(mut self, enabled: bool) -> Self {
+    self.texture_compression_bc = enabled;
+    self.require(wgpu::Features::TEXTURE_COMPRESSION_BC, enabled)
+  }
+
+  pub fn with_multi_draw_indirect(mut self, enabled: bool) -> Self {
+    self.multi_draw_indirect = enabled;
+    self.require(wgpu::Features::MULTI_DRAW_INDIRECT, enabled)
+  }
+
+  fn require(mut self, feature: wgpu::Features, required: bool) -> Self {
+    self.required_features.set(feature, required);
+    self
+  }
+
+  pub fn with_max_bind_groups(mut self, max: u32) -> Self {
+    self.max_bind_groups = max;
+    self
+  }
+
+  pub fn with_max_texture_array_size(mut self, max: u32) -> Self {
+    self.max_texture_array_size = max;
+    self
+  }
+
+  /// Features to request, optional ones not supported by the adapter are dropped with a warning.
+  /// Fails if a required feature is not supported.
+  pub fn features(&self, supported: wgpu::Features) -> Result<wgpu::Features, DeviceConfigError> {
+    let mut requested = wgpu::Features::empty();
+    requested.set(
+      wgpu::Features::NON_FILL_POLYGON_MODE,
+      self.polygon_mode_line,
+    );
+    requested.set(
+      wgpu::Features::SAMPLED_TEXTURE_BINDING_ARRAY,
+      self.texture_binding_array,
+    );
+    requested.set(wgpu::Features::TIMESTAMP_QUERY, self.timestamp_query);
+    requested.set(
+      wgpu::Features::TEXTURE_COMPRESSION_BC,
+      self.texture_compression_bc,
+    );
+    requested.set(
+      wgpu::Features::MULTI_DRAW_INDIRECT,
+      self.multi_draw_indirect,
+    );
+
+    let missing = (requested & self.required_features) - supported;
+    if !missing.is_empty() {
+      return Err(DeviceConfigError::UnsupportedFeatures(missing));
+    }
+
+    let unsupported = requested - supported;
+    if !unsupported.is_empty() {
+      warn!(
+        "Requested device features are not supported: {:?}",
+        unsupported
+      );
+    }
+    Ok(requested & supported)
+  }
+
+  /// Limits to request, clamped to what the adapter supports.
+  pub fn limits(&self, supported: &wgpu::Limits) -> wgpu::Limits {
+    let clamp = |name: &str, requested: u32, supported: u32| {
+      if requested > supported {
+        warn!(
+          "Requested {} of {} exceeds the supported {}",
+          name, requested, supported
+        );
+      }
+      requested.min(supported)
+    };
+
+    wgpu::Limits {
+      max_bind_groups: clamp(
+        "max bind groups",
+        self.max_bind_groups,
+        supported.max_bind_groups,
+      ),
+      max_sampled_textures_per_shader_stage: clamp(
+        "max texture array size",
+        self.max_texture_array_size,
+        supported.max_sampled_textures_per_shader_stage,
+      ),
+      ..wgpu::Limits::default()
+    }
+  }
+}
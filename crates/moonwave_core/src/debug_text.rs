@@ -0,0 +1,388 @@
+//! Minimal immediate mode text rendering for debug prints, independent of any UI.
+//!
+//! Texts queued through [`Core::debug_text`] are only drawn for the current frame, they are
+//! rendered as one quad per glyph from a monospace atlas and the queue is cleared afterwards.
+
+use std::sync::Arc;
+
+use ab_glyph::{point, Font, FontRef, ScaleFont};
+use moonwave_common::*;
+use moonwave_render::{
+  CommandEncoder, FrameGraph, FrameGraphNode, FrameNodeValue, RenderPassCommandEncoderBuilder,
+};
+use moonwave_resources::*;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use shaderc::ShaderKind;
+
+use crate::{Core, PresentToScreen, TextureGeneratorHost, TextureGeneratorNode, TextureSize};
+
+/// Monospace font used for debug texts.
+pub const DEBUG_TEXT_FONT: &[u8] = include_bytes!("./FiraMono-Medium.ttf");
+
+/// Size of a single glyph cell in pixels.
+pub const DEBUG_TEXT_GLYPH_WIDTH: u32 = 9;
+pub const DEBUG_TEXT_GLYPH_HEIGHT: u32 = 16;
+
+/// Pixels around each glyph cell of the atlas, glyphs may draw into it beyond their cell,
+/// e.g. antialiased edges of wide characters.
+pub const DEBUG_TEXT_GLYPH_PADDING: u32 = 2;
+
+/// Vertices generated for every visible glyph.
+pub const DEBUG_TEXT_VERTICES_PER_GLYPH: usize = 6;
+
+// Printable ascii characters are laid out in rows of the atlas.
+const ATLAS_FIRST_CHAR: char = ' ';
+const ATLAS_LAST_CHAR: char = '~';
+const ATLAS_COLUMNS: u32 = 16;
+const ATLAS_ROWS: u32 = (ATLAS_LAST_CHAR as u32 - ATLAS_FIRST_CHAR as u32) / ATLAS_COLUMNS + 1;
+
+static DEBUG_TEXT_RESOURCES: OnceCell<DebugTextResources> = OnceCell::new();
+
+/// A single line of debug text, position is the top left corner in pixels.
+#[derive(Debug, Clone)]
+pub struct DebugText {
+  pub text: String,
+  pub position: Vector2<f32>,
+  pub color: Vector4<f32>,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct DebugTextVertex {
+  pub position: [f32; 2],
+  pub uv: [f32; 2],
+  pub color: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for DebugTextVertex {}
+unsafe impl bytemuck::Zeroable for DebugTextVertex {}
+
+/// Texts queued for the current frame.
+#[derive(Default)]
+pub struct DebugTextQueue {
+  texts: Mutex<Vec<DebugText>>,
+}
+
+impl DebugTextQueue {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn push(&self, text: &str, position: Vector2<f32>, color: Vector4<f32>) {
+    self.texts.lock().push(DebugText {
+      text: text.to_string(),
+      position,
+      color,
+    });
+  }
+
+  pub fn len(&self) -> usize {
+    self.texts.lock().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.texts.lock().is_empty()
+  }
+
+  /// Removes all queued texts returning them.
+  pub fn take(&self) -> Vec<DebugText> {
+    std::mem::take(&mut *self.texts.lock())
+  }
+}
+
+/// Atlas cell of a character, characters outside of the atlas are shown as `?`.
+fn atlas_cell(c: char) -> (u32, u32) {
+  let c = if (ATLAS_FIRST_CHAR..=ATLAS_LAST_CHAR).contains(&c) {
+    c
+  } else {
+    '?'
+  };
+  let index = c as u32 - ATLAS_FIRST_CHAR as u32;
+  (index % ATLAS_COLUMNS, index / ATLAS_COLUMNS)
+}
+
+/// Builds the glyph quads of all texts in clip space for a screen of the given size.
+/// Whitespaces only advance the cursor and new lines start below the text's position.
+pub fn build_debug_text_vertices(
+  texts: &[DebugText],
+  screen_size: Vector2<f32>,
+) -> Vec<DebugTextVertex> {
+  let glyph = Vector2::new(
+    DEBUG_TEXT_GLYPH_WIDTH as f32,
+    DEBUG_TEXT_GLYPH_HEIGHT as f32,
+  );
+  let padding = Vector2::new(
+    DEBUG_TEXT_GLYPH_PADDING as f32,
+    DEBUG_TEXT_GLYPH_PADDING as f32,
+  );
+  let to_clip = |p: Vector2<f32>| {
+    [
+      p.x / screen_size.x * 2.0 - 1.0,
+      1.0 - p.y / screen_size.y * 2.0,
+    ]
+  };
+
+  let mut vertices = Vec::new();
+  for text in texts {
+    let color = [text.color.x, text.color.y, text.color.z, text.color.w];
+    let mut cursor = text.position;
+    for c in text.text.chars() {
+      if c == '\n' {
+        cursor = Vector2::new(text.position.x, cursor.y + glyph.y);
+        continue;
+      }
+      if c.is_whitespace() {
+        cursor.x += glyph.x;
+        continue;
+      }
+
+      let (column, row) = atlas_cell(c);
+      let uv_min = Vector2::new(
+        column as f32 / ATLAS_COLUMNS as f32,
+        row as f32 / ATLAS_ROWS as f32,
+      );
+      let uv_max = uv_min + Vector2::new(1.0 / ATLAS_COLUMNS as f32, 1.0 / ATLAS_ROWS as f32);
+      // Quads cover the padded atlas cell so overhanging glyphs are drawn completely.
+      let min = cursor - padding;
+      let max = cursor + glyph + padding;
+      let corners = [
+        (min, uv_min),
+        (Vector2::new(max.x, min.y), Vector2::new(uv_max.x, uv_min.y)),
+        (max, uv_max),
+        (Vector2::new(min.x, max.y), Vector2::new(uv_min.x, uv_max.y)),
+      ];
+      for index in [0, 1, 2, 0, 2, 3].iter() {
+        let (position, uv) = corners[*index];
+        vertices.push(DebugTextVertex {
+          position: to_clip(position),
+          uv: uv.into(),
+          color,
+        });
+      }
+
+      cursor.x += glyph.x;
+    }
+  }
+  vertices
+}
+
+/// Size of a glyph cell within the atlas including its padding.
+#[doc(hidden)]
+pub fn debug_text_atlas_cell_size() -> Vector2<u32> {
+  Vector2::new(
+    DEBUG_TEXT_GLYPH_WIDTH + 2 * DEBUG_TEXT_GLYPH_PADDING,
+    DEBUG_TEXT_GLYPH_HEIGHT + 2 * DEBUG_TEXT_GLYPH_PADDING,
+  )
+}
+
+/// Rasterizes all printable ascii characters into a white rgba atlas with coverage as alpha.
+#[doc(hidden)]
+pub fn rasterize_debug_text_atlas() -> (Vec<u8>, Vector2<u32>) {
+  let cell = debug_text_atlas_cell_size();
+  let size = Vector2::new(ATLAS_COLUMNS * cell.x, ATLAS_ROWS * cell.y);
+  let mut pixels = vec![0u8; (size.x * size.y * 4) as usize];
+
+  let font = FontRef::try_from_slice(DEBUG_TEXT_FONT).unwrap();
+  let scaled = font.as_scaled(DEBUG_TEXT_GLYPH_HEIGHT as f32);
+  for c in ATLAS_FIRST_CHAR..=ATLAS_LAST_CHAR {
+    let (column, row) = atlas_cell(c);
+    let origin = (column * cell.x, row * cell.y);
+
+    let mut glyph = scaled.scaled_glyph(c);
+    glyph.position = point(
+      (origin.0 + DEBUG_TEXT_GLYPH_PADDING) as f32,
+      (origin.1 + DEBUG_TEXT_GLYPH_PADDING) as f32 + scaled.ascent(),
+    );
+    if let Some(outlined) = font.outline_glyph(glyph) {
+      let bounds = outlined.px_bounds();
+      outlined.draw(|x, y, coverage| {
+        // Clip to the glyph's padded cell so neighbours don't bleed into each other.
+        let x = bounds.min.x as i32 + x as i32;
+        let y = bounds.min.y as i32 + y as i32;
+        let inside_x = x >= origin.0 as i32 && x < (origin.0 + cell.x) as i32;
+        let inside_y = y >= origin.1 as i32 && y < (origin.1 + cell.y) as i32;
+        if inside_x && inside_y {
+          let offset = ((y as u32 * size.x + x as u32) * 4) as usize;
+          pixels[offset..offset + 4].copy_from_slice(&[255, 255, 255, (coverage * 255.0) as u8]);
+        }
+      });
+    }
+  }
+
+  (pixels, size)
+}
+
+struct DebugTextResources {
+  host: Arc<TextureGeneratorHost>,
+  atlas: SampledTexture,
+  pipeline: ResourceRc<RenderPipeline>,
+}
+
+impl DebugTextResources {
+  fn new() -> Self {
+    let core = Core::get_instance();
+    let vs = core
+      .create_shader_from_glsl(
+        include_str!("./debug_text.vert"),
+        "DebugTextVS",
+        ShaderKind::Vertex,
+      )
+      .unwrap();
+    let fs = core
+      .create_shader_from_glsl(
+        include_str!("./debug_text.frag"),
+        "DebugTextFS",
+        ShaderKind::Fragment,
+      )
+      .unwrap();
+
+    let (pixels, size) = rasterize_debug_text_atlas();
+    let atlas = core.create_inited_sampled_texture(
+      Some("DebugTextAtlas"),
+      TextureUsage::SAMPLED,
+      TextureFormat::Rgba8Unorm,
+      size,
+      &pixels,
+      size.x as usize * 4,
+    );
+
+    let layout = core.create_pipeline_layout(
+      PipelineLayoutDescriptor::new().add_binding(
+        core
+          .get_gp_resources()
+          .sampled_texture_bind_group_layout
+          .clone(),
+      ),
+    );
+    let vertex_desc = VertexBuffer {
+      stride: std::mem::size_of::<DebugTextVertex>() as u64,
+      attributes: vec![
+        VertexAttribute {
+          name: "position".to_string(),
+          offset: 0,
+          location: 0,
+          format: VertexAttributeFormat::Float2,
+        },
+        VertexAttribute {
+          name: "uv".to_string(),
+          offset: 4 * 2,
+          location: 1,
+          format: VertexAttributeFormat::Float2,
+        },
+        VertexAttribute {
+          name: "color".to_string(),
+          offset: 4 * 4,
+          location: 2,
+          format: VertexAttributeFormat::Float4,
+        },
+      ],
+    };
+    let pipeline = core
+      .create_render_pipeline(
+        RenderPipelineDescriptor::new(layout, vertex_desc, vs, fs)
+          .add_color_output(TextureFormat::Bgra8UnormSrgb),
+      )
+      .unwrap();
+
+    Self {
+      host: TextureGeneratorHost::new(TextureSize::FullScreen, TextureFormat::Bgra8UnormSrgb),
+      atlas,
+      pipeline,
+    }
+  }
+}
+
+/// Pipeline and glyph atlas bind group of debug texts, used to draw `build_debug_text_vertices`
+/// within other render passes into `Bgra8UnormSrgb` targets, e.g. labels of the UI.
+pub fn debug_text_pipeline() -> (ResourceRc<RenderPipeline>, ResourceRc<BindGroup>) {
+  let resources = DEBUG_TEXT_RESOURCES.get_or_init(DebugTextResources::new);
+  (
+    resources.pipeline.clone(),
+    resources.atlas.bind_group.clone(),
+  )
+}
+
+/// Adds the glyph pass for all queued texts to the frame graph and clears the queue.
+pub(crate) fn add_debug_text_pass(core: &Core, graph: &FrameGraph, queue: &DebugTextQueue) {
+  let texts = queue.take();
+  if texts.is_empty() {
+    return;
+  }
+
+  optick::event!("Core::debug_text");
+  let resources = DEBUG_TEXT_RESOURCES.get_or_init(DebugTextResources::new);
+  let screen_size = core.get_swap_chain_size().cast::<f32>().unwrap();
+  let vertices = build_debug_text_vertices(&texts, screen_size);
+  let vertex_buffer = core.create_inited_buffer(
+    bytemuck::cast_slice(&vertices).to_vec().into_boxed_slice(),
+    BufferUsage::VERTEX,
+    Some("DebugText"),
+  );
+
+  let texture = graph.add_node(resources.host.create_node(), "DebugTextTextureHost");
+  let node = graph.add_node(
+    DebugTextNode {
+      vertex_buffer,
+      vertices: vertices.len() as u32,
+      atlas: resources.atlas.bind_group.clone(),
+      pipeline: resources.pipeline.clone(),
+    },
+    "DebugText",
+  );
+  graph
+    .connect(
+      texture,
+      TextureGeneratorNode::OUTPUT_TEXTURE,
+      node,
+      DebugTextNode::INPUT_TEXTURE,
+    )
+    .unwrap();
+  graph
+    .connect(
+      node,
+      DebugTextNode::OUTPUT_TEXTURE,
+      graph.get_end_node(),
+      PresentToScreen::INPUT_TEXTURE_DEBUG_TEXT,
+    )
+    .unwrap();
+}
+
+struct DebugTextNode {
+  vertex_buffer: ResourceRc<Buffer>,
+  vertices: u32,
+  atlas: ResourceRc<BindGroup>,
+  pipeline: ResourceRc<RenderPipeline>,
+}
+
+impl DebugTextNode {
+  const INPUT_TEXTURE: usize = 0;
+  const OUTPUT_TEXTURE: usize = 0;
+}
+
+impl FrameGraphNode for DebugTextNode {
+  fn execute(
+    &self,
+    inputs: &[Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
+    encoder: &mut CommandEncoder,
+  ) {
+    optick::event!("FrameGraph::DebugText");
+
+    let texture = inputs[Self::INPUT_TEXTURE].as_ref().unwrap();
+
+    let mut rp_builder = RenderPassCommandEncoderBuilder::new("DebugTextRenderPass");
+    rp_builder.add_color_output(
+      &texture.get_sampled_texture().view,
+      Vector4::new(0.0, 0.0, 0.0, 0.0),
+    );
+
+    let mut rp = encoder.create_render_pass_encoder(rp_builder);
+    rp.set_pipeline(self.pipeline.clone());
+    rp.set_vertex_buffer(self.vertex_buffer.clone());
+    rp.set_bind_group(0, self.atlas.clone());
+    rp.render(0..self.vertices);
+
+    outputs[Self::OUTPUT_TEXTURE] = Some(texture.clone());
+  }
+}
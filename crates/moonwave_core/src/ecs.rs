@@ -4,36 +4,52 @@ use futures::{executor::block_on, future::join_all, Future};
 use itertools::Itertools;
 use legion::*;
 use legion::{query::EntityFilter, World as LegionWorld};
-pub use legion::{system, Entity};
-use log::debug;
+pub use legion::{system, systems::SystemId, Entity};
+use log::{debug, warn};
 use once_cell::sync::OnceCell;
 use owning_ref::{OwningRef, OwningRefMut};
 use parking_lot::{Mutex, RwLock};
 use rayon::ThreadPool;
 use send_wrapper::SendWrapper;
 use std::{
+  any::{Any, TypeId},
   marker::PhantomData,
   pin::Pin,
   sync::{
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
     Arc, Weak,
   },
+  task::{Context, Poll, Waker},
+  time::{Duration, Instant},
 };
 
+/// Default amount of event iterations handled per tick.
+pub const DEFAULT_MAX_EVENT_ITERATIONS: usize = 64;
+
 pub struct World {
   /// Reference to legions ecs world.
   pub(crate) world: LegionWorld,
   /// All system factories that are evaluated when a new system is added or an old is removed.
-  systems: RwLock<Vec<(usize, Box<dyn SystemFactory>)>>,
+  /// Sorted by stage and priority within the stage.
+  systems: RwLock<Vec<(usize, i32, bool, Box<dyn SystemFactory>)>>,
   systems_dirty: AtomicBool,
   /// Built system schedulers for each stage.
-  built_systems: RwLock<Vec<SendWrapper<Schedule>>>,
+  /// Schedules in execution order, flagged if they only contain thread local systems.
+  built_systems: RwLock<Vec<(bool, SendWrapper<Schedule>)>>,
   /// Temporary systems that are always executed just once.
   temp_systems: Mutex<Vec<Box<dyn ParallelRunnable>>>,
   /// Temporary systems that are always executed just once.
   event_systems: Mutex<Vec<Box<dyn ParallelRunnable>>>,
-  /// Command buffers that are waiting to be executed.
-  command_buffers: Mutex<Vec<(CommandBuffer, Option<Arc<ActorInnerRef>>)>>,
+  /// Coalesced events and messages per type, each batch is delivered by a single system.
+  event_batches: Mutex<Vec<(TypeId, Box<dyn PendingEventBatch>)>>,
+  /// Event queries waiting to be resolved once their event has been handled.
+  event_queries: Mutex<Vec<Arc<dyn PendingEventQuery>>>,
+  /// Maximum amount of event iterations per tick, see `World::set_max_event_iterations`.
+  max_event_iterations: AtomicUsize,
+  /// Execution time of each world system during the last tick.
+  system_timings: Arc<Mutex<Vec<(SystemId, Duration)>>>,
+  /// Command buffers that are waiting to be executed together with their priority.
+  command_buffers: Mutex<Vec<(usize, CommandBuffer, Option<Arc<ActorInnerRef>>)>>,
 }
 
 impl World {
@@ -48,11 +64,33 @@ impl World {
       world,
       systems: RwLock::new(Vec::new()),
       event_systems: Mutex::new(Vec::with_capacity(128)),
+      event_batches: Mutex::new(Vec::new()),
+      event_queries: Mutex::new(Vec::new()),
+      max_event_iterations: AtomicUsize::new(DEFAULT_MAX_EVENT_ITERATIONS),
+      system_timings: Arc::new(Mutex::new(Vec::new())),
       temp_systems: Mutex::new(Vec::with_capacity(128)),
       command_buffers: Mutex::new(Vec::with_capacity(128)),
     }
   }
 
+  /// Limits how often events published while handling events are drained within a single tick.
+  /// Events exceeding the limit are handled in the next tick instead of stalling the frame.
+  pub fn set_max_event_iterations(&self, max: usize) {
+    self
+      .max_event_iterations
+      .store(max.max(1), Ordering::Relaxed);
+  }
+
+  pub fn get_max_event_iterations(&self) -> usize {
+    self.max_event_iterations.load(Ordering::Relaxed)
+  }
+
+  /// Systems that ran during the last tick together with their execution time.
+  /// Only registered world systems are timed, event and temporary systems are not.
+  pub fn get_system_timings(&self) -> Vec<(SystemId, Duration)> {
+    self.system_timings.lock().clone()
+  }
+
   /// Adds a temporary system to the world that will be executed exactly once.
   pub fn add_temp_system(&self, system: Box<dyn ParallelRunnable>) {
     let mut staging = self.temp_systems.lock();
@@ -65,19 +103,78 @@ impl World {
     systems.push(Box::new(actor_event_publish_system(event)));
   }
 
-  /// Adds a temporary system to the world that will be executed exactly once.
+  /// Schedules an event like `publish_event` but coalesces all events of the same type published
+  /// until the next event iteration into a single batch, e.g. for high frequency input events.
+  /// Receivers get the batch in publishing order through `EventReceiver::drain`.
+  pub fn publish_event_batched<T: Component + Clone + Sized + 'static>(&self, event: T) {
+    self.push_to_batch::<Vec<T>, _>(|batch| batch.push(event));
+  }
+
+  /// Adds to the pending batch of type `B`, creating it if this is the first entry of the iteration.
+  fn push_to_batch<B: PendingEventBatch + Default + 'static, F: FnOnce(&mut B)>(&self, push: F) {
+    let mut batches = self.event_batches.lock();
+    let type_id = TypeId::of::<B>();
+    let batch = match batches.iter_mut().position(|(id, _)| *id == type_id) {
+      Some(index) => &mut batches[index].1,
+      None => {
+        batches.push((type_id, Box::new(B::default())));
+        &mut batches.last_mut().unwrap().1
+      }
+    };
+    push(batch.as_any_mut().downcast_mut::<B>().unwrap());
+  }
+
+  /// Publishes an event wrapped in an `EventQuery` that can be answered by whichever receiver handles it.
+  /// Resolves to `None` if nobody replied within the tick the event has been delivered in.
+  pub fn publish_event_query<T, R>(&self, event: T) -> impl Future<Output = Option<R>>
+  where
+    T: Component + Clone + Sized + 'static,
+    R: Send + 'static,
+  {
+    let query = EventQuery::new(event);
+    let reply = EventQueryReply {
+      state: query.state.clone(),
+    };
+
+    self.event_queries.lock().push(query.state.clone());
+    self
+      .event_systems
+      .lock()
+      .push(Box::new(event_query_publish_system(query)));
+    reply
+  }
+
+  /// Sends a message to a single entity instead of broadcasting it to every receiver.
+  /// The message is delivered to the `Mailbox` of the entity and dropped if it has none.
+  /// All messages of a type sent until the next event iteration are delivered by a single system.
+  pub fn send_to<T: Component + Sized + 'static>(&self, entity: Entity, message: T) {
+    self.push_to_batch::<MessageBatch<T>, _>(|batch| batch.0.push((entity, message)));
+  }
+
+  /// Spawns an actor at root level and returns its handle together with a weak spawn
+  /// that can be used to modify the actor later on, e.g. from background tasks.
+  pub fn spawn_actor_weak<S: Spawnable + Send + Sync + 'static>(
+    &self,
+    s: S,
+  ) -> (ActorRc<S>, WeakSpawn<S>) {
+    let mut cmd = CommandBuffer::new(&self.world);
+    let actor = s.spawn(None, 0, &mut cmd);
+    self.add_command_buffer(cmd, 0, Some(actor.inner.clone()));
+
+    let weak = WeakSpawn::with_world(actor.inner.clone(), self);
+    (actor, weak)
+  }
+
+  /// Queues a command buffer for execution. Buffers with a lower priority are always flushed first,
+  /// buffers sharing the same priority are flushed in submission order.
   pub(crate) fn add_command_buffer(
     &self,
     cmd: CommandBuffer,
-    front: bool,
+    priority: usize,
     owner: Option<Arc<ActorInnerRef>>,
   ) {
     let mut staging = self.command_buffers.lock();
-    if front {
-      staging.insert(0, (cmd, owner));
-    } else {
-      staging.push((cmd, owner));
-    }
+    staging.push((priority, cmd, owner));
   }
 
   /// Adds a system to the default application stage causing the system tree to be
@@ -89,9 +186,38 @@ impl World {
   /// Adds a system to a specific stage causing the system tree to be
   /// marked as dirty and therefore will trigger rebuilding in the background
   pub fn add_system_to_stage<S: SystemFactory>(&self, system: S, stage: SystemStage) {
+    self.insert_system(system, stage, 0, false);
+  }
+
+  /// Adds a system to a specific stage that runs strictly after all systems of the same stage
+  /// with a lower priority and before all with a higher one, regardless of their component access.
+  /// Systems added without a priority have a priority of `0`.
+  pub fn add_system_to_stage_with_priority<S: SystemFactory>(
+    &self,
+    system: S,
+    stage: SystemStage,
+    priority: i32,
+  ) {
+    self.insert_system(system, stage, priority, false);
+  }
+
+  /// Adds a system to a specific stage that is always executed on the thread ticking the world
+  /// instead of the frame thread pool. Useful for systems touching thread-affine resources.
+  pub fn add_thread_local_system_to_stage<S: SystemFactory>(&self, system: S, stage: SystemStage) {
+    self.insert_system(system, stage, 0, true);
+  }
+
+  fn insert_system<S: SystemFactory>(
+    &self,
+    system: S,
+    stage: SystemStage,
+    priority: i32,
+    thread_local: bool,
+  ) {
     let mut systems = self.systems.write();
-    systems.push((stage.order_num(), Box::new(system)));
-    systems.sort_unstable_by_key(|(order_num, _)| *order_num);
+    systems.push((stage.order_num(), priority, thread_local, Box::new(system)));
+    // Stable sort keeps systems of the same priority in the order they have been added.
+    systems.sort_by_key(|(order_num, priority, ..)| (*order_num, *priority));
     self.systems_dirty.store(true, Ordering::Relaxed);
   }
 
@@ -107,16 +233,43 @@ impl World {
 
     // Group by stage.
     let systems = self.systems.read();
-    let groups = systems.iter().group_by(|(order_num, _)| *order_num);
+    let groups = systems.iter().group_by(|(order_num, ..)| *order_num);
 
-    // Each groups creates a new schedule.
+    // Each groups creates a new schedule, consecutive thread local systems are split into their own
+    // schedule as they have to run outside of the thread pool.
     let mut built = Vec::new();
     for (_, group) in &groups {
       let mut builder = Schedule::builder();
-      for (_, system) in group {
-        builder.add_system(system.create_system());
+      let mut current_priority = None;
+      let mut current_thread_local = None;
+      for (_, priority, thread_local, system) in group {
+        if current_thread_local.map_or(false, |current| current != *thread_local) {
+          built.push((
+            current_thread_local.unwrap(),
+            SendWrapper::new(builder.build()),
+          ));
+          builder = Schedule::builder();
+        } else if current_priority.map_or(false, |current| current != *priority) {
+          // Flushing splits the schedule so higher priorities only start once lower ones finished.
+          builder.flush();
+        }
+        current_priority = Some(*priority);
+        current_thread_local = Some(*thread_local);
+
+        let system = TimedSystem {
+          inner: system.create_system(),
+          timings: self.system_timings.clone(),
+        };
+        if *thread_local {
+          builder.add_thread_local(system);
+        } else {
+          builder.add_system(system);
+        }
       }
-      built.push(SendWrapper::new(builder.build()));
+      built.push((
+        current_thread_local.unwrap_or(false),
+        SendWrapper::new(builder.build()),
+      ));
     }
 
     // Update actual system schedules
@@ -128,8 +281,11 @@ impl World {
 
     #[allow(clippy::needless_collect)]
     {
-      let buffers = self.command_buffers.lock().drain(..).collect::<Vec<_>>();
-      for (mut buffer, _owner) in buffers.into_iter() {
+      let mut buffers = self.command_buffers.lock().drain(..).collect::<Vec<_>>();
+
+      // Parents must always be flushed before their children, stable sort keeps submission order.
+      buffers.sort_by_key(|(priority, ..)| *priority);
+      for (_, mut buffer, _owner) in buffers.into_iter() {
         buffer.flush(&mut self.world, resources);
       }
     }
@@ -149,16 +305,19 @@ impl World {
     // Event systems
     {
       optick::event!("World::tick::event");
-      loop {
+      let max_iterations = self.get_max_event_iterations();
+      let event_systems = &self.event_systems;
+      let event_batches = &self.event_batches;
+      let world = &mut self.world;
+      let iterations = run_bounded(max_iterations, || {
         optick::event!("World::tick::event::iteration");
 
-        // Drain event systems until empty.
-        let systems = {
-          let mut systems = self.event_systems.lock();
-          systems.drain(..).collect::<Vec<_>>()
-        };
+        // Drain event systems until empty, each batch becomes a single system.
+        let batches = event_batches.lock().drain(..).collect::<Vec<_>>();
+        let mut systems = event_systems.lock().drain(..).collect::<Vec<_>>();
+        systems.extend(batches.into_iter().map(|(_, batch)| batch.into_system()));
         if systems.is_empty() {
-          break;
+          return false;
         }
 
         // Execute systems
@@ -171,17 +330,33 @@ impl World {
           optick::event!("World::tick::event::iteration::execute");
           builder
             .build()
-            .execute_in_thread_pool(&mut self.world, &mut resources, pool);
+            .execute_in_thread_pool(world, &mut resources, pool);
         }
+        true
+      });
+
+      // Remaining events stay queued for the next tick.
+      let remaining = !event_systems.lock().is_empty() || !event_batches.lock().is_empty();
+      if iterations == max_iterations && remaining {
+        warn!(
+          "Event handling exceeded {} iterations, remaining events are deferred to the next frame",
+          max_iterations
+        );
       }
     }
 
     // Execute world systems
     {
       optick::event!("World::tick::systems");
+      self.system_timings.lock().clear();
       let mut systems = self.built_systems.write();
-      for system in systems.iter_mut() {
-        system.execute_in_thread_pool(&mut self.world, &mut resources, pool)
+      for (thread_local, system) in systems.iter_mut() {
+        // Executing within the pool would move thread local systems onto one of its threads.
+        if *thread_local {
+          system.execute(&mut self.world, &mut resources);
+        } else {
+          system.execute_in_thread_pool(&mut self.world, &mut resources, pool);
+        }
       }
     }
 
@@ -197,7 +372,27 @@ impl World {
         .build()
         .execute_in_thread_pool(&mut self.world, &mut resources, pool)
     }
+
+    // Unanswered queries won't receive a reply anymore once their event has been delivered,
+    // queries deferred to the next tick are kept.
+    self.event_queries.lock().retain(|query| {
+      if !query.is_delivered() {
+        return true;
+      }
+      query.finish();
+      false
+    });
+  }
+}
+
+/// Runs `iteration` until it reports that there was nothing left to do or `max_iterations` is reached.
+/// Returns the amount of iterations that did work.
+pub fn run_bounded<F: FnMut() -> bool>(max_iterations: usize, mut iteration: F) -> usize {
+  let mut iterations = 0;
+  while iterations < max_iterations && iteration() {
+    iterations += 1;
   }
+  iterations
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -277,6 +472,54 @@ impl Runnable for WrappedSystem {
   }
 }
 
+/// Measures the execution time of the wrapped system.
+struct TimedSystem {
+  inner: WrappedSystem,
+  timings: Arc<Mutex<Vec<(SystemId, Duration)>>>,
+}
+
+impl TimedSystem {
+  fn record(&self, start: Instant) {
+    let id = self
+      .inner
+      .name()
+      .cloned()
+      .unwrap_or_else(|| SystemId::from("unnamed"));
+    self.timings.lock().push((id, start.elapsed()));
+  }
+}
+
+impl Runnable for TimedSystem {
+  fn accesses_archetypes(&self) -> &ArchetypeAccess {
+    self.inner.accesses_archetypes()
+  }
+  fn command_buffer_mut(&mut self, world: WorldId) -> Option<&mut CommandBuffer> {
+    self.inner.command_buffer_mut(world)
+  }
+  fn name(&self) -> Option<&SystemId> {
+    self.inner.name()
+  }
+  fn prepare(&mut self, world: &LegionWorld) {
+    self.inner.prepare(world)
+  }
+  fn reads(&self) -> (&[ResourceTypeId], &[ComponentTypeId]) {
+    self.inner.reads()
+  }
+  fn run(&mut self, world: &mut LegionWorld, resources: &mut Resources) {
+    let start = Instant::now();
+    self.inner.run(world, resources);
+    self.record(start);
+  }
+  unsafe fn run_unsafe(&mut self, world: &LegionWorld, resources: &UnsafeResources) {
+    let start = Instant::now();
+    self.inner.run_unsafe(world, resources);
+    self.record(start);
+  }
+  fn writes(&self) -> (&[ResourceTypeId], &[ComponentTypeId]) {
+    self.inner.writes()
+  }
+}
+
 /// The system stage specifies the order the system is executed at.
 pub enum SystemStage {
   /// The cold stage is for system that should run as soon as possible when the frame just started.
@@ -423,9 +666,13 @@ impl<'a, T: Spawnable> SubWeakSpawn<'a, T> {
 
 impl<T: Spawnable + Send + Sync + 'static> WeakSpawn<T> {
   fn new(inner: Arc<ActorInnerRef>) -> Self {
+    Self::with_world(inner, Core::get_instance().get_world())
+  }
+
+  fn with_world(inner: Arc<ActorInnerRef>, world: &World) -> Self {
     Self {
       actor: inner,
-      cmd: CommandBuffer::new(&Core::get_instance().get_world().world),
+      cmd: CommandBuffer::new(&world.world),
       _p: PhantomData {},
     }
   }
@@ -469,9 +716,12 @@ impl<T: Spawnable + Send + Sync + 'static> WeakSpawn<T> {
   }
 
   pub fn flush(self) {
-    Core::get_instance()
-      .get_world()
-      .add_command_buffer(self.cmd, false, Some(self.actor.clone()));
+    self.flush_to(Core::get_instance().get_world());
+  }
+
+  /// Queues all recorded changes on the given world instead of the core world.
+  pub fn flush_to(self, world: &World) {
+    world.add_command_buffer(self.cmd, self.actor.level, Some(self.actor.clone()));
   }
 }
 
@@ -511,6 +761,34 @@ impl<'a, T: Spawnable + Send + Sync + 'static> std::ops::DerefMut for UnlockedWe
   }
 }
 
+/// Read and write component sets of all systems generated for an actor type.
+#[derive(Debug, Clone)]
+pub struct ComponentAccessSummary {
+  pub actor: &'static str,
+  pub reads: Vec<&'static str>,
+  pub writes: Vec<&'static str>,
+}
+
+impl ComponentAccessSummary {
+  /// Returns the components both actors write to, which prevents their systems from running in parallel.
+  pub fn write_conflicts<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'static str> + 'a {
+    self
+      .writes
+      .iter()
+      .filter(move |w| other.writes.contains(w) || other.reads.contains(w))
+      .chain(self.reads.iter().filter(move |r| other.writes.contains(r)))
+      .copied()
+  }
+}
+
+impl std::fmt::Display for ComponentAccessSummary {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "Actor {}", self.actor)?;
+    writeln!(f, "  reads:  {}", self.reads.join(", "))?;
+    write!(f, "  writes: {}", self.writes.join(", "))
+  }
+}
+
 /// Base actor component added to _all_ spawned actors during runtime.
 pub struct Actor {
   weak: Weak<ActorInnerRef>,
@@ -575,16 +853,36 @@ impl<T: Send + Sync + 'static> ActorRc<T> {
     self.inner.entity
   }
 
+  /// Panics if the actor has not been added to the world yet, see `try_entry`.
   pub fn entry(&self) -> ActorEntry<'_> {
+    self
+      .try_entry()
+      .expect("Actor has not been added to the world yet")
+  }
+
+  /// Entry of the actor, `None` until the command buffer spawning it has been flushed.
+  /// Once spawned the entity is only dropped when all references to it are removed.
+  pub fn try_entry(&self) -> Option<ActorEntry<'_>> {
     let entry = Core::get_instance()
       .get_world()
       .world
-      .entry_ref(self.inner.entity);
+      .entry_ref(self.inner.entity)
+      .ok()?;
+    Some(ActorEntry { entry })
+  }
 
-    // Unwrap is safe here as entity will be only dropped when all reference to it are removed
-    ActorEntry {
-      entry: entry.unwrap(),
-    }
+  /// Clones a single component of the actor, `None` if the actor doesn't have it or hasn't been spawned yet.
+  pub fn get_component<C: Clone + Send + Sync + 'static>(&self) -> Option<C> {
+    self.with_component(|component: &C| component.clone())
+  }
+
+  /// Gives borrowed access to a single component of the actor, `None` if the actor doesn't have it
+  /// or hasn't been spawned yet.
+  pub fn with_component<C: Send + Sync + 'static, R, F: FnOnce(&C) -> R>(&self, f: F) -> Option<R> {
+    self
+      .try_entry()?
+      .get(Reader::<C> { _p: PhantomData })
+      .map(f)
   }
 }
 
@@ -674,6 +972,204 @@ fn actor_event_publish<T: Component + Clone + Sized + 'static>(
   receiver.received.push(event.clone());
 }
 
+#[system(for_each)]
+#[allow(clippy::ptr_arg)]
+fn actor_event_publish_batch<T: Component + Clone + Sized + 'static>(
+  receiver: &mut EventReceiver<T>,
+  #[state] events: &Vec<T>,
+) {
+  receiver.received.extend(events.iter().cloned());
+}
+
+/// Delivers an event query like `actor_event_publish` and marks it as delivered.
+fn event_query_publish_system<T, R>(query: EventQuery<T, R>) -> impl ParallelRunnable
+where
+  T: Component + Clone + Sized + 'static,
+  R: Send + 'static,
+{
+  SystemBuilder::new("event_query_publish")
+    .with_query(<&mut EventReceiver<EventQuery<T, R>>>::query())
+    .build(move |_, world, _, receivers| {
+      query.state.inner.lock().delivered = true;
+      for receiver in receivers.iter_mut(world) {
+        receiver.received.push(query.clone());
+      }
+    })
+}
+
+/// Messages addressed to a single entity through `World::send_to`.
+pub struct Mailbox<T: Component + Sized + 'static> {
+  received: Vec<T>,
+}
+
+impl<T: Component + Sized + 'static> Mailbox<T> {
+  pub fn new() -> Self {
+    Self {
+      received: Vec::new(),
+    }
+  }
+  pub fn drain(&mut self) -> std::vec::Drain<T> {
+    self.received.drain(..)
+  }
+}
+
+/// Looks up the mailbox of each target entity instead of iterating all mailboxes.
+fn actor_message_deliver_system<T: Component + Sized + 'static>(
+  messages: Vec<(Entity, T)>,
+) -> impl ParallelRunnable {
+  let mut messages = Some(messages);
+  SystemBuilder::new("actor_message_deliver")
+    .write_component::<Mailbox<T>>()
+    .build(move |_, world, _, _| {
+      for (target, message) in messages.take().into_iter().flatten() {
+        // Entities without a mailbox, including despawned ones, drop the message.
+        if let Ok(mut entry) = world.entry_mut(target) {
+          if let Ok(mailbox) = entry.get_component_mut::<Mailbox<T>>() {
+            mailbox.received.push(message);
+          }
+        }
+      }
+    })
+}
+
+/// Events of a single type published through `World::publish_event_batched`.
+trait PendingEventBatch: Send {
+  fn as_any_mut(&mut self) -> &mut dyn Any;
+  fn into_system(self: Box<Self>) -> Box<dyn ParallelRunnable>;
+}
+
+impl<T: Component + Clone + Sized + 'static> PendingEventBatch for Vec<T> {
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  fn into_system(self: Box<Self>) -> Box<dyn ParallelRunnable> {
+    Box::new(actor_event_publish_batch_system(*self))
+  }
+}
+
+/// Messages of a single type sent through `World::send_to`, in sending order.
+struct MessageBatch<T>(Vec<(Entity, T)>);
+
+impl<T> Default for MessageBatch<T> {
+  fn default() -> Self {
+    Self(Vec::new())
+  }
+}
+
+impl<T: Component + Sized + 'static> PendingEventBatch for MessageBatch<T> {
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  fn into_system(self: Box<Self>) -> Box<dyn ParallelRunnable> {
+    Box::new(actor_message_deliver_system(self.0))
+  }
+}
+
+/// Event that carries a reply slot, answered by whichever receiver handles it first.
+pub struct EventQuery<T, R> {
+  pub request: T,
+  state: Arc<EventQueryState<R>>,
+}
+
+impl<T, R> EventQuery<T, R> {
+  fn new(request: T) -> Self {
+    Self {
+      request,
+      state: Arc::new(EventQueryState {
+        inner: Mutex::new(EventQueryInner {
+          reply: None,
+          answered: false,
+          delivered: false,
+          finished: false,
+          waker: None,
+        }),
+      }),
+    }
+  }
+
+  /// Answers the query, returns false if it has already been answered by another receiver.
+  pub fn reply(&self, value: R) -> bool {
+    let mut inner = self.state.inner.lock();
+    if inner.answered {
+      return false;
+    }
+    inner.answered = true;
+    inner.reply = Some(value);
+    if let Some(waker) = inner.waker.take() {
+      waker.wake();
+    }
+    true
+  }
+
+  pub fn is_answered(&self) -> bool {
+    self.state.inner.lock().answered
+  }
+}
+
+impl<T: Clone, R> Clone for EventQuery<T, R> {
+  fn clone(&self) -> Self {
+    Self {
+      request: self.request.clone(),
+      state: self.state.clone(),
+    }
+  }
+}
+
+struct EventQueryState<R> {
+  inner: Mutex<EventQueryInner<R>>,
+}
+
+struct EventQueryInner<R> {
+  reply: Option<R>,
+  answered: bool,
+  /// Set once the event has been handed to the receivers.
+  delivered: bool,
+  finished: bool,
+  waker: Option<Waker>,
+}
+
+trait PendingEventQuery: Send + Sync {
+  fn is_delivered(&self) -> bool;
+  fn finish(&self);
+}
+
+impl<R: Send> PendingEventQuery for EventQueryState<R> {
+  fn is_delivered(&self) -> bool {
+    self.inner.lock().delivered
+  }
+
+  fn finish(&self) {
+    let mut inner = self.inner.lock();
+    inner.finished = true;
+    if let Some(waker) = inner.waker.take() {
+      waker.wake();
+    }
+  }
+}
+
+struct EventQueryReply<R> {
+  state: Arc<EventQueryState<R>>,
+}
+
+impl<R> Future for EventQueryReply<R> {
+  type Output = Option<R>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    let mut inner = self.state.inner.lock();
+    if inner.answered {
+      return Poll::Ready(inner.reply.take());
+    }
+    if inner.finished {
+      return Poll::Ready(None);
+    }
+
+    inner.waker = Some(cx.waker().clone());
+    Poll::Pending
+  }
+}
+
 struct EventLogger;
 impl EventSender for EventLogger {
   fn send(&self, event: Event) -> bool {
@@ -1,4 +1,8 @@
 use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::panic::{catch_unwind, AssertUnwindSafe, Location};
+use std::sync::Arc;
+
+use crate::error;
 
 pub struct Execution {
   frame_thread_pool: ThreadPool,
@@ -41,3 +45,58 @@ impl Execution {
     &self.background_thread_pool
   }
 }
+
+/// Details of a panic that occurred within a background task.
+#[derive(Debug, Clone)]
+pub struct BackgroundTaskPanic {
+  pub message: String,
+  /// Where the task has been spawned.
+  pub location: &'static Location<'static>,
+}
+
+/// User callback invoked for every panicking background task, see `CoreConfig::with_background_panic_handler`.
+#[derive(Clone)]
+pub struct BackgroundPanicHandler(Arc<dyn Fn(&BackgroundTaskPanic) + Send + Sync>);
+
+impl BackgroundPanicHandler {
+  pub fn new<F: Fn(&BackgroundTaskPanic) + Send + Sync + 'static>(f: F) -> Self {
+    Self(Arc::new(f))
+  }
+
+  pub fn call(&self, panic: &BackgroundTaskPanic) {
+    (self.0)(panic)
+  }
+}
+
+impl std::fmt::Debug for BackgroundPanicHandler {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str("BackgroundPanicHandler")
+  }
+}
+
+/// Runs a background task, panics are logged together with the spawn location and forwarded
+/// to the handler instead of silently vanishing in the thread pool.
+pub fn catch_background_panic<OP: FnOnce()>(
+  op: OP,
+  location: &'static Location<'static>,
+  handler: Option<&BackgroundPanicHandler>,
+) -> Option<BackgroundTaskPanic> {
+  let payload = catch_unwind(AssertUnwindSafe(op)).err()?;
+  let message = if let Some(message) = payload.downcast_ref::<&str>() {
+    message.to_string()
+  } else if let Some(message) = payload.downcast_ref::<String>() {
+    message.clone()
+  } else {
+    "Unknown panic payload".to_string()
+  };
+
+  let panic = BackgroundTaskPanic { message, location };
+  error!(
+    "Background task spawned at {} panicked: {}",
+    panic.location, panic.message
+  );
+  if let Some(handler) = handler {
+    handler.call(&panic);
+  }
+  Some(panic)
+}
@@ -1,6 +1,12 @@
+use crate::WindowInput;
+
 pub trait Extension: Send + Sync + 'static {
   fn init(&mut self) {}
   fn before_tick(&mut self) {}
+  /// Window input offered on the main thread, returns whether the extension consumed it.
+  fn on_input(&mut self, _input: &WindowInput) -> bool {
+    false
+  }
 }
 
 pub(crate) struct ExtensionHost {
@@ -29,4 +35,9 @@ impl ExtensionHost {
       ext.before_tick();
     }
   }
+
+  /// Offers the input to the extensions in registration order until one consumes it.
+  pub fn on_input(&mut self, input: &WindowInput) -> bool {
+    self.extensions.iter_mut().any(|ext| ext.on_input(input))
+  }
 }
@@ -1,7 +1,9 @@
 use std::sync::Arc;
 
 use moonwave_common::*;
-use moonwave_render::{execute_wgpu_async, CommandEncoderOutput, FrameGraphNode, FrameNodeValue};
+use moonwave_render::{
+  execute_wgpu_async, CommandEncoderOutput, FrameGraphNode, FrameNodeValue, FrameTarget,
+};
 use parking_lot::RwLock;
 use wgpu::{LoadOp, Operations, RenderPassDescriptor};
 use wgpu_glyph::{GlyphBrush, GlyphBrushBuilder, Section, Text};
@@ -104,7 +106,7 @@ impl FrameGraphNode for GlyphFrameNode {
     outputs: &mut [Option<FrameNodeValue>],
     device: &wgpu::Device,
     queue: &wgpu::Queue,
-    _sc_frame: &wgpu::SwapChainFrame,
+    _target: &FrameTarget,
   ) -> CommandEncoderOutput {
     let texture_in = inputs[Self::INPUT_TEXTURE]
       .as_ref()
@@ -3,24 +3,40 @@
 
 mod application;
 mod base;
+mod cache;
+mod config;
+mod debug_text;
 mod ecs;
 mod execution;
 mod extension;
 mod glyph;
 mod logger;
+mod memory;
 mod nodes;
 mod service;
-mod memory;
+mod validation;
 
 pub use application::*;
-pub use base::{BindGroupLayoutSingleton, Core, OnceInFrame, ShaderKind, TaskKind};
+pub use base::{
+  compile_glsl, swap_chain_recovery, BindGroupLayoutSingleton, Core, OnceInFrame, ShaderError,
+  ShaderKind, SwapChainRecovery, TaskKind,
+};
+pub use cache::*;
+pub use config::*;
+pub use debug_text::*;
 pub use ecs::*;
+pub use execution::{catch_background_panic, BackgroundPanicHandler, BackgroundTaskPanic};
 pub use extension::*;
 pub use glyph::*;
 pub use logger::*;
-pub use nodes::{PresentToScreen, TextureGeneratorHost, TextureGeneratorNode, TextureSize};
-pub use service::*;
 pub use memory::*;
+pub use nodes::{
+  tonemap_aces, FrameThrottle, PingPongState, PingPongTextureHost, PingPongTextureNode,
+  PresentToScreen, PresentToTexture, TextureGeneratorHost, TextureGeneratorNode, TextureSize,
+  ThrottledNode, TonemapNode, FULLSCREEN_TRIANGLE_VS,
+};
+pub use service::*;
+pub use validation::*;
 
 pub use async_trait::async_trait;
 pub use futures::{executor::block_on, Future};
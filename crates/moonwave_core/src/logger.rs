@@ -1,33 +1,141 @@
 use chrono::*;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use thiserror::Error;
 
 pub use log::*;
 
+/// Target of the messages logged by the `logged` service extension.
+pub const SERVICE_LOG_TARGET: &str = "moonwave::service";
+
+/// Environment variable overriding the log filter, e.g. `MOONWAVE_LOG=debug,moonwave::service=off`.
+pub const LOG_FILTER_ENV: &str = "MOONWAVE_LOG";
+
+static LOG_FILTER: Lazy<RwLock<LogFilter>> = Lazy::new(|| RwLock::new(LogFilter::default()));
+
+/// Maximum log level per target, targets are matched by prefix and the longest match wins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+  pub default: LevelFilter,
+  pub targets: Vec<(String, LevelFilter)>,
+}
+
+impl Default for LogFilter {
+  /// Debug output of the engine while silencing noisy graphics backends.
+  fn default() -> Self {
+    Self::new()
+      .with_target("gfx", LevelFilter::Off)
+      .with_target("naga", LevelFilter::Off)
+      .with_target("wgpu", LevelFilter::Warn)
+  }
+}
+
+impl LogFilter {
+  /// Filter without any target specific levels.
+  pub fn new() -> Self {
+    Self {
+      default: LevelFilter::Debug,
+      targets: Vec::new(),
+    }
+  }
+
+  pub fn with_default(mut self, level: LevelFilter) -> Self {
+    self.default = level;
+    self
+  }
+
+  /// Sets the level of all targets starting with the given prefix, replacing a previous level.
+  pub fn with_target(mut self, prefix: &str, level: LevelFilter) -> Self {
+    self.targets.retain(|(target, _)| target != prefix);
+    self.targets.push((prefix.to_string(), level));
+    self
+  }
+
+  /// Parses comma separated directives of either `level` or `target=level` on top of this filter.
+  pub fn parse(mut self, spec: &str) -> Result<Self, LogFilterError> {
+    let parse_level = |level: &str| {
+      level
+        .trim()
+        .parse::<LevelFilter>()
+        .map_err(|_| LogFilterError::InvalidLevel(level.trim().to_string()))
+    };
+
+    for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+      self = match directive.find('=') {
+        Some(split) => {
+          let target = directive[..split].trim();
+          if target.is_empty() {
+            return Err(LogFilterError::MissingTarget(directive.to_string()));
+          }
+          self.with_target(target, parse_level(&directive[split + 1..])?)
+        }
+        None => self.with_default(parse_level(directive)?),
+      };
+    }
+    Ok(self)
+  }
+
+  /// Level of the longest matching target prefix, or the default level.
+  pub fn level_for(&self, target: &str) -> LevelFilter {
+    self
+      .targets
+      .iter()
+      .filter(|(prefix, _)| target.starts_with(prefix.as_str()))
+      .max_by_key(|(prefix, _)| prefix.len())
+      .map(|(_, level)| *level)
+      .unwrap_or(self.default)
+  }
+
+  pub fn enabled(&self, target: &str, level: Level) -> bool {
+    level <= self.level_for(target)
+  }
+
+  /// Most verbose level of any target, used to skip disabled messages as early as possible.
+  pub fn max_level(&self) -> LevelFilter {
+    self
+      .targets
+      .iter()
+      .map(|(_, level)| *level)
+      .fold(self.default, std::cmp::max)
+  }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum LogFilterError {
+  #[error("Unknown log level '{0}'")]
+  InvalidLevel(String),
+  #[error("Log filter directive '{0}' has no target")]
+  MissingTarget(String),
+}
+
+/// Replaces the active log filter at runtime.
+pub fn set_log_filter(filter: LogFilter) {
+  log::set_max_level(filter.max_level());
+  *LOG_FILTER.write() = filter;
+}
+
+/// Currently active log filter.
+pub fn get_log_filter() -> LogFilter {
+  LOG_FILTER.read().clone()
+}
+
 struct Logger;
 
 impl log::Log for Logger {
   fn enabled(&self, metadata: &Metadata) -> bool {
-    metadata.level() <= Level::Debug
+    LOG_FILTER
+      .read()
+      .enabled(metadata.target(), metadata.level())
   }
 
   fn log(&self, record: &Record) {
-    // Filter based on level.
+    // Filter based on level and target.
     if !self.enabled(record.metadata()) {
       return;
     }
 
-    // Filter modules
-    let target = record.target().to_string();
+    let target = record.target();
     let level = record.level();
-    if target.contains("gfx") || target.contains("naga") {
-      return;
-    }
-    if level != Level::Error
-      && level != Level::Warn
-      && (target.contains("wgpu") || target.contains("gfx"))
-    {
-      return;
-    }
-
     let message = format!("{}", record.args());
     let now: DateTime<Utc> = Utc::now();
     let time = now.format("%H:%M:%S%.6f").to_string();
@@ -38,10 +146,19 @@ impl log::Log for Logger {
   fn flush(&self) {}
 }
 
-pub(crate) fn init() {
+/// Installs the logger, directives of the `MOONWAVE_LOG` environment variable are applied on top of `filter`.
+pub(crate) fn init(filter: LogFilter) {
   start_deadlock_detection();
+  let filter = match std::env::var(LOG_FILTER_ENV) {
+    Ok(spec) => filter.clone().parse(&spec).unwrap_or_else(|err| {
+      println!("Ignoring {}: {}", LOG_FILTER_ENV, err);
+      filter
+    }),
+    Err(_) => filter,
+  };
+
   let logger = log::set_boxed_logger(Box::new(Logger));
-  log::set_max_level(LevelFilter::Debug);
+  set_log_filter(filter);
   logger.unwrap();
 }
 
@@ -1,36 +1,117 @@
 use crate::Core;
 use moonwave_common::Vector2;
-use moonwave_render::{CommandEncoder, CommandEncoderOutput, FrameGraphNode, FrameNodeValue};
+use moonwave_render::{
+  CommandEncoder, CommandEncoderOutput, FrameGraphNode, FrameNodeValue, FrameTarget,
+};
 use moonwave_resources::*;
 use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
 use shaderc::ShaderKind;
-use std::sync::Arc;
+use std::{collections::HashMap, ops::Range, sync::Arc};
+
+mod ping_pong;
+pub use ping_pong::*;
+
+mod throttled;
+pub use throttled::*;
+
+mod tonemap;
+pub use tonemap::*;
+
+/// Vertex shader drawing a single fullscreen triangle with uv, see `PresentToScreen::VERTICES`.
+pub const FULLSCREEN_TRIANGLE_VS: &str = include_str!("./passthrough.vert");
 
 static PRESENT_TO_SCREEN_PROGRAM: OnceCell<PresentToScreenResources> = OnceCell::new();
 
 pub struct PresentToScreen {}
 
 struct PresentToScreenResources {
-  _vs: ResourceRc<Shader>,
-  _fs: ResourceRc<Shader>,
-  _pipeline_layout: ResourceRc<PipelineLayout>,
-  pipeline: ResourceRc<RenderPipeline>,
+  vs: ResourceRc<Shader>,
+  fs: ResourceRc<Shader>,
+  pipeline_layout: ResourceRc<PipelineLayout>,
+  /// Pipelines of each target format, created on first use.
+  pipelines: Mutex<HashMap<TextureFormat, PresentPipelines>>,
+}
+
+#[derive(Clone)]
+struct PresentPipelines {
+  /// Writes the scene opaquely, the base layer of every presentation.
+  opaque: ResourceRc<RenderPipeline>,
+  /// Composites sprites, debug and UI layers with alpha blending on top.
+  blended: ResourceRc<RenderPipeline>,
+}
+
+impl PresentToScreenResources {
+  fn get_pipelines(&self, format: TextureFormat) -> PresentPipelines {
+    let mut pipelines = self.pipelines.lock();
+    pipelines
+      .entry(format)
+      .or_insert_with(|| {
+        let core = Core::get_instance();
+        let create_pipeline = |blend: Option<BlendState>| {
+          let pipeline_desc = RenderPipelineDescriptor::new_without_vertices(
+            self.pipeline_layout.clone(),
+            self.vs.clone(),
+            self.fs.clone(),
+          )
+          .add_color_output_with_blend(format, blend);
+          core.create_render_pipeline(pipeline_desc).unwrap()
+        };
+        PresentPipelines {
+          opaque: create_pipeline(None),
+          blended: create_pipeline(Some(RenderPipelineOutput::ALPHA_BLENDING)),
+        }
+      })
+      .clone()
+  }
 }
 
 impl PresentToScreen {
   pub const INPUT_TEXTURE: usize = 0;
+  pub const INPUT_TEXTURE_DEBUG: usize = 1;
   pub const INPUT_TEXTURE_UI: usize = 2;
+  pub const INPUT_TEXTURE_IMD_TEXT: usize = 3;
+  pub const INPUT_TEXTURE_SPRITES: usize = 4;
+  pub const INPUT_TEXTURE_DEBUG_TEXT: usize = 5;
+
+  /// Order the layers are drawn in, sprites belong to the scene and stay below debug drawings.
+  pub const LAYER_ORDER: [usize; 6] = [
+    Self::INPUT_TEXTURE,
+    Self::INPUT_TEXTURE_SPRITES,
+    Self::INPUT_TEXTURE_DEBUG,
+    Self::INPUT_TEXTURE_UI,
+    Self::INPUT_TEXTURE_IMD_TEXT,
+    Self::INPUT_TEXTURE_DEBUG_TEXT,
+  ];
+
+  /// Vertices drawn for the single fullscreen triangle generated by the passthrough shader.
+  pub const VERTICES: Range<u32> = 0..3;
+
+  /// Format of the headless target, presenting to the screen assumes the same for the swap chain.
+  pub const FORMAT: TextureFormat = TextureFormat::Bgra8UnormSrgb;
+
+  /// Blending of the layer connected to the given input, the scene is written opaquely while
+  /// all other layers are composited on top of it based on their alpha.
+  pub fn layer_blend(input: usize) -> Option<BlendState> {
+    match input {
+      Self::INPUT_TEXTURE => None,
+      _ => Some(RenderPipelineOutput::ALPHA_BLENDING),
+    }
+  }
+
+  /// Mirrors the vertex generation of the passthrough shader returning clip space position and uv.
+  pub fn fullscreen_triangle_vertex(index: u32) -> (Vector2<f32>, Vector2<f32>) {
+    let base = Vector2::new(((index << 1) & 2) as f32, (index & 2) as f32);
+    let position = base * 2.0 - Vector2::new(1.0, 1.0);
+    let uv = Vector2::new(base.x, 1.0 - base.y);
+    (position, uv)
+  }
 
   pub fn new() -> Self {
     let _ = PRESENT_TO_SCREEN_PROGRAM.get_or_init(|| {
       let core = Core::get_instance();
       let vs = core
-        .create_shader_from_glsl(
-          include_str!("./passthrough.vert"),
-          "PassthroughVS",
-          ShaderKind::Vertex,
-        )
+        .create_shader_from_glsl(FULLSCREEN_TRIANGLE_VS, "PassthroughVS", ShaderKind::Vertex)
         .unwrap();
 
       let fs = core
@@ -49,20 +130,14 @@ impl PresentToScreen {
       );
       let pipeline_layout = core.create_pipeline_layout(layout_desc);
 
-      let pipeline_desc = RenderPipelineDescriptor::new_without_vertices(
-        pipeline_layout.clone(),
-        vs.clone(),
-        fs.clone(),
-      )
-      .add_color_output(TextureFormat::Bgra8UnormSrgb);
-      let pipeline = core.create_render_pipeline(pipeline_desc);
-
-      PresentToScreenResources {
-        _vs: vs,
-        _fs: fs,
-        _pipeline_layout: pipeline_layout,
-        pipeline,
-      }
+      let resources = PresentToScreenResources {
+        vs,
+        fs,
+        pipeline_layout,
+        pipelines: Mutex::new(HashMap::new()),
+      };
+      resources.get_pipelines(Self::FORMAT);
+      resources
     });
 
     PresentToScreen {}
@@ -76,48 +151,141 @@ impl FrameGraphNode for PresentToScreen {
     _outputs: &mut [Option<FrameNodeValue>],
     device: &wgpu::Device,
     _queue: &wgpu::Queue,
-    sc_frame: &wgpu::SwapChainFrame,
+    target: &FrameTarget,
   ) -> CommandEncoderOutput {
     let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
       label: Some("CommandEncoderPresentToScreen"),
     });
 
-    {
-      let resources = PRESENT_TO_SCREEN_PROGRAM.get().unwrap();
-      let pipeline = resources.pipeline.get_raw();
-
-      let bind_groups = inputs
-        .iter()
-        .filter_map(|input| {
-          if let Some(FrameNodeValue::SampledTexture(texture)) = input {
-            Some(texture.bind_group.get_raw())
-          } else {
-            None
-          }
-        })
-        .collect::<Vec<_>>();
-
-      {
-        let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-          label: Some("RenderPassPresentToScreen"),
-          color_attachments: &[wgpu::RenderPassColorAttachment {
-            resolve_target: None,
-            view: &sc_frame.output.view,
-            ops: wgpu::Operations {
-              load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
-              store: true,
-            },
-          }],
-          depth_stencil_attachment: None,
-        });
-
-        for bind_group in bind_groups.iter() {
-          rp.set_pipeline(&*pipeline);
-          rp.set_bind_group(0, &*bind_group, &[]);
-          rp.draw(0..4, 0..1);
-        }
+    present_layers(
+      &mut encoder,
+      target.view(),
+      Self::FORMAT,
+      &input_layers(inputs),
+      "RenderPassPresentToScreen",
+    );
+
+    CommandEncoderOutput::from_raw(encoder.finish())
+  }
+}
+
+fn input_layers(inputs: &[Option<FrameNodeValue>]) -> Vec<(bool, ResourceRc<BindGroup>)> {
+  PresentToScreen::LAYER_ORDER
+    .iter()
+    .filter_map(|index| {
+      if let Some(Some(FrameNodeValue::SampledTexture(texture))) = inputs.get(*index) {
+        let blended = PresentToScreen::layer_blend(*index).is_some();
+        Some((blended, texture.bind_group.clone()))
+      } else {
+        None
       }
+    })
+    .collect::<Vec<_>>()
+}
+
+/// Draws all given sampled textures on top of each other into the target view within a single pass.
+/// The target is cleared once, layers flagged as blended are then composited in order with alpha
+/// blending while the others replace what has been drawn so far.
+fn present_layers(
+  encoder: &mut wgpu::CommandEncoder,
+  view: &wgpu::TextureView,
+  format: TextureFormat,
+  layers: &[(bool, ResourceRc<BindGroup>)],
+  label: &str,
+) {
+  let pipelines = PRESENT_TO_SCREEN_PROGRAM
+    .get()
+    .unwrap()
+    .get_pipelines(format);
+  let opaque_pipeline = pipelines.opaque.get_raw();
+  let blended_pipeline = pipelines.blended.get_raw();
+  let layers = layers
+    .iter()
+    .map(|(blended, bind_group)| (*blended, bind_group.get_raw()))
+    .collect::<Vec<_>>();
+
+  let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+    label: Some(label),
+    color_attachments: &[wgpu::RenderPassColorAttachment {
+      resolve_target: None,
+      view,
+      ops: wgpu::Operations {
+        load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+        store: true,
+      },
+    }],
+    depth_stencil_attachment: None,
+  });
+
+  let mut current = None;
+  for (blended, bind_group) in layers.iter() {
+    if current != Some(*blended) {
+      if *blended {
+        rp.set_pipeline(&*blended_pipeline);
+      } else {
+        rp.set_pipeline(&*opaque_pipeline);
+      }
+      current = Some(*blended);
     }
+    rp.set_bind_group(0, &*bind_group, &[]);
+    rp.draw(PresentToScreen::VERTICES, 0..1);
+  }
+}
+
+/// End node that renders the frame into a texture instead of the screen.
+/// The texture is shown on screen as well so the window keeps displaying the frame.
+pub struct PresentToTexture {
+  target: SampledTexture,
+  format: TextureFormat,
+}
+
+impl PresentToTexture {
+  pub const INPUT_TEXTURE: usize = PresentToScreen::INPUT_TEXTURE;
+  pub const INPUT_TEXTURE_DEBUG: usize = PresentToScreen::INPUT_TEXTURE_DEBUG;
+  pub const INPUT_TEXTURE_UI: usize = PresentToScreen::INPUT_TEXTURE_UI;
+  pub const INPUT_TEXTURE_IMD_TEXT: usize = PresentToScreen::INPUT_TEXTURE_IMD_TEXT;
+  pub const INPUT_TEXTURE_SPRITES: usize = PresentToScreen::INPUT_TEXTURE_SPRITES;
+  pub const INPUT_TEXTURE_DEBUG_TEXT: usize = PresentToScreen::INPUT_TEXTURE_DEBUG_TEXT;
+
+  /// The format has to be the one the target has been created with.
+  pub fn new(target: SampledTexture, format: TextureFormat) -> Self {
+    // Shares the passthrough program of the screen presentation.
+    let _ = PresentToScreen::new();
+    Self { target, format }
+  }
+
+  pub fn get_target(&self) -> &SampledTexture {
+    &self.target
+  }
+}
+
+impl FrameGraphNode for PresentToTexture {
+  fn execute_raw(
+    &self,
+    inputs: &[Option<FrameNodeValue>],
+    _outputs: &mut [Option<FrameNodeValue>],
+    device: &wgpu::Device,
+    _queue: &wgpu::Queue,
+    target: &FrameTarget,
+  ) -> CommandEncoderOutput {
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+      label: Some("CommandEncoderPresentToTexture"),
+    });
+
+    present_layers(
+      &mut encoder,
+      self.target.view.get_raw(),
+      self.format,
+      &input_layers(inputs),
+      "RenderPassPresentToTexture",
+    );
+    present_layers(
+      &mut encoder,
+      target.view(),
+      PresentToScreen::FORMAT,
+      &[(false, self.target.bind_group.clone())],
+      "RenderPassPresentToTextureScreen",
+    );
 
     CommandEncoderOutput::from_raw(encoder.finish())
   }
@@ -140,28 +308,56 @@ impl TextureSize {
 pub struct TextureGeneratorHost {
   size: TextureSize,
   format: TextureFormat,
-  active: Arc<Mutex<(Vector2<u32>, SampledTexture, bool)>>,
+  usage: TextureUsage,
+  active: Mutex<(Vector2<u32>, SampledTexture)>,
 }
 
 impl TextureGeneratorHost {
+  /// Creates a render target that can be rendered to and sampled.
   pub fn new(size: TextureSize, format: TextureFormat) -> Arc<Self> {
-    let core = Core::get_instance();
-    let actual_size = size.get_actual_size();
-    let texture = core.create_sampled_texture(
-      None,
-      TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED,
+    Self::new_with_usage(
+      size,
       format,
-      actual_size,
-      1,
-    );
+      TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED,
+    )
+  }
+
+  /// Same as `new` with custom usage flags, e.g. `COPY_SRC` for readbacks or `STORAGE` for compute.
+  /// Frame graph nodes sample the texture so `SAMPLED` is always added.
+  pub fn new_with_usage(
+    size: TextureSize,
+    format: TextureFormat,
+    usage: TextureUsage,
+  ) -> Arc<Self> {
+    let actual_size = size.get_actual_size();
+    let usage = usage | TextureUsage::SAMPLED;
+    let texture = Self::create_texture(format, usage, actual_size);
 
     Arc::new(Self {
       format,
       size,
-      active: Arc::new(Mutex::new((actual_size, texture, false))),
+      usage,
+      active: Mutex::new((actual_size, texture)),
     })
   }
 
+  fn create_texture(
+    format: TextureFormat,
+    usage: TextureUsage,
+    size: Vector2<u32>,
+  ) -> SampledTexture {
+    Core::get_instance().create_sampled_texture(None, usage, format, size, 1)
+  }
+
+  pub fn get_usage(&self) -> TextureUsage {
+    self.usage
+  }
+
+  /// Texture of the current resolution, e.g. to read back what has been rendered into it.
+  pub fn get_texture(&self) -> SampledTexture {
+    self.active.lock().1.clone()
+  }
+
   pub fn create_node(self: &Arc<Self>) -> TextureGeneratorNode {
     TextureGeneratorNode(self.clone())
   }
@@ -180,31 +376,12 @@ impl FrameGraphNode for TextureGeneratorNode {
     outputs: &mut [Option<FrameNodeValue>],
     _encoder: &mut CommandEncoder,
   ) {
-    // Recreate texture if resolution changed.
+    // Recreate texture if resolution changed, with the same usage the host has been created with.
     let size = self.0.size.get_actual_size();
-
-    let active_cloned = self.0.active.clone();
     let mut active = self.0.active.lock();
-    if size != active.0 && !active.2 {
-      let core = Core::get_instance();
-      active.2 = true;
-      let format = self.0.format;
-
-      core.spawn_background_task(move || {
-        /*
-        let texture = core.create_sampled_texture(
-          None,
-          TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED,
-          format,
-          size,
-          1,
-        );
-        let mut active = active_cloned.lock();
-        active.0 = size;
-        active.1 = texture;
-        active.2 = false;
-        */
-      });
+    if size != active.0 {
+      let texture = TextureGeneratorHost::create_texture(self.0.format, self.0.usage, size);
+      *active = (size, texture);
     }
 
     // Output
@@ -2,8 +2,9 @@
 
 layout (location = 0) out vec2 v_uv;
 
+// Single triangle covering the whole screen, drawn with 3 vertices and no buffers.
 void main()  {
   vec2 base = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
   gl_Position = vec4(base * 2.0 + -1.0, 0.0, 1.0);
   v_uv = vec2(base.x, 1.0 - base.y);
-}
\ No newline at end of file
+}
@@ -0,0 +1,111 @@
+use moonwave_common::Vector2;
+use moonwave_render::{CommandEncoder, FrameGraphNode, FrameNodeValue};
+use moonwave_resources::*;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+use super::TextureSize;
+use crate::Core;
+
+/// Tracks which of two alternating textures is written within the current frame.
+#[derive(Debug, Default)]
+pub struct PingPongState {
+  frame: Option<u64>,
+  current: usize,
+}
+
+impl PingPongState {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Swaps the textures once per frame and returns the indices of the current and previous texture.
+  /// Calling it multiple times within the same frame returns the same indices.
+  pub fn advance(&mut self, frame: u64) -> (usize, usize) {
+    if self.frame != Some(frame) {
+      if self.frame.is_some() {
+        self.current = 1 - self.current;
+      }
+      self.frame = Some(frame);
+    }
+    (self.current, 1 - self.current)
+  }
+}
+
+/// Pair of textures that swap roles every frame, e.g. for accumulation buffers of temporal effects.
+/// Nodes render into the current texture while sampling the result of the last frame.
+pub struct PingPongTextureHost {
+  size: TextureSize,
+  format: TextureFormat,
+  textures: Mutex<(Vector2<u32>, [SampledTexture; 2])>,
+  state: Mutex<PingPongState>,
+}
+
+impl PingPongTextureHost {
+  pub fn new(size: TextureSize, format: TextureFormat) -> Arc<Self> {
+    let actual_size = size.get_actual_size();
+    Arc::new(Self {
+      textures: Mutex::new((actual_size, Self::create_textures(format, actual_size))),
+      size,
+      format,
+      state: Mutex::new(PingPongState::new()),
+    })
+  }
+
+  fn create_textures(format: TextureFormat, size: Vector2<u32>) -> [SampledTexture; 2] {
+    let core = Core::get_instance();
+    let create = |label| {
+      core.create_sampled_texture(
+        Some(label),
+        TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED | TextureUsage::COPY_SRC,
+        format,
+        size,
+        1,
+      )
+    };
+    [create("PingPongTexture0"), create("PingPongTexture1")]
+  }
+
+  pub fn create_node(self: &Arc<Self>) -> PingPongTextureNode {
+    PingPongTextureNode(self.clone())
+  }
+}
+
+pub struct PingPongTextureNode(Arc<PingPongTextureHost>);
+
+impl PingPongTextureNode {
+  /// Texture to render into during this frame.
+  pub const OUTPUT_CURRENT: usize = 0;
+  /// Texture written during the last frame, empty on the first frame and after resizing.
+  pub const OUTPUT_PREVIOUS: usize = 1;
+}
+
+impl FrameGraphNode for PingPongTextureNode {
+  fn execute(
+    &self,
+    _inputs: &[Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
+    _encoder: &mut CommandEncoder,
+  ) {
+    let host = &self.0;
+    let (current, previous) = host
+      .state
+      .lock()
+      .advance(Core::get_instance().get_current_frame());
+
+    // History can't be kept across resolution changes.
+    let size = host.size.get_actual_size();
+    let mut textures = host.textures.lock();
+    if textures.0 != size {
+      *textures = (
+        size,
+        PingPongTextureHost::create_textures(host.format, size),
+      );
+    }
+
+    outputs[Self::OUTPUT_CURRENT] =
+      Some(FrameNodeValue::SampledTexture(textures.1[current].clone()));
+    outputs[Self::OUTPUT_PREVIOUS] =
+      Some(FrameNodeValue::SampledTexture(textures.1[previous].clone()));
+  }
+}
@@ -0,0 +1,85 @@
+use crate::Core;
+use moonwave_render::{CommandEncoderOutput, FrameGraphNode, FrameNodeValue, FrameTarget};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Frame based throttling that executes every n-th frame and retains the outputs in between.
+pub struct FrameThrottle<V: Clone> {
+  every: u64,
+  retained: Mutex<Option<Vec<V>>>,
+}
+
+impl<V: Clone> FrameThrottle<V> {
+  pub fn new(every: u64) -> Self {
+    assert!(every > 0, "Throttle interval must be at least one frame");
+    Self {
+      every,
+      retained: Mutex::new(None),
+    }
+  }
+
+  /// Runs the given function if the frame is due and retains its outputs,
+  /// otherwise the previously retained outputs are restored.
+  pub fn run<R, F: FnOnce(&mut [V]) -> R>(&self, frame: u64, outputs: &mut [V], f: F) -> Option<R> {
+    let mut retained = self.retained.lock();
+    match &*retained {
+      Some(previous) if frame % self.every != 0 => {
+        outputs.clone_from_slice(&previous[..outputs.len()]);
+        None
+      }
+      _ => {
+        let out = f(outputs);
+        *retained = Some(outputs.to_vec());
+        Some(out)
+      }
+    }
+  }
+}
+
+/// Wraps a frame node so that it is only executed every n-th frame.
+pub struct ThrottledNode<T: FrameGraphNode> {
+  node: Arc<T>,
+  throttle: Arc<FrameThrottle<Option<FrameNodeValue>>>,
+}
+
+impl<T: FrameGraphNode> ThrottledNode<T> {
+  pub fn new(node: T, every: u64) -> Self {
+    Self {
+      node: Arc::new(node),
+      throttle: Arc::new(FrameThrottle::new(every)),
+    }
+  }
+
+  /// Creates a node sharing the retained outputs that can be added to the frame graph.
+  pub fn create_node(&self) -> Self {
+    Self {
+      node: self.node.clone(),
+      throttle: self.throttle.clone(),
+    }
+  }
+}
+
+impl<T: FrameGraphNode> FrameGraphNode for ThrottledNode<T> {
+  fn execute_raw(
+    &self,
+    inputs: &[Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    target: &FrameTarget,
+  ) -> CommandEncoderOutput {
+    let frame = Core::get_instance().get_current_frame();
+    self
+      .throttle
+      .run(frame, outputs, |outputs| {
+        self
+          .node
+          .execute_raw(inputs, outputs, device, queue, target)
+      })
+      .unwrap_or_else(CommandEncoderOutput::empty)
+  }
+
+  fn get_bypass(&self) -> Option<(usize, usize)> {
+    self.node.get_bypass()
+  }
+}
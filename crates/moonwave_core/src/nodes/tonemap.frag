@@ -0,0 +1,31 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform texture2D t_source;
+layout(set = 0, binding = 1) uniform sampler s_source;
+
+#ifdef BLOOM
+layout(set = 1, binding = 0) uniform texture2D t_bloom;
+layout(set = 1, binding = 1) uniform sampler s_bloom;
+#endif
+
+layout(location=0) in vec2 v_uv;
+
+layout(location=0) out vec4 f_color;
+
+// Fitted ACES filmic curve, mirrors `tonemap_aces`.
+vec3 aces(vec3 x) {
+  const float a = 2.51;
+  const float b = 0.03;
+  const float c = 2.43;
+  const float d = 0.59;
+  const float e = 0.14;
+  return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+void main() {
+  vec4 source = texture(sampler2D(t_source, s_source), v_uv);
+#ifdef BLOOM
+  source.rgb += texture(sampler2D(t_bloom, s_bloom), v_uv).rgb;
+#endif
+  f_color = vec4(aces(max(source.rgb, vec3(0.0))), source.a);
+}
@@ -0,0 +1,127 @@
+use moonwave_common::{Vector3, Vector4};
+use moonwave_render::{
+  CommandEncoder, FrameGraphNode, FrameNodeValue, RenderPassCommandEncoderBuilder,
+};
+use moonwave_resources::*;
+use once_cell::sync::OnceCell;
+use shaderc::ShaderKind;
+
+use crate::{Core, PresentToScreen, FULLSCREEN_TRIANGLE_VS};
+
+static TONEMAP_RESOURCES: OnceCell<TonemapResources> = OnceCell::new();
+
+struct TonemapResources {
+  pipeline: ResourceRc<RenderPipeline>,
+  /// Variant adding the bloom texture onto the scene before mapping it.
+  bloom_pipeline: ResourceRc<RenderPipeline>,
+}
+
+impl TonemapResources {
+  fn new() -> Self {
+    let core = Core::get_instance();
+    let vs = core
+      .create_shader_from_glsl(FULLSCREEN_TRIANGLE_VS, "TonemapVS", ShaderKind::Vertex)
+      .unwrap();
+    let create_pipeline = |name: &str, source: &str, textures: usize| {
+      let fs = core
+        .create_shader_from_glsl(source, name, ShaderKind::Fragment)
+        .unwrap();
+      let layout = (0..textures).fold(PipelineLayoutDescriptor::new(), |desc, _| {
+        desc.add_binding(
+          core
+            .get_gp_resources()
+            .sampled_texture_bind_group_layout
+            .clone(),
+        )
+      });
+      core
+        .create_render_pipeline(
+          RenderPipelineDescriptor::new_without_vertices(
+            core.create_pipeline_layout(layout),
+            vs.clone(),
+            fs,
+          )
+          .add_color_output(TextureFormat::Bgra8UnormSrgb),
+        )
+        .unwrap()
+    };
+
+    let source = include_str!("./tonemap.frag");
+    Self {
+      pipeline: create_pipeline("TonemapFS", source, 1),
+      bloom_pipeline: create_pipeline(
+        "TonemapBloomFS",
+        &source.replace("#version 450", "#version 450\n#define BLOOM"),
+        2,
+      ),
+    }
+  }
+}
+
+/// Fitted ACES filmic curve mapping linear HDR color into `0..=1`, mirrors the tonemap shader.
+pub fn tonemap_aces(color: Vector3<f32>) -> Vector3<f32> {
+  let map = |x: f32| {
+    let x = x.max(0.0);
+    ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+  };
+  Vector3::new(map(color.x), map(color.y), map(color.z))
+}
+
+/// Maps a `Rgba16Float` scene target into an sRGB target that can be presented.
+/// An optional bloom texture, e.g. the output of a `BloomNode`, is added onto the scene first.
+/// It is sampled with the same coordinates, so it should be a full screen `Rgba16Float` target.
+pub struct TonemapNode;
+
+impl TonemapNode {
+  pub const INPUT_TEXTURE: usize = 0;
+  pub const INPUT_TARGET: usize = 1;
+  pub const INPUT_BLOOM: usize = 2;
+  pub const OUTPUT_TEXTURE: usize = 0;
+
+  pub fn new() -> Self {
+    let _ = TONEMAP_RESOURCES.get_or_init(TonemapResources::new);
+
+    Self
+  }
+}
+
+impl FrameGraphNode for TonemapNode {
+  fn execute(
+    &self,
+    inputs: &[Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
+    encoder: &mut CommandEncoder,
+  ) {
+    optick::event!("FrameGraph::Tonemap");
+    let input = |index: usize| inputs[index].as_ref().unwrap().get_sampled_texture();
+    let source = input(Self::INPUT_TEXTURE).bind_group.clone();
+    let target = input(Self::INPUT_TARGET).clone();
+    let bloom = FrameNodeValue::optional_input(inputs, Self::INPUT_BLOOM)
+      .and_then(FrameNodeValue::try_get_sampled_texture)
+      .map(|bloom| bloom.bind_group.clone());
+    let resources = TONEMAP_RESOURCES.get().unwrap();
+
+    {
+      let mut rpb = RenderPassCommandEncoderBuilder::new("tonemap_rp");
+      rpb.add_color_output(&target.view, Vector4::new(0.0, 0.0, 0.0, 1.0));
+
+      let mut rp = encoder.create_render_pass_encoder(rpb);
+      rp.set_bind_group(0, source);
+      match bloom {
+        Some(bloom) => {
+          rp.set_pipeline(resources.bloom_pipeline.clone());
+          rp.set_bind_group(1, bloom);
+        }
+        None => rp.set_pipeline(resources.pipeline.clone()),
+      }
+      rp.render(PresentToScreen::VERTICES);
+    }
+
+    outputs[Self::OUTPUT_TEXTURE] = Some(FrameNodeValue::SampledTexture(target));
+  }
+
+  /// Presents the untouched scene while disabled.
+  fn get_bypass(&self) -> Option<(usize, usize)> {
+    Some((Self::INPUT_TEXTURE, Self::OUTPUT_TEXTURE))
+  }
+}
@@ -1,7 +1,26 @@
-use std::{any::Any, sync::Arc};
+use std::{any::Any, sync::Arc, time::Duration};
 use std::{any::TypeId, collections::HashMap};
 
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+
+/// Time spent within service calls of `benched` services by `Trait::method`.
+/// Each locator owns its timings and hands them to the `benched` services registered with it.
+#[derive(Default)]
+pub struct ServiceTimings(Mutex<HashMap<String, Duration>>);
+
+impl ServiceTimings {
+  /// Adds the duration of a single service call, used by the `benched` service extension.
+  #[doc(hidden)]
+  pub fn record(&self, name: &str, duration: Duration) {
+    let mut timings = self.0.lock();
+    match timings.get_mut(name) {
+      Some(total) => *total += duration,
+      None => {
+        timings.insert(name.to_string(), duration);
+      }
+    }
+  }
+}
 
 pub trait ServiceSafeType: Any + Send + Sync + 'static {}
 
@@ -12,19 +31,26 @@ pub trait TypedServiceTrait: 'static {
 pub trait TypedServiceIntoHost: 'static {
   type Host: ServiceSafeType;
   fn into_host(self) -> Self::Host;
+
+  /// Called on registration, services that measure their calls record them into the given timings.
+  /// Extensions forward the timings to the service they wrap.
+  fn attach_timings(&mut self, _timings: &Arc<ServiceTimings>) {}
 }
 
 pub struct ServiceLocator {
   systems: RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync + 'static>>>,
+  timings: Arc<ServiceTimings>,
 }
 
 impl ServiceLocator {
   pub fn new() -> Self {
     Self {
       systems: RwLock::new(HashMap::new()),
+      timings: Arc::new(ServiceTimings::default()),
     }
   }
-  pub fn register<T: TypedServiceIntoHost>(&self, system: T) {
+  pub fn register<T: TypedServiceIntoHost>(&self, mut system: T) {
+    system.attach_timings(&self.timings);
     let mut systems = self.systems.write();
     systems.insert(TypeId::of::<T::Host>(), Arc::new(system.into_host()));
   }
@@ -41,4 +67,17 @@ impl ServiceLocator {
       .ok()
       .unwrap_or_else(|| panic!("Discovery of invalid type"))
   }
+
+  /// Total time spent in calls of `benched` services since the last reset by `Trait::method`,
+  /// e.g. to show service costs in an overlay without an optick capture.
+  /// Services are measured wherever `benched` appears among their extensions,
+  /// e.g. `benched().logged()`.
+  pub fn timings(&self) -> HashMap<String, Duration> {
+    self.timings.0.lock().clone()
+  }
+
+  /// Clears the collected timings, e.g. once per frame to show per frame costs.
+  pub fn reset_timings(&self) {
+    self.timings.0.lock().clear();
+  }
 }
@@ -0,0 +1,73 @@
+use futures::executor::block_on;
+use log::error;
+use parking_lot::{Mutex, ReentrantMutex};
+
+/// Abstraction over devices supporting error scopes.
+pub trait ErrorScopeHost {
+  fn push_error_scope(&self, filter: wgpu::ErrorFilter);
+  fn pop_error_scope(&self) -> Option<String>;
+}
+
+impl ErrorScopeHost for wgpu::Device {
+  fn push_error_scope(&self, filter: wgpu::ErrorFilter) {
+    wgpu::Device::push_error_scope(self, filter);
+  }
+
+  fn pop_error_scope(&self) -> Option<String> {
+    block_on(wgpu::Device::pop_error_scope(self)).map(|err| err.to_string())
+  }
+}
+
+/// A gpu error captured within an error scope together with the call that produced it.
+#[derive(Debug, Clone)]
+pub struct CapturedError {
+  pub context: &'static str,
+  pub message: String,
+}
+
+/// Collects all errors captured by error scopes.
+pub struct ErrorScopeCapture {
+  errors: Mutex<Vec<CapturedError>>,
+  /// Error scopes are global to the device, so only one thread may have scopes open at a time.
+  /// Nested captures of the same thread are fine as the innermost scope catches the error.
+  scope: ReentrantMutex<()>,
+}
+
+impl ErrorScopeCapture {
+  pub fn new() -> Self {
+    Self {
+      errors: Mutex::new(Vec::new()),
+      scope: ReentrantMutex::new(()),
+    }
+  }
+
+  /// Executes the given function within validation and out of memory error scopes.
+  /// Captures of other threads wait until the scopes are popped again, otherwise their errors
+  /// would end up in the scopes of this capture or the other way round.
+  pub fn capture<H: ErrorScopeHost, R, F: FnOnce() -> R>(
+    &self,
+    host: &H,
+    context: &'static str,
+    f: F,
+  ) -> R {
+    let _scope = self.scope.lock();
+    host.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+    host.push_error_scope(wgpu::ErrorFilter::Validation);
+    let out = f();
+
+    // Scopes are popped in reverse order.
+    for _ in 0..2 {
+      if let Some(message) = host.pop_error_scope() {
+        error!("{} produced a gpu error: {}", context, message);
+        self.errors.lock().push(CapturedError { context, message });
+      }
+    }
+
+    out
+  }
+
+  /// Returns and clears all captured errors.
+  pub fn drain(&self) -> Vec<CapturedError> {
+    self.errors.lock().drain(..).collect()
+  }
+}
@@ -0,0 +1,37 @@
+use legion::systems::CommandBuffer;
+use legion::Entity;
+use moonwave_core::{ActorRc, Core, CoreConfig, Spawnable};
+
+#[derive(Clone, Debug, PartialEq)]
+struct Barrel {
+  health: u32,
+}
+
+impl Spawnable for Barrel {
+  fn spawn(self, parent: Option<Entity>, level: usize, cmd: &mut CommandBuffer) -> ActorRc<Self> {
+    ActorRc::new(cmd, self, parent, level, Vec::new())
+  }
+}
+
+#[test]
+fn actor_component_access_test() {
+  if !Core::try_initialize_headless(CoreConfig::new()) {
+    return;
+  }
+  let core = Core::get_instance();
+
+  // The actor only exists in the world once its command buffer has been flushed.
+  let (actor, _) = core.get_world().spawn_actor_weak(Barrel { health: 3 });
+  assert!(actor.try_entry().is_none());
+  assert_eq!(actor.get_component::<Barrel>(), None);
+
+  Core::run_headless_frame().unwrap();
+  assert_eq!(actor.get_component::<Barrel>(), Some(Barrel { health: 3 }));
+  assert_eq!(
+    actor.with_component(|barrel: &Barrel| barrel.health),
+    Some(3)
+  );
+
+  // Components the actor doesn't have.
+  assert_eq!(actor.get_component::<u32>(), None);
+}
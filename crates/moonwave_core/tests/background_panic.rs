@@ -0,0 +1,36 @@
+use std::panic::Location;
+use std::sync::{Arc, Mutex};
+
+use moonwave_core::{catch_background_panic, BackgroundPanicHandler, CoreConfig};
+
+#[test]
+fn background_panic_is_caught_with_call_site() {
+  let location = Location::caller();
+  let panic = catch_background_panic(|| panic!("mesh bake failed"), location, None).unwrap();
+  assert_eq!(panic.message, "mesh bake failed");
+  assert_eq!(panic.location.file(), file!());
+
+  // Formatted panic messages are kept as well.
+  let panic = catch_background_panic(|| panic!("lod {} failed", 2), location, None).unwrap();
+  assert_eq!(panic.message, "lod 2 failed");
+}
+
+#[test]
+fn background_panic_is_forwarded_to_handler() {
+  let received = Arc::new(Mutex::new(Vec::new()));
+  let received_handler = received.clone();
+  let handler = BackgroundPanicHandler::new(move |panic| {
+    received_handler.lock().unwrap().push(panic.message.clone());
+  });
+
+  catch_background_panic(|| panic!("first"), Location::caller(), Some(&handler));
+  assert!(catch_background_panic(|| {}, Location::caller(), Some(&handler)).is_none());
+  assert_eq!(*received.lock().unwrap(), vec!["first".to_string()]);
+}
+
+#[test]
+fn background_panic_handler_is_configurable() {
+  let config = CoreConfig::new().with_background_panic_handler(|_| {});
+  assert!(config.background_panic_handler.is_some());
+  assert!(CoreConfig::new().background_panic_handler.is_none());
+}
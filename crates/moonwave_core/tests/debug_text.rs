@@ -0,0 +1,66 @@
+use moonwave_common::{Vector2, Vector4};
+use moonwave_core::{
+  build_debug_text_vertices, debug_text_atlas_cell_size, rasterize_debug_text_atlas,
+  DebugTextQueue, DEBUG_TEXT_GLYPH_PADDING, DEBUG_TEXT_VERTICES_PER_GLYPH,
+};
+
+#[test]
+fn debug_text_builds_quad_per_glyph() {
+  let queue = DebugTextQueue::new();
+  let white = Vector4::new(1.0, 1.0, 1.0, 1.0);
+  queue.push("fps: 60", Vector2::new(0.0, 0.0), white);
+  queue.push("a\nb", Vector2::new(10.0, 20.0), white);
+  assert_eq!(queue.len(), 2);
+
+  // Whitespaces and line breaks don't produce quads.
+  let texts = queue.take();
+  let vertices = build_debug_text_vertices(&texts, Vector2::new(800.0, 600.0));
+  assert_eq!(vertices.len(), 8 * DEBUG_TEXT_VERTICES_PER_GLYPH);
+
+  // Queue is cleared once the texts have been taken for drawing.
+  assert!(queue.is_empty());
+  assert!(build_debug_text_vertices(&queue.take(), Vector2::new(800.0, 600.0)).is_empty());
+}
+
+#[test]
+fn debug_text_starts_at_top_left() {
+  let queue = DebugTextQueue::new();
+  queue.push(
+    "x",
+    Vector2::new(0.0, 0.0),
+    Vector4::new(1.0, 0.0, 0.0, 1.0),
+  );
+
+  // The quad includes the padding of the glyph's cell.
+  let padding = DEBUG_TEXT_GLYPH_PADDING as f32;
+  let vertices = build_debug_text_vertices(&queue.take(), Vector2::new(800.0, 600.0));
+  assert_eq!(
+    vertices[0].position,
+    [-1.0 - padding / 400.0, 1.0 + padding / 300.0]
+  );
+  assert_eq!(vertices[0].color, [1.0, 0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn debug_text_glyphs_fit_into_their_cells() {
+  let (pixels, size) = rasterize_debug_text_atlas();
+  let cell = debug_text_atlas_cell_size();
+  let alpha = |x: u32, y: u32| pixels[((y * size.x + x) * 4 + 3) as usize];
+
+  // Coverage on the outer ring of a cell would mean the glyph has been clipped.
+  for cell_y in (0..size.y).step_by(cell.y as usize) {
+    for cell_x in (0..size.x).step_by(cell.x as usize) {
+      for x in cell_x..cell_x + cell.x {
+        assert_eq!(alpha(x, cell_y), 0);
+        assert_eq!(alpha(x, cell_y + cell.y - 1), 0);
+      }
+      for y in cell_y..cell_y + cell.y {
+        assert_eq!(alpha(cell_x, y), 0);
+        assert_eq!(alpha(cell_x + cell.x - 1, y), 0);
+      }
+    }
+  }
+
+  // Sanity check that glyphs have been drawn at all.
+  assert!(pixels.chunks_exact(4).any(|pixel| pixel[3] > 0));
+}
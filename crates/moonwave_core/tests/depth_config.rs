@@ -0,0 +1,16 @@
+use moonwave_core::CoreConfig;
+use moonwave_resources::DepthMode;
+use wgpu::TextureFormat;
+
+#[test]
+fn depth_config_test() {
+  let config = CoreConfig::new();
+  assert_eq!(config.depth_format, TextureFormat::Depth32Float);
+  assert_eq!(config.depth_mode, DepthMode::Standard);
+
+  let config = CoreConfig::new()
+    .with_depth_format(TextureFormat::Depth24Plus)
+    .with_depth_mode(DepthMode::ReverseZ);
+  assert_eq!(config.depth_format, TextureFormat::Depth24Plus);
+  assert_eq!(config.depth_mode, DepthMode::ReverseZ);
+}
@@ -0,0 +1,76 @@
+use moonwave_core::{Core, CoreConfig, DeviceConfig, DeviceConfigError};
+use wgpu::{Features, Limits};
+
+#[test]
+fn default_device_config_test() {
+  let config = CoreConfig::new().device;
+  let features = config.features(Features::all()).unwrap();
+
+  assert!(features.contains(Features::NON_FILL_POLYGON_MODE));
+  assert!(features.contains(Features::SAMPLED_TEXTURE_BINDING_ARRAY));
+  assert!(features.contains(Features::TIMESTAMP_QUERY));
+  assert!(features.contains(Features::TEXTURE_COMPRESSION_BC));
+  assert!(features.contains(Features::MULTI_DRAW_INDIRECT));
+}
+
+#[test]
+fn unsupported_features_are_dropped_test() {
+  let config = DeviceConfig::new().with_polygon_mode_line(false);
+  let features = config.features(Features::SAMPLED_TEXTURE_BINDING_ARRAY);
+
+  assert_eq!(features, Ok(Features::SAMPLED_TEXTURE_BINDING_ARRAY));
+}
+
+#[test]
+fn unsupported_required_features_fail_test() {
+  let config = DeviceConfig::new()
+    .with_timestamp_query(true)
+    .with_multi_draw_indirect(true);
+  assert_eq!(
+    config.features(Features::TIMESTAMP_QUERY),
+    Err(DeviceConfigError::UnsupportedFeatures(
+      Features::MULTI_DRAW_INDIRECT
+    ))
+  );
+
+  // Disabling a feature again makes it optional.
+  let config = config.with_multi_draw_indirect(false);
+  assert_eq!(
+    config.features(Features::TIMESTAMP_QUERY),
+    Ok(Features::TIMESTAMP_QUERY)
+  );
+}
+
+#[test]
+fn wireframe_requires_polygon_mode_line_test() {
+  let config = CoreConfig::new()
+    .with_wireframe(true)
+    .with_device(DeviceConfig::new().with_polygon_mode_line(false));
+  if !Core::try_initialize_headless(config) {
+    return;
+  }
+  let core = Core::get_instance();
+
+  // Neither the initial state nor enabling it later turn on wireframe mode.
+  assert!(!core.supports_wireframe());
+  assert!(!core.is_wireframe());
+  core.set_wireframe(true);
+  assert!(!core.is_wireframe());
+}
+
+#[test]
+fn limits_are_clamped_test() {
+  let supported = Limits {
+    max_bind_groups: 8,
+    max_sampled_textures_per_shader_stage: 64,
+    ..Limits::default()
+  };
+
+  let limits = DeviceConfig::new()
+    .with_max_bind_groups(6)
+    .with_max_texture_array_size(256)
+    .limits(&supported);
+
+  assert_eq!(limits.max_bind_groups, 6);
+  assert_eq!(limits.max_sampled_textures_per_shader_stage, 64);
+}
@@ -0,0 +1,42 @@
+use moonwave_core::{Core, CoreConfig};
+use moonwave_resources::{
+  BindGroupDescriptor, BindGroupLayoutDescriptor, BindGroupLayoutEntryType, BufferUsage,
+};
+
+#[test]
+fn concurrent_errors_are_attributed_to_their_scope_test() {
+  if !Core::try_initialize_headless(CoreConfig::new().with_error_scopes(true)) {
+    return;
+  }
+  let core = Core::get_instance();
+  let layout = core.create_bind_group_layout(
+    BindGroupLayoutDescriptor::new().add_entry(0, BindGroupLayoutEntryType::UniformBuffer),
+  );
+  let sampler = core.create_sampler();
+  assert!(core.drain_captured_errors().is_empty());
+
+  // Valid buffers are created concurrently, their scopes must neither catch nor swallow
+  // the errors of the invalid bind groups.
+  let creator = std::thread::spawn(|| {
+    for _ in 0..64 {
+      Core::get_instance().create_buffer(16, false, BufferUsage::UNIFORM, None);
+    }
+  });
+  for _ in 0..16 {
+    // A sampler bound to a uniform buffer slot.
+    core.create_bind_group(
+      BindGroupDescriptor::new(layout.clone()).add_sampler_binding(0, sampler.clone()),
+    );
+  }
+  creator.join().unwrap();
+
+  let errors = core.drain_captured_errors();
+  assert_eq!(errors.len(), 16);
+  assert!(errors
+    .iter()
+    .all(|error| error.context == "Core::create_bind_group"));
+
+  // Valid frames don't capture anything.
+  Core::run_headless_frame().unwrap();
+  assert!(core.drain_captured_errors().is_empty());
+}
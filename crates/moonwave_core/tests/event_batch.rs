@@ -0,0 +1,95 @@
+use legion::systems::ParallelRunnable;
+use legion::{IntoQuery, SystemBuilder};
+use moonwave_core::{EventReceiver, SystemStage, World};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct MouseMoved(i32, i32);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct KeyPressed(u32);
+
+type Received = Arc<Mutex<Vec<(u32, MouseMoved)>>>;
+
+fn create_world(received: Received) -> World {
+  let world = World::new();
+
+  world.add_temp_system(Box::new(SystemBuilder::new("spawn_receivers").build(
+    |cmd, _, _, _| {
+      for id in 0..2u32 {
+        cmd.push((id, EventReceiver::<MouseMoved>::new()));
+      }
+    },
+  )));
+
+  world.add_system_to_stage(
+    move || -> Box<dyn ParallelRunnable> {
+      let received = received.clone();
+      Box::new(
+        SystemBuilder::new("mouse_receiver")
+          .with_query(<(&u32, &mut EventReceiver<MouseMoved>)>::query())
+          .build(move |_, world, _, query| {
+            for (id, receiver) in query.iter_mut(world) {
+              for event in receiver.drain() {
+                received.lock().push((*id, event));
+              }
+            }
+          }),
+      )
+    },
+    SystemStage::Application(0),
+  );
+
+  world
+}
+
+#[test]
+fn batched_events_are_delivered_in_order_test() {
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(2)
+    .build()
+    .unwrap();
+  let received = Received::default();
+  let mut world = create_world(received.clone());
+  world.tick(0, &pool);
+
+  for i in 0..100 {
+    world.publish_event_batched(MouseMoved(i, -i));
+  }
+  // Other event types are batched separately and don't disturb receivers of the first.
+  world.publish_event_batched(KeyPressed(4));
+  world.tick(0, &pool);
+
+  let received = received.lock();
+  assert_eq!(received.len(), 200);
+  for id in 0..2u32 {
+    let events = received
+      .iter()
+      .filter(|(receiver, _)| *receiver == id)
+      .map(|(_, event)| *event)
+      .collect::<Vec<_>>();
+    assert_eq!(
+      events,
+      (0..100).map(|i| MouseMoved(i, -i)).collect::<Vec<_>>()
+    );
+  }
+}
+
+#[test]
+fn batched_and_single_events_mix_test() {
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(2)
+    .build()
+    .unwrap();
+  let received = Received::default();
+  let mut world = create_world(received.clone());
+  world.tick(0, &pool);
+
+  world.publish_event(MouseMoved(1, 1));
+  world.publish_event_batched(MouseMoved(2, 2));
+  world.publish_event_batched(MouseMoved(3, 3));
+  world.tick(0, &pool);
+
+  assert_eq!(received.lock().len(), 6);
+}
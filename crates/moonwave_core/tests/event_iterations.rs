@@ -0,0 +1,58 @@
+use moonwave_core::{run_bounded, World, DEFAULT_MAX_EVENT_ITERATIONS};
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy)]
+struct Ping(u32);
+
+#[test]
+fn self_republishing_handler_is_bounded_test() {
+  let mut queue = VecDeque::new();
+  queue.push_back(Ping(0));
+
+  // Every handled ping publishes the next one, which would never terminate without a limit.
+  let mut handled = Vec::new();
+  let iterations = run_bounded(8, || match queue.pop_front() {
+    Some(Ping(n)) => {
+      handled.push(n);
+      queue.push_back(Ping(n + 1));
+      true
+    }
+    None => false,
+  });
+
+  assert_eq!(iterations, 8);
+  assert_eq!(handled, (0..8).collect::<Vec<_>>());
+
+  // The remainder is left for the next frame.
+  assert_eq!(queue.len(), 1);
+}
+
+#[test]
+fn bounded_loop_stops_when_empty_test() {
+  let mut remaining = 3;
+  let iterations = run_bounded(8, || {
+    if remaining == 0 {
+      return false;
+    }
+    remaining -= 1;
+    true
+  });
+
+  assert_eq!(iterations, 3);
+}
+
+#[test]
+fn max_event_iterations_test() {
+  let world = World::new();
+  assert_eq!(
+    world.get_max_event_iterations(),
+    DEFAULT_MAX_EVENT_ITERATIONS
+  );
+
+  world.set_max_event_iterations(4);
+  assert_eq!(world.get_max_event_iterations(), 4);
+
+  // At least a single iteration is always handled.
+  world.set_max_event_iterations(0);
+  assert_eq!(world.get_max_event_iterations(), 1);
+}
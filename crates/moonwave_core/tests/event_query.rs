@@ -0,0 +1,132 @@
+use futures::{executor::block_on, future::BoxFuture, FutureExt};
+use legion::systems::ParallelRunnable;
+use legion::{IntoQuery, SystemBuilder};
+use moonwave_core::{Core, CoreConfig, EventQuery, EventReceiver, SystemStage, World};
+use parking_lot::Mutex;
+
+#[derive(Clone, Copy, PartialEq)]
+struct TileAt(i32, i32);
+
+struct Tile {
+  position: TileAt,
+  owner: u32,
+}
+
+type TileOwnerQuery = EventQuery<TileAt, u32>;
+
+fn create_world() -> World {
+  let world = World::new();
+  add_tiles(&world);
+  world
+}
+
+fn add_tiles(world: &World) {
+  // Spawn tiles owned by different actors.
+  world.add_temp_system(Box::new(SystemBuilder::new("spawn_tiles").build(
+    |cmd, _, _, _| {
+      for owner in 0..4 {
+        cmd.push((
+          Tile {
+            position: TileAt(owner as i32, 0),
+            owner,
+          },
+          EventReceiver::<TileOwnerQuery>::new(),
+        ));
+      }
+    },
+  )));
+
+  // Only the tile at the requested position answers.
+  world.add_system_to_stage(
+    || -> Box<dyn ParallelRunnable> {
+      Box::new(
+        SystemBuilder::new("tile_owner")
+          .with_query(<(&Tile, &mut EventReceiver<TileOwnerQuery>)>::query())
+          .build(|_, world, _, query| {
+            for (tile, receiver) in query.iter_mut(world) {
+              for event in receiver.drain() {
+                if event.request == tile.position {
+                  event.reply(tile.owner);
+                }
+              }
+            }
+          }),
+      )
+    },
+    SystemStage::Application(0),
+  );
+}
+
+#[test]
+fn event_query_reply_test() {
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(2)
+    .build()
+    .unwrap();
+  let mut world = create_world();
+  world.tick(0, &pool);
+
+  // Unresolved until the event has been handled.
+  let mut owner = world.publish_event_query::<_, u32>(TileAt(2, 0)).boxed();
+  assert_eq!((&mut owner).now_or_never(), None);
+
+  world.tick(0, &pool);
+  assert_eq!(block_on(owner), Some(2));
+}
+
+#[test]
+fn event_query_unanswered_test() {
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(2)
+    .build()
+    .unwrap();
+  let mut world = create_world();
+  world.tick(0, &pool);
+
+  // Nobody owns this tile so the query resolves empty after the tick.
+  let owner = world.publish_event_query::<_, u32>(TileAt(7, 7));
+  world.tick(0, &pool);
+  assert_eq!(block_on(owner), None);
+}
+
+static DEFERRED_OWNER: Mutex<Option<BoxFuture<'static, Option<u32>>>> =
+  parking_lot::const_mutex(None);
+
+/// Asks for the owner of a tile whenever it is delivered, so the query is published while events
+/// are still being handled.
+struct AskOwner;
+
+impl Clone for AskOwner {
+  fn clone(&self) -> Self {
+    let owner = Core::get_instance()
+      .get_world()
+      .publish_event_query::<_, u32>(TileAt(1, 0));
+    *DEFERRED_OWNER.lock() = Some(owner.boxed());
+    AskOwner
+  }
+}
+
+#[test]
+fn deferred_event_query_stays_pending_test() {
+  if !Core::try_initialize_headless(CoreConfig::new()) {
+    return;
+  }
+  let world = Core::get_instance().get_world();
+  world.set_max_event_iterations(1);
+  add_tiles(world);
+  world.add_temp_system(Box::new(SystemBuilder::new("spawn_asker").build(
+    |cmd, _, _, _| {
+      cmd.push((EventReceiver::<AskOwner>::new(),));
+    },
+  )));
+  Core::run_headless_frame().unwrap();
+
+  // The query is published within the only event iteration, its event is deferred to the next tick.
+  world.publish_event(AskOwner);
+  Core::run_headless_frame().unwrap();
+  let mut owner = DEFERRED_OWNER.lock().take().unwrap();
+  assert_eq!((&mut owner).now_or_never(), None);
+
+  Core::run_headless_frame().unwrap();
+  assert_eq!(block_on(owner), Some(1));
+}
@@ -0,0 +1,60 @@
+use legion::systems::ParallelRunnable;
+use legion::{IntoQuery, SystemBuilder};
+use moonwave_core::{Core, CoreConfig, EventReceiver, SystemStage};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Publishes the next ping whenever it is delivered, like a handler reacting to its own event
+/// type while events are still being handled.
+#[derive(Debug, PartialEq)]
+struct Ping(u32);
+
+impl Clone for Ping {
+  fn clone(&self) -> Self {
+    Core::get_instance()
+      .get_world()
+      .publish_event(Ping(self.0 + 1));
+    Ping(self.0)
+  }
+}
+
+#[test]
+fn self_republishing_event_is_bounded_per_tick_test() {
+  if !Core::try_initialize_headless(CoreConfig::new()) {
+    return;
+  }
+  let world = Core::get_instance().get_world();
+  world.set_max_event_iterations(4);
+
+  let received = Arc::new(Mutex::new(Vec::new()));
+  let received_cloned = received.clone();
+  world.add_temp_system(Box::new(SystemBuilder::new("spawn_receiver").build(
+    |cmd, _, _, _| {
+      cmd.push((EventReceiver::<Ping>::new(),));
+    },
+  )));
+  world.add_system_to_stage(
+    move || -> Box<dyn ParallelRunnable> {
+      let received = received_cloned.clone();
+      Box::new(
+        SystemBuilder::new("ping_receiver")
+          .with_query(<&mut EventReceiver<Ping>>::query())
+          .build(move |_, world, _, query| {
+            for receiver in query.iter_mut(world) {
+              received.lock().extend(receiver.drain().map(|Ping(n)| n));
+            }
+          }),
+      )
+    },
+    SystemStage::Application(0),
+  );
+  Core::run_headless_frame().unwrap();
+
+  // Every tick terminates after the iteration limit, the remaining pings are handled next tick.
+  world.publish_event(Ping(0));
+  Core::run_headless_frame().unwrap();
+  assert_eq!(*received.lock(), vec![0, 1, 2, 3]);
+
+  Core::run_headless_frame().unwrap();
+  assert_eq!(*received.lock(), (0..8).collect::<Vec<_>>());
+}
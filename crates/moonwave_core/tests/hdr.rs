@@ -0,0 +1,35 @@
+use moonwave_common::Vector3;
+use moonwave_core::{tonemap_aces, CoreConfig};
+use wgpu::{TextureFormat, TextureSampleType};
+
+#[test]
+fn hdr_color_target_format_test() {
+  assert_eq!(
+    CoreConfig::new().color_target_format(),
+    TextureFormat::Bgra8UnormSrgb
+  );
+
+  // HDR targets store unclamped floats so bright highlights survive until tonemapping.
+  let format = CoreConfig::new().with_hdr(true).color_target_format();
+  assert_eq!(format, TextureFormat::Rgba16Float);
+  assert!(matches!(
+    format.describe().sample_type,
+    TextureSampleType::Float { filterable: true }
+  ));
+}
+
+#[test]
+fn tonemap_aces_test() {
+  assert_eq!(
+    tonemap_aces(Vector3::new(0.0, 0.0, 0.0)),
+    Vector3::new(0.0, 0.0, 0.0)
+  );
+
+  // Bright values are compressed into the displayable range without clipping early.
+  let bright = tonemap_aces(Vector3::new(4.0, 16.0, 64.0));
+  assert!(bright.x < bright.y && bright.y <= bright.z);
+  assert!(bright.x > 0.9 && bright.z <= 1.0);
+
+  // Negative values from e.g. float precision issues are treated as black.
+  assert_eq!(tonemap_aces(Vector3::new(-1.0, 0.0, 0.0)).x, 0.0);
+}
@@ -0,0 +1,13 @@
+use moonwave_common::Vector2;
+use moonwave_core::CoreConfig;
+
+#[test]
+fn headless_size_test() {
+  assert_eq!(CoreConfig::new().headless_size, Vector2::new(1280, 720));
+
+  let config = CoreConfig::new()
+    .with_hdr(true)
+    .with_headless_size(Vector2::new(64, 32));
+  assert_eq!(config.headless_size, Vector2::new(64, 32));
+  assert!(config.hdr);
+}
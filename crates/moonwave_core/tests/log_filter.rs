@@ -0,0 +1,53 @@
+use moonwave_core::{Level, LevelFilter, LogFilter, LogFilterError, SERVICE_LOG_TARGET};
+
+#[test]
+fn default_log_filter_test() {
+  let filter = LogFilter::default();
+  assert!(filter.enabled("moonwave_scene::material", Level::Debug));
+  assert!(!filter.enabled("moonwave_scene::material", Level::Trace));
+  assert!(!filter.enabled("gfx_backend_vulkan", Level::Error));
+  assert!(!filter.enabled("naga::front", Level::Warn));
+  assert!(filter.enabled("wgpu_core::device", Level::Warn));
+  assert!(!filter.enabled("wgpu_core::device", Level::Info));
+  assert!(filter.enabled(SERVICE_LOG_TARGET, Level::Debug));
+}
+
+#[test]
+fn longest_target_wins_test() {
+  let filter = LogFilter::new()
+    .with_target("moonwave", LevelFilter::Warn)
+    .with_target("moonwave_shader", LevelFilter::Info);
+  assert_eq!(filter.level_for("moonwave_scene"), LevelFilter::Warn);
+  assert_eq!(
+    filter.level_for("moonwave_shader::graph"),
+    LevelFilter::Info
+  );
+  assert_eq!(filter.level_for("app"), LevelFilter::Debug);
+  assert_eq!(filter.max_level(), LevelFilter::Debug);
+
+  // Setting a target again replaces it.
+  let filter = filter.with_target("moonwave", LevelFilter::Off);
+  assert_eq!(filter.targets.len(), 2);
+  assert_eq!(filter.level_for("moonwave_scene"), LevelFilter::Off);
+}
+
+#[test]
+fn parse_log_filter_test() {
+  let filter = LogFilter::default()
+    .parse("info, moonwave::service=off,moonwave_shader=TRACE")
+    .unwrap();
+  assert_eq!(filter.default, LevelFilter::Info);
+  assert!(!filter.enabled(SERVICE_LOG_TARGET, Level::Error));
+  assert!(filter.enabled("moonwave_shader::graph", Level::Trace));
+  assert!(!filter.enabled("gfx_backend_vulkan", Level::Error));
+  assert_eq!(filter.max_level(), LevelFilter::Trace);
+
+  assert_eq!(
+    LogFilter::new().parse("moonwave=loud"),
+    Err(LogFilterError::InvalidLevel("loud".to_string()))
+  );
+  assert_eq!(
+    LogFilter::new().parse("=warn"),
+    Err(LogFilterError::MissingTarget("=warn".to_string()))
+  );
+}
@@ -0,0 +1,134 @@
+use legion::systems::ParallelRunnable;
+use legion::{IntoQuery, SystemBuilder};
+use moonwave_core::{Entity, Mailbox, SystemStage, World};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Damage(u32);
+
+type Received = Arc<Mutex<Vec<(u32, Damage)>>>;
+
+fn create_world(received: Received, entities: Arc<Mutex<Vec<Entity>>>) -> World {
+  let world = World::new();
+
+  world.add_temp_system(Box::new(SystemBuilder::new("spawn_mailboxes").build(
+    move |cmd, _, _, _| {
+      for id in 0..3u32 {
+        entities
+          .lock()
+          .push(cmd.push((id, Mailbox::<Damage>::new())));
+      }
+    },
+  )));
+
+  world.add_system_to_stage(
+    move || -> Box<dyn ParallelRunnable> {
+      let received = received.clone();
+      Box::new(
+        SystemBuilder::new("damage_receiver")
+          .with_query(<(&u32, &mut Mailbox<Damage>)>::query())
+          .build(move |_, world, _, query| {
+            for (id, mailbox) in query.iter_mut(world) {
+              for message in mailbox.drain() {
+                received.lock().push((*id, message));
+              }
+            }
+          }),
+      )
+    },
+    SystemStage::Application(0),
+  );
+
+  world
+}
+
+#[test]
+fn messages_only_reach_their_recipient_test() {
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(2)
+    .build()
+    .unwrap();
+  let received = Received::default();
+  let entities = Arc::new(Mutex::new(Vec::new()));
+  let mut world = create_world(received.clone(), entities.clone());
+  world.tick(0, &pool);
+
+  let entities = entities.lock().clone();
+  world.send_to(entities[1], Damage(10));
+  world.send_to(entities[1], Damage(5));
+  world.send_to(entities[2], Damage(1));
+  world.tick(0, &pool);
+
+  assert_eq!(
+    *received.lock(),
+    vec![(1, Damage(10)), (1, Damage(5)), (2, Damage(1))]
+  );
+}
+
+#[test]
+fn messages_to_entities_without_mailbox_are_dropped_test() {
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(2)
+    .build()
+    .unwrap();
+  let received = Received::default();
+  let entities = Arc::new(Mutex::new(Vec::new()));
+  let mut world = create_world(received.clone(), entities.clone());
+  world.tick(0, &pool);
+
+  // Entity without any mailbox.
+  let plain = Arc::new(Mutex::new(None));
+  let plain_cloned = plain.clone();
+  world.add_temp_system(Box::new(SystemBuilder::new("spawn_plain").build(
+    move |cmd, _, _, _| {
+      *plain_cloned.lock() = Some(cmd.push((7u32,)));
+    },
+  )));
+  world.tick(0, &pool);
+  let plain = plain.lock().unwrap();
+
+  // Broadcast events and messages of other types don't end up in mailboxes.
+  let entities = entities.lock().clone();
+  world.publish_event(Damage(4));
+  world.send_to(entities[0], 4u64);
+  world.send_to(plain, Damage(4));
+  world.tick(0, &pool);
+  assert!(received.lock().is_empty());
+
+  // The mailbox still works afterwards.
+  world.send_to(entities[0], Damage(2));
+  world.tick(0, &pool);
+  assert_eq!(*received.lock(), vec![(0, Damage(2))]);
+}
+
+#[test]
+fn many_messages_keep_their_order_test() {
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(2)
+    .build()
+    .unwrap();
+  let received = Received::default();
+  let entities = Arc::new(Mutex::new(Vec::new()));
+  let mut world = create_world(received.clone(), entities.clone());
+  world.set_max_event_iterations(1);
+  world.tick(0, &pool);
+
+  // Interleaved messages to several entities are all delivered within a single event iteration.
+  let entities = entities.lock().clone();
+  for value in 0..1000 {
+    world.send_to(entities[value as usize % 3], Damage(value));
+  }
+  world.tick(0, &pool);
+
+  let received = received.lock();
+  assert_eq!(received.len(), 1000);
+  for id in 0..3u32 {
+    let values = received
+      .iter()
+      .filter(|(receiver, _)| *receiver == id)
+      .map(|(_, Damage(value))| *value)
+      .collect::<Vec<_>>();
+    assert_eq!(values, (id..1000).step_by(3).collect::<Vec<_>>());
+  }
+}
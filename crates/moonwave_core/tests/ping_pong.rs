@@ -0,0 +1,20 @@
+use moonwave_core::PingPongState;
+
+#[test]
+fn ping_pong_alternates_per_frame_test() {
+  let mut state = PingPongState::new();
+  assert_eq!(state.advance(0), (0, 1));
+  assert_eq!(state.advance(1), (1, 0));
+  assert_eq!(state.advance(2), (0, 1));
+}
+
+#[test]
+fn ping_pong_is_stable_within_frame_test() {
+  let mut state = PingPongState::new();
+  state.advance(5);
+
+  // Several nodes sharing the host see the same textures within a frame.
+  assert_eq!(state.advance(6), (1, 0));
+  assert_eq!(state.advance(6), (1, 0));
+  assert_eq!(state.advance(7), (0, 1));
+}
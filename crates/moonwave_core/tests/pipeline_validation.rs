@@ -0,0 +1,50 @@
+use moonwave_core::{Core, CoreConfig, ShaderKind, FULLSCREEN_TRIANGLE_VS};
+use moonwave_resources::{
+  InputStepMode, PipelineLayoutDescriptor, RenderPipelineDescriptor, TextureFormat,
+  VertexAttribute, VertexAttributeFormat, VertexBuffer, VertexBufferLayoutError,
+};
+
+const WHITE_FS: &str = "#version 450
+layout(location = 0) out vec4 color;
+void main() { color = vec4(1.0); }
+";
+
+fn buffer(location: usize) -> VertexBuffer {
+  VertexBuffer {
+    stride: 16,
+    attributes: vec![VertexAttribute {
+      name: format!("attr{}", location),
+      offset: 0,
+      format: VertexAttributeFormat::Float4,
+      location,
+    }],
+  }
+}
+
+#[test]
+fn colliding_vertex_buffers_fail_pipeline_creation_test() {
+  if !Core::try_initialize_headless(CoreConfig::new()) {
+    return;
+  }
+  let core = Core::get_instance();
+  let vs = core
+    .create_shader_from_glsl(FULLSCREEN_TRIANGLE_VS, "validation_vs", ShaderKind::Vertex)
+    .unwrap();
+  let fs = core
+    .create_shader_from_glsl(WHITE_FS, "validation_fs", ShaderKind::Fragment)
+    .unwrap();
+  let layout = core.create_pipeline_layout(PipelineLayoutDescriptor::new());
+
+  // Per-instance data overlapping the vertex attributes is rejected before reaching the device.
+  let desc = RenderPipelineDescriptor::new(layout, buffer(0), vs, fs)
+    .add_vertex_buffer(buffer(0), InputStepMode::Instance)
+    .add_color_output(TextureFormat::Bgra8UnormSrgb);
+  assert_eq!(
+    core.create_render_pipeline(desc).err(),
+    Some(VertexBufferLayoutError::DuplicateLocation {
+      location: 0,
+      first_slot: 0,
+      second_slot: 1,
+    })
+  );
+}
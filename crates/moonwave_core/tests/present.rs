@@ -0,0 +1,159 @@
+use moonwave_common::Vector2;
+use moonwave_core::{Core, CoreConfig, PresentToScreen, ShaderKind, FULLSCREEN_TRIANGLE_VS};
+use moonwave_render::DeviceHost;
+use moonwave_resources::{
+  PipelineLayoutDescriptor, RenderPipelineDescriptor, RenderPipelineOutput, TextureFormat,
+  TextureUsage,
+};
+
+/// Writes the interpolated uv of the fullscreen triangle into red and green.
+const UV_FS: &str = "#version 450
+layout(location = 0) in vec2 v_uv;
+layout(location = 0) out vec4 color;
+void main() { color = vec4(v_uv, 0.0, 1.0); }
+";
+
+/// Interpolates the triangle uvs at the given clip space position, `None` if outside.
+fn interpolate_uv(point: Vector2<f32>) -> Option<Vector2<f32>> {
+  let (a, uv_a) = PresentToScreen::fullscreen_triangle_vertex(0);
+  let (b, uv_b) = PresentToScreen::fullscreen_triangle_vertex(1);
+  let (c, uv_c) = PresentToScreen::fullscreen_triangle_vertex(2);
+
+  let area = (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y);
+  let w_b = ((point.x - a.x) * (c.y - a.y) - (c.x - a.x) * (point.y - a.y)) / area;
+  let w_c = ((b.x - a.x) * (point.y - a.y) - (point.x - a.x) * (b.y - a.y)) / area;
+  let w_a = 1.0 - w_b - w_c;
+  if w_a < 0.0 || w_b < 0.0 || w_c < 0.0 {
+    return None;
+  }
+
+  Some(uv_a * w_a + uv_b * w_b + uv_c * w_c)
+}
+
+#[test]
+fn present_draws_single_triangle() {
+  assert_eq!(PresentToScreen::VERTICES, 0..3);
+}
+
+#[test]
+fn present_triangle_covers_screen() {
+  let corners = [
+    (Vector2::new(-1.0, -1.0), Vector2::new(0.0, 1.0)),
+    (Vector2::new(1.0, -1.0), Vector2::new(1.0, 1.0)),
+    (Vector2::new(-1.0, 1.0), Vector2::new(0.0, 0.0)),
+    (Vector2::new(1.0, 1.0), Vector2::new(1.0, 0.0)),
+  ];
+
+  for (corner, expected) in corners.iter() {
+    let uv = interpolate_uv(*corner).expect("screen corner outside of triangle");
+    let diff = uv - *expected;
+    assert!(diff.x.abs() < 1e-5 && diff.y.abs() < 1e-5);
+  }
+}
+
+#[test]
+fn fullscreen_triangle_shader_matches_mirror() {
+  if !Core::try_initialize_headless(CoreConfig::new()) {
+    return;
+  }
+  let core = Core::get_instance();
+  let vs = core
+    .create_shader_from_glsl(FULLSCREEN_TRIANGLE_VS, "fullscreen_vs", ShaderKind::Vertex)
+    .unwrap();
+  let fs = core
+    .create_shader_from_glsl(UV_FS, "fullscreen_uv_fs", ShaderKind::Fragment)
+    .unwrap();
+  let layout = core.create_pipeline_layout(PipelineLayoutDescriptor::new());
+  let pipeline = core
+    .create_render_pipeline(
+      RenderPipelineDescriptor::new_without_vertices(layout, vs, fs)
+        .add_color_output(TextureFormat::Rgba8Unorm),
+    )
+    .unwrap();
+
+  // Draw the triangle just like the present pass does, into a target cleared to transparent.
+  let size = Vector2::new(16, 8);
+  let texture = core.create_texture(
+    Some("FullscreenTriangle"),
+    TextureUsage::RENDER_ATTACHMENT | TextureUsage::COPY_SRC,
+    TextureFormat::Rgba8Unorm,
+    size,
+    1,
+  );
+  let view = texture.get_raw().create_view(&Default::default());
+  let mut encoder = core
+    .get_device()
+    .create_command_encoder(&Default::default());
+  {
+    let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+      label: Some("FullscreenTriangle"),
+      color_attachments: &[wgpu::RenderPassColorAttachment {
+        resolve_target: None,
+        view: &view,
+        ops: wgpu::Operations {
+          load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+          store: true,
+        },
+      }],
+      depth_stencil_attachment: None,
+    });
+    rp.set_pipeline(pipeline.get_raw());
+    rp.draw(PresentToScreen::VERTICES, 0..1);
+  }
+  core.get_queue().submit(std::iter::once(encoder.finish()));
+  let pixels = core.read_texture(&texture, TextureFormat::Rgba8Unorm, size);
+
+  // Every pixel is covered and sees the uv the mirror interpolates at its center.
+  for (index, pixel) in pixels.chunks_exact(4).enumerate() {
+    let (x, y) = (index as u32 % size.x, index as u32 / size.x);
+    let center = Vector2::new(
+      (x as f32 + 0.5) / size.x as f32 * 2.0 - 1.0,
+      1.0 - (y as f32 + 0.5) / size.y as f32 * 2.0,
+    );
+    let uv = interpolate_uv(center).expect("pixel center outside of triangle");
+    assert_eq!(pixel[3], 255, "pixel {:?} not covered", (x, y));
+    for (channel, expected) in [uv.x, uv.y].iter().enumerate() {
+      let diff = (pixel[channel] as f32 - expected * 255.0).abs();
+      assert!(diff <= 2.0, "pixel {:?} has uv {:?}", (x, y), &pixel[..2]);
+    }
+  }
+}
+
+#[test]
+fn present_blends_layers_over_opaque_scene() {
+  assert_eq!(
+    PresentToScreen::layer_blend(PresentToScreen::INPUT_TEXTURE),
+    None
+  );
+  for input in [
+    PresentToScreen::INPUT_TEXTURE_SPRITES,
+    PresentToScreen::INPUT_TEXTURE_DEBUG,
+    PresentToScreen::INPUT_TEXTURE_UI,
+    PresentToScreen::INPUT_TEXTURE_IMD_TEXT,
+    PresentToScreen::INPUT_TEXTURE_DEBUG_TEXT,
+  ]
+  .iter()
+  {
+    assert_eq!(
+      PresentToScreen::layer_blend(*input),
+      Some(RenderPipelineOutput::ALPHA_BLENDING)
+    );
+  }
+}
+
+#[test]
+fn present_layer_order_test() {
+  // Existing graphs keep connecting to the original slots.
+  assert_eq!(PresentToScreen::INPUT_TEXTURE_DEBUG, 1);
+  assert_eq!(PresentToScreen::INPUT_TEXTURE_UI, 2);
+
+  // Every input is drawn exactly once, the scene first and sprites right on top of it.
+  let mut order = PresentToScreen::LAYER_ORDER.to_vec();
+  assert_eq!(order[0], PresentToScreen::INPUT_TEXTURE);
+  assert_eq!(order[1], PresentToScreen::INPUT_TEXTURE_SPRITES);
+  order.sort_unstable();
+  assert_eq!(
+    order,
+    (0..PresentToScreen::LAYER_ORDER.len()).collect::<Vec<_>>()
+  );
+}
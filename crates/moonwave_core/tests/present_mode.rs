@@ -0,0 +1,13 @@
+use moonwave_core::CoreConfig;
+use wgpu::PresentMode;
+
+#[test]
+fn default_present_mode_test() {
+  assert_eq!(CoreConfig::new().present_mode, PresentMode::Mailbox);
+}
+
+#[test]
+fn configured_present_mode_test() {
+  let config = CoreConfig::new().with_present_mode(PresentMode::Immediate);
+  assert_eq!(config.present_mode, PresentMode::Immediate);
+}
@@ -0,0 +1,31 @@
+use moonwave_common::Vector2;
+use moonwave_core::{Core, CoreConfig};
+use wgpu::TextureFormat;
+
+#[test]
+fn render_to_texture_test() {
+  let config = CoreConfig::new().with_headless_size(Vector2::new(64, 32));
+  if !Core::try_initialize_headless(config) {
+    return;
+  }
+  let core = Core::get_instance();
+
+  // Targets don't have to share the format or size of the screen.
+  let size = Vector2::new(16, 8);
+  let target = core.render_to_texture(size, TextureFormat::Rgba8Unorm);
+  Core::run_headless_frame().unwrap();
+
+  // Without any layers the presentation only clears the target to white.
+  let pixels = core.read_texture(&target.texture, TextureFormat::Rgba8Unorm, size);
+  assert_eq!(pixels.len(), 16 * 8 * 4);
+  assert!(pixels.iter().all(|channel| *channel == 255));
+
+  // Other formats get presentation pipelines of their own, white is stored as half float 1.0.
+  let target = core.render_to_texture(size, TextureFormat::Rgba16Float);
+  Core::run_headless_frame().unwrap();
+  let pixels = core.read_texture(&target.texture, TextureFormat::Rgba16Float, size);
+  assert_eq!(pixels.len(), 16 * 8 * 8);
+  assert!(pixels
+    .chunks_exact(2)
+    .all(|channel| u16::from_le_bytes([channel[0], channel[1]]) == 0x3c00));
+}
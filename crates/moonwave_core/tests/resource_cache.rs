@@ -0,0 +1,40 @@
+use moonwave_core::ResourceCache;
+use std::sync::Arc;
+
+#[test]
+fn identical_keys_share_one_resource() {
+  let cache = ResourceCache::new();
+  let mut created = 0;
+
+  let a = cache.get_or_insert_with(1, || {
+    created += 1;
+    Arc::new("pipeline")
+  });
+  let b = cache.get_or_insert_with(1, || {
+    created += 1;
+    Arc::new("pipeline")
+  });
+  let c = cache.get_or_insert_with(2, || Arc::new("other pipeline"));
+
+  assert_eq!(created, 1);
+  assert!(Arc::ptr_eq(&a, &b));
+  assert!(!Arc::ptr_eq(&a, &c));
+  assert_eq!(cache.len(), 2);
+  assert_eq!(cache.hits(), 1);
+
+  cache.clear();
+  assert!(cache.is_empty());
+}
+
+#[test]
+fn failed_creations_are_not_cached() {
+  let cache = ResourceCache::<u32>::new();
+
+  let failed: Result<u32, &str> = cache.get_or_try_insert_with(7, || Err("compilation failed"));
+  assert!(failed.is_err());
+  assert!(cache.is_empty());
+
+  let ok: Result<u32, &str> = cache.get_or_try_insert_with(7, || Ok(42));
+  assert_eq!(ok, Ok(42));
+  assert_eq!(cache.len(), 1);
+}
@@ -0,0 +1,32 @@
+use moonwave_common::Vector2;
+use moonwave_core::{Core, CoreConfig};
+use wgpu::{TextureFormat, TextureUsage};
+
+#[test]
+fn formats_of_shared_resources_test() {
+  if !Core::try_initialize_headless(CoreConfig::new()) {
+    return;
+  }
+  let core = Core::get_instance();
+
+  // Formats can still be attached once the resource has been cloned, all clones see them.
+  let sampler = core.create_sampler();
+  let shared = sampler.clone();
+  assert_eq!(sampler.get_formats(), None);
+  let sampler = sampler.with_formats(vec![TextureFormat::Rgba8Unorm]);
+  assert_eq!(shared.get_formats(), Some(vec![TextureFormat::Rgba8Unorm]));
+  assert_eq!(sampler.get_formats(), shared.get_formats());
+
+  // Views of a shared texture take over its format.
+  let texture = core.create_texture(
+    None,
+    TextureUsage::SAMPLED,
+    TextureFormat::Rgba16Float,
+    Vector2::new(4, 4),
+    1,
+  );
+  let shared = texture.clone();
+  let view = core.create_texture_view(texture);
+  assert_eq!(view.get_formats(), Some(vec![TextureFormat::Rgba16Float]));
+  assert_eq!(shared.get_formats(), view.get_formats());
+}
@@ -0,0 +1,68 @@
+use moonwave_core::{service_trait, ServiceLocator};
+use std::time::Duration;
+
+#[service_trait]
+pub trait TimedService {
+  fn call(&self, millis: u64) -> u64;
+  fn other(&self);
+}
+
+struct SleepingService;
+
+#[service_trait]
+impl TimedService for SleepingService {
+  fn call(&self, millis: u64) -> u64 {
+    std::thread::sleep(Duration::from_millis(millis));
+    millis
+  }
+
+  fn other(&self) {}
+}
+
+#[test]
+fn service_timings_test() {
+  let locator = ServiceLocator::new();
+  locator.register(SleepingService.benched());
+  let service = locator.discover::<TimedService>();
+  assert_eq!(service.call(2), 2);
+  service.call(3);
+  service.other();
+
+  let timings = locator.timings();
+  assert!(timings["TimedService::call"] >= Duration::from_millis(5));
+  assert!(timings.contains_key("TimedService::other"));
+
+  locator.reset_timings();
+  assert!(locator.timings().is_empty());
+}
+
+#[test]
+fn service_timings_per_locator_test() {
+  let benched = ServiceLocator::new();
+  benched.register(SleepingService.benched());
+  let plain = ServiceLocator::new();
+  plain.register(SleepingService);
+
+  // Calls are only measured by the locator the benched service is registered with.
+  plain.discover::<TimedService>().call(1);
+  assert!(plain.timings().is_empty());
+  assert!(benched.timings().is_empty());
+
+  benched.discover::<TimedService>().call(1);
+  assert!(plain.timings().is_empty());
+  assert_eq!(benched.timings().len(), 1);
+}
+
+#[test]
+fn nested_service_timings_test() {
+  // Benched services are measured regardless of the extensions wrapped around them.
+  let inner = ServiceLocator::new();
+  inner.register(SleepingService.benched().logged());
+  inner.discover::<TimedService>().call(1);
+  assert!(inner.timings()["TimedService::call"] >= Duration::from_millis(1));
+
+  let outer = ServiceLocator::new();
+  outer.register(SleepingService.logged().benched());
+  outer.discover::<TimedService>().call(1);
+  assert!(outer.timings()["TimedService::call"] >= Duration::from_millis(1));
+}
@@ -0,0 +1,17 @@
+use moonwave_core::{compile_glsl, ShaderError, ShaderKind};
+
+const BROKEN_FS: &str = "#version 450
+layout (location = 0) out vec4 color;
+void main() {
+  color = vec4(undefined_value, 1.0);
+}
+";
+
+#[test]
+fn shader_error_keeps_source_test() {
+  let err = compile_glsl(BROKEN_FS, "broken_fs", ShaderKind::Fragment).unwrap_err();
+  assert!(matches!(err, ShaderError::SpirVCompilationFailed(..)));
+  assert!(err.message().contains("undefined_value"));
+  assert_eq!(err.source_code(), BROKEN_FS);
+  assert!(err.to_string().contains(BROKEN_FS));
+}
@@ -0,0 +1,46 @@
+use legion::systems::CommandBuffer;
+use legion::{Entity, IntoQuery, SystemBuilder};
+use moonwave_core::{ActorRc, Spawnable, World};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+struct Crate;
+
+impl Spawnable for Crate {
+  fn spawn(self, parent: Option<Entity>, level: usize, cmd: &mut CommandBuffer) -> ActorRc<Self> {
+    ActorRc::new(cmd, self, parent, level, Vec::new())
+  }
+}
+
+struct Health(u32);
+
+#[test]
+fn weak_spawn_adds_components_after_flush() {
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(2)
+    .build()
+    .unwrap();
+  let mut world = World::new();
+
+  let (actor, mut weak) = world.spawn_actor_weak(Crate);
+  weak.add_component(Health(42));
+  weak.flush_to(&world);
+
+  let found = Arc::new(Mutex::new(Vec::new()));
+  let found_cloned = found.clone();
+  world.add_temp_system(Box::new(
+    SystemBuilder::new("find_health")
+      .with_query(<(Entity, &Crate, &Health)>::query())
+      .build(move |_, world, _, query| {
+        for (entity, _, health) in query.iter(world) {
+          found_cloned.lock().push((*entity, health.0));
+        }
+      }),
+  ));
+  world.tick(0, &pool);
+
+  assert_eq!(*found.lock(), vec![(actor.get_entity(), 42)]);
+
+  // Despawning on drop requires a running core.
+  std::mem::forget(actor);
+}
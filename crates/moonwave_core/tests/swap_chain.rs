@@ -0,0 +1,26 @@
+use moonwave_core::{swap_chain_recovery, SwapChainRecovery};
+use wgpu::SwapChainError;
+
+#[test]
+fn outdated_and_lost_swap_chains_are_recreated() {
+  assert_eq!(
+    swap_chain_recovery(&SwapChainError::Outdated),
+    SwapChainRecovery::Recreate
+  );
+  assert_eq!(
+    swap_chain_recovery(&SwapChainError::Lost),
+    SwapChainRecovery::Recreate
+  );
+}
+
+#[test]
+fn timeouts_skip_the_frame() {
+  assert_eq!(
+    swap_chain_recovery(&SwapChainError::Timeout),
+    SwapChainRecovery::Skip
+  );
+  assert_eq!(
+    swap_chain_recovery(&SwapChainError::OutOfMemory),
+    SwapChainRecovery::Fail
+  );
+}
@@ -0,0 +1,51 @@
+use legion::systems::ParallelRunnable;
+use legion::SystemBuilder;
+use moonwave_core::{SystemStage, World};
+use parking_lot::Mutex;
+use std::{sync::Arc, time::Duration};
+
+type Order = Arc<Mutex<Vec<&'static str>>>;
+
+/// System without any component access that legion would otherwise run in parallel.
+fn recording_system(
+  order: &Order,
+  name: &'static str,
+  sleep: u64,
+) -> impl Fn() -> Box<dyn ParallelRunnable> {
+  let order = order.clone();
+  move || -> Box<dyn ParallelRunnable> {
+    let order = order.clone();
+    Box::new(SystemBuilder::new(name).build(move |_, _, _, _| {
+      std::thread::sleep(Duration::from_millis(sleep));
+      order.lock().push(name);
+    }))
+  }
+}
+
+#[test]
+fn systems_run_in_priority_order_test() {
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(4)
+    .build()
+    .unwrap();
+  let order = Order::default();
+  let mut world = World::new();
+
+  let stage = SystemStage::Application(0);
+  world.add_system_to_stage_with_priority(recording_system(&order, "movement", 0), stage, 10);
+  world.add_system_to_stage_with_priority(recording_system(&order, "input", 4), stage, -10);
+  world.add_system_to_stage(recording_system(&order, "default", 2), stage);
+  world.add_system_to_stage_with_priority(
+    recording_system(&order, "later_stage", 0),
+    SystemStage::Application(1),
+    -100,
+  );
+
+  for _ in 0..4 {
+    world.tick(0, &pool);
+    assert_eq!(
+      order.lock().drain(..).collect::<Vec<_>>(),
+      vec!["input", "default", "movement", "later_stage"]
+    );
+  }
+}
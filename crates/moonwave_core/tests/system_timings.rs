@@ -0,0 +1,50 @@
+use legion::systems::ParallelRunnable;
+use legion::SystemBuilder;
+use moonwave_core::{SystemStage, World};
+use std::time::Duration;
+
+fn named_system(name: &'static str) -> impl Fn() -> Box<dyn ParallelRunnable> {
+  move || -> Box<dyn ParallelRunnable> { Box::new(SystemBuilder::new(name).build(|_, _, _, _| {})) }
+}
+
+#[test]
+fn system_timings_test() {
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(2)
+    .build()
+    .unwrap();
+  let mut world = World::new();
+  assert!(world.get_system_timings().is_empty());
+
+  world.add_system_to_stage(named_system("first"), SystemStage::Application(0));
+  world.add_system_to_stage(
+    || -> Box<dyn ParallelRunnable> {
+      Box::new(
+        SystemBuilder::new("second")
+          .build(|_, _, _, _| std::thread::sleep(Duration::from_millis(2))),
+      )
+    },
+    SystemStage::Application(1),
+  );
+  world.tick(0, &pool);
+
+  let timings = world.get_system_timings();
+  let mut names = timings
+    .iter()
+    .map(|(id, _)| id.to_string())
+    .collect::<Vec<_>>();
+  names.sort();
+  assert_eq!(names, vec!["first", "second"]);
+
+  // The sleeping system is measured including its sleep.
+  let second = timings
+    .iter()
+    .find(|(id, _)| id.to_string() == "second")
+    .map(|(_, duration)| *duration)
+    .unwrap();
+  assert!(second >= Duration::from_millis(2));
+
+  // Timings only cover the most recent tick.
+  world.tick(0, &pool);
+  assert_eq!(world.get_system_timings().len(), 2);
+}
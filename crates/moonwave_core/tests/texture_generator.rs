@@ -0,0 +1,34 @@
+use moonwave_common::Vector2;
+use moonwave_core::{Core, CoreConfig, TextureGeneratorHost, TextureSize};
+use wgpu::{TextureFormat, TextureUsage};
+
+#[test]
+fn texture_generator_usage_test() {
+  if !Core::try_initialize_headless(CoreConfig::new()) {
+    return;
+  }
+  let core = Core::get_instance();
+
+  // Custom usage replaces the default render attachment usage, sampling is always possible.
+  let size = Vector2::new(8, 4);
+  let host = TextureGeneratorHost::new_with_usage(
+    TextureSize::Custom(size),
+    TextureFormat::Rgba8Unorm,
+    TextureUsage::COPY_SRC,
+  );
+  assert_eq!(
+    host.get_usage(),
+    TextureUsage::COPY_SRC | TextureUsage::SAMPLED
+  );
+
+  // Copying from the texture is only valid if it has been created with the custom usage.
+  let texture = host.get_texture();
+  let pixels = core.read_texture(&texture.texture, TextureFormat::Rgba8Unorm, size);
+  assert_eq!(pixels.len(), 8 * 4 * 4);
+
+  let host = TextureGeneratorHost::new(TextureSize::Custom(size), TextureFormat::Rgba8Unorm);
+  assert_eq!(
+    host.get_usage(),
+    TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED
+  );
+}
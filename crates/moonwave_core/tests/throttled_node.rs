@@ -0,0 +1,24 @@
+use moonwave_core::FrameThrottle;
+
+#[test]
+fn throttle_executes_every_n_frames() {
+  let throttle = FrameThrottle::<Option<u32>>::new(3);
+  let mut executed = Vec::new();
+  let mut outputs = vec![None; 2];
+
+  for frame in 0..7u64 {
+    let result = throttle.run(frame, &mut outputs, |outputs| {
+      outputs[0] = Some(frame as u32);
+      frame
+    });
+    if let Some(frame) = result {
+      executed.push(frame);
+    }
+
+    // In between executions the last outputs must be reused.
+    assert_eq!(outputs[0], Some((frame - frame % 3) as u32));
+    outputs = vec![None; 2];
+  }
+
+  assert_eq!(executed, vec![0, 3, 6]);
+}
@@ -0,0 +1,19 @@
+#![feature(arbitrary_self_types)]
+#![allow(dead_code)]
+
+use moonwave_core::actor;
+
+struct Health(u32);
+
+struct Conflicting;
+
+#[actor]
+impl Conflicting {
+  #[actor_tick(real)]
+  fn tick(&self, health: &mut Health) {}
+
+  #[actor_spawn]
+  fn on_spawn(&self, health: &Health) {}
+}
+
+fn main() {}
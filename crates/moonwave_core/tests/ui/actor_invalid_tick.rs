@@ -0,0 +1,14 @@
+#![feature(arbitrary_self_types)]
+#![allow(dead_code)]
+
+use moonwave_core::actor;
+
+struct Ticking;
+
+#[actor]
+impl Ticking {
+  #[actor_tick(frame)]
+  fn tick(&self) {}
+}
+
+fn main() {}
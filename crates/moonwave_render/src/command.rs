@@ -8,6 +8,7 @@ use std::{
 use futures::Future;
 use moonwave_common::*;
 use moonwave_resources::*;
+use thiserror::Error;
 
 pub struct CommandEncoderOutput {
   pub command_buffer: Option<wgpu::CommandBuffer>,
@@ -119,6 +120,10 @@ impl<'a> CommandEncoder<'a> {
       builder,
       encoder: &mut self.encoder,
       commands: Vec::new(),
+      multi_draw_indirect: self
+        .device
+        .features()
+        .contains(wgpu::Features::MULTI_DRAW_INDIRECT),
     }
   }
 
@@ -133,8 +138,9 @@ impl<'a> CommandEncoder<'a> {
 #[derive(Clone)]
 pub struct RenderPassCommandEncoderBuilder {
   name: String,
-  outputs: Vec<(ResourceRc<TextureView>, ColorRGBA32)>,
+  outputs: Vec<(ResourceRc<TextureView>, Option<ColorRGBA32>)>,
   depth: Option<ResourceRc<TextureView>>,
+  depth_mode: DepthMode,
 }
 
 impl RenderPassCommandEncoderBuilder {
@@ -143,16 +149,80 @@ impl RenderPassCommandEncoderBuilder {
       name: name.to_string(),
       outputs: Vec::new(),
       depth: None,
+      depth_mode: DepthMode::Standard,
     }
   }
 
   pub fn add_color_output(&mut self, view: &ResourceRc<TextureView>, clear: ColorRGBA32) {
-    self.outputs.push((view.clone(), clear));
+    self.outputs.push((view.clone(), Some(clear)));
+  }
+
+  /// Adds a color output that keeps its previous content instead of being cleared.
+  pub fn add_color_output_preserved(&mut self, view: &ResourceRc<TextureView>) {
+    self.outputs.push((view.clone(), None));
   }
 
   pub fn add_depth(&mut self, view: &ResourceRc<TextureView>) {
     self.depth = Some(view.clone());
   }
+
+  /// Clears the depth output to the far plane of the given mode.
+  pub fn set_depth_mode(&mut self, mode: DepthMode) {
+    self.depth_mode = mode;
+  }
+
+  pub fn get_depth_clear_value(&self) -> f32 {
+    self.depth_mode.clear_value()
+  }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RenderPassValidationError {
+  #[error("Render pass '{pass}' has {attachments} color attachments but the pipeline writes {outputs} color outputs")]
+  AttachmentCount {
+    pass: String,
+    attachments: usize,
+    outputs: usize,
+  },
+  #[error("Color attachment {index} of render pass '{pass}' is {attachment:?} but the pipeline writes {output:?}")]
+  FormatMismatch {
+    pass: String,
+    index: usize,
+    attachment: TextureFormat,
+    output: TextureFormat,
+  },
+}
+
+/// Checks that the color attachments of a render pass match the color outputs of a pipeline.
+/// Attachments of unknown format are only counted.
+pub fn validate_color_attachments(
+  pass: &str,
+  attachments: &[Option<TextureFormat>],
+  outputs: &[TextureFormat],
+) -> Result<(), RenderPassValidationError> {
+  if attachments.len() != outputs.len() {
+    return Err(RenderPassValidationError::AttachmentCount {
+      pass: pass.to_string(),
+      attachments: attachments.len(),
+      outputs: outputs.len(),
+    });
+  }
+
+  for (index, (attachment, output)) in attachments.iter().zip(outputs.iter()).enumerate() {
+    match attachment {
+      Some(attachment) if attachment != output => {
+        return Err(RenderPassValidationError::FormatMismatch {
+          pass: pass.to_string(),
+          index,
+          attachment: *attachment,
+          output: *output,
+        })
+      }
+      _ => {}
+    }
+  }
+
+  Ok(())
 }
 
 pub fn get_wgpu_color_rgb(color: ColorRGBA32) -> wgpu::Color {
@@ -164,18 +234,50 @@ pub fn get_wgpu_color_rgb(color: ColorRGBA32) -> wgpu::Color {
   }
 }
 
+/// Arguments of a single indexed indirect draw as laid out within an indirect buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DrawIndexedIndirectArgs {
+  pub index_count: u32,
+  pub instance_count: u32,
+  pub first_index: u32,
+  pub base_vertex: i32,
+  pub first_instance: u32,
+}
+
+unsafe impl bytemuck::Pod for DrawIndexedIndirectArgs {}
+unsafe impl bytemuck::Zeroable for DrawIndexedIndirectArgs {}
+
+impl DrawIndexedIndirectArgs {
+  /// Byte stride between consecutive draws within an indirect buffer.
+  pub const SIZE: u64 = std::mem::size_of::<Self>() as u64;
+
+  /// Offsets of `count` draws laid out back to back starting at `offset`.
+  pub fn offsets(offset: u64, count: u32) -> impl Iterator<Item = u64> {
+    (0..count as u64).map(move |index| offset + index * Self::SIZE)
+  }
+}
+
 enum RenderPassCommand {
   SetRenderPipeline(ResourceRc<RenderPipeline>),
-  SetVertexBuffer(ResourceRc<Buffer>),
+  SetVertexBuffer(u32, ResourceRc<Buffer>),
   SetIndexBuffer(IndexFormat, ResourceRc<Buffer>),
   SetBindGroup(u32, ResourceRc<BindGroup>),
+  Render(Range<u32>),
   RenderIndexed(Range<u32>),
+  RenderInstanced(Range<u32>, Range<u32>),
+  RenderIndexedInstanced(Range<u32>, Range<u32>),
+  RenderIndexedIndirect(ResourceRc<Buffer>, u64),
+  MultiRenderIndexedIndirect(ResourceRc<Buffer>, u64, u32),
+  SetScissorRect(u32, u32, u32, u32),
 }
 
 pub struct RenderPassCommandEncoder<'a> {
   builder: RenderPassCommandEncoderBuilder,
   encoder: &'a mut wgpu::CommandEncoder,
   commands: Vec<RenderPassCommand>,
+  /// Whether the device supports multi draw indirect, single indirect draws are used otherwise.
+  multi_draw_indirect: bool,
 }
 
 impl<'a> Drop for RenderPassCommandEncoder<'a> {
@@ -201,7 +303,10 @@ impl<'a> Drop for RenderPassCommandEncoder<'a> {
           view: &*output.0,
           ops: wgpu::Operations {
             store: true,
-            load: wgpu::LoadOp::Clear(get_wgpu_color_rgb(output.1)),
+            load: output
+              .1
+              .map(|clear| wgpu::LoadOp::Clear(get_wgpu_color_rgb(clear)))
+              .unwrap_or(wgpu::LoadOp::Load),
           },
         })
         .collect::<Vec<_>>(),
@@ -210,7 +315,7 @@ impl<'a> Drop for RenderPassCommandEncoder<'a> {
           view: &*depth,
           depth_ops: Some(wgpu::Operations {
             store: true,
-            load: wgpu::LoadOp::Clear(1.0),
+            load: wgpu::LoadOp::Clear(self.builder.depth_mode.clear_value()),
           }),
           stencil_ops: None,
         }
@@ -226,16 +331,45 @@ impl<'a> Drop for RenderPassCommandEncoder<'a> {
           RenderPassCommand::SetBindGroup(binding, bind) => {
             rp.set_bind_group(*binding, bind.get_raw(), &[])
           }
-          RenderPassCommand::SetVertexBuffer(buffer) => {
-            rp.set_vertex_buffer(0, buffer.get_raw().slice(0..))
+          RenderPassCommand::SetVertexBuffer(slot, buffer) => {
+            rp.set_vertex_buffer(*slot, buffer.get_raw().slice(0..))
           }
           RenderPassCommand::SetIndexBuffer(format, buffer) => {
             rp.set_index_buffer(buffer.get_raw().slice(0..), *format)
           }
+          RenderPassCommand::Render(range) => {
+            optick::event!("FrameGraph::RenderPassEncoder::draw");
+            rp.draw(range.clone(), 0..1)
+          }
           RenderPassCommand::RenderIndexed(range) => {
             optick::event!("FrameGraph::RenderPassEncoder::draw_indexed");
             rp.draw_indexed(range.clone(), 0, 0..1)
           }
+          RenderPassCommand::RenderInstanced(range, instances) => {
+            optick::event!("FrameGraph::RenderPassEncoder::draw_instanced");
+            rp.draw(range.clone(), instances.clone())
+          }
+          RenderPassCommand::RenderIndexedInstanced(range, instances) => {
+            optick::event!("FrameGraph::RenderPassEncoder::draw_indexed_instanced");
+            rp.draw_indexed(range.clone(), 0, instances.clone())
+          }
+          RenderPassCommand::RenderIndexedIndirect(buffer, offset) => {
+            optick::event!("FrameGraph::RenderPassEncoder::draw_indexed_indirect");
+            rp.draw_indexed_indirect(buffer.get_raw(), *offset)
+          }
+          RenderPassCommand::MultiRenderIndexedIndirect(buffer, offset, count) => {
+            optick::event!("FrameGraph::RenderPassEncoder::multi_draw_indexed_indirect");
+            if self.multi_draw_indirect {
+              rp.multi_draw_indexed_indirect(buffer.get_raw(), *offset, *count)
+            } else {
+              for offset in DrawIndexedIndirectArgs::offsets(*offset, *count) {
+                rp.draw_indexed_indirect(buffer.get_raw(), offset)
+              }
+            }
+          }
+          RenderPassCommand::SetScissorRect(x, y, width, height) => {
+            rp.set_scissor_rect(*x, *y, *width, *height)
+          }
           _ => {}
         }
       }
@@ -244,16 +378,40 @@ impl<'a> Drop for RenderPassCommandEncoder<'a> {
 }
 
 impl<'a> RenderPassCommandEncoder<'a> {
+  /// Debug builds panic with a descriptive error if the attachments don't match the pipeline.
   pub fn set_pipeline(&mut self, pipeline: ResourceRc<RenderPipeline>) {
+    if cfg!(debug_assertions) {
+      if let Some(outputs) = pipeline.get_formats() {
+        let attachments = self
+          .builder
+          .outputs
+          .iter()
+          .map(|(view, _)| {
+            view
+              .get_formats()
+              .and_then(|formats| formats.first().copied())
+          })
+          .collect::<Vec<_>>();
+        if let Err(err) = validate_color_attachments(&self.builder.name, &attachments, &outputs) {
+          panic!("{}", err);
+        }
+      }
+    }
+
     self
       .commands
       .push(RenderPassCommand::SetRenderPipeline(pipeline));
   }
 
   pub fn set_vertex_buffer(&mut self, buffer: ResourceRc<Buffer>) {
+    self.set_vertex_buffer_slot(0, buffer);
+  }
+
+  /// Binds a vertex buffer to the given slot, e.g. slot 1 for per-instance data.
+  pub fn set_vertex_buffer_slot(&mut self, slot: u32, buffer: ResourceRc<Buffer>) {
     self
       .commands
-      .push(RenderPassCommand::SetVertexBuffer(buffer));
+      .push(RenderPassCommand::SetVertexBuffer(slot, buffer));
   }
 
   pub fn set_index_buffer(&mut self, buffer: ResourceRc<Buffer>, format: IndexFormat) {
@@ -268,9 +426,66 @@ impl<'a> RenderPassCommandEncoder<'a> {
       .push(RenderPassCommand::SetBindGroup(binding, bind_group));
   }
 
+  pub fn render(&mut self, range: Range<u32>) {
+    self.commands.push(RenderPassCommand::Render(range));
+  }
+
   pub fn render_indexed(&mut self, range: Range<u32>) {
     self.commands.push(RenderPassCommand::RenderIndexed(range));
   }
+
+  /// Draws the vertex range once per instance, e.g. quads generated within the vertex shader.
+  pub fn render_instanced(&mut self, range: Range<u32>, instances: Range<u32>) {
+    self
+      .commands
+      .push(RenderPassCommand::RenderInstanced(range, instances));
+  }
+
+  pub fn render_indexed_instanced(&mut self, range: Range<u32>, instances: Range<u32>) {
+    self
+      .commands
+      .push(RenderPassCommand::RenderIndexedInstanced(range, instances));
+  }
+
+  /// Draws with the `DrawIndexedIndirectArgs` found at `offset` within the buffer, e.g. written
+  /// by a compute pass. The buffer needs `BufferUsage::INDIRECT`.
+  pub fn render_indexed_indirect(&mut self, buffer: ResourceRc<Buffer>, offset: u64) {
+    debug_assert!(
+      offset % 4 == 0,
+      "Indirect offsets have to be 4 byte aligned"
+    );
+    self
+      .commands
+      .push(RenderPassCommand::RenderIndexedIndirect(buffer, offset));
+  }
+
+  /// Restricts following draws to the given area of the attachments in pixels.
+  /// The area has to lie within the attachments, covering them completely disables clipping again.
+  pub fn set_scissor_rect(&mut self, x: u32, y: u32, width: u32, height: u32) {
+    self
+      .commands
+      .push(RenderPassCommand::SetScissorRect(x, y, width, height));
+  }
+
+  /// Issues `count` indirect draws laid out back to back starting at `offset`.
+  /// Uses a single call if the device has the multi draw indirect feature enabled, see
+  /// `DeviceConfig::multi_draw_indirect`, and falls back to one indirect draw per entry otherwise.
+  pub fn multi_draw_indexed_indirect(
+    &mut self,
+    buffer: ResourceRc<Buffer>,
+    offset: u64,
+    count: u32,
+  ) {
+    debug_assert!(
+      offset % 4 == 0,
+      "Indirect offsets have to be 4 byte aligned"
+    );
+    self
+      .commands
+      .push(RenderPassCommand::MultiRenderIndexedIndirect(
+        buffer, offset, count,
+      ));
+  }
 }
 
 pub fn waker_fn<F: Fn() + Send + Sync + 'static>(f: F) -> Waker {
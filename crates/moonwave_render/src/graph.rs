@@ -1,13 +1,17 @@
-use crate::{CommandEncoder, CommandEncoderOutput};
+use crate::{CommandEncoder, CommandEncoderOutput, FrameTarget, GpuTimer, NodeTiming, UploadQueue};
 use generational_arena::Arena;
 use moonwave_resources::{BindGroup, Buffer, ResourceRc, SampledTexture, TextureView};
 use multimap::MultiMap;
 use parking_lot::{RwLock, RwLockReadGuard};
 use rayon::{prelude::*, ThreadPool};
 use std::{
-  collections::HashMap,
+  collections::{HashMap, HashSet},
   fmt::{Debug, Formatter},
-  sync::Arc,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  time::Instant,
 };
 
 pub use generational_arena::Index;
@@ -27,12 +31,18 @@ pub trait FrameGraphNode: Send + Sync + 'static {
     outputs: &mut [Option<FrameNodeValue>],
     device: &wgpu::Device,
     _queue: &wgpu::Queue,
-    _sc_frame: &wgpu::SwapChainFrame,
+    _target: &FrameTarget,
   ) -> CommandEncoderOutput {
     let mut encoder = CommandEncoder::new(device, "NodeGraphEncoder");
     self.execute(inputs, outputs, &mut encoder);
     encoder.finish()
   }
+
+  /// Input that is passed through to the output of the returned pair while the node is disabled.
+  /// Without a bypass all outputs of a disabled node stay empty.
+  fn get_bypass(&self) -> Option<(usize, usize)> {
+    None
+  }
 }
 
 const MAX_LAYERS: usize = 8;
@@ -51,12 +61,18 @@ struct ConnectedEdges {
 }
 
 pub struct FrameGraph {
+  /// Locked before `edges_arena` whenever both are held.
   node_arena: RwLock<Arena<ConnectedNode>>,
   edges_arena: RwLock<Arena<ConnectedEdges>>,
   end_node: Index,
   output_map: Vec<Vec<Option<FrameNodeValue>>>,
   levels_map: MultiMap<usize, TraversedGraphNode>,
   traversed_node_cache: HashMap<Index, usize>,
+  timings: RwLock<HashMap<String, NodeTiming>>,
+  disabled_nodes: RwLock<HashSet<String>>,
+  gpu_timing: AtomicBool,
+  gpu_timer: Option<GpuTimer>,
+  upload_queue: Arc<UploadQueue>,
 }
 
 impl FrameGraph {
@@ -80,15 +96,51 @@ impl FrameGraph {
         MAX_LAYERS * MAX_INPUT_OUTPUTS_PER_NODE * MAX_NODES_PER_LAYER,
       ),
       end_node,
+      timings: RwLock::new(HashMap::new()),
+      disabled_nodes: RwLock::new(HashSet::new()),
+      gpu_timing: AtomicBool::new(false),
+      gpu_timer: None,
+      upload_queue: Arc::new(UploadQueue::new()),
     }
   }
 
+  /// Uses the given queue for buffer writes instead of a queue owned by the graph.
+  pub fn with_upload_queue(mut self, queue: Arc<UploadQueue>) -> Self {
+    self.upload_queue = queue;
+    self
+  }
+
+  /// Queue of buffer writes flushed before the commands of every level are submitted.
+  pub fn get_upload_queue(&self) -> &Arc<UploadQueue> {
+    &self.upload_queue
+  }
+
+  /// Measures GPU time of every node using timestamp queries if the device supports them.
+  /// This waits for the GPU at the end of every frame, so it should only be enabled for profiling.
+  pub fn set_gpu_timing(&self, enabled: bool) {
+    self.gpu_timing.store(enabled, Ordering::Relaxed);
+  }
+
+  /// Timings of all nodes executed in the last frame by node name.
+  /// Nodes sharing a name are summed up.
+  pub fn get_node_timings(&self) -> HashMap<String, NodeTiming> {
+    self.timings.read().clone()
+  }
+
   /// Returns the end node.
   pub fn get_end_node(&self) -> Index {
     self.end_node
   }
 
+  /// Replaces the implementation of the end node while keeping its index, returns the previous one.
+  pub fn replace_end_node(&self, node: Arc<dyn FrameGraphNode>) -> Arc<dyn FrameGraphNode> {
+    let mut nodes = self.node_arena.write();
+    let end_node = nodes.get_mut(self.end_node).unwrap();
+    std::mem::replace(&mut end_node.node, node)
+  }
+
   /// Resets the frame graph by removing all nodes and sets up a new end node.
+  /// Enabled flags are kept, see `set_node_enabled`.
   pub fn reset(&mut self) {
     let mut nodes = self.node_arena.write();
     let end_node_impl = nodes.get(self.end_node).unwrap().node.clone();
@@ -112,6 +164,23 @@ impl FrameGraph {
     })
   }
 
+  /// Disabled nodes aren't executed and pass their bypass input through instead,
+  /// e.g. to toggle optional post effects without rewiring the graph.
+  /// Nodes are recreated every frame, so the flag is kept by name and applies to all nodes sharing it.
+  pub fn set_node_enabled(&self, name: &str, enabled: bool) {
+    let mut disabled = self.disabled_nodes.write();
+    if enabled {
+      disabled.remove(name);
+    } else {
+      disabled.insert(name.to_string());
+    }
+  }
+
+  /// Whether nodes of the given name are executed.
+  pub fn is_node_enabled(&self, name: &str) -> bool {
+    !self.disabled_nodes.read().contains(name)
+  }
+
   /// Connects one nodes output to another nodes input.
   pub fn connect(
     &self,
@@ -128,15 +197,18 @@ impl FrameGraph {
       return Err(GraphConnectError::MaximumInputsReached);
     };
 
-    let mut edges = self.edges_arena.write();
     let mut nodes = self.node_arena.write();
+    let mut edges = self.edges_arena.write();
     let destination_node = nodes
       .get_mut(destination)
       .ok_or(GraphConnectError::InvalidDestination)?;
 
     // Target input is already connected.
     if destination_node.inputs[destination_input].is_some() {
-      return Err(GraphConnectError::AlreadyConnected);
+      return Err(GraphConnectError::AlreadyConnected {
+        destination,
+        input: destination_input,
+      });
     }
 
     // Target input is empty so simply create the connection.
@@ -149,6 +221,44 @@ impl FrameGraph {
     Ok(())
   }
 
+  /// Removes the connection of a nodes input so it can be connected to something else.
+  /// Returns the source node and output that was connected, if any.
+  pub fn disconnect(
+    &self,
+    destination: Index,
+    destination_input: usize,
+  ) -> Result<Option<(Index, usize)>, GraphConnectError> {
+    if destination_input >= MAX_INPUT_OUTPUTS_PER_NODE {
+      return Err(GraphConnectError::MaximumInputsReached);
+    };
+
+    let mut nodes = self.node_arena.write();
+    let mut edges = self.edges_arena.write();
+    let destination_node = nodes
+      .get_mut(destination)
+      .ok_or(GraphConnectError::InvalidDestination)?;
+
+    Ok(
+      destination_node.inputs[destination_input]
+        .take()
+        .and_then(|edge| edges.remove(edge))
+        .map(|edge| (edge.owner_node_index, edge.output_index)),
+    )
+  }
+
+  /// Source node and output connected to the given input, if any.
+  pub fn get_connection(
+    &self,
+    destination: Index,
+    destination_input: usize,
+  ) -> Option<(Index, usize)> {
+    let nodes = self.node_arena.read();
+    let edge = (*nodes.get(destination)?.inputs.get(destination_input)?)?;
+    let edges = self.edges_arena.read();
+    let edge = edges.get(edge)?;
+    Some((edge.owner_node_index, edge.output_index))
+  }
+
   fn traverse_node(
     cache: &mut HashMap<Index, usize>,
     levels_map: &mut MultiMap<usize, TraversedGraphNode>,
@@ -213,7 +323,7 @@ impl FrameGraph {
   /// Executes the graph using the given scheduler.
   pub fn execute<T: DeviceHost>(
     &mut self,
-    sc_frame: Arc<wgpu::SwapChainFrame>,
+    target: Arc<FrameTarget>,
     device_host: &'static T,
     pool: &ThreadPool,
   ) {
@@ -237,6 +347,19 @@ impl FrameGraph {
       }
       let cache = &mut self.traversed_node_cache;
 
+      // Setup gpu timing lazily.
+      if !self.gpu_timing.load(Ordering::Relaxed) {
+        self.gpu_timer = None;
+      } else if self.gpu_timer.is_none() {
+        self.gpu_timer = GpuTimer::new(
+          device_host.get_device(),
+          device_host.get_queue(),
+          (MAX_LAYERS * MAX_NODES_PER_LAYER) as u32,
+        );
+      }
+      let mut timed_nodes = Vec::with_capacity(MAX_LAYERS * MAX_NODES_PER_LAYER);
+      let disabled_nodes = self.disabled_nodes.read().clone();
+
       // Create async executer.
       let mut local_pool = futures::executor::LocalPool::new();
       let local_spawner = local_pool.spawner();
@@ -264,7 +387,7 @@ impl FrameGraph {
         let nodes = self.node_arena.read();
         let read_nodes = nodes_in_level
           .iter()
-          .map(|node| (nodes.get(node.index).unwrap(), node.inputs))
+          .map(|node| (nodes.get(node.index).unwrap(), node.inputs, node.index))
           .collect::<Vec<_>>();
 
         let mut empty = [Vec::with_capacity(0)];
@@ -289,13 +412,14 @@ impl FrameGraph {
             .par_iter()
             .zip(outputs_per_node)
             .enumerate()
-            .map(|(_i, ((node, inputs), (_oi, outputs)))| {
+            .map(|(_i, ((node, inputs, index), (_oi, outputs)))| {
               optick::event!("FrameGraph::node");
 
-              // Prepare node execution
-              optick::tag!("name", node.name);
+              // Prepare node execution, names are not unique so the tag includes index and level.
+              let tag = node_tag(&node.name, *index, level);
+              optick::tag!("name", tag.as_str());
               let node_trait = node.node.clone();
-              let label = format!("NodeCommandEncoder_{}", node.name);
+              let label = format!("NodeCommandEncoder_{}", tag);
 
               // Map outputs -> inputs.
               /*
@@ -331,7 +455,23 @@ impl FrameGraph {
                 })
                 .collect::<Vec<_>>();
 
-              let sc_cloned = sc_frame.clone();
+              // Disabled nodes only forward their bypass input.
+              let start = Instant::now();
+              if disabled_nodes.contains(&node.name) {
+                // Outputs are reused across frames and may still hold values of other nodes.
+                outputs.iter_mut().for_each(|output| *output = None);
+                if let Some((input, output)) = node_trait.get_bypass() {
+                  outputs[output] = inputs[input].clone();
+                }
+                return (
+                  CommandEncoderOutput {
+                    command_buffer: None,
+                  },
+                  start.elapsed(),
+                );
+              }
+
+              let target_cloned = target.clone();
               let out = {
                 optick::event!("FrameGraph::record_commands");
                 optick::tag!("name", label);
@@ -342,11 +482,11 @@ impl FrameGraph {
                   outputs,
                   device_host.get_device(),
                   device_host.get_queue(),
-                  &*sc_cloned,
+                  &*target_cloned,
                 )
               };
 
-              out
+              (out, start.elapsed())
             })
             .collect::<Vec<_>>()
         });
@@ -354,16 +494,61 @@ impl FrameGraph {
         {
           optick::event!("FrameGraph::submit_level");
           optick::tag!("level", level as u32);
-          let mut buffers = Vec::with_capacity(encoder_outputs.len());
+          let device = device_host.get_device();
+          let mut buffers = Vec::with_capacity(encoder_outputs.len() * 3);
+
+          // Writes queued by the nodes of this level have to land before their commands.
+          if let Some(uploads) = self.upload_queue.flush(device) {
+            buffers.push(uploads);
+          }
 
-          for out in encoder_outputs {
+          for ((node, _, _), (out, cpu)) in read_nodes.iter().zip(encoder_outputs) {
+            // Surround node commands with timestamps.
+            let timer = self
+              .gpu_timer
+              .as_ref()
+              .filter(|timer| (timed_nodes.len() as u32) < timer.capacity());
+            if let Some(timer) = timer {
+              buffers.push(timer.timestamp(device, timed_nodes.len() as u32, false));
+            }
             if let Some(buffer) = out.command_buffer {
               buffers.push(buffer);
             }
+            if let Some(timer) = timer {
+              buffers.push(timer.timestamp(device, timed_nodes.len() as u32, true));
+            }
+            timed_nodes.push((node.name.clone(), cpu));
           }
           device_host.get_queue().submit(buffers);
         }
       }
+
+      // Collect timings
+      let gpu_times = self
+        .gpu_timer
+        .as_ref()
+        .map(|timer| {
+          let slots = (timed_nodes.len() as u32).min(timer.capacity());
+          if slots == 0 {
+            return Vec::new();
+          }
+          let device = device_host.get_device();
+          device_host
+            .get_queue()
+            .submit(Some(timer.resolve(device, slots)));
+          timer.read(device, slots)
+        })
+        .unwrap_or_default();
+
+      let mut timings = HashMap::with_capacity(timed_nodes.len());
+      for (index, (name, cpu)) in timed_nodes.into_iter().enumerate() {
+        let timing: &mut NodeTiming = timings.entry(name).or_default();
+        timing.cpu += cpu;
+        if let Some(gpu) = gpu_times.get(index) {
+          timing.gpu = Some(timing.gpu.unwrap_or_default() + *gpu);
+        }
+      }
+      *self.timings.write() = timings;
     }
 
     // Reset
@@ -372,6 +557,12 @@ impl FrameGraph {
   }
 }
 
+/// Unique label of a node instance used for profiling, e.g. `pbr_main_color#3:1@2`.
+pub fn node_tag(name: &str, index: Index, level: usize) -> String {
+  let (slot, generation) = index.into_raw_parts();
+  format!("{}#{}:{}@{}", name, slot, generation, level)
+}
+
 #[derive(Clone)]
 pub enum FrameNodeValue {
   Buffer(ResourceRc<Buffer>),
@@ -401,8 +592,8 @@ pub enum GraphConnectError {
   MaximumOutputsReached,
   #[error("The target node does not exist")]
   InvalidDestination,
-  #[error("The target nodes input is already connected")]
-  AlreadyConnected,
+  #[error("Input {input} of the target node {destination:?} is already connected")]
+  AlreadyConnected { destination: Index, input: usize },
 }
 
 #[derive(Clone)]
@@ -417,7 +608,7 @@ pub trait DeviceHost: Send + Sync + 'static {
 }
 
 macro_rules! impl_get_node_specific {
-  ($getter:ident, $ty:ident, $rty:ty) => {
+  ($getter:ident, $try_getter:ident, $ty:ident, $rty:ty) => {
     impl FrameNodeValue {
       pub fn $getter(&self) -> &$rty {
         match self {
@@ -429,10 +620,45 @@ macro_rules! impl_get_node_specific {
           ),
         }
       }
+
+      /// Same as the panicking getter but returns `None` for values of another kind.
+      pub fn $try_getter(&self) -> Option<&$rty> {
+        match self {
+          FrameNodeValue::$ty(group) => Some(group),
+          _ => None,
+        }
+      }
     }
   };
 }
 
-impl_get_node_specific!(get_bind_group, BindGroup, ResourceRc<BindGroup>);
-impl_get_node_specific!(get_texture_view, TextureView, ResourceRc<TextureView>);
-impl_get_node_specific!(get_sampled_texture, SampledTexture, SampledTexture);
+impl_get_node_specific!(get_buffer, try_get_buffer, Buffer, ResourceRc<Buffer>);
+impl_get_node_specific!(
+  get_bind_group,
+  try_get_bind_group,
+  BindGroup,
+  ResourceRc<BindGroup>
+);
+impl_get_node_specific!(
+  get_texture_view,
+  try_get_texture_view,
+  TextureView,
+  ResourceRc<TextureView>
+);
+impl_get_node_specific!(
+  get_sampled_texture,
+  try_get_sampled_texture,
+  SampledTexture,
+  SampledTexture
+);
+
+impl FrameNodeValue {
+  /// Value connected to an optional input, `None` if the input is not connected.
+  /// Combined with the `try_get_*` getters nodes can work with or without e.g. a depth input.
+  pub fn optional_input(
+    inputs: &[Option<FrameNodeValue>],
+    index: usize,
+  ) -> Option<&FrameNodeValue> {
+    inputs.get(index).and_then(|input| input.as_ref())
+  }
+}
@@ -5,3 +5,12 @@ pub use command::*;
 
 mod graph;
 pub use graph::*;
+
+mod target;
+pub use target::*;
+
+mod timing;
+pub use timing::*;
+
+mod upload;
+pub use upload::*;
@@ -0,0 +1,22 @@
+use moonwave_resources::{ResourceRc, TextureView};
+
+/// Final target of a frame that end nodes like `PresentToScreen` draw into.
+pub enum FrameTarget {
+  /// Frame acquired from the swap chain of the window.
+  SwapChain(wgpu::SwapChainFrame),
+  /// Offscreen texture used when running without a window, see `Core::initialize_headless`.
+  Offscreen(ResourceRc<TextureView>),
+}
+
+impl FrameTarget {
+  pub fn view(&self) -> &wgpu::TextureView {
+    match self {
+      FrameTarget::SwapChain(frame) => &frame.output.view,
+      FrameTarget::Offscreen(view) => view.get_raw(),
+    }
+  }
+
+  pub fn is_offscreen(&self) -> bool {
+    matches!(self, FrameTarget::Offscreen(_))
+  }
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use crate::execute_wgpu_async;
+
+/// Time a frame graph node took during the last executed frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NodeTiming {
+  /// Time spent recording the nodes commands on the CPU.
+  pub cpu: Duration,
+  /// Time the nodes commands took on the GPU, only available with timestamp query support.
+  pub gpu: Option<Duration>,
+}
+
+impl NodeTiming {
+  /// GPU time if available, otherwise the CPU record duration.
+  pub fn cost(&self) -> Duration {
+    self.gpu.unwrap_or(self.cpu)
+  }
+}
+
+/// Converts pairs of begin and end timestamp ticks into durations.
+pub fn timestamps_to_durations(timestamps: &[u64], period: f32) -> Vec<Duration> {
+  timestamps
+    .chunks_exact(2)
+    .map(|pair| {
+      let ticks = pair[1].saturating_sub(pair[0]);
+      Duration::from_nanos((ticks as f64 * period as f64) as u64)
+    })
+    .collect()
+}
+
+/// Measures GPU time of individual command buffers using timestamp queries.
+pub(crate) struct GpuTimer {
+  query_set: wgpu::QuerySet,
+  buffer: wgpu::Buffer,
+  capacity: u32,
+  period: f32,
+}
+
+impl GpuTimer {
+  /// Returns `None` if the device does not support timestamp queries.
+  pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, capacity: u32) -> Option<Self> {
+    if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+      return None;
+    }
+
+    let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+      ty: wgpu::QueryType::Timestamp,
+      count: capacity * 2,
+    });
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+      label: Some("GpuTimerBuffer"),
+      size: capacity as u64 * 2 * std::mem::size_of::<u64>() as u64,
+      usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+      mapped_at_creation: false,
+    });
+
+    Some(Self {
+      query_set,
+      buffer,
+      capacity,
+      period: queue.get_timestamp_period(),
+    })
+  }
+
+  pub fn capacity(&self) -> u32 {
+    self.capacity
+  }
+
+  /// Command buffer writing the begin (`end = false`) or end timestamp of the given slot.
+  pub fn timestamp(&self, device: &wgpu::Device, slot: u32, end: bool) -> wgpu::CommandBuffer {
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+      label: Some("GpuTimerTimestamp"),
+    });
+    encoder.write_timestamp(&self.query_set, slot * 2 + end as u32);
+    encoder.finish()
+  }
+
+  /// Resolves the first `slots` timings, has to be submitted after all timed command buffers.
+  pub fn resolve(&self, device: &wgpu::Device, slots: u32) -> wgpu::CommandBuffer {
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+      label: Some("GpuTimerResolve"),
+    });
+    encoder.resolve_query_set(&self.query_set, 0..slots * 2, &self.buffer, 0);
+    encoder.finish()
+  }
+
+  /// Waits for the resolved timestamps and returns the duration of each slot.
+  pub fn read(&self, device: &wgpu::Device, slots: u32) -> Vec<Duration> {
+    let size = slots as u64 * 2 * std::mem::size_of::<u64>() as u64;
+    if size == 0 {
+      return Vec::new();
+    }
+
+    let slice = self.buffer.slice(0..size);
+    execute_wgpu_async(device, async {
+      slice.map_async(wgpu::MapMode::Read).await.unwrap();
+    });
+    let durations = {
+      let mapped = slice.get_mapped_range();
+      let timestamps = mapped
+        .chunks_exact(8)
+        .map(|raw| {
+          let mut bytes = [0u8; 8];
+          bytes.copy_from_slice(raw);
+          u64::from_le_bytes(bytes)
+        })
+        .collect::<Vec<_>>();
+      timestamps_to_durations(&timestamps, self.period)
+    };
+    self.buffer.unmap();
+    durations
+  }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use moonwave_resources::{Buffer, ResourceRc};
+use parking_lot::Mutex;
+use wgpu::util::DeviceExt;
+
+/// A single queued write into a buffer, `target` indexes the buffers known to the queue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferUpload {
+  pub target: usize,
+  pub offset: u64,
+  pub data: Vec<u8>,
+}
+
+/// Copy from the shared staging buffer into one of the targets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UploadCopy {
+  pub target: usize,
+  pub staging_offset: u64,
+  pub target_offset: u64,
+  pub size: u64,
+}
+
+/// All writes of a frame packed into a single staging buffer.
+#[derive(Debug, Default)]
+pub struct CoalescedUploads {
+  pub staging: Vec<u8>,
+  pub copies: Vec<UploadCopy>,
+}
+
+/// Packs all uploads into one staging blob, writes that overlap or touch each other share a copy.
+/// Overlapping bytes are taken from the write that has been queued last.
+pub fn coalesce_uploads(uploads: &[BufferUpload]) -> CoalescedUploads {
+  let mut ranges = uploads
+    .iter()
+    .filter(|upload| !upload.data.is_empty())
+    .map(|upload| {
+      (
+        upload.target,
+        upload.offset,
+        upload.offset + upload.data.len() as u64,
+      )
+    })
+    .collect::<Vec<_>>();
+  ranges.sort_unstable();
+
+  // Merge ranges of the same target and assign their place in the staging buffer.
+  let mut copies: Vec<UploadCopy> = Vec::with_capacity(ranges.len());
+  let mut staging_size = 0;
+  for (target, start, end) in ranges {
+    match copies.last_mut() {
+      Some(last) if last.target == target && start <= last.target_offset + last.size => {
+        let grown = end.saturating_sub(last.target_offset + last.size);
+        last.size += grown;
+        staging_size += grown;
+      }
+      _ => {
+        copies.push(UploadCopy {
+          target,
+          staging_offset: staging_size,
+          target_offset: start,
+          size: end - start,
+        });
+        staging_size += end - start;
+      }
+    }
+  }
+
+  // Copies of each target, they are sorted by their offset within the target.
+  let mut target_copies: HashMap<usize, Vec<&UploadCopy>> = HashMap::new();
+  for copy in copies.iter() {
+    target_copies.entry(copy.target).or_default().push(copy);
+  }
+
+  // Fill in queue order so later writes win.
+  let mut staging = vec![0u8; staging_size as usize];
+  for upload in uploads.iter().filter(|upload| !upload.data.is_empty()) {
+    let candidates = &target_copies[&upload.target];
+    let copy = match candidates.binary_search_by_key(&upload.offset, |copy| copy.target_offset) {
+      Ok(index) => candidates[index],
+      Err(index) => candidates[index - 1],
+    };
+    let start = (copy.staging_offset + upload.offset - copy.target_offset) as usize;
+    staging[start..start + upload.data.len()].copy_from_slice(&upload.data);
+  }
+
+  CoalescedUploads { staging, copies }
+}
+
+/// Collects buffer writes of a frame and uploads them at once through a single staging buffer.
+/// The frame graph flushes the queue before submitting the commands of each level.
+pub struct UploadQueue {
+  pending: Mutex<PendingUploads>,
+}
+
+/// Buffers written since the last flush with their index, followed by the writes into them.
+#[derive(Default)]
+struct PendingUploads {
+  targets: Vec<ResourceRc<Buffer>>,
+  indices: HashMap<ResourceRc<Buffer>, usize>,
+  uploads: Vec<BufferUpload>,
+}
+
+impl UploadQueue {
+  pub fn new() -> Self {
+    Self {
+      pending: Mutex::new(PendingUploads::default()),
+    }
+  }
+
+  /// Queues a write, offset and size have to be multiples of `wgpu::COPY_BUFFER_ALIGNMENT`.
+  pub fn write(&self, buffer: &ResourceRc<Buffer>, offset: u64, data: &[u8]) {
+    debug_assert!(
+      offset % wgpu::COPY_BUFFER_ALIGNMENT == 0
+        && data.len() as u64 % wgpu::COPY_BUFFER_ALIGNMENT == 0,
+      "Queued buffer writes have to be 4 byte aligned"
+    );
+
+    let mut pending = self.pending.lock();
+    let PendingUploads {
+      targets,
+      indices,
+      uploads,
+    } = &mut *pending;
+    let target = *indices.entry(buffer.clone()).or_insert_with(|| {
+      targets.push(buffer.clone());
+      targets.len() - 1
+    });
+    uploads.push(BufferUpload {
+      target,
+      offset,
+      data: data.to_vec(),
+    });
+  }
+
+  /// Amount of writes waiting for the next flush.
+  pub fn len(&self) -> usize {
+    self.pending.lock().uploads.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.pending.lock().uploads.is_empty()
+  }
+
+  /// Uploads all queued writes, the returned commands have to be submitted before any command using them.
+  pub fn flush(&self, device: &wgpu::Device) -> Option<wgpu::CommandBuffer> {
+    optick::event!("UploadQueue::flush");
+    let PendingUploads {
+      targets, uploads, ..
+    } = std::mem::take(&mut *self.pending.lock());
+    if uploads.is_empty() {
+      return None;
+    }
+
+    let coalesced = coalesce_uploads(&uploads);
+    let staging = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+      label: Some("UploadQueueStaging"),
+      contents: &coalesced.staging,
+      usage: wgpu::BufferUsage::COPY_SRC,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+      label: Some("UploadQueueEncoder"),
+    });
+    for copy in coalesced.copies {
+      encoder.copy_buffer_to_buffer(
+        &staging,
+        copy.staging_offset,
+        &*targets[copy.target].get_raw(),
+        copy.target_offset,
+        copy.size,
+      );
+    }
+    Some(encoder.finish())
+  }
+}
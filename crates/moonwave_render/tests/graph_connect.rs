@@ -0,0 +1,63 @@
+use moonwave_render::{FrameGraph, FrameGraphNode, GraphConnectError};
+
+struct MockNode;
+impl FrameGraphNode for MockNode {}
+
+#[test]
+fn already_connected_names_input_test() {
+  let graph = FrameGraph::new(MockNode);
+  let a = graph.add_node(MockNode, "a");
+  let b = graph.add_node(MockNode, "b");
+  let end = graph.get_end_node();
+
+  graph.connect(a, 0, end, 2).unwrap();
+  match graph.connect(b, 0, end, 2) {
+    Err(GraphConnectError::AlreadyConnected { destination, input }) => {
+      assert_eq!(destination, end);
+      assert_eq!(input, 2);
+    }
+    other => panic!("Unexpected result {:?}", other),
+  }
+}
+
+#[test]
+fn disconnect_allows_replacing_test() {
+  let graph = FrameGraph::new(MockNode);
+  let a = graph.add_node(MockNode, "a");
+  let b = graph.add_node(MockNode, "b");
+  let end = graph.get_end_node();
+
+  graph.connect(a, 1, end, 0).unwrap();
+  assert_eq!(graph.disconnect(end, 0).unwrap(), Some((a, 1)));
+  assert_eq!(graph.get_connection(end, 0), None);
+  assert_eq!(graph.disconnect(end, 0).unwrap(), None);
+
+  graph.connect(b, 0, end, 0).unwrap();
+  assert_eq!(graph.get_connection(end, 0), Some((b, 0)));
+}
+
+#[test]
+fn node_enabled_flag_test() {
+  let graph = FrameGraph::new(MockNode);
+  graph.add_node(MockNode, "a");
+  assert!(graph.is_node_enabled("a"));
+  assert_eq!(MockNode.get_bypass(), None);
+
+  graph.set_node_enabled("a", false);
+  assert!(!graph.is_node_enabled("a"));
+  assert!(graph.is_node_enabled("b"));
+  graph.set_node_enabled("a", true);
+  assert!(graph.is_node_enabled("a"));
+}
+
+#[test]
+fn node_enabled_flag_survives_reset_test() {
+  let mut graph = FrameGraph::new(MockNode);
+  graph.add_node(MockNode, "a");
+  graph.set_node_enabled("a", false);
+
+  // Nodes are added again every frame after the reset.
+  graph.reset();
+  graph.add_node(MockNode, "a");
+  assert!(!graph.is_node_enabled("a"));
+}
@@ -0,0 +1,252 @@
+use moonwave_render::{
+  CommandEncoder, DeviceHost, FrameGraph, FrameGraphNode, FrameNodeValue, FrameTarget,
+};
+use moonwave_resources::{Buffer, ResourceRc, ResourceStorage, TextureView};
+use parking_lot::Mutex;
+use std::sync::{
+  atomic::{AtomicUsize, Ordering},
+  Arc,
+};
+
+struct TestHost {
+  device: wgpu::Device,
+  queue: wgpu::Queue,
+}
+
+impl DeviceHost for TestHost {
+  fn get_device(&self) -> &wgpu::Device {
+    &self.device
+  }
+  fn get_queue(&self) -> &wgpu::Queue {
+    &self.queue
+  }
+}
+
+/// Creates a device without a surface, `None` if the machine has no usable adapter.
+fn create_host() -> Option<&'static TestHost> {
+  futures::executor::block_on(async {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::all());
+    let adapter = instance
+      .request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        compatible_surface: None,
+      })
+      .await?;
+    let (device, queue) = adapter
+      .request_device(&wgpu::DeviceDescriptor::default(), None)
+      .await
+      .ok()?;
+    Some(&*Box::leak(Box::new(TestHost { device, queue })))
+  })
+}
+
+fn create_buffer(host: &TestHost, storage: &ResourceStorage) -> ResourceRc<Buffer> {
+  storage.create_proxy(host.device.create_buffer(&wgpu::BufferDescriptor {
+    label: None,
+    size: 16,
+    usage: wgpu::BufferUsage::UNIFORM,
+    mapped_at_creation: false,
+  }))
+}
+
+fn create_view(host: &TestHost, storage: &ResourceStorage) -> ResourceRc<TextureView> {
+  let texture = host.device.create_texture(&wgpu::TextureDescriptor {
+    label: None,
+    size: wgpu::Extent3d {
+      width: 4,
+      height: 4,
+      depth_or_array_layers: 1,
+    },
+    mip_level_count: 1,
+    sample_count: 1,
+    dimension: wgpu::TextureDimension::D2,
+    format: wgpu::TextureFormat::Rgba8Unorm,
+    usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+  });
+  storage.create_proxy(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+fn create_target(host: &TestHost, storage: &ResourceStorage) -> Arc<FrameTarget> {
+  Arc::new(FrameTarget::Offscreen(create_view(host, storage)))
+}
+
+struct SourceNode(ResourceRc<Buffer>);
+impl FrameGraphNode for SourceNode {
+  fn execute(
+    &self,
+    _inputs: &[Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
+    _encoder: &mut CommandEncoder,
+  ) {
+    outputs[0] = Some(FrameNodeValue::Buffer(self.0.clone()));
+  }
+}
+
+/// Replaces its input with its own buffer, bypassing the input if requested.
+struct EffectNode {
+  buffer: ResourceRc<Buffer>,
+  executions: Arc<AtomicUsize>,
+  bypass: bool,
+}
+impl FrameGraphNode for EffectNode {
+  fn execute(
+    &self,
+    _inputs: &[Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
+    _encoder: &mut CommandEncoder,
+  ) {
+    self.executions.fetch_add(1, Ordering::SeqCst);
+    outputs[0] = Some(FrameNodeValue::Buffer(self.buffer.clone()));
+  }
+
+  fn get_bypass(&self) -> Option<(usize, usize)> {
+    if self.bypass {
+      Some((0, 0))
+    } else {
+      None
+    }
+  }
+}
+
+/// Records the buffer that arrived at its first input.
+#[derive(Clone, Default)]
+struct RecordingEndNode(Arc<Mutex<Option<Option<ResourceRc<Buffer>>>>>);
+impl FrameGraphNode for RecordingEndNode {
+  fn execute(
+    &self,
+    inputs: &[Option<FrameNodeValue>],
+    _outputs: &mut [Option<FrameNodeValue>],
+    _encoder: &mut CommandEncoder,
+  ) {
+    let input = FrameNodeValue::optional_input(inputs, 0)
+      .and_then(|input| input.try_get_buffer())
+      .cloned();
+    *self.0.lock() = Some(input);
+  }
+}
+
+impl RecordingEndNode {
+  fn take(&self) -> Option<ResourceRc<Buffer>> {
+    self.0.lock().take().expect("End node was not executed")
+  }
+}
+
+struct Setup {
+  host: &'static TestHost,
+  pool: rayon::ThreadPool,
+  target: Arc<FrameTarget>,
+  source: ResourceRc<Buffer>,
+  effect: ResourceRc<Buffer>,
+  executions: Arc<AtomicUsize>,
+}
+
+impl Setup {
+  fn new() -> Option<Self> {
+    let host = create_host()?;
+    let storage = ResourceStorage::new();
+    Some(Self {
+      host,
+      pool: rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .build()
+        .unwrap(),
+      target: create_target(host, &storage),
+      source: create_buffer(host, &storage),
+      effect: create_buffer(host, &storage),
+      executions: Arc::new(AtomicUsize::new(0)),
+    })
+  }
+
+  /// Builds source -> effect -> end and executes a single frame.
+  fn run_frame(&self, graph: &mut FrameGraph, bypass: bool) {
+    let source = graph.add_node(SourceNode(self.source.clone()), "source");
+    let effect = graph.add_node(
+      EffectNode {
+        buffer: self.effect.clone(),
+        executions: self.executions.clone(),
+        bypass,
+      },
+      "effect",
+    );
+    graph.connect(source, 0, effect, 0).unwrap();
+    graph.connect(effect, 0, graph.get_end_node(), 0).unwrap();
+    graph.execute(self.target.clone(), self.host, &self.pool);
+  }
+}
+
+#[test]
+fn disabled_node_forwards_bypass_test() {
+  let setup = match Setup::new() {
+    Some(setup) => setup,
+    None => return,
+  };
+  let end = RecordingEndNode::default();
+  let mut graph = FrameGraph::new(end.clone());
+
+  setup.run_frame(&mut graph, true);
+  assert!(end.take() == Some(setup.effect.clone()));
+  assert_eq!(setup.executions.load(Ordering::SeqCst), 1);
+
+  // The flag outlives the reset at the end of every frame.
+  graph.set_node_enabled("effect", false);
+  for _ in 0..2 {
+    setup.run_frame(&mut graph, true);
+    assert!(end.take() == Some(setup.source.clone()));
+  }
+  assert_eq!(setup.executions.load(Ordering::SeqCst), 1);
+
+  graph.set_node_enabled("effect", true);
+  setup.run_frame(&mut graph, true);
+  assert!(end.take() == Some(setup.effect.clone()));
+  assert_eq!(setup.executions.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn disabled_node_without_bypass_outputs_nothing_test() {
+  let setup = match Setup::new() {
+    Some(setup) => setup,
+    None => return,
+  };
+  let end = RecordingEndNode::default();
+  let mut graph = FrameGraph::new(end.clone());
+
+  // A previous frame leaves values in the reused output slots.
+  setup.run_frame(&mut graph, false);
+  assert!(end.take() == Some(setup.effect.clone()));
+
+  graph.set_node_enabled("effect", false);
+  setup.run_frame(&mut graph, false);
+  assert!(end.take().is_none());
+  assert_eq!(setup.executions.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn frame_node_value_getters_test() {
+  let host = match create_host() {
+    Some(host) => host,
+    None => return,
+  };
+  let storage = ResourceStorage::new();
+  let buffer = create_buffer(host, &storage);
+  let view = create_view(host, &storage);
+  let inputs = vec![
+    Some(FrameNodeValue::Buffer(buffer.clone())),
+    None,
+    Some(FrameNodeValue::TextureView(view.clone())),
+  ];
+
+  // Matching variants return their value, all others nothing.
+  let value = FrameNodeValue::optional_input(&inputs, 0).unwrap();
+  assert!(value.try_get_buffer() == Some(&buffer));
+  assert!(value.try_get_texture_view().is_none());
+  assert!(value.try_get_bind_group().is_none());
+  assert!(value.try_get_sampled_texture().is_none());
+
+  let value = FrameNodeValue::optional_input(&inputs, 2).unwrap();
+  assert!(value.try_get_texture_view() == Some(&view));
+  assert!(value.try_get_buffer().is_none());
+
+  // Unconnected inputs and ones beyond the slots of the node.
+  assert!(FrameNodeValue::optional_input(&inputs, 1).is_none());
+  assert!(FrameNodeValue::optional_input(&inputs, 3).is_none());
+}
@@ -0,0 +1,32 @@
+use moonwave_common::bytemuck::cast_slice;
+use moonwave_render::DrawIndexedIndirectArgs;
+
+#[test]
+fn indirect_args_layout_test() {
+  // Matches the layout wgpu expects for indexed indirect draws.
+  assert_eq!(DrawIndexedIndirectArgs::SIZE, 20);
+
+  let args = [
+    DrawIndexedIndirectArgs {
+      index_count: 36,
+      instance_count: 2,
+      first_index: 6,
+      base_vertex: -4,
+      first_instance: 1,
+    },
+    DrawIndexedIndirectArgs::default(),
+  ];
+  let words: &[u32] = cast_slice(&args);
+  assert_eq!(&words[..5], &[36, 2, 6, (-4i32) as u32, 1]);
+  assert_eq!(&words[5..], &[0; 5]);
+}
+
+#[test]
+fn indirect_offsets_test() {
+  // Draws issued one by one when multi draw indirect isn't available.
+  assert_eq!(
+    DrawIndexedIndirectArgs::offsets(40, 3).collect::<Vec<_>>(),
+    vec![40, 60, 80]
+  );
+  assert_eq!(DrawIndexedIndirectArgs::offsets(0, 0).count(), 0);
+}
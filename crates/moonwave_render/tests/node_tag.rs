@@ -0,0 +1,19 @@
+use moonwave_render::{node_tag, FrameGraph, FrameGraphNode};
+
+struct MockNode;
+impl FrameGraphNode for MockNode {}
+
+#[test]
+fn nodes_sharing_a_name_get_unique_tags() {
+  let graph = FrameGraph::new(MockNode);
+  let a = graph.add_node(MockNode, "pbr_main_color");
+  let b = graph.add_node(MockNode, "pbr_main_color");
+
+  let tag_a = node_tag("pbr_main_color", a, 1);
+  let tag_b = node_tag("pbr_main_color", b, 1);
+  assert_ne!(tag_a, tag_b);
+
+  let (slot, generation) = a.into_raw_parts();
+  assert_eq!(tag_a, format!("pbr_main_color#{}:{}@1", slot, generation));
+  assert!(node_tag("pbr_main_color", a, 2).ends_with("@2"));
+}
@@ -0,0 +1,53 @@
+use moonwave_render::{validate_color_attachments, RenderPassValidationError};
+use moonwave_resources::TextureFormat;
+
+#[test]
+fn matching_attachments_are_valid() {
+  let formats = [TextureFormat::Bgra8UnormSrgb, TextureFormat::Rgba16Float];
+  assert!(
+    validate_color_attachments("pbr", &[Some(formats[0]), Some(formats[1])], &formats).is_ok()
+  );
+
+  // Attachments of unknown format are only counted.
+  assert!(validate_color_attachments("pbr", &[None, Some(formats[1])], &formats).is_ok());
+}
+
+#[test]
+fn attachment_count_mismatch_is_descriptive() {
+  let err = validate_color_attachments(
+    "pbr",
+    &[Some(TextureFormat::Bgra8UnormSrgb)],
+    &[TextureFormat::Bgra8UnormSrgb, TextureFormat::Rgba16Float],
+  )
+  .unwrap_err();
+  assert_eq!(
+    err,
+    RenderPassValidationError::AttachmentCount {
+      pass: "pbr".to_string(),
+      attachments: 1,
+      outputs: 2,
+    }
+  );
+  assert_eq!(
+    err.to_string(),
+    "Render pass 'pbr' has 1 color attachments but the pipeline writes 2 color outputs"
+  );
+}
+
+#[test]
+fn attachment_format_mismatch_is_descriptive() {
+  let err = validate_color_attachments(
+    "ui",
+    &[Some(TextureFormat::Rgba8Unorm)],
+    &[TextureFormat::Bgra8UnormSrgb],
+  )
+  .unwrap_err();
+  assert!(matches!(
+    err,
+    RenderPassValidationError::FormatMismatch { index: 0, .. }
+  ));
+  assert!(err
+    .to_string()
+    .contains("Color attachment 0 of render pass 'ui'"));
+  assert!(err.to_string().contains("Bgra8UnormSrgb"));
+}
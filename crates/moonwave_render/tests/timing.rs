@@ -0,0 +1,33 @@
+use moonwave_render::{timestamps_to_durations, NodeTiming};
+use std::time::Duration;
+
+#[test]
+fn timestamps_are_converted_per_node() {
+  // Two nodes, the timestamp period is 2ns per tick.
+  let durations = timestamps_to_durations(&[100, 150, 150, 400], 2.0);
+  assert_eq!(
+    durations,
+    vec![Duration::from_nanos(100), Duration::from_nanos(500)]
+  );
+
+  // Wrapped or out of order timestamps never produce negative durations.
+  assert_eq!(
+    timestamps_to_durations(&[10, 5], 1.0),
+    vec![Duration::from_nanos(0)]
+  );
+}
+
+#[test]
+fn node_cost_falls_back_to_cpu_time() {
+  let cpu_only = NodeTiming {
+    cpu: Duration::from_micros(20),
+    gpu: None,
+  };
+  assert_eq!(cpu_only.cost(), Duration::from_micros(20));
+
+  let with_gpu = NodeTiming {
+    gpu: Some(Duration::from_micros(300)),
+    ..cpu_only
+  };
+  assert_eq!(with_gpu.cost(), Duration::from_micros(300));
+}
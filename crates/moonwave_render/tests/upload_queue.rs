@@ -0,0 +1,98 @@
+use moonwave_render::{coalesce_uploads, BufferUpload, CoalescedUploads};
+
+/// Applies all copies to CPU side copies of the targets.
+fn apply(coalesced: &CoalescedUploads, targets: &mut [Vec<u8>]) {
+  for copy in &coalesced.copies {
+    let source = &coalesced.staging[copy.staging_offset as usize..][..copy.size as usize];
+    targets[copy.target][copy.target_offset as usize..][..copy.size as usize]
+      .copy_from_slice(source);
+  }
+}
+
+#[test]
+fn small_writes_are_coalesced_test() {
+  // Sixteen uniforms writing their own 16 byte slot of a shared buffer.
+  let uploads = (0..16u8)
+    .map(|i| BufferUpload {
+      target: 0,
+      offset: i as u64 * 16,
+      data: vec![i; 16],
+    })
+    .rev()
+    .collect::<Vec<_>>();
+
+  let coalesced = coalesce_uploads(&uploads);
+  assert_eq!(coalesced.copies.len(), 1);
+  assert_eq!(coalesced.staging.len(), 256);
+
+  let mut targets = vec![vec![0xff; 256]];
+  apply(&coalesced, &mut targets);
+  for (i, slot) in targets[0].chunks(16).enumerate() {
+    assert!(slot.iter().all(|byte| *byte == i as u8));
+  }
+}
+
+#[test]
+fn writes_to_multiple_targets_test() {
+  let uploads = vec![
+    BufferUpload {
+      target: 1,
+      offset: 8,
+      data: vec![1; 8],
+    },
+    BufferUpload {
+      target: 0,
+      offset: 0,
+      data: vec![2; 4],
+    },
+    BufferUpload {
+      target: 1,
+      offset: 64,
+      data: vec![3; 4],
+    },
+    // Overlaps the first write, queued later so it wins.
+    BufferUpload {
+      target: 1,
+      offset: 4,
+      data: vec![4; 8],
+    },
+  ];
+
+  let coalesced = coalesce_uploads(&uploads);
+  assert_eq!(coalesced.copies.len(), 3);
+  assert_eq!(coalesced.staging.len(), 4 + 12 + 4);
+
+  let mut targets = vec![vec![0; 16], vec![0; 128]];
+  apply(&coalesced, &mut targets);
+  assert_eq!(&targets[0][..8], &[2, 2, 2, 2, 0, 0, 0, 0]);
+  assert_eq!(&targets[1][..4], &[0; 4]);
+  assert_eq!(&targets[1][4..12], &[4; 8]);
+  assert_eq!(&targets[1][12..16], &[1; 4]);
+  assert_eq!(&targets[1][64..68], &[3; 4]);
+}
+
+#[test]
+fn many_targets_test() {
+  // Two separate writes into each of many targets, queued interleaved.
+  let targets_count = 512;
+  let uploads = (0..2)
+    .flat_map(|slot| {
+      (0..targets_count).map(move |target| BufferUpload {
+        target,
+        offset: slot * 32,
+        data: vec![(target % 251) as u8 + slot as u8; 4],
+      })
+    })
+    .collect::<Vec<_>>();
+
+  let coalesced = coalesce_uploads(&uploads);
+  assert_eq!(coalesced.copies.len(), targets_count * 2);
+
+  let mut targets = vec![vec![0xff; 64]; targets_count];
+  apply(&coalesced, &mut targets);
+  for (index, target) in targets.iter().enumerate() {
+    let value = (index % 251) as u8;
+    assert_eq!(&target[..4], &[value; 4]);
+    assert_eq!(&target[32..36], &[value + 1; 4]);
+  }
+}
@@ -1,12 +1,21 @@
 #![allow(clippy::new_without_default)]
 
+use std::num::NonZeroU8;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::{hash::Hash, marker::PhantomData};
 use std::{hash::Hasher, sync::Arc};
 
-pub use wgpu::{IndexFormat, TextureFormat, TextureUsage};
+use parking_lot::RwLock;
+use thiserror::Error;
+pub use wgpu::{
+  AddressMode, BlendState, CompareFunction, Face, FilterMode, FrontFace, IndexFormat,
+  InputStepMode, PolygonMode, PrimitiveTopology, TextureFormat, TextureUsage, VertexFormat,
+};
 
 struct ResourceLife {
   original: Resource,
+  _allocation: Option<GpuAllocation>,
+  formats: RwLock<Option<Vec<TextureFormat>>>,
 }
 
 impl Drop for ResourceLife {
@@ -34,6 +43,20 @@ impl<T> PartialEq for ResourceRc<T> {
 
 impl<T> Eq for ResourceRc<T> {}
 
+impl<T> ResourceRc<T> {
+  /// Attaches the formats of a texture or view, or the color output formats of a render pipeline.
+  /// The formats are shared by all clones of the resource.
+  pub fn with_formats(self, formats: Vec<TextureFormat>) -> Self {
+    *self.life.formats.write() = Some(formats);
+    self
+  }
+
+  /// Formats attached with `with_formats`, `None` if they are unknown.
+  pub fn get_formats(&self) -> Option<Vec<TextureFormat>> {
+    self.life.formats.read().clone()
+  }
+}
+
 impl<T> Clone for ResourceRc<T> {
   fn clone(&self) -> ResourceRc<T> {
     ResourceRc {
@@ -60,21 +83,128 @@ pub enum Resource {
   RenderPipeline(wgpu::RenderPipeline),
 }
 
-pub struct ResourceStorage;
+/// Approximate amount of GPU memory held by a resource, released again on drop.
+pub struct GpuAllocation {
+  size: u64,
+  allocated: Arc<AtomicU64>,
+}
+
+impl GpuAllocation {
+  pub fn size(&self) -> u64 {
+    self.size
+  }
+}
+
+impl Drop for GpuAllocation {
+  fn drop(&mut self) {
+    self.allocated.fetch_sub(self.size, Ordering::Relaxed);
+  }
+}
+
+pub struct ResourceStorage {
+  allocated: Arc<AtomicU64>,
+}
 
 impl ResourceStorage {
   pub fn new() -> Self {
-    Self {}
+    Self {
+      allocated: Arc::new(AtomicU64::new(0)),
+    }
   }
 
   pub fn create_proxy<T: IntoResource>(&self, resource: T) -> ResourceRc<T::ProxyType> {
     ResourceRc {
       life: Arc::new(ResourceLife {
         original: resource.into(),
+        _allocation: None,
+        formats: RwLock::new(None),
+      }),
+      _ty: PhantomData,
+    }
+  }
+
+  /// Creates a proxy for a resource that holds roughly `size` bytes of GPU memory.
+  pub fn create_sized_proxy<T: IntoResource>(
+    &self,
+    resource: T,
+    size: u64,
+  ) -> ResourceRc<T::ProxyType> {
+    ResourceRc {
+      life: Arc::new(ResourceLife {
+        original: resource.into(),
+        _allocation: Some(self.track_allocation(size)),
+        formats: RwLock::new(None),
       }),
       _ty: PhantomData,
     }
   }
+
+  /// Adds `size` bytes to the estimate until the returned allocation is dropped.
+  pub fn track_allocation(&self, size: u64) -> GpuAllocation {
+    self.allocated.fetch_add(size, Ordering::Relaxed);
+    GpuAllocation {
+      size,
+      allocated: self.allocated.clone(),
+    }
+  }
+
+  /// Approximate amount of bytes currently allocated by buffers and textures.
+  pub fn allocated_bytes(&self) -> u64 {
+    self.allocated.load(Ordering::Relaxed)
+  }
+}
+
+/// Approximate size in bytes of a 2D texture including all of its mip levels.
+pub fn texture_byte_size(
+  format: TextureFormat,
+  width: u32,
+  height: u32,
+  layers: u32,
+  mips: u32,
+) -> u64 {
+  let info = format.describe();
+  let (block_width, block_height) = (
+    info.block_dimensions.0 as u64,
+    info.block_dimensions.1 as u64,
+  );
+
+  (0..mips.max(1))
+    .map(|mip| {
+      let width = (width as u64 >> mip).max(1);
+      let height = (height as u64 >> mip).max(1);
+      let blocks =
+        ((width + block_width - 1) / block_width) * ((height + block_height - 1) / block_height);
+      blocks * info.block_size as u64
+    })
+    .sum::<u64>()
+    * layers as u64
+}
+
+/// Bytes of a single row of texel blocks, e.g. four pixel rows for block compressed formats.
+pub fn texture_bytes_per_row(format: TextureFormat, width: u32) -> u32 {
+  let info = format.describe();
+  let block_width = info.block_dimensions.0 as u32;
+  (width + block_width - 1) / block_width * info.block_size as u32
+}
+
+/// Size of a mip level rounded up to whole texel blocks.
+pub fn texture_mip_physical_size(
+  format: TextureFormat,
+  width: u32,
+  height: u32,
+  mip: u32,
+) -> (u32, u32) {
+  let info = format.describe();
+  let (block_width, block_height) = (
+    info.block_dimensions.0 as u32,
+    info.block_dimensions.1 as u32,
+  );
+  let width = (width >> mip).max(1);
+  let height = (height >> mip).max(1);
+  (
+    (width + block_width - 1) / block_width * block_width,
+    (height + block_height - 1) / block_height * block_height,
+  )
 }
 
 // Resource types
@@ -110,7 +240,7 @@ make_into_resource!(BindGroup, BindGroup);
 make_into_resource!(RenderPipeline, RenderPipeline);
 
 // Definition structures
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub enum VertexAttributeFormat {
   Float4,
   Float3,
@@ -120,6 +250,16 @@ pub enum VertexAttributeFormat {
   UInt3,
   UInt2,
   UInt,
+  Int4,
+  Int3,
+  Int2,
+  Int,
+  /// Four normalized bytes read as `vec4`, e.g. for vertex colors.
+  Unorm8x4,
+  /// Two half precision floats read as `vec2`, e.g. for uvs.
+  Half2,
+  /// Four half precision floats read as `vec4`.
+  Half4,
 }
 
 impl VertexAttributeFormat {
@@ -133,11 +273,18 @@ impl VertexAttributeFormat {
       VertexAttributeFormat::UInt3 => wgpu::VertexFormat::Uint32x3,
       VertexAttributeFormat::UInt2 => wgpu::VertexFormat::Uint32x2,
       VertexAttributeFormat::UInt => wgpu::VertexFormat::Uint32,
+      VertexAttributeFormat::Int4 => wgpu::VertexFormat::Sint32x4,
+      VertexAttributeFormat::Int3 => wgpu::VertexFormat::Sint32x3,
+      VertexAttributeFormat::Int2 => wgpu::VertexFormat::Sint32x2,
+      VertexAttributeFormat::Int => wgpu::VertexFormat::Sint32,
+      VertexAttributeFormat::Unorm8x4 => wgpu::VertexFormat::Unorm8x4,
+      VertexAttributeFormat::Half2 => wgpu::VertexFormat::Float16x2,
+      VertexAttributeFormat::Half4 => wgpu::VertexFormat::Float16x4,
     }
   }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct VertexAttribute {
   pub name: String,
   pub offset: u64,
@@ -145,7 +292,7 @@ pub struct VertexAttribute {
   pub location: usize,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct VertexBuffer {
   pub stride: u64,
   pub attributes: Vec<VertexAttribute>,
@@ -196,11 +343,15 @@ pub struct BindGroupLayoutDescriptor {
 pub struct BindGroupLayoutEntry {
   pub binding: u32,
   pub ty: BindGroupLayoutEntryType,
+  /// Whether textures can be sampled with filtering, for samplers whether they are filtering.
+  pub filterable: bool,
 }
 
 pub enum BindGroupLayoutEntryType {
   UniformBuffer,
   Sampler,
+  /// Depth comparison sampler e.g. for shadow maps (`sampler2DShadow`).
+  ComparisonSampler,
   SingleTexture,
   ArrayTexture(usize),
 }
@@ -213,9 +364,110 @@ impl BindGroupLayoutDescriptor {
   }
 
   pub fn add_entry(mut self, binding: u32, ty: BindGroupLayoutEntryType) -> Self {
-    self.entries.push(BindGroupLayoutEntry { binding, ty });
+    self.entries.push(BindGroupLayoutEntry {
+      binding,
+      ty,
+      filterable: true,
+    });
+    self
+  }
+
+  /// Adds a non-filterable texture or non-filtering sampler entry, e.g. for data textures.
+  pub fn add_unfiltered_entry(mut self, binding: u32, ty: BindGroupLayoutEntryType) -> Self {
+    self.entries.push(BindGroupLayoutEntry {
+      binding,
+      ty,
+      filterable: false,
+    });
     self
   }
+
+  /// Ensures filtering samplers are never paired with non-filterable textures.
+  pub fn validate(&self) -> Result<(), BindGroupLayoutError> {
+    let sampler = self
+      .entries
+      .iter()
+      .find(|entry| matches!(entry.ty, BindGroupLayoutEntryType::Sampler) && entry.filterable);
+    let texture = self.entries.iter().find(|entry| {
+      matches!(
+        entry.ty,
+        BindGroupLayoutEntryType::SingleTexture | BindGroupLayoutEntryType::ArrayTexture(_)
+      ) && !entry.filterable
+    });
+
+    match (sampler, texture) {
+      (Some(sampler), Some(texture)) => Err(BindGroupLayoutError::FilteringMismatch {
+        sampler: sampler.binding,
+        texture: texture.binding,
+      }),
+      _ => Ok(()),
+    }
+  }
+}
+
+/// Describes how textures are sampled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerConfig {
+  pub filter: FilterMode,
+  pub address_mode: AddressMode,
+  /// Maximum anisotropy, keeps textures viewed at grazing angles sharp.
+  pub anisotropy_clamp: Option<NonZeroU8>,
+}
+
+impl SamplerConfig {
+  pub fn new() -> Self {
+    Self {
+      filter: FilterMode::Nearest,
+      address_mode: AddressMode::Repeat,
+      anisotropy_clamp: None,
+    }
+  }
+
+  pub fn with_filter(mut self, filter: FilterMode) -> Self {
+    self.filter = filter;
+    self
+  }
+
+  pub fn with_address_mode(mut self, address_mode: AddressMode) -> Self {
+    self.address_mode = address_mode;
+    self
+  }
+
+  /// Enables anisotropic filtering, which always requires linear filtering.
+  pub fn with_anisotropy(mut self, clamp: u8) -> Self {
+    self.anisotropy_clamp = NonZeroU8::new(clamp);
+    self.filter = FilterMode::Linear;
+    self
+  }
+
+  /// Anisotropy has to be a power of two up to 16 and requires linear filtering.
+  pub fn validate(&self) -> Result<(), SamplerConfigError> {
+    match self.anisotropy_clamp {
+      Some(clamp) if !matches!(clamp.get(), 1 | 2 | 4 | 8 | 16) => {
+        Err(SamplerConfigError::InvalidAnisotropy(clamp.get()))
+      }
+      Some(clamp) if clamp.get() > 1 && self.filter != FilterMode::Linear => {
+        Err(SamplerConfigError::AnisotropyRequiresLinearFilter)
+      }
+      _ => Ok(()),
+    }
+  }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SamplerConfigError {
+  #[error("Anisotropy clamp {0} must be one of 1, 2, 4, 8 or 16")]
+  InvalidAnisotropy(u8),
+  #[error("Anisotropic filtering requires a linear filter")]
+  AnisotropyRequiresLinearFilter,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum BindGroupLayoutError {
+  #[error(
+    "The filtering sampler at {sampler} can't sample the non-filterable texture at {texture}"
+  )]
+  FilteringMismatch { sampler: u32, texture: u32 },
 }
 
 pub struct PipelineLayoutDescriptor {
@@ -285,17 +537,135 @@ pub enum BindGroupEntry {
   Sampler(ResourceRc<Sampler>),
 }
 
+/// Vertex buffer of a render pipeline, buffers are bound to slots in the order they were added.
+#[derive(Clone, Debug)]
+pub struct RenderPipelineVertexBuffer {
+  pub layout: VertexBuffer,
+  pub step_mode: InputStepMode,
+}
+
+/// Ensures no shader location is fed by more than one attribute across all vertex buffers.
+pub fn validate_vertex_buffers(
+  buffers: &[RenderPipelineVertexBuffer],
+) -> Result<(), VertexBufferLayoutError> {
+  let mut seen: Vec<(usize, usize)> = Vec::new();
+  for (slot, buffer) in buffers.iter().enumerate() {
+    for attribute in &buffer.layout.attributes {
+      if let Some((_, first_slot)) = seen.iter().find(|(loc, _)| *loc == attribute.location) {
+        return Err(VertexBufferLayoutError::DuplicateLocation {
+          location: attribute.location,
+          first_slot: *first_slot,
+          second_slot: slot,
+        });
+      }
+      seen.push((attribute.location, slot));
+    }
+  }
+  Ok(())
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum VertexBufferLayoutError {
+  #[error("Shader location {location} is used by vertex buffers {first_slot} and {second_slot}")]
+  DuplicateLocation {
+    location: usize,
+    first_slot: usize,
+    second_slot: usize,
+  },
+}
+
 pub struct RenderPipelineDescriptor {
   pub layout: ResourceRc<PipelineLayout>,
   pub vertex_shader: ResourceRc<Shader>,
-  pub vertex_desc: Option<VertexBuffer>,
+  pub vertex_buffers: Vec<RenderPipelineVertexBuffer>,
   pub fragment_shader: ResourceRc<Shader>,
   pub outputs: Vec<RenderPipelineOutput>,
-  pub depth: Option<TextureFormat>,
+  pub depth: Option<RenderPipelineDepth>,
+  pub depth_mode: DepthMode,
+  pub polygon_mode: PolygonMode,
+  pub topology: PrimitiveTopology,
+  pub front_face: FrontFace,
+  pub cull_mode: Option<Face>,
 }
 
 pub struct RenderPipelineOutput {
   pub format: TextureFormat,
+  /// `None` replaces the target color instead of blending with it.
+  pub blend: Option<BlendState>,
+}
+
+impl RenderPipelineOutput {
+  /// Blends the output over the target based on its alpha, used by `add_color_output`.
+  pub const ALPHA_BLENDING: BlendState = BlendState {
+    color: wgpu::BlendComponent {
+      src_factor: wgpu::BlendFactor::SrcAlpha,
+      dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+      operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent {
+      src_factor: wgpu::BlendFactor::SrcAlpha,
+      dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+      operation: wgpu::BlendOperation::Add,
+    },
+  };
+
+  /// Adds the output onto the target, e.g. to accumulate light.
+  pub const ADDITIVE_BLENDING: BlendState = BlendState {
+    color: wgpu::BlendComponent {
+      src_factor: wgpu::BlendFactor::One,
+      dst_factor: wgpu::BlendFactor::One,
+      operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent {
+      src_factor: wgpu::BlendFactor::One,
+      dst_factor: wgpu::BlendFactor::One,
+      operation: wgpu::BlendOperation::Add,
+    },
+  };
+}
+
+/// How depth values are distributed within the depth buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DepthMode {
+  /// Near plane at 0 and far plane at 1.
+  Standard,
+  /// Near plane at 1 and far plane at 0, gives far better precision for distant geometry
+  /// with floating point depth buffers.
+  ReverseZ,
+}
+
+impl Default for DepthMode {
+  fn default() -> Self {
+    DepthMode::Standard
+  }
+}
+
+impl DepthMode {
+  /// Value the depth buffer is cleared to, equal to the far plane.
+  pub fn clear_value(&self) -> f32 {
+    match self {
+      DepthMode::Standard => 1.0,
+      DepthMode::ReverseZ => 0.0,
+    }
+  }
+
+  /// Translates a compare function meant for standard depth into this mode.
+  pub fn compare(&self, standard: CompareFunction) -> CompareFunction {
+    match (self, standard) {
+      (DepthMode::Standard, compare) => compare,
+      (DepthMode::ReverseZ, CompareFunction::Less) => CompareFunction::Greater,
+      (DepthMode::ReverseZ, CompareFunction::LessEqual) => CompareFunction::GreaterEqual,
+      (DepthMode::ReverseZ, CompareFunction::Greater) => CompareFunction::Less,
+      (DepthMode::ReverseZ, CompareFunction::GreaterEqual) => CompareFunction::LessEqual,
+      (DepthMode::ReverseZ, compare) => compare,
+    }
+  }
+}
+
+pub struct RenderPipelineDepth {
+  pub format: TextureFormat,
+  pub compare: CompareFunction,
+  pub write_enabled: bool,
 }
 
 impl RenderPipelineDescriptor {
@@ -307,11 +677,19 @@ impl RenderPipelineDescriptor {
   ) -> Self {
     Self {
       layout,
-      vertex_desc: Some(vertex_desc),
+      vertex_buffers: vec![RenderPipelineVertexBuffer {
+        layout: vertex_desc,
+        step_mode: InputStepMode::Vertex,
+      }],
       vertex_shader,
       fragment_shader,
       depth: None,
+      depth_mode: DepthMode::Standard,
       outputs: Vec::new(),
+      polygon_mode: PolygonMode::Fill,
+      topology: PrimitiveTopology::TriangleList,
+      front_face: FrontFace::Ccw,
+      cull_mode: Some(Face::Back),
     }
   }
 
@@ -322,21 +700,88 @@ impl RenderPipelineDescriptor {
   ) -> Self {
     Self {
       layout,
-      vertex_desc: None,
+      vertex_buffers: Vec::new(),
       vertex_shader,
       fragment_shader,
       depth: None,
+      depth_mode: DepthMode::Standard,
       outputs: Vec::new(),
+      polygon_mode: PolygonMode::Fill,
+      topology: PrimitiveTopology::TriangleList,
+      front_face: FrontFace::Ccw,
+      cull_mode: Some(Face::Back),
     }
   }
 
-  pub fn add_color_output(mut self, format: TextureFormat) -> Self {
-    self.outputs.push(RenderPipelineOutput { format });
+  /// Adds another vertex buffer bound to the next slot, e.g. per-instance data with
+  /// `InputStepMode::Instance`.
+  pub fn add_vertex_buffer(mut self, layout: VertexBuffer, step_mode: InputStepMode) -> Self {
+    self
+      .vertex_buffers
+      .push(RenderPipelineVertexBuffer { layout, step_mode });
+    self
+  }
+
+  pub fn add_instance_buffer(self, layout: VertexBuffer) -> Self {
+    self.add_vertex_buffer(layout, InputStepMode::Instance)
+  }
+
+  pub fn add_color_output(self, format: TextureFormat) -> Self {
+    self.add_color_output_with_blend(format, Some(RenderPipelineOutput::ALPHA_BLENDING))
+  }
+
+  /// Same as `add_color_output` with a custom blend state, `None` writes the color opaquely.
+  pub fn add_color_output_with_blend(
+    mut self,
+    format: TextureFormat,
+    blend: Option<BlendState>,
+  ) -> Self {
+    self.outputs.push(RenderPipelineOutput { format, blend });
+    self
+  }
+
+  /// Regular geometry uses `CompareFunction::Less` with depth writes enabled,
+  /// skyboxes usually want `LessEqual` without writes and overlays `Always`.
+  pub fn add_depth(
+    mut self,
+    format: TextureFormat,
+    compare: CompareFunction,
+    write_enabled: bool,
+  ) -> Self {
+    self.depth = Some(RenderPipelineDepth {
+      format,
+      compare,
+      write_enabled,
+    });
+    self
+  }
+
+  /// Depth compare functions are given for standard depth and flipped for reverse-Z.
+  pub fn with_depth_mode(mut self, mode: DepthMode) -> Self {
+    self.depth_mode = mode;
+    self
+  }
+
+  /// Line and point modes require the `NON_FILL_POLYGON_MODE` device feature.
+  pub fn with_polygon_mode(mut self, mode: PolygonMode) -> Self {
+    self.polygon_mode = mode;
+    self
+  }
+
+  pub fn with_topology(mut self, topology: PrimitiveTopology) -> Self {
+    self.topology = topology;
+    self
+  }
+
+  /// Winding of front facing triangles, defaults to counter clockwise.
+  pub fn with_front_face(mut self, front_face: FrontFace) -> Self {
+    self.front_face = front_face;
     self
   }
 
-  pub fn add_depth(mut self, format: TextureFormat) -> Self {
-    self.depth = Some(format);
+  /// Faces that are culled, defaults to back faces and `None` draws both sides.
+  pub fn with_cull_mode(mut self, cull_mode: Option<Face>) -> Self {
+    self.cull_mode = cull_mode;
     self
   }
 }
@@ -0,0 +1,38 @@
+use moonwave_resources::{
+  BindGroupLayoutDescriptor, BindGroupLayoutEntryType, BindGroupLayoutError,
+};
+
+#[test]
+fn filterable_texture_with_linear_sampler() {
+  let desc = BindGroupLayoutDescriptor::new()
+    .add_entry(0, BindGroupLayoutEntryType::SingleTexture)
+    .add_entry(1, BindGroupLayoutEntryType::Sampler);
+
+  assert!(desc.entries.iter().all(|entry| entry.filterable));
+  assert_eq!(desc.validate(), Ok(()));
+}
+
+#[test]
+fn data_texture_with_non_filtering_sampler() {
+  let desc = BindGroupLayoutDescriptor::new()
+    .add_unfiltered_entry(0, BindGroupLayoutEntryType::SingleTexture)
+    .add_unfiltered_entry(1, BindGroupLayoutEntryType::Sampler);
+
+  assert!(desc.entries.iter().all(|entry| !entry.filterable));
+  assert_eq!(desc.validate(), Ok(()));
+}
+
+#[test]
+fn data_texture_with_filtering_sampler() {
+  let desc = BindGroupLayoutDescriptor::new()
+    .add_unfiltered_entry(0, BindGroupLayoutEntryType::ArrayTexture(4))
+    .add_entry(1, BindGroupLayoutEntryType::Sampler);
+
+  assert_eq!(
+    desc.validate(),
+    Err(BindGroupLayoutError::FilteringMismatch {
+      sampler: 1,
+      texture: 0,
+    })
+  );
+}
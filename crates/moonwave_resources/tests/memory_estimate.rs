@@ -0,0 +1,33 @@
+use moonwave_resources::{texture_byte_size, ResourceStorage, TextureFormat};
+
+#[test]
+fn texture_size_test() {
+  assert_eq!(texture_byte_size(TextureFormat::Rgba8Unorm, 4, 4, 1, 1), 64);
+  assert_eq!(
+    texture_byte_size(TextureFormat::Rgba8Unorm, 4, 4, 2, 1),
+    128
+  );
+  // 4x4 + 2x2 + 1x1 pixels.
+  assert_eq!(texture_byte_size(TextureFormat::Rgba8Unorm, 4, 4, 1, 3), 84);
+  // A single 4x4 block of 8 bytes.
+  assert_eq!(
+    texture_byte_size(TextureFormat::Bc1RgbaUnorm, 4, 4, 1, 1),
+    8
+  );
+}
+
+#[test]
+fn memory_estimate_test() {
+  let storage = ResourceStorage::new();
+  let size = texture_byte_size(TextureFormat::Rgba8Unorm, 4096, 4096, 1, 1);
+  assert_eq!(size, 4096 * 4096 * 4);
+
+  let texture = storage.track_allocation(size);
+  let buffer = storage.track_allocation(1024);
+  assert_eq!(storage.allocated_bytes(), size + 1024);
+
+  drop(texture);
+  assert_eq!(storage.allocated_bytes(), 1024);
+  drop(buffer);
+  assert_eq!(storage.allocated_bytes(), 0);
+}
@@ -0,0 +1,30 @@
+use moonwave_resources::{FilterMode, SamplerConfig, SamplerConfigError};
+
+#[test]
+fn anisotropy_enables_linear_filtering() {
+  let config = SamplerConfig::new().with_anisotropy(16);
+  assert_eq!(config.filter, FilterMode::Linear);
+  assert_eq!(config.anisotropy_clamp.map(|clamp| clamp.get()), Some(16));
+  assert_eq!(config.validate(), Ok(()));
+
+  // Zero disables anisotropy again.
+  assert_eq!(
+    SamplerConfig::new().with_anisotropy(0).anisotropy_clamp,
+    None
+  );
+}
+
+#[test]
+fn invalid_anisotropy_is_rejected() {
+  assert_eq!(
+    SamplerConfig::new().with_anisotropy(3).validate(),
+    Err(SamplerConfigError::InvalidAnisotropy(3))
+  );
+  assert_eq!(
+    SamplerConfig::new()
+      .with_anisotropy(8)
+      .with_filter(FilterMode::Nearest)
+      .validate(),
+    Err(SamplerConfigError::AnisotropyRequiresLinearFilter)
+  );
+}
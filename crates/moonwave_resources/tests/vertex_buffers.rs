@@ -0,0 +1,49 @@
+use moonwave_resources::{
+  validate_vertex_buffers, InputStepMode, RenderPipelineVertexBuffer, VertexAttribute,
+  VertexAttributeFormat, VertexBuffer, VertexBufferLayoutError,
+};
+
+fn buffer(locations: &[usize], step_mode: InputStepMode) -> RenderPipelineVertexBuffer {
+  RenderPipelineVertexBuffer {
+    layout: VertexBuffer {
+      stride: 16 * locations.len() as u64,
+      attributes: locations
+        .iter()
+        .enumerate()
+        .map(|(i, location)| VertexAttribute {
+          name: format!("attr{}", location),
+          offset: 16 * i as u64,
+          format: VertexAttributeFormat::Float4,
+          location: *location,
+        })
+        .collect(),
+    },
+    step_mode,
+  }
+}
+
+#[test]
+fn per_instance_buffer_test() {
+  // Instance matrices continue after the locations of the vertex attributes.
+  let buffers = [
+    buffer(&[0, 1, 2], InputStepMode::Vertex),
+    buffer(&[3, 4, 5, 6], InputStepMode::Instance),
+  ];
+  assert_eq!(validate_vertex_buffers(&buffers), Ok(()));
+}
+
+#[test]
+fn duplicate_location_test() {
+  let buffers = [
+    buffer(&[0, 1], InputStepMode::Vertex),
+    buffer(&[1, 2], InputStepMode::Instance),
+  ];
+  assert_eq!(
+    validate_vertex_buffers(&buffers),
+    Err(VertexBufferLayoutError::DuplicateLocation {
+      location: 1,
+      first_slot: 0,
+      second_slot: 1,
+    })
+  );
+}
@@ -8,6 +8,10 @@ pub enum BoundingShape {
     min: Vector3<f32>,
     max: Vector3<f32>,
   },
+  Sphere {
+    center: Vector3<f32>,
+    radius: f32,
+  },
 }
 
 impl BoundingShape {
@@ -16,18 +20,9 @@ impl BoundingShape {
     transform: Option<&Transform>,
   ) -> Self {
     // Generate matrix.
-    let matrix = if let Some(transform) = transform {
-      let transform = transform.get();
-      let translation = Matrix4::from_translation(transform.position);
-      let rotation = Matrix4::from_angle_x(Rad(transform.rotation.x))
-        * Matrix4::from_angle_y(Rad(transform.rotation.y))
-        * Matrix4::from_angle_z(Rad(transform.rotation.z));
-      let scale =
-        Matrix4::from_nonuniform_scale(transform.scale.x, transform.scale.y, transform.scale.z);
-      translation * rotation * scale
-    } else {
-      Matrix4::identity()
-    };
+    let matrix = transform
+      .map(|transform| transform.calculate_transform_matrix())
+      .unwrap_or_else(Matrix4::identity);
 
     // Find bounds.
     let mut min_x = f32::MAX;
@@ -56,6 +51,109 @@ impl BoundingShape {
     }
   }
 
+  /// Computes the local space bounds of the given mesh.
+  pub fn from_mesh<T: MeshVertex, I: MeshIndex>(mesh: &Mesh<T, I>) -> Self {
+    Self::new(mesh, None)
+  }
+
+  /// Computes a local space bounding sphere of the given mesh.
+  /// Cheaper to test against the frustum than an AABB but usually less tight.
+  pub fn sphere_from_mesh<T: MeshVertex, I: MeshIndex>(mesh: &Mesh<T, I>) -> Self {
+    let (center, radius) = mesh.compute_bounding_sphere();
+    BoundingShape::Sphere { center, radius }
+  }
+
+  /// Transforms local space bounds into world space using the given transform.
+  pub fn transformed(&self, transform: &Transform) -> Self {
+    let matrix = transform.calculate_transform_matrix();
+
+    match self {
+      BoundingShape::AABB { min, max } => {
+        // Transform all corners and rebuild axis aligned bounds around them.
+        let mut out_min = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut out_max = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
+        for i in 0..8 {
+          let corner = Vector4::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+            1.0,
+          );
+          let world_space = matrix * corner;
+          let world_space_norm = world_space.xyz() / world_space.w;
+          out_min.x = out_min.x.min(world_space_norm.x);
+          out_min.y = out_min.y.min(world_space_norm.y);
+          out_min.z = out_min.z.min(world_space_norm.z);
+          out_max.x = out_max.x.max(world_space_norm.x);
+          out_max.y = out_max.y.max(world_space_norm.y);
+          out_max.z = out_max.z.max(world_space_norm.z);
+        }
+
+        BoundingShape::AABB {
+          min: out_min,
+          max: out_max,
+        }
+      }
+      BoundingShape::Sphere { center, radius } => {
+        let world_space = matrix * Vector4::new(center.x, center.y, center.z, 1.0);
+
+        // Non uniform scaling stretches the sphere, so the largest axis has to be used.
+        let scale = matrix
+          .x
+          .truncate()
+          .magnitude()
+          .max(matrix.y.truncate().magnitude())
+          .max(matrix.z.truncate().magnitude());
+
+        BoundingShape::Sphere {
+          center: world_space.xyz() / world_space.w,
+          radius: radius * scale,
+        }
+      }
+    }
+  }
+
+  /// Distance along the ray to the first hit, `0.0` if the origin lies inside.
+  /// `direction` doesn't have to be normalized, the distance is measured in multiples of it.
+  pub fn intersect_ray(&self, origin: Vector3<f32>, direction: Vector3<f32>) -> Option<f32> {
+    match self {
+      BoundingShape::AABB { min, max } => {
+        // Slab method, divisions by zero result in infinities which work out for parallel rays.
+        let mut t_min = 0.0f32;
+        let mut t_max = f32::MAX;
+        for axis in 0..3 {
+          let inv = 1.0 / direction[axis];
+          let t0 = (min[axis] - origin[axis]) * inv;
+          let t1 = (max[axis] - origin[axis]) * inv;
+          // NaN occurs for parallel rays starting exactly on a slab, count those as inside.
+          if t0.is_nan() || t1.is_nan() {
+            continue;
+          }
+          t_min = t_min.max(t0.min(t1));
+          t_max = t_max.min(t0.max(t1));
+          if t_min > t_max {
+            return None;
+          }
+        }
+        Some(t_min)
+      }
+      BoundingShape::Sphere { center, radius } => {
+        let offset = origin - center;
+        let a = direction.dot(direction);
+        let b = offset.dot(direction);
+        let c = offset.dot(offset) - radius * radius;
+        if c <= 0.0 {
+          return Some(0.0);
+        }
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 || b > 0.0 {
+          return None;
+        }
+        Some((-b - discriminant.sqrt()) / a)
+      }
+    }
+  }
+
   pub fn plane_distance(plane: &Vector4<f32>, target: &Vector3<f32>) -> f32 {
     plane.w + plane.xyz().dot(*target)
   }
@@ -107,6 +205,10 @@ impl BoundingShape {
         }
         true
       }
+      BoundingShape::Sphere { center, radius } => frustum_planes.iter().all(|plane| {
+        // Planes might not be normalized, so scale the radius accordingly.
+        Self::plane_distance(plane, center) >= -radius * plane.xyz().magnitude()
+      }),
     }
   }
 }
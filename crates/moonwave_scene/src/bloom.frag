@@ -0,0 +1,59 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform BloomPassUniform {
+  vec2 texel_size;
+  vec2 direction;
+  float threshold;
+  float intensity;
+  uint mode;
+} u_bloom;
+
+layout(set = 1, binding = 0) uniform texture2D t_source;
+layout(set = 1, binding = 1) uniform sampler s_source;
+
+layout(location=0) in vec2 v_uv;
+
+layout(location=0) out vec4 f_color;
+
+const uint MODE_DOWNSAMPLE = 0;
+const uint MODE_BLUR = 1;
+const uint MODE_SCALE = 2;
+
+// 9 tap gaussian reduced to 5 bilinear taps, mirrors `BLOOM_BLUR_OFFSETS` and `BLOOM_BLUR_WEIGHTS`.
+const float OFFSETS[3] = float[](0.0, 1.3846153846, 3.2307692308);
+const float WEIGHTS[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+
+vec3 sample_source(vec2 uv) {
+  return texture(sampler2D(t_source, s_source), uv).rgb;
+}
+
+// Mirrors `bloom_bright_pass`.
+vec3 bright_pass(vec3 color) {
+  if (u_bloom.threshold <= 0.0) {
+    return color;
+  }
+  float brightness = max(color.r, max(color.g, color.b));
+  return color * (max(brightness - u_bloom.threshold, 0.0) / max(brightness, 0.0001));
+}
+
+void main() {
+  vec3 color;
+  if (u_bloom.mode == MODE_DOWNSAMPLE) {
+    // Four bilinear taps average a 4x4 block of source texels.
+    vec2 offset = u_bloom.texel_size;
+    color = sample_source(v_uv + vec2(-offset.x, -offset.y));
+    color += sample_source(v_uv + vec2(offset.x, -offset.y));
+    color += sample_source(v_uv + vec2(-offset.x, offset.y));
+    color += sample_source(v_uv + vec2(offset.x, offset.y));
+    color = bright_pass(max(color * 0.25, vec3(0.0)));
+  } else if (u_bloom.mode == MODE_BLUR) {
+    color = sample_source(v_uv) * WEIGHTS[0];
+    for (int i = 1; i < 3; i++) {
+      vec2 offset = u_bloom.direction * u_bloom.texel_size * OFFSETS[i];
+      color += (sample_source(v_uv + offset) + sample_source(v_uv - offset)) * WEIGHTS[i];
+    }
+  } else {
+    color = sample_source(v_uv) * u_bloom.intensity;
+  }
+  f_color = vec4(color, 1.0);
+}
@@ -0,0 +1,393 @@
+use moonwave_common::*;
+use moonwave_core::{optick, Core, OnceCell, ShaderKind, FULLSCREEN_TRIANGLE_VS};
+use moonwave_render::{
+  CommandEncoder, FrameGraphNode, FrameNodeValue, RenderPassCommandEncoderBuilder,
+};
+use moonwave_resources::{
+  BindGroup, BindGroupDescriptor, BlendState, FilterMode, PipelineLayoutDescriptor, RenderPipeline,
+  RenderPipelineDescriptor, RenderPipelineOutput, ResourceRc, Sampler, TextureFormat, TextureUsage,
+  TextureView,
+};
+use moonwave_shader::uniform;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+use crate::{Uniform, MATERIAL_UNIFORM_LAYOUT};
+
+/// Fragment shader of all bloom passes, the pass is selected through `BloomPassUniform::mode`.
+pub const BLOOM_FRAGMENT_SHADER: &str = include_str!("./bloom.frag");
+
+/// Texel offsets of the linear sampled gaussian blur taps, mirrors the fragment shader.
+pub const BLOOM_BLUR_OFFSETS: [f32; 3] = [0.0, 1.384_615_4, 3.230_769_3];
+/// Weights of the blur taps, all but the center tap are applied on both sides.
+pub const BLOOM_BLUR_WEIGHTS: [f32; 3] = [0.227_027_03, 0.316_216_22, 0.070_270_27];
+
+/// Upper limit of downsampled levels, further levels hardly add any visible glow.
+pub const MAX_BLOOM_ITERATIONS: u32 = 8;
+
+const BLOOM_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+static BLOOM_RESOURCES: OnceCell<BloomResources> = OnceCell::new();
+
+#[uniform]
+pub struct BloomPassUniform {
+  texel_size: Vector2<f32>,
+  direction: Vector2<f32>,
+  threshold: f32,
+  intensity: f32,
+  mode: u32,
+}
+
+struct BloomResources {
+  pipeline: ResourceRc<RenderPipeline>,
+  additive_pipeline: ResourceRc<RenderPipeline>,
+  sampler: ResourceRc<Sampler>,
+  /// Bind group of the scene color of the previous frame, reused as long as the input stays the same.
+  source: Mutex<Option<(ResourceRc<TextureView>, ResourceRc<BindGroup>)>>,
+  /// Level textures keyed by resolution and level count, nodes are recreated with every frame graph.
+  chains: Mutex<HashMap<(u32, u32, usize), BloomChain>>,
+}
+
+impl BloomResources {
+  fn new() -> Self {
+    let core = Core::get_instance();
+    let vs = core
+      .create_shader_from_glsl(FULLSCREEN_TRIANGLE_VS, "BloomVS", ShaderKind::Vertex)
+      .unwrap();
+    let fs = core
+      .create_shader_from_glsl(BLOOM_FRAGMENT_SHADER, "BloomFS", ShaderKind::Fragment)
+      .unwrap();
+
+    let layout = core.create_pipeline_layout(
+      PipelineLayoutDescriptor::new()
+        .add_binding(MATERIAL_UNIFORM_LAYOUT.clone())
+        .add_binding(
+          core
+            .get_gp_resources()
+            .sampled_texture_bind_group_layout
+            .clone(),
+        ),
+    );
+    let create_pipeline = |blend: Option<BlendState>| {
+      core
+        .create_render_pipeline(
+          RenderPipelineDescriptor::new_without_vertices(layout.clone(), vs.clone(), fs.clone())
+            .add_color_output_with_blend(BLOOM_FORMAT, blend),
+        )
+        .unwrap()
+    };
+
+    Self {
+      pipeline: create_pipeline(None),
+      additive_pipeline: create_pipeline(Some(RenderPipelineOutput::ADDITIVE_BLENDING)),
+      sampler: core.create_filtered_sampler(FilterMode::Linear),
+      source: Mutex::new(None),
+      chains: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Bind group sampling the view with linear filtering.
+  fn create_bind_group(&self, view: ResourceRc<TextureView>) -> ResourceRc<BindGroup> {
+    let core = Core::get_instance();
+    core.create_bind_group(
+      BindGroupDescriptor::new(
+        core
+          .get_gp_resources()
+          .sampled_texture_bind_group_layout
+          .clone(),
+      )
+      .add_texture_binding(0, view)
+      .add_sampler_binding(1, self.sampler.clone()),
+    )
+  }
+
+  fn get_source_bind_group(&self, view: &ResourceRc<TextureView>) -> ResourceRc<BindGroup> {
+    let mut cached = self.source.lock();
+    if let Some((cached_view, bind_group)) = cached.as_ref() {
+      if cached_view == view {
+        return bind_group.clone();
+      }
+    }
+
+    let bind_group = self.create_bind_group(view.clone());
+    *cached = Some((view.clone(), bind_group.clone()));
+    bind_group
+  }
+}
+
+/// Part of the color that exceeds the threshold and starts to glow, mirrors the fragment shader.
+/// The hue is kept while the brightness is reduced by the threshold.
+pub fn bloom_bright_pass(color: Vector3<f32>, threshold: f32) -> Vector3<f32> {
+  if threshold <= 0.0 {
+    return color;
+  }
+  let brightness = color.x.max(color.y).max(color.z);
+  color * ((brightness - threshold).max(0.0) / brightness.max(0.0001))
+}
+
+/// Sizes of the downsampled levels, each level halves the previous one starting at half resolution.
+pub fn bloom_level_sizes(size: Vector2<u32>, iterations: u32) -> Vec<Vector2<u32>> {
+  (1..=iterations.max(1).min(MAX_BLOOM_ITERATIONS))
+    .map(|level| Vector2::new((size.x >> level).max(1), (size.y >> level).max(1)))
+    .collect()
+}
+
+/// Single draw of the bloom chain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BloomPass {
+  /// Downsamples the scene color into the first level, keeping only parts above the threshold.
+  BrightPass,
+  /// Downsamples the previous level into the given one.
+  Downsample(usize),
+  /// Blurs the level horizontally into its temporary texture.
+  BlurHorizontal(usize),
+  /// Blurs the temporary texture vertically back into the level.
+  BlurVertical(usize),
+  /// Adds the level onto the next larger level.
+  Upsample(usize),
+  /// Writes the first level scaled by the intensity into the target.
+  Composite,
+}
+
+/// Passes drawn in order for the given amount of iterations.
+pub fn bloom_passes(iterations: u32) -> Vec<BloomPass> {
+  let levels = iterations.max(1).min(MAX_BLOOM_ITERATIONS) as usize;
+  let mut passes = vec![BloomPass::BrightPass];
+  for level in 0..levels {
+    if level > 0 {
+      passes.push(BloomPass::Downsample(level));
+    }
+    passes.push(BloomPass::BlurHorizontal(level));
+    passes.push(BloomPass::BlurVertical(level));
+  }
+  passes.extend((1..levels).rev().map(BloomPass::Upsample));
+  passes.push(BloomPass::Composite);
+  passes
+}
+
+/// Tweakables of the bloom.
+#[derive(Debug, Clone, Copy)]
+pub struct BloomSettings {
+  /// Brightness above which colors start to glow, HDR values above 1 are typical.
+  pub threshold: f32,
+  /// Scales the glow that is added onto the scene.
+  pub intensity: f32,
+  /// Amount of downsampled levels, more levels spread the glow further.
+  pub iterations: u32,
+}
+
+impl Default for BloomSettings {
+  fn default() -> Self {
+    Self {
+      threshold: 1.0,
+      intensity: 0.1,
+      iterations: 5,
+    }
+  }
+}
+
+impl BloomSettings {
+  pub fn build_uniform(&self, pass: BloomPass, source_size: Vector2<u32>) -> BloomPassUniform {
+    let (mode, direction) = match pass {
+      BloomPass::BrightPass | BloomPass::Downsample(_) => (0, Vector2::zero()),
+      BloomPass::BlurHorizontal(_) => (1, Vector2::new(1.0, 0.0)),
+      BloomPass::BlurVertical(_) => (1, Vector2::new(0.0, 1.0)),
+      BloomPass::Upsample(_) | BloomPass::Composite => (2, Vector2::zero()),
+    };
+    BloomPassUniform {
+      texel_size: Vector2::new(1.0 / source_size.x as f32, 1.0 / source_size.y as f32),
+      direction,
+      // Only the first downsample cuts off dark parts.
+      threshold: match pass {
+        BloomPass::BrightPass => self.threshold,
+        _ => 0.0,
+      },
+      intensity: match pass {
+        BloomPass::Composite => self.intensity,
+        _ => 1.0,
+      },
+      mode,
+    }
+  }
+}
+
+/// Downsampled level together with the temporary texture of its separable blur.
+struct BloomLevel {
+  size: Vector2<u32>,
+  view: ResourceRc<TextureView>,
+  bind_group: ResourceRc<BindGroup>,
+  temp_view: ResourceRc<TextureView>,
+  temp_bind_group: ResourceRc<BindGroup>,
+}
+
+struct BloomChain {
+  levels: Vec<BloomLevel>,
+  uniforms: Vec<Uniform<BloomPassUniform>>,
+}
+
+impl BloomChain {
+  fn new(resources: &BloomResources, size: Vector2<u32>, settings: &BloomSettings) -> Self {
+    let core = Core::get_instance();
+    let create = |label: &str, size: Vector2<u32>| {
+      let texture = core.create_texture(
+        Some(label),
+        TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED,
+        BLOOM_FORMAT,
+        size,
+        1,
+      );
+      let view = core.create_texture_view(texture);
+      let bind_group = resources.create_bind_group(view.clone());
+      (view, bind_group)
+    };
+
+    let levels = bloom_level_sizes(size, settings.iterations)
+      .into_iter()
+      .map(|size| {
+        let (view, bind_group) = create("BloomLevel", size);
+        let (temp_view, temp_bind_group) = create("BloomLevelTemp", size);
+        BloomLevel {
+          size,
+          view,
+          bind_group,
+          temp_view,
+          temp_bind_group,
+        }
+      })
+      .collect();
+    let uniforms = bloom_passes(settings.iterations)
+      .into_iter()
+      .map(|pass| Uniform::new(settings.build_uniform(pass, size)))
+      .collect();
+
+    Self { levels, uniforms }
+  }
+}
+
+/// Bloom post effect, meant to run on the HDR color target of the PBR pass before tonemapping.
+/// Bright parts are extracted, blurred across several downsampled levels and written scaled by
+/// the intensity into the target, which is expected to be a full screen `Rgba16Float` texture.
+/// Connect the output to `TonemapNode::INPUT_BLOOM` to add it onto the scene.
+/// A disabled node has no bypass and leaves its output empty, so the tonemapper skips the bloom.
+pub struct BloomNode {
+  settings: BloomSettings,
+}
+
+impl BloomNode {
+  pub const INPUT_COLOR: usize = 0;
+  pub const INPUT_TARGET: usize = 1;
+  pub const OUTPUT_BLOOM: usize = 0;
+
+  pub fn new() -> Self {
+    Self {
+      settings: BloomSettings::default(),
+    }
+  }
+
+  pub fn with_threshold(mut self, threshold: f32) -> Self {
+    self.settings.threshold = threshold;
+    self
+  }
+
+  pub fn with_intensity(mut self, intensity: f32) -> Self {
+    self.settings.intensity = intensity;
+    self
+  }
+
+  pub fn with_iterations(mut self, iterations: u32) -> Self {
+    self.settings.iterations = iterations;
+    self
+  }
+
+  pub fn get_settings(&self) -> &BloomSettings {
+    &self.settings
+  }
+}
+
+impl FrameGraphNode for BloomNode {
+  fn execute(
+    &self,
+    inputs: &[Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
+    encoder: &mut CommandEncoder,
+  ) {
+    optick::event!("FrameGraph::Bloom");
+    let resources = BLOOM_RESOURCES.get_or_init(BloomResources::new);
+
+    let input = |index: usize| inputs[index].as_ref().unwrap().get_sampled_texture();
+    let source = resources.get_source_bind_group(&input(Self::INPUT_COLOR).view);
+    let target = input(Self::INPUT_TARGET).clone();
+
+    // Levels follow the resolution of the scene, chains of previous resolutions are dropped.
+    let size = Core::get_instance().get_swap_chain_size();
+    let passes = bloom_passes(self.settings.iterations);
+    let mut chains = resources.chains.lock();
+    chains.retain(|(width, height, _), _| *width == size.x && *height == size.y);
+    let chain = chains
+      .entry((
+        size.x,
+        size.y,
+        bloom_level_sizes(size, self.settings.iterations).len(),
+      ))
+      .or_insert_with(|| BloomChain::new(resources, size, &self.settings));
+    let levels = &chain.levels;
+
+    for (pass, uniform) in passes.into_iter().zip(chain.uniforms.iter()) {
+      // Source size, source, target and whether the target is added onto.
+      let (source_size, source, target_view, additive) = match pass {
+        BloomPass::BrightPass => (size, source.clone(), &levels[0].view, false),
+        BloomPass::Downsample(level) => (
+          levels[level - 1].size,
+          levels[level - 1].bind_group.clone(),
+          &levels[level].view,
+          false,
+        ),
+        BloomPass::BlurHorizontal(level) => (
+          levels[level].size,
+          levels[level].bind_group.clone(),
+          &levels[level].temp_view,
+          false,
+        ),
+        BloomPass::BlurVertical(level) => (
+          levels[level].size,
+          levels[level].temp_bind_group.clone(),
+          &levels[level].view,
+          false,
+        ),
+        BloomPass::Upsample(level) => (
+          levels[level].size,
+          levels[level].bind_group.clone(),
+          &levels[level - 1].view,
+          true,
+        ),
+        BloomPass::Composite => (
+          levels[0].size,
+          levels[0].bind_group.clone(),
+          &target.view,
+          false,
+        ),
+      };
+
+      *uniform.get_mut() = self.settings.build_uniform(pass, source_size);
+      let uniform = uniform.as_generic();
+      let uniform = uniform.get_resources(encoder).bind_group.clone();
+
+      let mut rpb = RenderPassCommandEncoderBuilder::new("bloom_rp");
+      let pipeline = if additive {
+        rpb.add_color_output_preserved(target_view);
+        resources.additive_pipeline.clone()
+      } else {
+        rpb.add_color_output(target_view, Vector4::new(0.0, 0.0, 0.0, 1.0));
+        resources.pipeline.clone()
+      };
+
+      let mut rp = encoder.create_render_pass_encoder(rpb);
+      rp.set_pipeline(pipeline);
+      rp.set_bind_group(0, uniform);
+      rp.set_bind_group(1, source);
+      rp.render(0..3);
+    }
+
+    outputs[Self::OUTPUT_BLOOM] = Some(FrameNodeValue::SampledTexture(target));
+  }
+}
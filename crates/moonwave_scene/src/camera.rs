@@ -1,6 +1,7 @@
 use legion::systems::ParallelRunnable;
 use moonwave_common::*;
 use moonwave_core::{system, Core, SystemStage};
+use moonwave_resources::DepthMode;
 use moonwave_shader::uniform;
 
 use crate::Uniform;
@@ -13,6 +14,22 @@ pub struct CameraUniform {
   position: Vector3<f32>,
 }
 
+impl CameraUniform {
+  /// Camera of screen space overlays, positions are given in pixels starting at the top left like the UI.
+  pub(crate) fn screen_space(size: Vector2<u32>, depth_mode: DepthMode) -> Self {
+    let projection = depth_mode_projection(
+      depth_mode,
+      ortho(0.0, size.x as f32, size.y as f32, 0.0, -100.0, 100.0),
+    );
+    Self {
+      projection,
+      view: Matrix4::identity(),
+      projection_view: projection,
+      position: Vector3::zero(),
+    }
+  }
+}
+
 /// Used to tag the camera actor that is the scenes main / active camera
 pub struct MainCameraTag;
 
@@ -23,6 +40,11 @@ pub struct Camera {
   pub up: Vector3<f32>,
   pub aspect: f32,
   pub fov_y: f32,
+  /// Reverse-Z requires pipelines and render passes to use the same depth mode.
+  /// Defaults to the depth mode of the core, see `CoreConfig::depth_mode`.
+  pub depth_mode: DepthMode,
+  /// Visible height in world units when using an orthographic projection, e.g. for 2D scenes.
+  pub orthographic: Option<f32>,
   z_near: f32,
   z_far: f32,
 }
@@ -31,8 +53,8 @@ static REGISTERED_SYSTEM: std::sync::Once = std::sync::Once::new();
 
 impl Camera {
   pub fn new() -> Self {
+    let core = Core::get_instance();
     REGISTERED_SYSTEM.call_once(|| {
-      let core = Core::get_instance();
       core.get_world().add_system_to_stage(
         || -> Box<dyn ParallelRunnable> { Box::new(update_camera_matrices_system()) },
         SystemStage::RenderingPreperations,
@@ -44,6 +66,8 @@ impl Camera {
       z_near: 0.01,
       fov_y: std::f32::consts::FRAC_PI_4,
       aspect: 1.0,
+      depth_mode: core.get_depth_mode(),
+      orthographic: None,
       position: Vector3::new(0.0, 0.0, 0.0),
       target: Vector3::new(0.0, 0.0, 1.0),
       up: Vector3::new(0.0, 1.0, 0.0),
@@ -55,6 +79,47 @@ impl Camera {
       }),
     }
   }
+  /// Switches to an orthographic projection showing `height` world units vertically.
+  pub fn with_orthographic(mut self, height: f32) -> Self {
+    self.orthographic = Some(height);
+    self
+  }
+
+  pub fn with_depth_mode(mut self, mode: DepthMode) -> Self {
+    self.depth_mode = mode;
+    self
+  }
+
+  /// Distance of the near clipping plane.
+  pub fn get_z_near(&self) -> f32 {
+    self.z_near
+  }
+
+  /// Distance of the far clipping plane.
+  pub fn get_z_far(&self) -> f32 {
+    self.z_far
+  }
+
+  /// Projection matrix based on the current camera settings.
+  pub fn calculate_projection(&self) -> Matrix4<f32> {
+    let projection = match self.orthographic {
+      Some(height) => {
+        let half_height = height / 2.0;
+        let half_width = half_height * self.aspect;
+        ortho(
+          -half_width,
+          half_width,
+          -half_height,
+          half_height,
+          self.z_near,
+          self.z_far,
+        )
+      }
+      None => perspective(Rad(self.fov_y), self.aspect, self.z_near, self.z_far),
+    };
+    depth_mode_projection(self.depth_mode, projection)
+  }
+
   pub fn calculate_frustum_planes(&self, planes: &mut [Vector4<f32>; 6]) {
     // Extract planes from view projection.
     let vp = self.uniform.get().projection_view;
@@ -70,36 +135,114 @@ impl Camera {
     for i in 0..4 {
       planes[3][i] = vp[i][3] - vp[i][1]
     } // bottom
-    for i in 0..4 {
-      planes[4][i] = vp[i][3] + vp[i][2]
-    } // far
-    for i in 0..4 {
-      planes[5][i] = vp[i][3] - vp[i][2]
-    } // near
+
+    // Reverse-Z maps depth to `w..0`, so the far plane lies at 0 and the near plane at `w`.
+    match self.depth_mode {
+      DepthMode::Standard => {
+        for i in 0..4 {
+          planes[4][i] = vp[i][3] + vp[i][2]
+        } // near
+        for i in 0..4 {
+          planes[5][i] = vp[i][3] - vp[i][2]
+        } // far
+      }
+      DepthMode::ReverseZ => {
+        for i in 0..4 {
+          planes[4][i] = vp[i][2]
+        } // far
+        for i in 0..4 {
+          planes[5][i] = vp[i][3] - vp[i][2]
+        } // near
+      }
+    }
 
     // Normalize planes
     for plane in planes {
       *plane = plane.normalize();
     }
   }
+
+  /// World space frustum corners, near plane first then far plane each in the order
+  /// bottom left, bottom right, top right, top left.
+  pub fn calculate_frustum_corners(&self) -> [Vector3<f32>; 8] {
+    let inverse = self
+      .uniform
+      .get()
+      .projection_view
+      .invert()
+      .unwrap_or_else(Matrix4::identity);
+
+    let mut corners = [Vector3::zero(); 8];
+    for (index, corner) in corners.iter_mut().enumerate() {
+      let z = if index < 4 { -1.0 } else { 1.0 };
+      let (x, y) = match index % 4 {
+        0 => (-1.0, -1.0),
+        1 => (1.0, -1.0),
+        2 => (1.0, 1.0),
+        _ => (-1.0, 1.0),
+      };
+      let world = inverse * Vector4::new(x, y, z, 1.0);
+      *corner = world.truncate() / world.w;
+    }
+    corners
+  }
+
+  /// World space ray through a point on screen for CPU side picking, see `unproject_screen_ray`.
+  /// Uses the current camera settings instead of the matrices uploaded last frame.
+  pub fn screen_ray(&self, x: f32, y: f32) -> (Vector3<f32>, Vector3<f32>) {
+    let projection_view =
+      self.calculate_projection() * look_at(self.position, self.target, self.up);
+    let inverse = projection_view.invert().unwrap_or_else(Matrix4::identity);
+    unproject_screen_ray(inverse, self.depth_mode, x, y)
+  }
+}
+
+/// Ray through the given point on screen, `x` and `y` are within `0..=1` starting at the top left.
+/// Returns the origin on the near plane and the normalized direction towards the far plane.
+pub fn unproject_screen_ray(
+  inverse_projection_view: Matrix4<f32>,
+  depth_mode: DepthMode,
+  x: f32,
+  y: f32,
+) -> (Vector3<f32>, Vector3<f32>) {
+  let (near, far) = match depth_mode {
+    DepthMode::Standard => (-1.0, 1.0),
+    DepthMode::ReverseZ => (1.0, 0.0),
+  };
+  let unproject = |z: f32| {
+    let world = inverse_projection_view * Vector4::new(x * 2.0 - 1.0, 1.0 - y * 2.0, z, 1.0);
+    world.truncate() / world.w
+  };
+
+  let origin = unproject(near);
+  (origin, (unproject(far) - origin).normalize())
+}
+
+/// Adjusts an OpenGL style projection matrix as built by `perspective` or `ortho` to the given depth mode.
+/// Reverse-Z remaps clip space depth from `-w..w` to `w..0`, moving the near plane to 1 and the far plane to 0.
+pub fn depth_mode_projection(mode: DepthMode, projection: Matrix4<f32>) -> Matrix4<f32> {
+  match mode {
+    DepthMode::Standard => projection,
+    DepthMode::ReverseZ => {
+      #[rustfmt::skip]
+      let reverse = Matrix4::new(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, -0.5, 0.0,
+        0.0, 0.0, 0.5, 1.0,
+      );
+      reverse * projection
+    }
+  }
 }
 
 #[system(par_for_each)]
 fn update_camera_matrices(camera: &Camera) {
   // Build projection
-  let projection = perspective(
-    Rad(camera.fov_y),
-    camera.aspect,
-    camera.z_near,
-    camera.z_far,
-  );
+  let projection = camera.calculate_projection();
 
   // Build view matrix
-  let view = Matrix4::look_at_rh(
-    Point3::from_vec(camera.position),
-    Point3::from_vec(camera.target),
-    camera.up,
-  );
+  let view = look_at(camera.position, camera.target, camera.up);
 
   // Build together
   let projection_view = projection * view;
@@ -0,0 +1,28 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform DepthVisualizeUniform {
+  float z_near;
+  float z_far;
+  uint reverse_z;
+} u_depth;
+
+layout(set = 1, binding = 0) uniform texture2D t_depth;
+layout(set = 1, binding = 1) uniform sampler s_depth;
+
+layout(location=0) in vec2 v_uv;
+
+layout(location=0) out vec4 f_color;
+
+// Mirrors `linearize_depth`.
+float linearize_depth(float depth) {
+  float n = u_depth.z_near;
+  float f = u_depth.z_far;
+  float ndc = u_depth.reverse_z != 0 ? 1.0 - 2.0 * depth : depth;
+  return 2.0 * n * f / ((f + n) - ndc * (f - n));
+}
+
+void main() {
+  float depth = texture(sampler2D(t_depth, s_depth), v_uv).r;
+  float gray = clamp((linearize_depth(depth) - u_depth.z_near) / (u_depth.z_far - u_depth.z_near), 0.0, 1.0);
+  f_color = vec4(vec3(gray), 1.0);
+}
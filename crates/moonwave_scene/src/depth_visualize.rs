@@ -0,0 +1,173 @@
+use moonwave_common::*;
+use moonwave_core::{optick, Core, OnceCell, ShaderKind, FULLSCREEN_TRIANGLE_VS};
+use moonwave_render::{
+  CommandEncoder, FrameGraphNode, FrameNodeValue, RenderPassCommandEncoderBuilder,
+};
+use moonwave_resources::{
+  BindGroup, BindGroupDescriptor, BindGroupLayout, BindGroupLayoutDescriptor,
+  BindGroupLayoutEntryType, DepthMode, PipelineLayoutDescriptor, RenderPipeline,
+  RenderPipelineDescriptor, ResourceRc, Sampler, TextureFormat, TextureView,
+};
+use moonwave_shader::uniform;
+use parking_lot::Mutex;
+
+use crate::{Camera, Uniform, MATERIAL_UNIFORM_LAYOUT};
+
+/// Fragment shader turning a depth texture into linear grayscale.
+pub const DEPTH_VISUALIZE_FRAGMENT_SHADER: &str = include_str!("./depth_visualize.frag");
+
+static DEPTH_VISUALIZE_RESOURCES: OnceCell<DepthVisualizeResources> = OnceCell::new();
+
+#[uniform]
+pub struct DepthVisualizeUniform {
+  z_near: f32,
+  z_far: f32,
+  reverse_z: u32,
+}
+
+struct DepthVisualizeResources {
+  pipeline: ResourceRc<RenderPipeline>,
+  depth_layout: ResourceRc<BindGroupLayout>,
+  sampler: ResourceRc<Sampler>,
+  /// Bind group of the previous frame, reused as long as the depth target stays the same.
+  depth: Mutex<Option<(ResourceRc<TextureView>, ResourceRc<BindGroup>)>>,
+}
+
+impl DepthVisualizeResources {
+  fn new() -> Self {
+    let core = Core::get_instance();
+    let vs = core
+      .create_shader_from_glsl(
+        FULLSCREEN_TRIANGLE_VS,
+        "DepthVisualizeVS",
+        ShaderKind::Vertex,
+      )
+      .unwrap();
+    let fs = core
+      .create_shader_from_glsl(
+        DEPTH_VISUALIZE_FRAGMENT_SHADER,
+        "DepthVisualizeFS",
+        ShaderKind::Fragment,
+      )
+      .unwrap();
+
+    // Depth can't be filtered.
+    let depth_layout = core.create_bind_group_layout(
+      BindGroupLayoutDescriptor::new()
+        .add_unfiltered_entry(0, BindGroupLayoutEntryType::SingleTexture)
+        .add_unfiltered_entry(1, BindGroupLayoutEntryType::Sampler),
+    );
+    let layout = core.create_pipeline_layout(
+      PipelineLayoutDescriptor::new()
+        .add_binding(MATERIAL_UNIFORM_LAYOUT.clone())
+        .add_binding(depth_layout.clone()),
+    );
+    let pipeline = core
+      .create_render_pipeline(
+        RenderPipelineDescriptor::new_without_vertices(layout, vs, fs)
+          .add_color_output(TextureFormat::Bgra8UnormSrgb),
+      )
+      .unwrap();
+
+    Self {
+      pipeline,
+      depth_layout,
+      sampler: core.create_sampler(),
+      depth: Mutex::new(None),
+    }
+  }
+
+  fn get_depth_bind_group(&self, view: ResourceRc<TextureView>) -> ResourceRc<BindGroup> {
+    let mut cached = self.depth.lock();
+    if let Some((cached_view, bind_group)) = cached.as_ref() {
+      if *cached_view == view {
+        return bind_group.clone();
+      }
+    }
+
+    let bind_group = Core::get_instance().create_bind_group(
+      BindGroupDescriptor::new(self.depth_layout.clone())
+        .add_texture_binding(0, view.clone())
+        .add_sampler_binding(1, self.sampler.clone()),
+    );
+    *cached = Some((view, bind_group.clone()));
+    bind_group
+  }
+}
+
+/// View space distance of a perspective depth buffer value, mirrors the fragment shader.
+pub fn linearize_depth(depth: f32, z_near: f32, z_far: f32, depth_mode: DepthMode) -> f32 {
+  let ndc = match depth_mode {
+    DepthMode::Standard => depth,
+    DepthMode::ReverseZ => 1.0 - 2.0 * depth,
+  };
+  2.0 * z_near * z_far / ((z_far + z_near) - ndc * (z_far - z_near))
+}
+
+/// Debug node rendering a depth texture as linear grayscale, black at the near and white at
+/// the far plane. The output can be routed into `PresentToScreen` like any color texture.
+/// The optional color input is only forwarded while the node is disabled, e.g. the scene color.
+pub struct DepthVisualizeNode {
+  uniform: DepthVisualizeUniform,
+  /// Created on the first execution, nodes of the same frame graph level can't share a uniform.
+  gpu_uniform: OnceCell<Uniform<DepthVisualizeUniform>>,
+}
+
+impl DepthVisualizeNode {
+  pub const INPUT_DEPTH: usize = 0;
+  pub const INPUT_TARGET: usize = 1;
+  pub const INPUT_COLOR: usize = 2;
+  pub const OUTPUT_COLOR: usize = 0;
+
+  pub fn new(z_near: f32, z_far: f32, depth_mode: DepthMode) -> Self {
+    Self {
+      uniform: DepthVisualizeUniform {
+        z_near,
+        z_far,
+        reverse_z: (depth_mode == DepthMode::ReverseZ) as u32,
+      },
+      gpu_uniform: OnceCell::new(),
+    }
+  }
+
+  pub fn from_camera(camera: &Camera) -> Self {
+    Self::new(camera.get_z_near(), camera.get_z_far(), camera.depth_mode)
+  }
+}
+
+impl FrameGraphNode for DepthVisualizeNode {
+  fn execute(
+    &self,
+    inputs: &[Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
+    encoder: &mut CommandEncoder,
+  ) {
+    optick::event!("FrameGraph::DepthVisualize");
+    let resources = DEPTH_VISUALIZE_RESOURCES.get_or_init(DepthVisualizeResources::new);
+
+    let uniform = self.gpu_uniform.get_or_init(|| Uniform::new(self.uniform));
+    let uniform = uniform.as_generic();
+    let uniform = uniform.get_resources(encoder);
+
+    let input = |index: usize| inputs[index].as_ref().unwrap().get_sampled_texture();
+    let depth = resources.get_depth_bind_group(input(Self::INPUT_DEPTH).view.clone());
+    let target = input(Self::INPUT_TARGET).clone();
+
+    {
+      let mut rpb = RenderPassCommandEncoderBuilder::new("depth_visualize_rp");
+      rpb.add_color_output(&target.view, Vector4::new(0.0, 0.0, 0.0, 1.0));
+
+      let mut rp = encoder.create_render_pass_encoder(rpb);
+      rp.set_pipeline(resources.pipeline.clone());
+      rp.set_bind_group(0, uniform.bind_group.clone());
+      rp.set_bind_group(1, depth);
+      rp.render(0..3);
+    }
+
+    outputs[Self::OUTPUT_COLOR] = Some(FrameNodeValue::SampledTexture(target));
+  }
+
+  fn get_bypass(&self) -> Option<(usize, usize)> {
+    Some((Self::INPUT_COLOR, Self::OUTPUT_COLOR))
+  }
+}
@@ -0,0 +1,62 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform DepthOfFieldUniform {
+  mat4 inv_projection_view;
+  vec3 camera_position;
+  float focus_distance;
+  float aperture;
+  float max_radius;
+  float far_depth;
+  uint samples;
+} u_dof;
+
+layout(set = 1, binding = 0) uniform texture2D t_color;
+layout(set = 1, binding = 1) uniform texture2D t_depth;
+layout(set = 1, binding = 2) uniform sampler s_gbuffer;
+
+layout(location=0) in vec2 v_uv;
+
+layout(location=0) out vec4 f_color;
+
+const float GOLDEN_ANGLE = 2.39996323;
+
+#include <world_from_depth>
+
+// Mirrors `circle_of_confusion`, the sky counts as infinitely far away.
+float circle_of_confusion(vec2 uv) {
+  float depth = texture(sampler2D(t_depth, s_gbuffer), uv).r;
+  if (depth == u_dof.far_depth) {
+    return clamp(u_dof.aperture, 0.0, 1.0);
+  }
+
+  float dist = max(distance(u_dof.camera_position, world_from_depth(uv, depth, u_dof.inv_projection_view)), 0.0001);
+  return clamp(u_dof.aperture * abs(dist - u_dof.focus_distance) / dist, 0.0, 1.0);
+}
+
+void main() {
+  vec4 base = texture(sampler2D(t_color, s_gbuffer), v_uv);
+  float radius = circle_of_confusion(v_uv) * u_dof.max_radius;
+
+  // In focus, nothing to blur.
+  if (radius < 0.5) {
+    f_color = base;
+    return;
+  }
+
+  // Gather samples on a spiral covering the circle of confusion.
+  vec2 texel = 1.0 / vec2(textureSize(sampler2D(t_color, s_gbuffer), 0));
+  vec4 sum = base;
+  float total = 1.0;
+  for (uint i = 0; i < u_dof.samples; i++) {
+    float r = sqrt((float(i) + 0.5) / float(u_dof.samples)) * radius;
+    float angle = float(i) * GOLDEN_ANGLE;
+    vec2 uv = v_uv + vec2(cos(angle), sin(angle)) * r * texel;
+
+    // Sharp samples must not bleed into blurry neighbours.
+    float weight = clamp(circle_of_confusion(uv) * u_dof.max_radius / r, 0.0, 1.0);
+    sum += texture(sampler2D(t_color, s_gbuffer), uv) * weight;
+    total += weight;
+  }
+
+  f_color = sum / total;
+}
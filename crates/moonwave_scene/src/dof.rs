@@ -0,0 +1,167 @@
+use moonwave_common::*;
+use moonwave_core::{optick, OnceCell};
+use moonwave_render::{CommandEncoder, FrameGraphNode, FrameNodeValue};
+use moonwave_resources::DepthMode;
+use moonwave_shader::uniform;
+
+use crate::{Camera, ScreenEffectPass, Uniform};
+
+/// Fragment shader gathering the blurred color within the circle of confusion.
+/// Includes have to be resolved through `screen_effect_shader`.
+pub const DOF_FRAGMENT_SHADER: &str = include_str!("./dof.frag");
+
+static DOF_PASS: OnceCell<ScreenEffectPass> = OnceCell::new();
+
+#[uniform]
+pub struct DepthOfFieldUniform {
+  inv_projection_view: Matrix4<f32>,
+  camera_position: Vector3<f32>,
+  focus_distance: f32,
+  aperture: f32,
+  max_radius: f32,
+  far_depth: f32,
+  samples: u32,
+}
+
+/// Blur amount in `0..=1` of a surface at the given distance, mirrors the fragment shader.
+pub fn circle_of_confusion(distance: f32, focus_distance: f32, aperture: f32) -> f32 {
+  let distance = distance.max(0.0001);
+  (aperture * (distance - focus_distance).abs() / distance).clamp(0.0, 1.0)
+}
+
+/// Tweakables of the depth of field.
+#[derive(Debug, Clone, Copy)]
+pub struct DepthOfFieldSettings {
+  /// World space distance from the camera that stays perfectly sharp.
+  pub focus_distance: f32,
+  /// Scales how quickly surfaces blur with their distance to the focus plane.
+  pub aperture: f32,
+  /// Blur radius in pixels of surfaces that are completely out of focus.
+  pub max_radius: f32,
+  /// Amount of samples gathered for blurred pixels.
+  pub samples: u32,
+}
+
+impl Default for DepthOfFieldSettings {
+  fn default() -> Self {
+    Self {
+      focus_distance: 10.0,
+      aperture: 0.5,
+      max_radius: 8.0,
+      samples: 24,
+    }
+  }
+}
+
+impl DepthOfFieldSettings {
+  pub fn build_uniform(
+    &self,
+    projection_view: Matrix4<f32>,
+    camera_position: Vector3<f32>,
+    depth_mode: DepthMode,
+  ) -> DepthOfFieldUniform {
+    DepthOfFieldUniform {
+      inv_projection_view: projection_view.invert().unwrap_or_else(Matrix4::identity),
+      camera_position,
+      focus_distance: self.focus_distance,
+      aperture: self.aperture,
+      max_radius: self.max_radius,
+      far_depth: depth_mode.clear_value(),
+      samples: self.samples.max(1),
+    }
+  }
+}
+
+/// Depth of field post effect, meant to run after the PBR pass.
+/// Blurs the color target based on the distance of each pixel to the focus plane.
+pub struct DepthOfFieldNode {
+  settings: DepthOfFieldSettings,
+  uniform: DepthOfFieldUniform,
+  /// Created on the first execution, see `ScreenEffectPass`.
+  gpu_uniform: OnceCell<Uniform<DepthOfFieldUniform>>,
+}
+
+impl DepthOfFieldNode {
+  pub const INPUT_COLOR: usize = 0;
+  pub const INPUT_DEPTH: usize = 1;
+  pub const INPUT_TARGET: usize = 2;
+  pub const OUTPUT_COLOR: usize = 0;
+
+  pub fn new(
+    projection_view: Matrix4<f32>,
+    camera_position: Vector3<f32>,
+    depth_mode: DepthMode,
+    focus_distance: f32,
+    aperture: f32,
+  ) -> Self {
+    let settings = DepthOfFieldSettings {
+      focus_distance,
+      aperture,
+      ..Default::default()
+    };
+    Self {
+      uniform: settings.build_uniform(projection_view, camera_position, depth_mode),
+      settings,
+      gpu_uniform: OnceCell::new(),
+    }
+  }
+
+  pub fn from_camera(camera: &Camera, focus_distance: f32, aperture: f32) -> Self {
+    let uniform = camera.uniform.get();
+    Self::new(
+      uniform.projection_view,
+      camera.position,
+      camera.depth_mode,
+      focus_distance,
+      aperture,
+    )
+  }
+
+  pub fn with_max_radius(mut self, max_radius: f32) -> Self {
+    self.settings.max_radius = max_radius;
+    self.uniform.max_radius = max_radius;
+    self
+  }
+
+  pub fn with_samples(mut self, samples: u32) -> Self {
+    self.settings.samples = samples;
+    self.uniform.samples = samples.max(1);
+    self
+  }
+
+  pub fn get_settings(&self) -> &DepthOfFieldSettings {
+    &self.settings
+  }
+}
+
+impl FrameGraphNode for DepthOfFieldNode {
+  fn execute(
+    &self,
+    inputs: &[Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
+    encoder: &mut CommandEncoder,
+  ) {
+    optick::event!("FrameGraph::DepthOfField");
+    let pass = DOF_PASS.get_or_init(|| ScreenEffectPass::new("dof", DOF_FRAGMENT_SHADER, 2));
+    let uniform = self.gpu_uniform.get_or_init(|| Uniform::new(self.uniform));
+
+    let input = |index: usize| inputs[index].as_ref().unwrap().get_sampled_texture();
+    let target = input(Self::INPUT_TARGET).clone();
+    pass.render(
+      encoder,
+      uniform,
+      vec![
+        input(Self::INPUT_COLOR).view.clone(),
+        input(Self::INPUT_DEPTH).view.clone(),
+      ],
+      &target,
+    );
+
+    outputs[Self::OUTPUT_COLOR] = Some(FrameNodeValue::SampledTexture(target));
+  }
+
+  /// Forwards the sharp scene while disabled.
+  fn get_bypass(&self) -> Option<(usize, usize)> {
+    Some((Self::INPUT_COLOR, Self::OUTPUT_COLOR))
+  }
+}
@@ -1,3 +1,9 @@
+//! Immediate mode debug drawing.
+//!
+//! Shapes drawn through [`line`], [`sphere`] and [`aabb`] are only visible for the current frame,
+//! they are accumulated until the debugger renders them in a single batched draw and cleared
+//! afterwards. Call them every frame to keep the shapes visible.
+
 use std::sync::Arc;
 
 use generational_arena::Arena;
@@ -5,12 +11,22 @@ use lazy_static::lazy_static;
 use legion::{world::SubWorld, *};
 use moonwave_common::*;
 use moonwave_core::{
-  Core, Extension, Glyph, GlyphFrameNode, PresentToScreen, SystemFactory, TextureGeneratorHost,
-  TextureGeneratorNode, WrappedSystem,
+  Core, Extension, Glyph, GlyphFrameNode, PresentToScreen, ShaderKind, SystemFactory,
+  TextureGeneratorHost, TextureGeneratorNode, WrappedSystem, DEBUG_TEXT_FONT,
+};
+use moonwave_render::{
+  CommandEncoder, FrameGraphNode, FrameNodeValue, RenderPassCommandEncoderBuilder,
+};
+use moonwave_resources::{
+  Buffer, BufferUsage, PipelineLayoutDescriptor, PrimitiveTopology, RenderPipeline,
+  RenderPipelineDescriptor, ResourceRc, TextureFormat, VertexAttribute, VertexAttributeFormat,
+  VertexBuffer,
 };
 use parking_lot::RwLock;
 
-use crate::{Camera, MainCameraTag};
+use crate::{
+  Camera, GenericUniform, MainCameraTag, TransformUniform, Uniform, MATERIAL_UNIFORM_LAYOUT,
+};
 
 pub struct ImmediateModeDebugger {
   glyph: Glyph,
@@ -19,8 +35,7 @@ pub struct ImmediateModeDebugger {
 
 impl ImmediateModeDebugger {
   fn new() -> Self {
-    let font = include_bytes!("./FiraMono-Medium.ttf");
-    let glyph = Glyph::new(font);
+    let glyph = Glyph::new(DEBUG_TEXT_FONT);
 
     Self {
       glyph,
@@ -38,7 +53,17 @@ impl ImmediateModeDebugger {
   }
 }
 
+/// Draws the frustum of the camera on the same entity as debug lines, updated every frame.
+pub struct DebugFrustum {
+  pub color: Vector4<f32>,
+}
+
 pub enum DebuggerObject {
+  Line {
+    from: Vector3<f32>,
+    to: Vector3<f32>,
+    color: Vector4<f32>,
+  },
   UIText {
     color: Vector4<f32>,
     size: f32,
@@ -54,6 +79,36 @@ pub enum DebuggerObject {
 
 lazy_static! {
   pub static ref IMMEDIATE_MODE_DEBUGGER: ImmediateModeDebugger = ImmediateModeDebugger::new();
+  static ref FRAME_LINES: RwLock<Vec<DebugLineVertex>> = RwLock::new(Vec::new());
+}
+
+/// Amount of segments used to approximate each of the circles of a debug sphere.
+pub const SPHERE_SEGMENTS: usize = 24;
+
+/// Draws a line from `a` to `b` for the current frame.
+pub fn line(a: Vector3<f32>, b: Vector3<f32>, color: Vector4<f32>) {
+  push_line(&mut FRAME_LINES.write(), a, b, color);
+}
+
+/// Draws a wireframe sphere for the current frame, made of one circle around each axis.
+pub fn sphere(center: Vector3<f32>, radius: f32, color: Vector4<f32>) {
+  let mut lines = FRAME_LINES.write();
+  for (from, to) in sphere_edges(center, radius) {
+    push_line(&mut lines, from, to, color);
+  }
+}
+
+/// Draws an axis aligned bounding box for the current frame.
+pub fn aabb(min: Vector3<f32>, max: Vector3<f32>, color: Vector4<f32>) {
+  let mut lines = FRAME_LINES.write();
+  for (from, to) in aabb_edges(min, max).iter() {
+    push_line(&mut lines, *from, *to, color);
+  }
+}
+
+/// Amount of lines drawn for the current frame that have not been rendered yet.
+pub fn pending_lines() -> usize {
+  FRAME_LINES.read().len() / 2
 }
 
 pub struct ImmediateModeDebuggerExt {
@@ -73,20 +128,168 @@ impl Extension for ImmediateModeDebuggerExt {
     Core::get_instance().get_world().add_system_to_stage(
       IMDTickSystem {
         host: self.host.clone().unwrap(),
+        lines: Arc::new(DebugLineResources::new()),
       },
       moonwave_core::SystemStage::Rendering,
     )
   }
 }
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct DebugLineVertex {
+  position: [f32; 3],
+  color: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for DebugLineVertex {}
+unsafe impl bytemuck::Zeroable for DebugLineVertex {}
+
+struct DebugLineResources {
+  host: Arc<TextureGeneratorHost>,
+  pipeline: ResourceRc<RenderPipeline>,
+  uniform: Uniform<TransformUniform>,
+}
+
+impl DebugLineResources {
+  fn new() -> Self {
+    let core = Core::get_instance();
+    let vs = core
+      .create_shader_from_glsl(
+        include_str!("./imd_line.vert"),
+        "IMDLineVS",
+        ShaderKind::Vertex,
+      )
+      .unwrap();
+    let fs = core
+      .create_shader_from_glsl(
+        include_str!("./imd_line.frag"),
+        "IMDLineFS",
+        ShaderKind::Fragment,
+      )
+      .unwrap();
+
+    let layout = core.create_pipeline_layout(
+      PipelineLayoutDescriptor::new().add_binding(MATERIAL_UNIFORM_LAYOUT.clone()),
+    );
+    let vertex_desc = VertexBuffer {
+      stride: std::mem::size_of::<DebugLineVertex>() as u64,
+      attributes: vec![
+        VertexAttribute {
+          name: "position".to_string(),
+          offset: 0,
+          location: 0,
+          format: VertexAttributeFormat::Float3,
+        },
+        VertexAttribute {
+          name: "color".to_string(),
+          offset: 4 * 3,
+          location: 1,
+          format: VertexAttributeFormat::Float4,
+        },
+      ],
+    };
+    let pipeline = core
+      .create_render_pipeline(
+        RenderPipelineDescriptor::new(layout, vertex_desc, vs, fs)
+          .add_color_output(TextureFormat::Bgra8UnormSrgb)
+          .with_topology(PrimitiveTopology::LineList),
+      )
+      .unwrap();
+
+    Self {
+      host: TextureGeneratorHost::new(
+        moonwave_core::TextureSize::FullScreen,
+        TextureFormat::Bgra8UnormSrgb,
+      ),
+      pipeline,
+      uniform: Uniform::new(TransformUniform {
+        matrix: Matrix4::identity(),
+      }),
+    }
+  }
+}
+
+/// Returns the 12 edges of a frustum given its corners as returned by `Camera::calculate_frustum_corners`.
+pub fn frustum_edges(corners: &[Vector3<f32>; 8]) -> [(Vector3<f32>, Vector3<f32>); 12] {
+  let mut edges = [(Vector3::zero(), Vector3::zero()); 12];
+  for i in 0..4 {
+    let next = (i + 1) % 4;
+    edges[i] = (corners[i], corners[next]);
+    edges[i + 4] = (corners[i + 4], corners[next + 4]);
+    edges[i + 8] = (corners[i], corners[i + 4]);
+  }
+  edges
+}
+
+/// Returns the 12 edges of an axis aligned bounding box.
+pub fn aabb_edges(min: Vector3<f32>, max: Vector3<f32>) -> [(Vector3<f32>, Vector3<f32>); 12] {
+  let corners = [
+    Vector3::new(min.x, min.y, min.z),
+    Vector3::new(max.x, min.y, min.z),
+    Vector3::new(max.x, max.y, min.z),
+    Vector3::new(min.x, max.y, min.z),
+    Vector3::new(min.x, min.y, max.z),
+    Vector3::new(max.x, min.y, max.z),
+    Vector3::new(max.x, max.y, max.z),
+    Vector3::new(min.x, max.y, max.z),
+  ];
+  frustum_edges(&corners)
+}
+
+/// Returns the edges of three circles around the x, y and z axis approximating a sphere.
+pub fn sphere_edges(center: Vector3<f32>, radius: f32) -> Vec<(Vector3<f32>, Vector3<f32>)> {
+  let point = |axis: usize, segment: usize| {
+    let angle = (segment % SPHERE_SEGMENTS) as f32 / SPHERE_SEGMENTS as f32 * std::f32::consts::TAU;
+    let (sin, cos) = (angle.sin() * radius, angle.cos() * radius);
+    center
+      + match axis {
+        0 => Vector3::new(0.0, cos, sin),
+        1 => Vector3::new(cos, 0.0, sin),
+        _ => Vector3::new(cos, sin, 0.0),
+      }
+  };
+
+  (0..3)
+    .flat_map(|axis| {
+      (0..SPHERE_SEGMENTS).map(move |segment| (point(axis, segment), point(axis, segment + 1)))
+    })
+    .collect()
+}
+
+fn push_line(
+  vertices: &mut Vec<DebugLineVertex>,
+  from: Vector3<f32>,
+  to: Vector3<f32>,
+  color: Vector4<f32>,
+) {
+  let color = [color.x, color.y, color.z, color.w];
+  vertices.push(DebugLineVertex {
+    position: from.into(),
+    color,
+  });
+  vertices.push(DebugLineVertex {
+    position: to.into(),
+    color,
+  });
+}
+
 #[system]
 #[read_component(MainCameraTag)]
 #[read_component(Camera)]
-fn immediate_mode_debugger_tick(world: &mut SubWorld, #[state] host: &Arc<TextureGeneratorHost>) {
+#[read_component(DebugFrustum)]
+fn immediate_mode_debugger_tick(
+  world: &mut SubWorld,
+  #[state] host: &Arc<TextureGeneratorHost>,
+  #[state] lines: &Arc<DebugLineResources>,
+) {
   let core = Core::get_instance();
   //let win_size = core.get_swap_chain_size();
   let frame_graph = core.get_frame_graph();
 
+  // Lines drawn this frame, cleared so they have to be redrawn next frame.
+  let mut line_vertices = std::mem::take(&mut *FRAME_LINES.write());
+
   // Get main camera.
   let mut main_cam_query = <(&Camera, &MainCameraTag)>::query();
   let main_cam = main_cam_query.iter(world).next();
@@ -95,12 +298,24 @@ fn immediate_mode_debugger_tick(world: &mut SubWorld, #[state] host: &Arc<Textur
   }
   let camera_view = main_cam.unwrap().0.uniform.get().view;
   let camera_proj = main_cam.unwrap().0.uniform.get().projection;
+  let camera_projection_view = main_cam.unwrap().0.uniform.get().projection_view;
+
+  // Frustums of debugged cameras based on their current matrices.
+  for (camera, frustum) in <(&Camera, &DebugFrustum)>::query().iter(world) {
+    let corners = camera.calculate_frustum_corners();
+    for (from, to) in frustum_edges(&corners).iter() {
+      push_line(&mut line_vertices, *from, *to, frustum.color);
+    }
+  }
 
   // Prepare debug objects
   let objects = IMMEDIATE_MODE_DEBUGGER.arena.read();
   let mut text_stack_height = 0.0;
   for (_, object) in objects.iter() {
     match object {
+      DebuggerObject::Line { from, to, color } => {
+        push_line(&mut line_vertices, *from, *to, *color);
+      }
       DebuggerObject::SceneText {
         position,
         color,
@@ -120,10 +335,48 @@ fn immediate_mode_debugger_tick(world: &mut SubWorld, #[state] host: &Arc<Textur
         );
         text_stack_height += *size;
       }
-      _ => panic!("Unknown debug object"),
     }
   }
 
+  // Build line rendering.
+  if !line_vertices.is_empty() {
+    lines.uniform.get_mut().matrix = camera_projection_view;
+
+    let vertex_buffer = core.create_inited_buffer(
+      bytemuck::cast_slice(&line_vertices)
+        .to_vec()
+        .into_boxed_slice(),
+      BufferUsage::VERTEX,
+      Some("IMDLines"),
+    );
+    let lines_texture = frame_graph.add_node(lines.host.create_node(), "IMDLinesTextureHost");
+    let lines_node = frame_graph.add_node(
+      DebugLineNode {
+        vertex_buffer,
+        vertices: line_vertices.len() as u32,
+        pipeline: lines.pipeline.clone(),
+        uniform: lines.uniform.as_generic(),
+      },
+      "IMDLines",
+    );
+    frame_graph
+      .connect(
+        lines_texture,
+        TextureGeneratorNode::OUTPUT_TEXTURE,
+        lines_node,
+        DebugLineNode::INPUT_TEXTURE,
+      )
+      .unwrap();
+    frame_graph
+      .connect(
+        lines_node,
+        DebugLineNode::OUTPUT_TEXTURE,
+        frame_graph.get_end_node(),
+        PresentToScreen::INPUT_TEXTURE_DEBUG,
+      )
+      .unwrap();
+  }
+
   // Build texture node.
   let input_texture = host.create_node();
   let input_texture_index = frame_graph.add_node(input_texture, "IMDTextureHost");
@@ -148,18 +401,60 @@ fn immediate_mode_debugger_tick(world: &mut SubWorld, #[state] host: &Arc<Textur
       node_index,
       GlyphFrameNode::OUTPUT_TEXTURE,
       frame_graph.get_end_node(),
-      PresentToScreen::INPUT_TEXTURE_UI + 1,
+      PresentToScreen::INPUT_TEXTURE_IMD_TEXT,
     )
     .unwrap();
 }
 
 struct IMDTickSystem {
   host: Arc<TextureGeneratorHost>,
+  lines: Arc<DebugLineResources>,
 }
 impl SystemFactory for IMDTickSystem {
   fn create_system(&self) -> WrappedSystem {
     WrappedSystem(Box::new(immediate_mode_debugger_tick_system(
       self.host.clone(),
+      self.lines.clone(),
     )))
   }
 }
+
+struct DebugLineNode {
+  vertex_buffer: ResourceRc<Buffer>,
+  vertices: u32,
+  pipeline: ResourceRc<RenderPipeline>,
+  uniform: GenericUniform,
+}
+
+impl DebugLineNode {
+  const INPUT_TEXTURE: usize = 0;
+  const OUTPUT_TEXTURE: usize = 0;
+}
+
+impl FrameGraphNode for DebugLineNode {
+  fn execute(
+    &self,
+    inputs: &[Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
+    encoder: &mut CommandEncoder,
+  ) {
+    moonwave_core::optick::event!("FrameGraph::IMDLines");
+
+    let texture = inputs[Self::INPUT_TEXTURE].as_ref().unwrap();
+    let uniform = self.uniform.get_resources(encoder);
+
+    let mut rp_builder = RenderPassCommandEncoderBuilder::new("IMDRenderPassLines");
+    rp_builder.add_color_output(
+      &texture.get_sampled_texture().view,
+      Vector4::new(0.0, 0.0, 0.0, 0.0),
+    );
+
+    let mut rp = encoder.create_render_pass_encoder(rp_builder);
+    rp.set_pipeline(self.pipeline.clone());
+    rp.set_vertex_buffer(self.vertex_buffer.clone());
+    rp.set_bind_group(0, uniform.bind_group.clone());
+    rp.render(0..self.vertices);
+
+    outputs[Self::OUTPUT_TEXTURE] = Some(texture.clone());
+  }
+}
@@ -0,0 +1,217 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use moonwave_common::{Matrix4, SquareMatrix};
+use moonwave_resources::{
+  BindGroup, Buffer, BufferUsage, IndexFormat, ResourceRc, VertexAttribute, VertexAttributeFormat,
+  VertexBuffer,
+};
+use moonwave_shader::{ShaderBuildParams, VertexStruct};
+
+use crate::pbr::register_pbr_system;
+use crate::{
+  BuiltMaterial, Material, MaterialError, Mesh, MeshIndex, MeshVertex, MeshVertexNormal,
+  ShaderOptionsMeshRenderer, StagedBuffer, Transform, TransformUniform, Uniform,
+};
+
+/// Raw per-instance data as uploaded into the instance buffer.
+pub type InstanceMatrix = [[f32; 4]; 4];
+
+/// First shader location of the instance matrix, directly after the attributes of the mesh.
+pub fn instance_matrix_location(vertex: &VertexBuffer) -> usize {
+  vertex
+    .attributes
+    .iter()
+    .map(|attribute| attribute.location + 1)
+    .max()
+    .unwrap_or(0)
+}
+
+/// Layout of the instance buffer starting at the given shader location,
+/// every matrix column is read from its own location.
+pub fn instance_vertex_buffer(location: usize) -> VertexBuffer {
+  VertexBuffer {
+    stride: std::mem::size_of::<InstanceMatrix>() as u64,
+    attributes: (0..4)
+      .map(|column| VertexAttribute {
+        name: format!("instance_matrix_{}", column),
+        offset: column as u64 * 16,
+        format: VertexAttributeFormat::Float4,
+        location: location + column,
+      })
+      .collect(),
+  }
+}
+
+/// A single instanced draw call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstancedDrawCall {
+  pub indices: Range<u32>,
+  pub instances: Range<u32>,
+}
+
+/// CPU side list of instance transforms that can individually be toggled.
+#[derive(Debug, Clone, Default)]
+pub struct InstanceTransforms {
+  matrices: Vec<Matrix4<f32>>,
+  visible: Vec<bool>,
+}
+
+impl InstanceTransforms {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn push(&mut self, matrix: Matrix4<f32>) -> usize {
+    self.matrices.push(matrix);
+    self.visible.push(true);
+    self.matrices.len() - 1
+  }
+
+  pub fn push_transform(&mut self, transform: &Transform) -> usize {
+    self.push(transform.calculate_transform_matrix())
+  }
+
+  pub fn set(&mut self, index: usize, matrix: Matrix4<f32>) {
+    self.matrices[index] = matrix;
+  }
+
+  pub fn set_visible(&mut self, index: usize, visible: bool) {
+    self.visible[index] = visible;
+  }
+
+  /// Applies the given matrix to all instances so they move together.
+  pub fn transform_all(&mut self, matrix: Matrix4<f32>) {
+    for instance in self.matrices.iter_mut() {
+      *instance = matrix * *instance;
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.matrices.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.matrices.is_empty()
+  }
+
+  pub fn len_visible(&self) -> usize {
+    self.visible.iter().filter(|visible| **visible).count()
+  }
+
+  /// Visible instances packed tightly so they can be drawn with a single instance range.
+  pub fn build_instance_data(&self) -> Vec<InstanceMatrix> {
+    self
+      .matrices
+      .iter()
+      .zip(self.visible.iter())
+      .filter(|(_, visible)| **visible)
+      .map(|(matrix, _)| (*matrix).into())
+      .collect()
+  }
+
+  /// Builds the draw call for a mesh with the given amount of indices, `None` if nothing is visible.
+  pub fn draw_call(&self, indices: u32) -> Option<InstancedDrawCall> {
+    let instances = self.len_visible() as u32;
+    if instances == 0 {
+      return None;
+    }
+
+    Some(InstancedDrawCall {
+      indices: 0..indices,
+      instances: 0..instances,
+    })
+  }
+}
+
+impl From<Vec<Matrix4<f32>>> for InstanceTransforms {
+  fn from(matrices: Vec<Matrix4<f32>>) -> Self {
+    Self {
+      visible: vec![true; matrices.len()],
+      matrices,
+    }
+  }
+}
+
+/// Renders a single mesh and material many times with one instanced draw call.
+/// Unlike static mesh combining the instance transforms stay editable after creation.
+/// Packed instance matrices are bound to vertex buffer slot 1 and applied in the vertex shader.
+pub struct InstancedMesh {
+  pub(crate) vertex_buffer: ResourceRc<Buffer>,
+  pub(crate) index_buffer: ResourceRc<Buffer>,
+  pub(crate) index_format: IndexFormat,
+  pub(crate) indices: u32,
+  pub(crate) material: Arc<BuiltMaterial>,
+  pub(crate) bindings: Vec<ResourceRc<BindGroup>>,
+  pub(crate) uniform: Uniform<TransformUniform>,
+  pub(crate) instance_buffer: StagedBuffer<InstanceMatrix>,
+  transforms: InstanceTransforms,
+}
+
+impl InstancedMesh {
+  /// Fails if the material can't be built, e.g. because of an invalid custom node.
+  pub fn new<
+    T: MeshVertex + MeshVertexNormal + VertexStruct + Send + Sync + 'static,
+    I: MeshIndex + Send + Sync + 'static,
+  >(
+    material: &Material,
+    mesh: &Mesh<T, I>,
+    bindings: Vec<ResourceRc<BindGroup>>,
+    transforms: impl Into<InstanceTransforms>,
+  ) -> Result<Self, MaterialError> {
+    register_pbr_system();
+
+    // Build material
+    let material = material.build(&Self::build_params::<T>())?;
+
+    // Instance buffer is sized for all instances, invisible ones are simply not uploaded.
+    let transforms = transforms.into();
+    let instance_buffer = StagedBuffer::new(transforms.len().max(1) as u64, BufferUsage::VERTEX);
+    *instance_buffer.get_mut() = transforms.build_instance_data();
+
+    Ok(Self {
+      vertex_buffer: mesh.build_vertex_buffer(),
+      index_buffer: mesh.build_index_buffer(),
+      index_format: I::get_format(),
+      indices: mesh.len_indices() as u32,
+      uniform: Uniform::new(TransformUniform {
+        matrix: Matrix4::identity(),
+      }),
+      material,
+      bindings,
+      instance_buffer,
+      transforms,
+    })
+  }
+
+  /// Params materials of instanced meshes with vertices of type `T` are built with.
+  pub fn build_params<T: VertexStruct>() -> ShaderBuildParams {
+    let mut params = ShaderBuildParams::new();
+    params.add(ShaderOptionsMeshRenderer {
+      no_transform: false,
+      instance_location: Some(instance_matrix_location(&T::generate_buffer())),
+    });
+    params
+  }
+
+  pub fn get_transforms(&self) -> &InstanceTransforms {
+    &self.transforms
+  }
+
+  /// Modifies instance transforms, changes are uploaded with the next frame.
+  pub fn modify_transforms<F: FnOnce(&mut InstanceTransforms)>(&mut self, f: F) {
+    let len = self.transforms.len();
+    f(&mut self.transforms);
+    assert_eq!(
+      len,
+      self.transforms.len(),
+      "Instances can't be added to an existing instanced mesh."
+    );
+
+    *self.instance_buffer.get_mut() = self.transforms.build_instance_data();
+  }
+
+  pub fn draw_call(&self) -> Option<InstancedDrawCall> {
+    self.transforms.draw_call(self.indices)
+  }
+}
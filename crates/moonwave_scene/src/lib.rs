@@ -33,6 +33,27 @@ pub use light::*;
 mod aabb;
 pub use aabb::*;
 
+mod instancing;
+pub use instancing::*;
+
+mod screen_effect;
+pub use screen_effect::*;
+
+mod ssr;
+pub use ssr::*;
+
+mod dof;
+pub use dof::*;
+
+mod bloom;
+pub use bloom::*;
+
+mod depth_visualize;
+pub use depth_visualize::*;
+
+mod sprite;
+pub use sprite::*;
+
 pub mod imd;
 
 pub mod texture_array;
@@ -2,22 +2,23 @@ use std::{collections::HashMap, hash::Hash};
 use std::{hash::Hasher, sync::Arc};
 
 use lazy_static::lazy_static;
-use moonwave_core::{Core, OnceCell, ShaderKind};
+use moonwave_core::{Core, OnceCell, ShaderError, ShaderKind};
 use moonwave_resources::{
-  BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntryType, PipelineLayout,
-  PipelineLayoutDescriptor, RenderPipeline, RenderPipelineDescriptor, ResourceRc, Shader,
-  TextureFormat,
+  BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntryType, CompareFunction, DepthMode,
+  Face, FrontFace, PipelineLayout, PipelineLayoutDescriptor, PolygonMode, RenderPipeline,
+  RenderPipelineDescriptor, ResourceRc, Shader, TextureFormat, VertexBufferLayoutError,
 };
 use moonwave_shader::{
-  BuiltShaderBindGroup, BuiltShaderGraph, Construct, ConvertHomgenous, Deconstruct, Index,
-  InputPassthroughNode, Multiply, ShaderBuildParams, ShaderGraph, ShaderNode, ShaderType,
-  Vector3Upgrade,
+  BuiltShaderBindGroup, BuiltShaderGraph, Construct, ConvertHomgenous, Deconstruct,
+  GraphConnectError, Index, InputPassthroughNode, Multiply, NormalMapUnpackNode, ShaderBuildParams,
+  ShaderGraph, ShaderNode, ShaderType, TextureSampleNode, Vector3Upgrade,
 };
 use parking_lot::RwLock;
+use thiserror::Error;
 
 use crate::{
-  CameraUniform, DirectionalLightShaderNode, LightsUniform, ShaderOptionsMeshRenderer,
-  TransformUniform,
+  instance_vertex_buffer, CameraUniform, DirectionalLightShaderNode, LightsUniform,
+  ShaderOptionsMeshRenderer, TransformUniform,
 };
 
 lazy_static! {
@@ -36,7 +37,14 @@ lazy_static! {
 
 pub struct Material {
   graph: RwLock<ShaderGraph>,
-  built: RwLock<HashMap<u64, Arc<BuiltMaterial>>>,
+  built: RwLock<HashMap<(u64, bool), Arc<BuiltMaterial>>>,
+  depth_compare: CompareFunction,
+  depth_write: bool,
+  depth_mode: Option<DepthMode>,
+  color_format: Option<TextureFormat>,
+  depth_format: Option<TextureFormat>,
+  front_face: FrontFace,
+  cull_mode: Option<Face>,
 }
 
 impl Material {
@@ -44,60 +52,191 @@ impl Material {
     Self {
       graph: RwLock::new(graph),
       built: RwLock::new(HashMap::new()),
+      depth_compare: CompareFunction::Less,
+      depth_write: true,
+      depth_mode: None,
+      color_format: None,
+      depth_format: None,
+      front_face: FrontFace::Ccw,
+      cull_mode: Some(Face::Back),
     }
   }
 
-  pub fn build(&self, params: &ShaderBuildParams) -> Arc<BuiltMaterial> {
-    let mut built_cache = self.built.write();
-    if let Some(built) = built_cache.get(&params.hash) {
-      return built.clone();
-    }
+  /// Overrides the default depth test of `Less` with depth writes, e.g. for skyboxes or overlays.
+  pub fn with_depth(mut self, compare: CompareFunction, write_enabled: bool) -> Self {
+    self.depth_compare = compare;
+    self.depth_write = write_enabled;
+    self
+  }
 
-    // Build shaders
+  /// Has to match the depth mode of the camera rendering this material.
+  /// Defaults to the depth mode of the core, see `CoreConfig::depth_mode`.
+  pub fn with_depth_mode(mut self, mode: DepthMode) -> Self {
+    self.depth_mode = Some(mode);
+    self
+  }
+
+  /// Has to match the color target this material renders into, e.g. `Rgba16Float` for HDR targets.
+  /// Defaults to the main color target format of the core, see `CoreConfig::hdr`.
+  pub fn with_color_format(mut self, format: TextureFormat) -> Self {
+    self.color_format = Some(format);
+    self
+  }
+
+  /// Has to match the depth target this material renders into.
+  /// Defaults to the main depth target format of the core, see `CoreConfig::depth_format`.
+  pub fn with_depth_format(mut self, format: TextureFormat) -> Self {
+    self.depth_format = Some(format);
+    self
+  }
+
+  /// Winding of front facing triangles, e.g. `Cw` for imported meshes with flipped winding.
+  pub fn with_front_face(mut self, front_face: FrontFace) -> Self {
+    self.front_face = front_face;
+    self
+  }
+
+  /// Faces that are culled, `None` renders both sides e.g. for foliage. Defaults to back faces.
+  pub fn with_cull_mode(mut self, cull_mode: Option<Face>) -> Self {
+    self.cull_mode = cull_mode;
+    self
+  }
+
+  /// Explicitly declared color format, `None` if the main color target format is used.
+  pub fn get_color_format(&self) -> Option<TextureFormat> {
+    self.color_format
+  }
+
+  /// Explicitly declared depth format, `None` if the main depth target format is used.
+  pub fn get_depth_format(&self) -> Option<TextureFormat> {
+    self.depth_format
+  }
+
+  /// Explicitly declared depth mode, `None` if the depth mode of the core is used.
+  pub fn get_depth_mode(&self) -> Option<DepthMode> {
+    self.depth_mode
+  }
+
+  pub fn get_front_face(&self) -> FrontFace {
+    self.front_face
+  }
+
+  pub fn get_cull_mode(&self) -> Option<Face> {
+    self.cull_mode
+  }
+
+  /// Generated vertex and fragment GLSL without compiling it, e.g. to debug the node graph.
+  pub fn dump_shaders(&self, params: &ShaderBuildParams) -> (String, String) {
+    let built = self.build_graph(params);
+    (built.vs, built.fs)
+  }
+
+  fn build_graph(&self, params: &ShaderBuildParams) -> BuiltShaderGraph {
     let mut graph = self.graph.write();
     let outputs = graph
       .get_color_outputs()
       .iter()
       .map(|(_, _, index)| *index)
       .collect::<Vec<_>>();
-    let built = graph.build(&outputs, params);
+    graph.build(&outputs, params)
+  }
 
-    // Compile
+  /// Builds shaders and pipeline for the given params, results are cached.
+  /// Invalid GLSL, e.g. of a custom node, is returned together with the generated source.
+  pub fn build(&self, params: &ShaderBuildParams) -> Result<Arc<BuiltMaterial>, MaterialError> {
+    // Wireframe mode is part of the key so toggling it rebuilds the pipelines.
     let core = Core::get_instance();
-    let vertex_shader = core
-      .create_shader_from_glsl(built.vs.as_str(), "material_vs", ShaderKind::Vertex)
-      .unwrap();
-    let fragment_shader = core
-      .create_shader_from_glsl(built.fs.as_str(), "material_fs", ShaderKind::Fragment)
-      .unwrap();
+    let key = (params.hash, core.is_wireframe());
+
+    let mut built_cache = self.built.write();
+    if let Some(built) = built_cache.get(&key) {
+      return Ok(built.clone());
+    }
+
+    // Build shaders
+    let built = self.build_graph(params);
+
+    // Compile
+    let vertex_shader =
+      core.create_cached_shader_from_glsl(built.vs.as_str(), "material_vs", ShaderKind::Vertex)?;
+    let fragment_shader = core.create_cached_shader_from_glsl(
+      built.fs.as_str(),
+      "material_fs",
+      ShaderKind::Fragment,
+    )?;
 
-    // Create layout
+    // Create layout, the kind of each bind group is part of the pipeline key.
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
     let mut desc = PipelineLayoutDescriptor::new();
 
     for group in built.bind_groups.iter() {
       let layout = match group {
-        BuiltShaderBindGroup::Uniform(_) => MATERIAL_UNIFORM_LAYOUT.clone(),
-        BuiltShaderBindGroup::SampledTexture(_) => MATERIAL_TEXTURE_LAYOUT.clone(),
-        BuiltShaderBindGroup::SampledTextureArray(arr) => core
-          .get_gp_resources()
-          .get_sampled_texture_array_bind_group_layout(arr.size as usize)
-          .clone(),
+        BuiltShaderBindGroup::Uniform(_) => {
+          0u32.hash(&mut hasher);
+          MATERIAL_UNIFORM_LAYOUT.clone()
+        }
+        BuiltShaderBindGroup::SampledTexture(_) => {
+          1u32.hash(&mut hasher);
+          MATERIAL_TEXTURE_LAYOUT.clone()
+        }
+        BuiltShaderBindGroup::SampledTextureArray(arr) => {
+          2u32.hash(&mut hasher);
+          arr.size.hash(&mut hasher);
+          core
+            .get_gp_resources()
+            .get_sampled_texture_array_bind_group_layout(arr.size as usize)
+            .clone()
+        }
       };
       desc = desc.add_binding(layout);
     }
     let layout = core.create_pipeline_layout(desc);
 
-    // Build pbr pipeline.
-    let pipeline = core.create_render_pipeline(
-      RenderPipelineDescriptor::new(
+    // Identical shader sources with identical vertex layouts and render states share a single pipeline.
+    // Different vertex formats may generate the same source, e.g. `Unorm8x4` and `Float4` are both `vec4`.
+    built.vs.hash(&mut hasher);
+    built.fs.hash(&mut hasher);
+    built.vb.hash(&mut hasher);
+    key.1.hash(&mut hasher);
+    self.depth_compare.hash(&mut hasher);
+    self.depth_write.hash(&mut hasher);
+    let depth_mode = self.depth_mode.unwrap_or_else(|| core.get_depth_mode());
+    depth_mode.hash(&mut hasher);
+    let color_format = self
+      .color_format
+      .unwrap_or_else(|| core.get_color_target_format());
+    color_format.hash(&mut hasher);
+    let depth_format = self
+      .depth_format
+      .unwrap_or_else(|| core.get_depth_target_format());
+    depth_format.hash(&mut hasher);
+    self.front_face.hash(&mut hasher);
+    self.cull_mode.hash(&mut hasher);
+
+    // Build pbr pipeline, instanced materials read their transform from a second vertex buffer.
+    let instance_location = params.get::<ShaderOptionsMeshRenderer>().instance_location;
+    let pipeline = core.create_cached_render_pipeline(hasher.finish(), || {
+      let desc = RenderPipelineDescriptor::new(
         layout.clone(),
         built.vb.clone(),
         vertex_shader.clone(),
         fragment_shader.clone(),
       )
-      .add_depth(TextureFormat::Depth32Float)
-      .add_color_output(TextureFormat::Bgra8UnormSrgb),
-    );
+      .add_depth(depth_format, self.depth_compare, self.depth_write)
+      .add_color_output(color_format)
+      .with_depth_mode(depth_mode)
+      .with_front_face(self.front_face)
+      .with_cull_mode(self.cull_mode)
+      .with_polygon_mode(if key.1 {
+        PolygonMode::Line
+      } else {
+        PolygonMode::Fill
+      });
+      match instance_location {
+        Some(location) => desc.add_instance_buffer(instance_vertex_buffer(location)),
+        None => desc,
+      }
+    })?;
 
     let built_material = Arc::new(BuiltMaterial {
       shader: built,
@@ -106,11 +245,19 @@ impl Material {
       layout,
       pbr_pipeline: pipeline,
     });
-    built_cache.insert(params.hash, built_material.clone());
-    built_material
+    built_cache.insert(key, built_material.clone());
+    Ok(built_material)
   }
 }
 
+#[derive(Error, Debug)]
+pub enum MaterialError {
+  #[error(transparent)]
+  Shader(#[from] ShaderError),
+  #[error(transparent)]
+  VertexBuffers(#[from] VertexBufferLayoutError),
+}
+
 pub struct BuiltMaterial {
   pub shader: BuiltShaderGraph,
   pub vertex_shader: ResourceRc<Shader>,
@@ -119,6 +266,18 @@ pub struct BuiltMaterial {
   pub pbr_pipeline: ResourceRc<RenderPipeline>,
 }
 
+impl BuiltMaterial {
+  /// Number of uniforms the shaders bind, they come first followed by the textures.
+  pub fn uniform_count(&self) -> usize {
+    self
+      .shader
+      .bind_groups
+      .iter()
+      .filter(|group| matches!(group, BuiltShaderBindGroup::Uniform(_)))
+      .count()
+  }
+}
+
 impl Hash for BuiltMaterial {
   fn hash<H: Hasher>(&self, state: &mut H) {
     self.vertex_shader.hash(state);
@@ -143,6 +302,12 @@ impl PBRShaderNode {
   pub const INPUT_METALLIC: usize = 5;
   pub const INPUT_ROUGHNESS: usize = 6;
   pub const INPUT_NORMAL: usize = 7;
+  /// Multiplied into the base color, white unless connected to e.g. `connect_vertex_color`.
+  pub const INPUT_VERTEX_COLOR: usize = 8;
+
+  /// Name of the input passthrough, defaults of unconnected inputs can be overridden with it through
+  /// `ShaderBuildParams::override_default`.
+  pub const PASSTHROUGH_NAME: &str = "pbr";
 
   pub fn new() -> PBRShaderNode {
     Self {}
@@ -157,6 +322,7 @@ impl PBRShaderNode {
     // Add passthrough node for pbr node inputs
     let input_index = graph.add_node(
       InputPassthroughNode::new()
+        .with_name(Self::PASSTHROUGH_NAME)
         .add_input(ShaderType::Float3, "vec3(0, 0, 0)")
         .add_input(ShaderType::Float3, "vec3(0, 0, 0)")
         .add_input(ShaderType::Float3, "vec3(0, 0, 0)")
@@ -164,7 +330,8 @@ impl PBRShaderNode {
         .add_input(ShaderType::Float4, "vec4(0, 0, 0, 0)")
         .add_input(ShaderType::Float, "0.0")
         .add_input(ShaderType::Float, "0.0")
-        .add_input(ShaderType::Float3, "vec3(0, 1.0, 0)"),
+        .add_input(ShaderType::Float3, "vec3(0, 1.0, 0)")
+        .add_input(ShaderType::Float4, "vec4(1.0, 1.0, 1.0, 1.0)"),
     );
 
     // Build shaders from material.
@@ -172,6 +339,7 @@ impl PBRShaderNode {
     let (_, model_in) = graph.add_uniform::<TransformUniform>("transform");
     let (_, lights_in) = graph.add_uniform::<LightsUniform>("lights");
 
+    let instance_transform = graph.add_node(InstanceTransformNode {});
     let vertex_transform = graph.add_node(VertexTransformNode {});
     let dir_light = graph.add_node(DirectionalLightShaderNode {});
     let mat_prepare = graph.add_node(MaterialPrepareNode {});
@@ -180,28 +348,46 @@ impl PBRShaderNode {
     let alpha_color = graph.add_node(Construct::new(ShaderType::Float4).unwrap());
     let base_color = graph.add_node(Deconstruct::new(ShaderType::Float4).unwrap());
     let alpha_discard = graph.add_node(AlphaDiscardNode(0.9));
+    let tinted_color = graph.add_node(Multiply::new(ShaderType::Float4));
+
+    // Instance transform, forwards the vertex unchanged unless the material is built for instancing.
+    for (input, instance_input) in [
+      (Self::INPUT_POSITION, InstanceTransformNode::INPUT_POSITION),
+      (Self::INPUT_VNORMAL, InstanceTransformNode::INPUT_NORMAL),
+      (Self::INPUT_VTANGENT, InstanceTransformNode::INPUT_TANGENT),
+      (
+        Self::INPUT_VBITANGENT,
+        InstanceTransformNode::INPUT_BITANGENT,
+      ),
+    ]
+    .iter()
+    {
+      graph
+        .connect(input_index, *input, instance_transform, *instance_input)
+        .unwrap();
+    }
 
     // Normal transform.
     graph
       .connect(
-        input_index,
-        Self::INPUT_VNORMAL,
+        instance_transform,
+        InstanceTransformNode::OUTPUT_NORMAL,
         normal,
         NormalTransformNode::INPUT_NORMAL,
       )
       .unwrap();
     graph
       .connect(
-        input_index,
-        Self::INPUT_VTANGENT,
+        instance_transform,
+        InstanceTransformNode::OUTPUT_TANGENT,
         normal,
         NormalTransformNode::INPUT_TANGENT,
       )
       .unwrap();
     graph
       .connect(
-        input_index,
-        Self::INPUT_VBITANGENT,
+        instance_transform,
+        InstanceTransformNode::OUTPUT_BITANGENT,
         normal,
         NormalTransformNode::INPUT_BITANGENT,
       )
@@ -234,8 +420,8 @@ impl PBRShaderNode {
     // Vertex transform
     graph
       .connect(
-        input_index,
-        Self::INPUT_POSITION,
+        instance_transform,
+        InstanceTransformNode::OUTPUT_POSITION,
         vertex_transform,
         VertexTransformNode::INPUT_VPOSITION,
       )
@@ -307,11 +493,29 @@ impl PBRShaderNode {
       )
       .unwrap();
 
-    // Pixel
+    // Vertex color tints the base color.
     graph
       .connect(
         input_index,
         Self::INPUT_BASE_COLOR,
+        tinted_color,
+        Multiply::INPUT_A,
+      )
+      .unwrap();
+    graph
+      .connect(
+        input_index,
+        Self::INPUT_VERTEX_COLOR,
+        tinted_color,
+        Multiply::INPUT_B,
+      )
+      .unwrap();
+
+    // Pixel
+    graph
+      .connect(
+        tinted_color,
+        Multiply::OUTPUT,
         pixel,
         PixelPrepareNode::INPUT_BASE_COLOR,
       )
@@ -378,8 +582,8 @@ impl PBRShaderNode {
     // Color to color ouput
     graph
       .connect(
-        input_index,
-        Self::INPUT_BASE_COLOR,
+        tinted_color,
+        Multiply::OUTPUT,
         base_color,
         Deconstruct::INPUT,
       )
@@ -439,6 +643,122 @@ impl PBRShaderNode {
 
     (graph, input_index)
   }
+
+  /// Unpacks a sampled tangent space normal map and connects it to the pbr normal input.
+  pub fn connect_normal_map(
+    graph: &mut ShaderGraph,
+    sample: Index,
+    pbr: Index,
+  ) -> Result<Index, GraphConnectError> {
+    let unpack = graph.add_node(NormalMapUnpackNode {});
+    graph.connect(
+      sample,
+      TextureSampleNode::OUTPUT_COLOR,
+      unpack,
+      NormalMapUnpackNode::INPUT_COLOR,
+    )?;
+    graph.connect(
+      unpack,
+      NormalMapUnpackNode::OUTPUT_NORMAL,
+      pbr,
+      Self::INPUT_NORMAL,
+    )?;
+    Ok(unpack)
+  }
+
+  /// Connects the vertex color attribute, e.g. `MyVertex::OUTPUT_COLOR`, to the pbr vertex color input.
+  pub fn connect_vertex_color(
+    graph: &mut ShaderGraph,
+    vertex_in: Index,
+    color_output: usize,
+    pbr: Index,
+  ) -> Result<(), GraphConnectError> {
+    graph.connect(vertex_in, color_output, pbr, Self::INPUT_VERTEX_COLOR)
+  }
+}
+
+/// Applies the per-instance matrix of instanced materials to the vertex in object space.
+#[derive(Debug)]
+struct InstanceTransformNode;
+impl InstanceTransformNode {
+  const INPUT_POSITION: usize = 0;
+  const INPUT_NORMAL: usize = 1;
+  const INPUT_TANGENT: usize = 2;
+  const INPUT_BITANGENT: usize = 3;
+  const OUTPUT_POSITION: usize = 0;
+  const OUTPUT_NORMAL: usize = 1;
+  const OUTPUT_TANGENT: usize = 2;
+  const OUTPUT_BITANGENT: usize = 3;
+}
+
+impl ShaderNode for InstanceTransformNode {
+  fn get_available_stages(&self) -> (bool, bool) {
+    (true, false)
+  }
+
+  fn get_outputs(&self) -> Vec<ShaderType> {
+    vec![ShaderType::Float3; 4]
+  }
+
+  fn generate_global_code_with_params(
+    &self,
+    _inputs: &[Option<String>],
+    _outputs: &[Option<String>],
+    output: &mut String,
+    params: &ShaderBuildParams,
+  ) {
+    if let Some(location) = params.get::<ShaderOptionsMeshRenderer>().instance_location {
+      *output += format!(
+        "layout (location = {}) in mat4 a_instance_matrix;\n",
+        location
+      )
+      .as_str();
+    }
+  }
+
+  fn generate_with_params(
+    &self,
+    inputs: &[Option<String>],
+    outputs: &[Option<String>],
+    output: &mut String,
+    params: &ShaderBuildParams,
+  ) {
+    let input = |index: usize| inputs[index].as_ref().unwrap();
+    let output_name = |index: usize| outputs[index].as_ref().unwrap();
+
+    if params
+      .get::<ShaderOptionsMeshRenderer>()
+      .instance_location
+      .is_none()
+    {
+      for index in 0..4 {
+        *output += format!("vec3 {} = {};\n", output_name(index), input(index)).as_str();
+      }
+      return;
+    }
+
+    // Normals use the normal matrix while tangents follow the surface, mirrors `transform_vertex`.
+    *output += format!(
+      r#"
+        mat3 instance_direction_matrix = mat3(a_instance_matrix);
+        mat3 instance_normal_matrix = transpose(inverse(instance_direction_matrix));
+        vec4 instance_position = a_instance_matrix * vec4({}, 1.0);
+        vec3 {} = instance_position.xyz / instance_position.w;
+        vec3 {} = normalize(instance_normal_matrix * {});
+        vec3 {} = normalize(instance_direction_matrix * {});
+        vec3 {} = normalize(instance_direction_matrix * {});
+      "#,
+      input(Self::INPUT_POSITION),
+      output_name(Self::OUTPUT_POSITION),
+      output_name(Self::OUTPUT_NORMAL),
+      input(Self::INPUT_NORMAL),
+      output_name(Self::OUTPUT_TANGENT),
+      input(Self::INPUT_TANGENT),
+      output_name(Self::OUTPUT_BITANGENT),
+      input(Self::INPUT_BITANGENT),
+    )
+    .as_str();
+  }
 }
 
 #[derive(Debug)]
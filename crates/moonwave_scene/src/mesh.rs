@@ -1,11 +1,14 @@
 use moonwave_common::{
   bytemuck::{cast_slice, Pod, Zeroable},
-  InnerSpace, Vector2, Vector3,
+  InnerSpace, Matrix, Matrix3, Matrix4, SquareMatrix, Vector2, Vector3, Vector4,
 };
 use moonwave_core::rayon::prelude::*;
 use moonwave_core::{Core, Itertools};
 use moonwave_resources::{Buffer, BufferUsage, IndexFormat, ResourceRc};
 use moonwave_shader::VertexStruct;
+use std::convert::TryFrom;
+
+use crate::Transform;
 
 pub struct Mesh<T: MeshVertex, I: MeshIndex> {
   indices: Vec<I>,
@@ -55,6 +58,47 @@ impl<T: MeshVertex, I: MeshIndex> Mesh<T, I> {
     self.indices.iter()
   }
 
+  /// Approximates the smallest sphere enclosing all vertices using Ritter's algorithm.
+  /// Returns `(center, radius)`, an empty mesh results in a zero sized sphere at the origin.
+  pub fn compute_bounding_sphere(&self) -> (Vector3<f32>, f32) {
+    let first = match self.vertices.first() {
+      Some(vertex) => vertex.get_position(),
+      None => return (Vector3::new(0.0, 0.0, 0.0), 0.0),
+    };
+
+    // Find two points far apart from each other to build the initial sphere.
+    let farthest_from = |from: Vector3<f32>| {
+      self
+        .vertices
+        .iter()
+        .map(|vertex| vertex.get_position())
+        .fold(from, |best, position| {
+          if (position - from).magnitude2() > (best - from).magnitude2() {
+            position
+          } else {
+            best
+          }
+        })
+    };
+    let a = farthest_from(first);
+    let b = farthest_from(a);
+    let mut center = (a + b) * 0.5;
+    let mut radius = (b - a).magnitude() * 0.5;
+
+    // Grow sphere until all points are enclosed.
+    for vertex in self.vertices.iter() {
+      let position = vertex.get_position();
+      let distance = (position - center).magnitude();
+      if distance > radius {
+        let new_radius = (radius + distance) * 0.5;
+        center += (position - center) * ((new_radius - radius) / distance);
+        radius = new_radius;
+      }
+    }
+
+    (center, radius)
+  }
+
   pub fn push_vertex(&mut self, vertex: T) {
     self.vertices.push(vertex);
   }
@@ -86,7 +130,16 @@ impl<T: MeshVertex, I: MeshIndex> Mesh<T, I> {
     Core::get_instance().create_inited_buffer(raw_boxed, BufferUsage::VERTEX, None)
   }
 
+  /// Panics if any index points past the vertices of the mesh.
   pub fn build_index_buffer(&self) -> ResourceRc<Buffer> {
+    debug_assert!(
+      self.vertices.len() <= I::max_vertices(),
+      "Mesh has {} vertices which can't be addressed by index format {:?}",
+      self.vertices.len(),
+      I::get_format()
+    );
+    self.assert_indices_in_range();
+
     // Build raw
     let raw = cast_slice(&self.indices);
     let raw_boxed = Box::from(raw);
@@ -94,6 +147,154 @@ impl<T: MeshVertex, I: MeshIndex> Mesh<T, I> {
     // Build buffer.
     Core::get_instance().create_inited_buffer(raw_boxed, BufferUsage::INDEX, None)
   }
+
+  /// Picks the smallest index format able to address all vertices of the mesh.
+  pub fn optimal_index_format(&self) -> IndexFormat {
+    if self.vertices.len() <= u16::max_vertices() {
+      IndexFormat::Uint16
+    } else {
+      IndexFormat::Uint32
+    }
+  }
+
+  /// Builds the index buffer with the smallest possible index format regardless of `I`.
+  pub fn build_with_optimal_index(&self) -> (ResourceRc<Buffer>, IndexFormat) {
+    self.assert_indices_in_range();
+    let format = self.optimal_index_format();
+    let raw = match format {
+      IndexFormat::Uint16 => {
+        let indices = self
+          .indices
+          .iter()
+          .map(|i| u16::from_usize(i.as_usize()))
+          .collect::<Vec<_>>();
+        Box::from(cast_slice(&indices))
+      }
+      IndexFormat::Uint32 => {
+        let indices = self
+          .indices
+          .iter()
+          .map(|i| u32::from_usize(i.as_usize()))
+          .collect::<Vec<_>>();
+        Box::from(cast_slice(&indices))
+      }
+    };
+
+    // Build buffer.
+    let buffer = Core::get_instance().create_inited_buffer(raw, BufferUsage::INDEX, None);
+    (buffer, format)
+  }
+
+  fn assert_indices_in_range(&self) {
+    if let Some(max) = self.indices.iter().map(|i| i.as_usize()).max() {
+      assert!(
+        max < self.vertices.len(),
+        "Index {} is out of range for a mesh with {} vertices",
+        max,
+        self.vertices.len()
+      );
+    }
+  }
+}
+
+impl<T: MeshVertexNormal, I: MeshIndex> Mesh<T, I> {
+  /// Merges all meshes into a single one with each mesh's transform baked into its vertices.
+  /// Useful to turn static scenery made of many small meshes into a single draw at load time.
+  /// Panics if the merged vertices can't be addressed by the index type, use `u32` indices then.
+  pub fn concat(meshes: &[(Self, Matrix4<f32>)]) -> Self {
+    let vertices = meshes.iter().map(|(mesh, _)| mesh.len_vertices()).sum();
+    assert!(
+      vertices <= I::max_vertices(),
+      "Concatenated mesh has {} vertices which can't be addressed by index format {:?}",
+      vertices,
+      I::get_format()
+    );
+
+    let mut merged = Mesh::with_capacity(
+      vertices,
+      meshes.iter().map(|(mesh, _)| mesh.len_indices()).sum(),
+    );
+
+    for (mesh, matrix) in meshes {
+      let offset = merged.vertices.len();
+      let normal_matrix = normal_matrix(matrix);
+      merged.vertices.extend(
+        mesh
+          .vertices
+          .iter()
+          .map(|vertex| transform_vertex(vertex, matrix, &normal_matrix)),
+      );
+      merged
+        .indices
+        .extend(mesh.indices.iter().map(|i| i.with_offset(offset)));
+    }
+
+    merged
+  }
+
+  /// Copy of the mesh with the transform baked into its vertices, e.g. for pre-placed props
+  /// that are drawn without a transform uniform.
+  pub fn transformed(&self, transform: &Transform) -> Self {
+    self.transformed_by_matrix(&transform.calculate_transform_matrix())
+  }
+
+  /// Same as `transformed` with an explicit model matrix.
+  pub fn transformed_by_matrix(&self, matrix: &Matrix4<f32>) -> Self {
+    let normal_matrix = normal_matrix(matrix);
+    Self {
+      vertices: self
+        .vertices
+        .iter()
+        .map(|vertex| transform_vertex(vertex, matrix, &normal_matrix))
+        .collect(),
+      indices: self.indices.clone(),
+    }
+  }
+}
+
+/// Inverse transpose of the upper 3x3 part of a model matrix,
+/// keeps normals perpendicular to their surface under non-uniform scaling.
+pub fn normal_matrix(matrix: &Matrix4<f32>) -> Matrix3<f32> {
+  let upper = Matrix3::from_cols(
+    matrix.x.truncate(),
+    matrix.y.truncate(),
+    matrix.z.truncate(),
+  );
+  upper
+    .invert()
+    .map(|inverse| inverse.transpose())
+    .unwrap_or(upper)
+}
+
+/// Applies the model matrix to the position and the normal matrix to the normal.
+/// Tangent and bitangent lie within the surface and are transformed like it by the upper 3x3 part.
+pub(crate) fn transform_vertex<T: MeshVertexNormal>(
+  vertex: &T,
+  matrix: &Matrix4<f32>,
+  normal_matrix: &Matrix3<f32>,
+) -> T {
+  let position = vertex.get_position();
+  let transformed = matrix * Vector4::new(position.x, position.y, position.z, 1.0);
+  let upper = Matrix3::from_cols(
+    matrix.x.truncate(),
+    matrix.y.truncate(),
+    matrix.z.truncate(),
+  );
+  let direction = |matrix: &Matrix3<f32>, value: &Vector3<f32>| {
+    let value = matrix * value;
+    if value.magnitude2() > 0.0 {
+      value.normalize()
+    } else {
+      value
+    }
+  };
+
+  let mut new_vertex = *vertex;
+  *new_vertex.get_position_mut() = transformed.truncate() / transformed.w;
+  *new_vertex.get_normal_mut() = direction(normal_matrix, vertex.get_normal());
+  *new_vertex.get_tangent_mut() = direction(&upper, vertex.get_tangent());
+  *new_vertex.get_bitangent_mut() = direction(&upper, vertex.get_bitangent());
+  new_vertex
 }
 
 impl<T: MeshVertexNormal + MeshVertexUV, I: MeshIndex> Mesh<T, I> {
@@ -179,6 +380,69 @@ impl<T: MeshVertexNormal + MeshVertexUV, I: MeshIndex> Mesh<T, I> {
       }
     }
   }
+
+  /// Generates tangents and bitangents from the uv gradients of each face (Lengyel's method).
+  /// Normals have to be present already, tangents are orthonormalized against them and the
+  /// bitangent is flipped for mirrored uvs.
+  pub fn generate_tangents(&mut self) {
+    let mut tangents = vec![Vector3::new(0.0, 0.0, 0.0); self.vertices.len()];
+    let mut bitangents = vec![Vector3::new(0.0, 0.0, 0.0); self.vertices.len()];
+
+    for (i1, i2, i3) in self.indices.iter().tuples() {
+      let is = [i1.as_usize(), i2.as_usize(), i3.as_usize()];
+      let v1 = &self.vertices[is[0]];
+      let v2 = &self.vertices[is[1]];
+      let v3 = &self.vertices[is[2]];
+
+      let delta_pos_2_1 = v2.get_position() - v1.get_position();
+      let delta_pos_3_1 = v3.get_position() - v1.get_position();
+      let delta_uv_2_1 = v2.get_uv() - v1.get_uv();
+      let delta_uv_3_1 = v3.get_uv() - v1.get_uv();
+
+      // Faces without uv area have no defined tangent space.
+      let det = delta_uv_2_1.x * delta_uv_3_1.y - delta_uv_2_1.y * delta_uv_3_1.x;
+      if det.abs() <= f32::EPSILON {
+        continue;
+      }
+      let r = 1.0 / det;
+      let tangent = (delta_pos_2_1 * delta_uv_3_1.y - delta_pos_3_1 * delta_uv_2_1.y) * r;
+      let bitangent = (delta_pos_3_1 * delta_uv_2_1.x - delta_pos_2_1 * delta_uv_3_1.x) * r;
+
+      for i in is.iter() {
+        tangents[*i] += tangent;
+        bitangents[*i] += bitangent;
+      }
+    }
+
+    for ((vertex, tangent), bitangent) in self
+      .vertices
+      .iter_mut()
+      .zip(tangents.into_iter())
+      .zip(bitangents.into_iter())
+    {
+      let normal = *vertex.get_normal();
+
+      // Gram-Schmidt orthogonalize, fall back to any perpendicular axis.
+      let mut orthogonal = tangent - normal * normal.dot(tangent);
+      if orthogonal.magnitude2() <= f32::EPSILON {
+        let axis = if normal.x.abs() < 0.9 {
+          Vector3::unit_x()
+        } else {
+          Vector3::unit_y()
+        };
+        orthogonal = axis - normal * normal.dot(axis);
+      }
+      let tangent = orthogonal.normalize();
+
+      let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 {
+        -1.0
+      } else {
+        1.0
+      };
+      *vertex.get_tangent_mut() = tangent;
+      *vertex.get_bitangent_mut() = normal.cross(tangent) * handedness;
+    }
+  }
 }
 
 pub trait MeshVertex: Zeroable + Pod + VertexStruct + Send + Sync {
@@ -191,6 +455,12 @@ pub trait MeshVertexUV: MeshVertex {
   fn get_uv_mut(&mut self) -> &mut Vector2<f32>;
 }
 
+/// Per vertex color, the PBR material multiplies it into the base color.
+pub trait MeshVertexColor: MeshVertex {
+  fn get_color(&self) -> &Vector4<f32>;
+  fn get_color_mut(&mut self) -> &mut Vector4<f32>;
+}
+
 pub trait MeshVertexNormal: MeshVertex {
   fn get_normal(&self) -> &Vector3<f32>;
   fn get_normal_mut(&mut self) -> &mut Vector3<f32>;
@@ -205,6 +475,8 @@ pub trait MeshVertexNormal: MeshVertex {
 pub trait MeshIndex: Pod {
   fn with_offset(self, offset: usize) -> Self;
   fn as_usize(self) -> usize;
+  fn from_usize(index: usize) -> Self;
+  fn max_vertices() -> usize;
   fn get_format() -> IndexFormat;
 }
 impl MeshIndex for u16 {
@@ -214,6 +486,13 @@ impl MeshIndex for u16 {
   fn with_offset(self, offset: usize) -> Self {
     self + offset as u16
   }
+  fn from_usize(index: usize) -> Self {
+    debug_assert!(index <= u16::MAX as usize, "Index {} overflows u16", index);
+    index as u16
+  }
+  fn max_vertices() -> usize {
+    u16::MAX as usize + 1
+  }
   fn get_format() -> IndexFormat {
     IndexFormat::Uint16
   }
@@ -225,6 +504,14 @@ impl MeshIndex for u32 {
   fn with_offset(self, offset: usize) -> Self {
     self + offset as u32
   }
+  fn from_usize(index: usize) -> Self {
+    debug_assert!(index <= u32::MAX as usize, "Index {} overflows u32", index);
+    index as u32
+  }
+  fn max_vertices() -> usize {
+    // Saturates on targets where usize can't hold all addressable vertices.
+    usize::try_from(u32::MAX as u64 + 1).unwrap_or(usize::MAX)
+  }
   fn get_format() -> IndexFormat {
     IndexFormat::Uint32
   }
@@ -1,6 +1,7 @@
 use std::{any::Any, cell::RefCell, marker::PhantomData};
 
 use itertools::Itertools;
+use lazy_static::lazy_static;
 use moonwave_common::{
   bytemuck::{cast_slice, Pod},
   *,
@@ -8,7 +9,7 @@ use moonwave_common::{
 use moonwave_core::*;
 use moonwave_render::RenderPassCommandEncoder;
 use moonwave_resources::*;
-use parking_lot::{Mutex, MutexGuard};
+use parking_lot::{Mutex, MutexGuard, RwLock};
 use rayon::prelude::*;
 
 use crate::{
@@ -16,13 +17,107 @@ use crate::{
     SharedAreaBuffer, SharedAreaBufferAllocation, SharedAreaBufferOptions, SharedSimpleBuffer,
     SharedSimpleBufferAllocation,
   },
-  Mesh, MeshIndex, MeshVertex, MeshVertexNormal, Transform,
+  normal_matrix, transform_vertex, Mesh, MeshIndex, MeshVertex, MeshVertexNormal, Transform,
 };
 
 thread_local! {
   static STAGING_BELL: RefCell<StagingBelt> = RefCell::new(StagingBelt::new(2 * 1024 * 1024));
 }
 
+lazy_static! {
+  static ref GLOBAL_CONFIG: RwLock<StaticMeshCombinerConfig> =
+    RwLock::new(StaticMeshCombinerConfig::default());
+}
+
+/// Buffer sizes of a static mesh combiner, a new generation of buffers is created once one is full.
+/// Meshes are split into chunks, so a single mesh can use at most `max_vertex_chunks * vertices_per_chunk`
+/// vertices and `max_index_chunks * indices_per_chunk` indices.
+/// Vertex chunks are clamped per index type, see `for_index`, as indices address the whole generation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StaticMeshCombinerConfig {
+  /// Vertices per allocation, small meshes waste up to one chunk each.
+  pub vertices_per_chunk: usize,
+  /// Indices per allocation, indices of a mesh are allocated contiguously.
+  /// Should be a multiple of three, otherwise draws of neighbouring meshes can't be merged.
+  pub indices_per_chunk: usize,
+  /// Vertex chunks per generation.
+  pub max_vertex_chunks: usize,
+  /// Index chunks per generation.
+  pub max_index_chunks: usize,
+}
+
+impl Default for StaticMeshCombinerConfig {
+  /// Generations of 512k vertices and 1.5M indices, roughly three indices per vertex.
+  fn default() -> Self {
+    Self {
+      vertices_per_chunk: 1024,
+      indices_per_chunk: 96,
+      max_vertex_chunks: 512,
+      max_index_chunks: 16 * 1024,
+    }
+  }
+}
+
+impl StaticMeshCombinerConfig {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_vertices_per_chunk(mut self, vertices: usize) -> Self {
+    self.vertices_per_chunk = vertices;
+    self
+  }
+
+  pub fn with_indices_per_chunk(mut self, indices: usize) -> Self {
+    self.indices_per_chunk = indices;
+    self
+  }
+
+  pub fn with_max_vertex_chunks(mut self, chunks: usize) -> Self {
+    self.max_vertex_chunks = chunks;
+    self
+  }
+
+  pub fn with_max_index_chunks(mut self, chunks: usize) -> Self {
+    self.max_index_chunks = chunks;
+    self
+  }
+
+  /// Config used by mesh renderers for combiners created from now on.
+  pub fn global() -> Self {
+    *GLOBAL_CONFIG.read()
+  }
+
+  /// Changes the config of combiners created afterwards, existing combiners keep their sizes.
+  pub fn set_global(config: Self) {
+    *GLOBAL_CONFIG.write() = config;
+  }
+
+  /// Amount of vertex and index chunks a mesh occupies.
+  pub fn chunks_for(&self, vertices: usize, indices: usize) -> (usize, usize) {
+    (
+      (vertices + self.vertices_per_chunk - 1) / self.vertices_per_chunk,
+      (indices + self.indices_per_chunk - 1) / self.indices_per_chunk,
+    )
+  }
+
+  /// Config with `max_vertex_chunks` clamped so that every vertex of a generation is addressable by `I`.
+  pub fn for_index<I: MeshIndex>(mut self) -> Self {
+    let addressable_chunks = (I::max_vertices() / self.vertices_per_chunk).max(1);
+    self.max_vertex_chunks = self.max_vertex_chunks.min(addressable_chunks);
+    self
+  }
+
+  /// Whether a mesh with indices of type `I` fits into a single generation at all.
+  pub fn fits<I: MeshIndex>(&self, vertices: usize, indices: usize) -> bool {
+    let config = self.for_index::<I>();
+    let (vertex_chunks, index_chunks) = config.chunks_for(vertices, indices);
+    vertices <= I::max_vertices()
+      && vertex_chunks <= config.max_vertex_chunks
+      && index_chunks <= config.max_index_chunks
+  }
+}
+
 pub struct StaticMeshCombiner<T: Sized, I: Sized = u16> {
   vertices_per_chunk: usize,
   indices_per_chunk: usize,
@@ -96,69 +191,71 @@ impl<T: Send + Sync + 'static, I: MeshIndex + Send + Sync + 'static> GenericStat
       return;
     }
 
-    // Sort by index start position.
-    let mut sorted_entries = entries.iter().collect_vec();
-    sorted_entries.sort_unstable_by_key(|e| e.ib.chunk_start);
-
-    // Merge calls
-    /*
-    let mut current_start = sorted_entries[0].ib.chunk_start * self.indices_per_chunk;
-    let mut current_length = sorted_entries[0].indices;
-    let mut undrawn = Some(sorted_entries[0]);
-    */
+    // Entries that directly follow each other are drawn with a single call.
+    let ranges = coalesce_draw_ranges(
+      entries
+        .iter()
+        .map(|entry| CombinedDrawRange::from_entry(entry, self.indices_per_chunk))
+        .collect(),
+    );
 
     let mut prev_generation = usize::MAX;
-    for entry in sorted_entries.into_iter() {
-      if entry.generation != prev_generation {
+    for range in ranges {
+      if range.generation != prev_generation {
         let generations = self.generations.lock();
-        let generation = generations.get(entry.generation).unwrap();
+        let generation = generations.get(range.generation).unwrap();
         pass.set_vertex_buffer(generation.vertex_buffer.clone());
         pass.set_index_buffer(generation.index_buffer.clone(), I::get_format());
-        prev_generation = entry.generation;
+        prev_generation = range.generation;
       }
-      let start = entry.ib.chunk_start * self.indices_per_chunk;
-      pass.render_indexed(start as u32..(start + entry.indices) as u32);
+      pass.render_indexed(range.start as u32..range.end as u32);
     }
+  }
+}
 
-    /*
-    for entry in sorted_entries.into_iter().skip(1) {
-      let next_start = entry.ib.chunk_start * self.indices_per_chunk;
-
-      // Check if it is a follow up
-      if next_start == (current_start + current_length) && entry.generation == prev_generation {
-        undrawn = Some(entry);
-        current_length += entry.indices;
-        continue;
-      }
-
-      // Check if we need to also have a generation switch.
-      if entry.generation != prev_generation {
-        let generations = self.generations.lock();
-        let generation = generations.get(entry.generation).unwrap();
-        pass.set_vertex_buffer(generation.vertex_buffer.clone());
-        pass.set_index_buffer(generation.index_buffer.clone(), I::get_format());
-        prev_generation = entry.generation;
-      }
+/// Index range of a combined mesh within the index buffer of its generation.
+/// `padded_end` is the end of its last chunk, the indices in between form degenerate triangles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CombinedDrawRange {
+  pub generation: usize,
+  pub start: usize,
+  pub end: usize,
+  pub padded_end: usize,
+}
 
-      // Not a follow up therefore we need to have a render call here.
-      undrawn = None;
-      pass.render_indexed(current_start as u32..(current_start + current_length) as u32);
-      current_start = next_start;
-      current_length = entry.indices;
+impl CombinedDrawRange {
+  fn from_entry(entry: &StaticMeshCombinerEntry, indices_per_chunk: usize) -> Self {
+    let start = entry.ib.chunk_start * indices_per_chunk;
+    Self {
+      generation: entry.generation,
+      start,
+      end: start + entry.indices,
+      padded_end: (entry.ib.chunk_start + entry.ib.chunk_length) * indices_per_chunk,
     }
+  }
+}
 
-    // End up with a render call.
-    if let Some(undrawn) = undrawn {
-      if undrawn.generation != prev_generation {
-        let generations = self.generations.lock();
-        let generation = generations.get(undrawn.generation).unwrap();
-        pass.set_vertex_buffer(generation.vertex_buffer.clone());
-        pass.set_index_buffer(generation.index_buffer.clone(), I::get_format());
+/// Sorts the ranges and merges those that directly follow each other within the same generation.
+/// Padding in between is only drawn if it consists of whole triangles, otherwise the following
+/// triangles would be misaligned.
+pub fn coalesce_draw_ranges(mut ranges: Vec<CombinedDrawRange>) -> Vec<CombinedDrawRange> {
+  ranges.sort_unstable_by_key(|range| (range.generation, range.start));
+
+  let mut merged: Vec<CombinedDrawRange> = Vec::with_capacity(ranges.len());
+  for range in ranges {
+    match merged.last_mut() {
+      Some(last)
+        if last.generation == range.generation
+          && last.padded_end == range.start
+          && (last.padded_end - last.end) % 3 == 0 =>
+      {
+        last.end = range.end;
+        last.padded_end = range.padded_end;
       }
-      pass.render_indexed(current_start as u32..(current_start + current_length) as u32);
+      _ => merged.push(range),
     }
-    */
   }
+  merged
 }
 
 impl<
@@ -172,6 +269,15 @@ impl<
     max_vertex_chunks: usize,
     max_index_chunks: usize,
   ) -> Self {
+    let max_vertex_chunks = StaticMeshCombinerConfig {
+      vertices_per_chunk,
+      indices_per_chunk,
+      max_vertex_chunks,
+      max_index_chunks,
+    }
+    .for_index::<I>()
+    .max_vertex_chunks;
+
     Self {
       vertices_per_chunk,
       indices_per_chunk,
@@ -187,6 +293,24 @@ impl<
     }
   }
 
+  pub fn from_config(config: StaticMeshCombinerConfig) -> Self {
+    Self::new(
+      config.vertices_per_chunk,
+      config.indices_per_chunk,
+      config.max_vertex_chunks,
+      config.max_index_chunks,
+    )
+  }
+
+  pub fn get_config(&self) -> StaticMeshCombinerConfig {
+    StaticMeshCombinerConfig {
+      vertices_per_chunk: self.vertices_per_chunk,
+      indices_per_chunk: self.indices_per_chunk,
+      max_vertex_chunks: self.max_vertex_chunks,
+      max_index_chunks: self.max_index_chunks,
+    }
+  }
+
   pub fn remove(&self, entry: StaticMeshCombinerEntry) {
     let mut generations = self.generations.lock();
     let generation = generations.get_mut(entry.generation).unwrap();
@@ -247,10 +371,9 @@ impl<
     }
 
     // Get mesh stats.
-    let total_indices = mesh.len_indices();
-    let total_vertices = mesh.len_vertices();
-    let chunks_indices = (total_indices as f32 / self.indices_per_chunk as f32).ceil() as usize;
-    let chunks_vertices = (total_vertices as f32 / self.vertices_per_chunk as f32).ceil() as usize;
+    let (chunks_vertices, chunks_indices) = self
+      .get_config()
+      .chunks_for(mesh.len_vertices(), mesh.len_indices());
 
     // Get free space in vertex buffer.
     let vb = {
@@ -290,64 +413,11 @@ impl<
 
     // Build vertex buffer with preapplied transform.
     let transform_matrix = transform.calculate_transform_matrix();
-    let normal_matrix = transform_matrix;
+    let normal_matrix = normal_matrix(&transform_matrix);
 
     let vertices = mesh
       .iter_vertices()
-      .map(|vertex| {
-        /*
-        let position = transform_matrix.transform_vector(*vertex.get_position());
-        let normal = normal_matrix.transform_vector(*vertex.get_normal());
-        let tangent = normal_matrix.transform_vector(*vertex.get_tangent());
-        let bitangent = normal_matrix.transform_vector(*vertex.get_bitangent());
-
-        let mut new_vertex = *vertex;
-        *new_vertex.get_position_mut() = position;
-        *new_vertex.get_normal_mut() = normal;
-        *new_vertex.get_tangent_mut() = tangent;
-        *new_vertex.get_bitangent_mut() = bitangent;
-
-        new_vertex
-        */
-        let position = transform_matrix
-          * Vector4::new(
-            vertex.get_position().x,
-            vertex.get_position().y,
-            vertex.get_position().z,
-            1.0,
-          );
-        let normal = normal_matrix
-          * Vector4::new(
-            vertex.get_normal().x,
-            vertex.get_normal().y,
-            vertex.get_normal().z,
-            1.0,
-          );
-        let tangent = normal_matrix
-          * Vector4::new(
-            vertex.get_tangent().x,
-            vertex.get_tangent().y,
-            vertex.get_tangent().z,
-            1.0,
-          );
-        let bitangent = normal_matrix
-          * Vector4::new(
-            vertex.get_bitangent().x,
-            vertex.get_bitangent().y,
-            vertex.get_bitangent().z,
-            1.0,
-          );
-
-        let mut new_vertex = *vertex;
-        *new_vertex.get_position_mut() = position.xyz().div_element_wise(position.w);
-        /*
-         *new_vertex.get_normal_mut() = normal.xyz().div_element_wise(normal.w);
-         *new_vertex.get_tangent_mut() = tangent.xyz().div_element_wise(tangent.w);
-         *new_vertex.get_bitangent_mut() = bitangent.xyz().div_element_wise(bitangent.w);
-         */
-
-        new_vertex
-      })
+      .map(|vertex| transform_vertex(vertex, &transform_matrix, &normal_matrix))
       .collect::<Vec<_>>();
 
     // Place vertices into buffer.
@@ -367,7 +437,7 @@ impl<
       });
 
     // Rebuild indices for mesh with new offsets.
-    let indices = mesh
+    let mut indices = mesh
       .iter_indices()
       .map(|index| {
         let chunk_index = index.as_usize() / self.vertices_per_chunk;
@@ -375,6 +445,11 @@ impl<
       })
       .collect_vec();
 
+    // Pad the last chunk with degenerate triangles so draws of following entries can be merged.
+    if let Some(last) = indices.last().copied() {
+      indices.resize(chunks_indices * self.indices_per_chunk, last);
+    }
+
     // Place indices into buffer.
     let indices_raw = cast_slice(&indices);
     STAGING_BELL.with(|belt| {
@@ -5,33 +5,109 @@ use legion::IntoQuery;
 use moonwave_common::{MetricSpace, Vector4};
 use moonwave_core::*;
 use moonwave_render::{
-  CommandEncoder, FrameGraphNode, FrameNodeValue, RenderPassCommandEncoderBuilder,
+  CommandEncoder, FrameGraph, FrameGraphNode, FrameNodeValue, Index, RenderPassCommandEncoder,
+  RenderPassCommandEncoderBuilder,
 };
 use moonwave_resources::{
-  BindGroup, Buffer, IndexFormat, RenderPipeline, ResourceRc, TextureFormat,
+  BindGroup, Buffer, DepthMode, IndexFormat, RenderPipeline, ResourceRc, SampledTexture,
+  TextureFormat, TextureUsage,
 };
 use moonwave_shader::ShaderBuildParams;
 use moonwave_shader::VertexStruct;
+use moonwave_util::group_adjacent_by;
 use parking_lot::Mutex;
 use rayon::prelude::*;
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::sync::Arc;
+use thiserror::Error;
 
 use crate::opt::GenericStaticMeshCombiner;
 use crate::opt::StaticMeshCombiner;
+use crate::opt::StaticMeshCombinerConfig;
 use crate::opt::StaticMeshCombinerEntry;
 use crate::MeshVertexNormal;
 use crate::TransformOptimization;
 use crate::{
-  BoundingShape, BuiltMaterial, Camera, GenericUniform, LightManager, MainCameraTag, Material,
-  Mesh, MeshIndex, MeshVertex, Transform,
+  BoundingShape, BuiltMaterial, Camera, CameraUniform, GenericUniform, InstancedDrawCall,
+  InstancedMesh, LightManager, MainCameraTag, Material, MaterialError, Mesh, MeshIndex, MeshVertex,
+  PubUniformResources, StagedBufferAccessor, Transform, TransformUniform, Uniform,
 };
 
+/// Camera, transform and lights uniforms of `PBRShaderNode`, bound before the extra uniforms.
+const PBR_UNIFORMS: usize = 3;
+
 static REGISTERED_SYSTEM: std::sync::Once = std::sync::Once::new();
 static PBR_MAIN_COLOR: OnceCell<Arc<TextureGeneratorHost>> = OnceCell::new();
 static PBR_MAIN_DEPTH: OnceCell<Arc<TextureGeneratorHost>> = OnceCell::new();
+/// Tonemapped sRGB target, only used in HDR mode.
+static PBR_MAIN_LDR: OnceCell<Arc<TextureGeneratorHost>> = OnceCell::new();
+/// Depth target of the screen space overlay pass, keeps the scene depth intact for post processing.
+static PBR_OVERLAY_DEPTH: OnceCell<Arc<TextureGeneratorHost>> = OnceCell::new();
+/// Pixel space camera of screen space overlays.
+static PBR_OVERLAY_CAMERA: OnceCell<Uniform<CameraUniform>> = OnceCell::new();
+
+/// Lazily sets up the pbr render targets and frame graph system.
+pub(crate) fn register_pbr_system() {
+  REGISTERED_SYSTEM.call_once(|| {
+    // Create texture nodes.
+    let core = Core::get_instance();
+    // The color target can be copied from, e.g. for readbacks of the rendered scene.
+    let color = TextureGeneratorHost::new_with_usage(
+      TextureSize::FullScreen,
+      core.get_color_target_format(),
+      TextureUsage::RENDER_ATTACHMENT | TextureUsage::COPY_SRC,
+    );
+    let depth = TextureGeneratorHost::new(TextureSize::FullScreen, core.get_depth_target_format());
+
+    PBR_MAIN_COLOR.set(color).ok().unwrap();
+    PBR_MAIN_DEPTH.set(depth).ok().unwrap();
+    let overlay_depth =
+      TextureGeneratorHost::new(TextureSize::FullScreen, core.get_depth_target_format());
+    PBR_OVERLAY_DEPTH.set(overlay_depth).ok().unwrap();
+    let overlay_camera =
+      CameraUniform::screen_space(core.get_swap_chain_size(), core.get_depth_mode());
+    PBR_OVERLAY_CAMERA
+      .set(Uniform::new(overlay_camera))
+      .ok()
+      .unwrap();
+    if core.is_hdr() {
+      let ldr = TextureGeneratorHost::new(TextureSize::FullScreen, TextureFormat::Bgra8UnormSrgb);
+      PBR_MAIN_LDR.set(ldr).ok().unwrap();
+    }
+
+    // Add system if not added yet.
+    Core::get_instance()
+      .get_world()
+      .add_system_to_stage(CreatePBRFrameGraphSystem, SystemStage::Rendering);
+  });
+}
+
+/// Main scene color target of the `CoreConfig::hdr` dependent format, before any tonemapping.
+/// `None` until the first mesh renderer has been created.
+pub fn get_pbr_main_color_target() -> Option<SampledTexture> {
+  PBR_MAIN_COLOR.get().map(|color| color.get_texture())
+}
+
+/// How the mesh of a `MeshRenderer` ends up on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshRenderPath {
+  /// Merged into the shared buffers of a static mesh combiner with pre-transformed vertices.
+  Combined,
+  /// Drawn with its own vertex and index buffers and a transform uniform.
+  Individual,
+}
+
+impl MeshRenderPath {
+  /// Static meshes are combined unless combining has been disabled for them.
+  pub fn select(transform: &Transform, allow_combining: bool) -> Self {
+    match transform.get().opt {
+      TransformOptimization::Static if allow_combining => MeshRenderPath::Combined,
+      _ => MeshRenderPath::Individual,
+    }
+  }
+}
 
 pub struct MeshRenderer {
   vertex_buffer: Option<ResourceRc<Buffer>>,
@@ -41,9 +117,16 @@ pub struct MeshRenderer {
   index_format: IndexFormat,
   material: Arc<BuiltMaterial>,
   bindings: Vec<ResourceRc<BindGroup>>,
+  /// Transform of static meshes that are drawn individually, they have no uniform on their own.
+  static_uniform: Option<Uniform<TransformUniform>>,
+  /// Additional per object uniforms bound after the camera, transform and lights uniforms.
+  uniforms: Vec<GenericUniform>,
+  render_path: MeshRenderPath,
 }
 
 impl MeshRenderer {
+  /// Fails if the shaders of the material don't compile, e.g. because of an invalid custom node,
+  /// or the material expects other extra uniforms than the ones passed.
   pub fn new<
     T: MeshVertex + MeshVertexNormal + VertexStruct + Send + Sync + 'static,
     I: MeshIndex + Send + Sync + 'static,
@@ -52,69 +135,112 @@ impl MeshRenderer {
     mesh: &Mesh<T, I>,
     bindings: Vec<ResourceRc<BindGroup>>,
     transform: &Transform,
-  ) -> Self {
-    REGISTERED_SYSTEM.call_once(|| {
-      // Create texture nodes.
-      let color = TextureGeneratorHost::new(TextureSize::FullScreen, TextureFormat::Bgra8UnormSrgb);
-      let depth = TextureGeneratorHost::new(TextureSize::FullScreen, TextureFormat::Depth32Float);
-
-      PBR_MAIN_COLOR.set(color).ok().unwrap();
-      PBR_MAIN_DEPTH.set(depth).ok().unwrap();
-
-      // Add system if not added yet.
-      Core::get_instance()
-        .get_world()
-        .add_system_to_stage(CreatePBRFrameGraphSystem, SystemStage::Rendering);
-    });
+  ) -> Result<Self, MeshRendererError> {
+    Self::new_with_combining(material, mesh, bindings, Vec::new(), transform, true)
+  }
 
-    // Build material
-    let mut params = ShaderBuildParams::new();
-    params.add(ShaderOptionsMeshRenderer {
-      no_transform: matches!(transform.get().opt, TransformOptimization::Static),
-    });
-    let material = material.build(&params);
-
-    // Further processes
-    let (vertex_buffer, index_buffer, static_entry) = match transform.get().opt {
-      // For static we start merging the to be rendered object into a shared buffer.
-      TransformOptimization::Static => {
-        // Create group
-        let group = StaticRenderGroup {
-          material: material.clone(),
-          index_size: std::mem::size_of::<I>(),
-          bindings: bindings.clone(),
-        };
-
-        let mut mesh_groups = MERGED_MESH_GROUPS.lock();
-        let entry = if let Some(any_combiner) = mesh_groups.get(&group) {
-          let combiner = any_combiner
-            .as_any()
-            .downcast_ref::<StaticMeshCombiner<T, I>>()
-            .unwrap();
-          combiner.insert(mesh, transform).unwrap()
-        } else {
-          // No mesh combiner for this specific group found -> create a new one
-          let combiner = StaticMeshCombiner::<T, I>::new(1024, 16, 1024, 10 * 1024);
-          let entry = combiner.insert(mesh, transform).unwrap();
-
-          // Store mesh combiner for future objects of same group.
-          let boxed = Box::new(combiner);
-          mesh_groups.insert(group.clone(), boxed);
-
-          entry
-        };
-
-        (None, None, Some((group, entry)))
+  /// Same as `new` but static meshes are drawn individually instead of being merged into a
+  /// static mesh combiner, e.g. for huge meshes or ones that are frequently toggled.
+  pub fn new_uncombined<
+    T: MeshVertex + MeshVertexNormal + VertexStruct + Send + Sync + 'static,
+    I: MeshIndex + Send + Sync + 'static,
+  >(
+    material: &Material,
+    mesh: &Mesh<T, I>,
+    bindings: Vec<ResourceRc<BindGroup>>,
+    transform: &Transform,
+  ) -> Result<Self, MeshRendererError> {
+    Self::new_with_combining(material, mesh, bindings, Vec::new(), transform, false)
+  }
+
+  /// Same as `new_uncombined` but binds additional per object uniforms, e.g. a tint or an animation time.
+  /// The material graph has to add and use the matching uniforms after the ones of `PBRShaderNode`,
+  /// so that they are bound in order right after the camera, transform and lights uniforms.
+  /// Meshes with custom uniforms are always drawn individually.
+  pub fn new_with_uniforms<
+    T: MeshVertex + MeshVertexNormal + VertexStruct + Send + Sync + 'static,
+    I: MeshIndex + Send + Sync + 'static,
+  >(
+    material: &Material,
+    mesh: &Mesh<T, I>,
+    bindings: Vec<ResourceRc<BindGroup>>,
+    uniforms: Vec<GenericUniform>,
+    transform: &Transform,
+  ) -> Result<Self, MeshRendererError> {
+    Self::new_with_combining(material, mesh, bindings, uniforms, transform, false)
+  }
+
+  fn new_with_combining<
+    T: MeshVertex + MeshVertexNormal + VertexStruct + Send + Sync + 'static,
+    I: MeshIndex + Send + Sync + 'static,
+  >(
+    material: &Material,
+    mesh: &Mesh<T, I>,
+    bindings: Vec<ResourceRc<BindGroup>>,
+    uniforms: Vec<GenericUniform>,
+    transform: &Transform,
+    allow_combining: bool,
+  ) -> Result<Self, MeshRendererError> {
+    register_pbr_system();
+
+    // Build material, combined meshes are already transformed into world space.
+    let build = |render_path: MeshRenderPath| {
+      let mut params = ShaderBuildParams::new();
+      params.add(ShaderOptionsMeshRenderer {
+        no_transform: render_path == MeshRenderPath::Combined,
+        instance_location: None,
+      });
+      material.build(&params)
+    };
+    let mut render_path = MeshRenderPath::select(transform, allow_combining);
+    let mut material = build(render_path)?;
+
+    // For static we start merging the to be rendered object into a shared buffer.
+    // Meshes exceeding a whole generation of the group's combiner can only be drawn individually.
+    let mut static_entry = None;
+    if render_path == MeshRenderPath::Combined {
+      let group = StaticRenderGroup {
+        material: material.clone(),
+        index_size: std::mem::size_of::<I>(),
+        bindings: bindings.clone(),
+      };
+      match insert_into_static_group(&group, mesh, transform) {
+        Some(entry) => static_entry = Some((group, entry)),
+        None => {
+          warn!(
+            "Mesh with {} vertices and {} indices exceeds the static mesh combiner config and is drawn individually",
+            mesh.len_vertices(),
+            mesh.len_indices()
+          );
+          render_path = MeshRenderPath::Individual;
+          material = build(render_path)?;
+        }
       }
-      // For dynamic we need to create vertex and index buffers on the fly.
-      TransformOptimization::Dynamic => (
+    }
+    if render_path == MeshRenderPath::Individual {
+      check_extra_uniforms(&material, &uniforms)?;
+    }
+
+    // Static meshes drawn individually still need their transform as uniform.
+    let static_uniform = match (render_path, &transform.get().opt) {
+      (MeshRenderPath::Individual, TransformOptimization::Static) => {
+        Some(Uniform::new(TransformUniform {
+          matrix: transform.calculate_transform_matrix(),
+        }))
+      }
+      _ => None,
+    };
+
+    // For dynamic we need to create vertex and index buffers on the fly.
+    let (vertex_buffer, index_buffer) = match render_path {
+      MeshRenderPath::Combined => (None, None),
+      MeshRenderPath::Individual => (
         Some(mesh.build_vertex_buffer()),
         Some(mesh.build_index_buffer()),
-        None,
       ),
     };
 
-    Self {
+    Ok(Self {
       vertex_buffer,
       index_buffer,
       static_entry,
@@ -122,7 +248,189 @@ impl MeshRenderer {
       material,
       index_format: I::get_format(),
       bindings,
+      static_uniform,
+      uniforms,
+      render_path,
+    })
+  }
+
+  pub fn get_render_path(&self) -> MeshRenderPath {
+    self.render_path
+  }
+
+  /// Vertex buffer of individually drawn meshes, combined ones share the buffers of their combiner.
+  pub fn get_vertex_buffer(&self) -> Option<&ResourceRc<Buffer>> {
+    self.vertex_buffer.as_ref()
+  }
+
+  /// Index buffer of individually drawn meshes, combined ones share the buffers of their combiner.
+  pub fn get_index_buffer(&self) -> Option<&ResourceRc<Buffer>> {
+    self.index_buffer.as_ref()
+  }
+
+  /// Rebuilds the material and pipeline without respawning, e.g. to toggle a highlight at runtime.
+  /// Combined meshes are moved into the combiner group of the new material, which is why the mesh
+  /// and transform the renderer has been created with have to be passed again.
+  /// If the shaders fail to compile or the extra uniforms don't match the previous material is kept.
+  pub fn set_material<
+    T: MeshVertex + MeshVertexNormal + VertexStruct + Send + Sync + 'static,
+    I: MeshIndex + Send + Sync + 'static,
+  >(
+    &mut self,
+    material: &Material,
+    params: &ShaderBuildParams,
+    mesh: &Mesh<T, I>,
+    transform: &Transform,
+  ) -> Result<(), MeshRendererError> {
+    debug_assert_eq!(
+      mesh.len_indices() as u32,
+      self.indices,
+      "Mesh differs from the one the renderer has been created with"
+    );
+
+    let mut params = params.clone();
+    params.add(ShaderOptionsMeshRenderer {
+      no_transform: self.render_path == MeshRenderPath::Combined,
+      instance_location: None,
+    });
+    let material = material.build(&params)?;
+    if Arc::ptr_eq(&material, &self.material) {
+      return Ok(());
+    }
+    if self.render_path == MeshRenderPath::Individual {
+      check_extra_uniforms(&material, &self.uniforms)?;
+    }
+
+    if let Some((group, entry)) = self.static_entry.take() {
+      let new_group = StaticRenderGroup {
+        material: material.clone(),
+        ..group.clone()
+      };
+      let new_entry = match insert_into_static_group(&new_group, mesh, transform) {
+        Some(new_entry) => new_entry,
+        None => {
+          self.static_entry = Some((group, entry));
+          return Err(MeshRendererError::ExceedsCombiner {
+            vertices: mesh.len_vertices(),
+            indices: mesh.len_indices(),
+          });
+        }
+      };
+      remove_from_static_group::<T, I>(&group, entry);
+      self.static_entry = Some((new_group, new_entry));
+    }
+    self.material = material;
+    Ok(())
+  }
+
+  pub fn get_material(&self) -> &Arc<BuiltMaterial> {
+    &self.material
+  }
+
+  pub fn get_uniforms(&self) -> &[GenericUniform] {
+    &self.uniforms
+  }
+
+  /// Uniforms of individually drawn meshes in binding order,
+  /// the camera, transform and lights uniforms followed by the extra uniforms.
+  pub fn get_bound_uniforms(
+    &self,
+    camera: GenericUniform,
+    transform: &Transform,
+    lights: GenericUniform,
+  ) -> Vec<GenericUniform> {
+    let transform = self
+      .static_uniform
+      .as_ref()
+      .or_else(|| transform.uniform.as_ref())
+      .unwrap()
+      .as_generic();
+    vec![camera, transform, lights]
+      .into_iter()
+      .chain(self.uniforms.iter().cloned())
+      .collect()
+  }
+}
+
+/// Extra uniforms have to match the ones the material binds, otherwise drawing would fail later on.
+fn check_extra_uniforms(
+  material: &BuiltMaterial,
+  uniforms: &[GenericUniform],
+) -> Result<(), MeshRendererError> {
+  let expected = material.uniform_count().saturating_sub(PBR_UNIFORMS);
+  if expected != uniforms.len() {
+    return Err(MeshRendererError::ExtraUniformMismatch {
+      expected,
+      passed: uniforms.len(),
+    });
+  }
+  Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum MeshRendererError {
+  #[error(transparent)]
+  Material(#[from] MaterialError),
+  #[error("Material binds {expected} extra uniforms but {passed} have been passed")]
+  ExtraUniformMismatch { expected: usize, passed: usize },
+  #[error("Mesh with {vertices} vertices and {indices} indices exceeds the static mesh combiner of the material")]
+  ExceedsCombiner { vertices: usize, indices: usize },
+}
+
+/// Merges the mesh into the combiner of the group, the combiner is created on first use.
+/// `None` if the mesh exceeds a whole generation of the combiner, which keeps the config it has
+/// been created with even if the global config changed since.
+fn insert_into_static_group<
+  T: MeshVertex + MeshVertexNormal + VertexStruct + Send + Sync + 'static,
+  I: MeshIndex + Send + Sync + 'static,
+>(
+  group: &StaticRenderGroup,
+  mesh: &Mesh<T, I>,
+  transform: &Transform,
+) -> Option<StaticMeshCombinerEntry> {
+  let fits =
+    |config: StaticMeshCombinerConfig| config.fits::<I>(mesh.len_vertices(), mesh.len_indices());
+
+  let mut mesh_groups = MERGED_MESH_GROUPS.lock();
+  if let Some(any_combiner) = mesh_groups.get(group) {
+    let combiner = any_combiner
+      .as_any()
+      .downcast_ref::<StaticMeshCombiner<T, I>>()
+      .unwrap();
+    if !fits(combiner.get_config()) {
+      return None;
+    }
+    combiner.insert(mesh, transform)
+  } else {
+    // No mesh combiner for this specific group found -> create a new one
+    let config = StaticMeshCombinerConfig::global();
+    if !fits(config) {
+      return None;
     }
+    let combiner = StaticMeshCombiner::<T, I>::from_config(config);
+    let entry = combiner.insert(mesh, transform)?;
+
+    // Store mesh combiner for future objects of same group.
+    mesh_groups.insert(group.clone(), Box::new(combiner));
+    Some(entry)
+  }
+}
+
+/// Frees the space of an entry within the combiner of the group.
+fn remove_from_static_group<
+  T: MeshVertex + MeshVertexNormal + VertexStruct + Send + Sync + 'static,
+  I: MeshIndex + Send + Sync + 'static,
+>(
+  group: &StaticRenderGroup,
+  entry: StaticMeshCombinerEntry,
+) {
+  let mesh_groups = MERGED_MESH_GROUPS.lock();
+  if let Some(any_combiner) = mesh_groups.get(group) {
+    let combiner = any_combiner
+      .as_any()
+      .downcast_ref::<StaticMeshCombiner<T, I>>()
+      .unwrap();
+    combiner.remove(entry);
   }
 }
 
@@ -155,12 +463,68 @@ impl Eq for StaticRenderGroup {}
 lazy_static! {
   static ref MERGED_MESH_GROUPS: Mutex<HashMap<StaticRenderGroup, Box<dyn GenericStaticMeshCombiner + Send + Sync + 'static>>> =
     Mutex::new(HashMap::new());
+  static ref PBR_POST_PROCESSES: Mutex<Vec<Box<PBRPostProcess>>> = Mutex::new(Vec::new());
+}
+
+/// Adds nodes of a post effect onto the scene targets, called while the PBR frame graph is built.
+pub type PBRPostProcess = dyn Fn(&mut PBRPostProcessChain, &Camera) + Send + Sync + 'static;
+
+/// Adds a post effect to the default PBR frame graph, e.g. `DepthOfFieldNode` or `BloomNode`.
+/// Effects run in the order they have been added between the PBR pass and tonemapping,
+/// each one receives the main camera and the color of the previous one.
+pub fn add_pbr_post_process(
+  post_process: impl Fn(&mut PBRPostProcessChain, &Camera) + Send + Sync + 'static,
+) {
+  PBR_POST_PROCESSES.lock().push(Box::new(post_process));
+}
+
+/// Targets of the PBR pass post effects are chained onto, each one given as node and output.
+pub struct PBRPostProcessChain<'a> {
+  graph: &'a FrameGraph,
+  color: (Index, usize),
+  depth: (Index, usize),
+  bloom: Option<(Index, usize)>,
+}
+
+impl<'a> PBRPostProcessChain<'a> {
+  pub fn get_graph(&self) -> &'a FrameGraph {
+    self.graph
+  }
+
+  /// Scene color so far, in the HDR format of `CoreConfig::hdr` until tonemapped.
+  pub fn get_color(&self) -> (Index, usize) {
+    self.color
+  }
+
+  /// Depth drawn by the PBR pass.
+  pub fn get_depth(&self) -> (Index, usize) {
+    self.depth
+  }
+
+  /// Continues the chain with the output of an effect that processed the current color.
+  pub fn set_color(&mut self, node: Index, output: usize) {
+    self.color = (node, output);
+  }
+
+  /// Texture added onto the scene while tonemapping, see `TonemapNode::INPUT_BLOOM`.
+  /// Ignored without HDR as there is no tonemapping.
+  pub fn set_bloom(&mut self, node: Index, output: usize) {
+    self.bloom = Some((node, output));
+  }
+
+  /// Connects an output of the chain, e.g. `get_color`, into an input of an effect's node.
+  pub fn connect(&self, (source, output): (Index, usize), node: Index, input: usize) {
+    self.graph.connect(source, output, node, input).unwrap();
+  }
 }
 
 #[derive(Hash)]
 pub(crate) struct ShaderOptionsMeshRenderer {
   /// Disables transform matrix transformation
   pub(crate) no_transform: bool,
+  /// First shader location of the per-instance transform read from the instance buffer,
+  /// only set for instanced meshes.
+  pub(crate) instance_location: Option<usize>,
 }
 
 #[system]
@@ -169,13 +533,15 @@ pub(crate) struct ShaderOptionsMeshRenderer {
 #[read_component(MainCameraTag)]
 #[read_component(Camera)]
 #[read_component(BoundingShape)]
+#[read_component(ScreenSpace)]
 #[read_component(LightManager)]
+#[read_component(InstancedMesh)]
 pub fn create_pbr_frame_graph(world: &mut SubWorld) {
   optick::event!("create_pbr_frame_graph");
 
   // Get main camera and its frame node.
   let mut main_cam_frustum = [Vector4::<f32>::new(0.0, 0.0, 0.0, 0.0); 6];
-  let (main_cam_uniform, main_cam_eye) = {
+  let (main_cam_uniform, main_cam_eye, depth_mode) = {
     let mut main_cam_query = <(&Camera, &MainCameraTag)>::query();
     let main_cam = main_cam_query.iter(world).next();
     if main_cam.is_none() {
@@ -183,7 +549,11 @@ pub fn create_pbr_frame_graph(world: &mut SubWorld) {
     }
     let (main_cam, _) = main_cam.unwrap();
     main_cam.calculate_frustum_planes(&mut main_cam_frustum);
-    (main_cam.uniform.clone(), main_cam.position)
+    (
+      main_cam.uniform.clone(),
+      main_cam.position,
+      main_cam.depth_mode,
+    )
   };
 
   // Query light manager.
@@ -197,68 +567,122 @@ pub fn create_pbr_frame_graph(world: &mut SubWorld) {
   };
 
   // Query all meshes
-  let mut objs_query = <(&mut MeshRenderer, &Transform, &BoundingShape)>::query();
+  let mut objs_query = <(
+    &mut MeshRenderer,
+    &Transform,
+    Option<&BoundingShape>,
+    Option<&ScreenSpace>,
+  )>::query();
 
   // Query all relevant visible meshes and calculate cam distance for later depth based sorting.
   let ready_entities = objs_query
     .par_iter_mut(world)
     // Filter out invisible meshes and calculate their distance to camera.
-    .filter_map(|(obj, transform, bshape)| {
+    .filter_map(|(obj, transform, bshape, screen_space)| {
       // Remove out of frustum
-      if !bshape.visible_in_frustum(&main_cam_frustum) {
+      let screen_space = screen_space.is_some();
+      if !passes_frustum_culling(bshape, screen_space, &main_cam_frustum) {
         return None;
       }
 
       // Calculate distance
       let distance = transform.get().position.distance(main_cam_eye).abs();
-      Some((obj, transform, distance))
+      Some((obj, transform, distance, screen_space))
     })
     .collect::<Vec<_>>();
 
-  // Query all static static meshes
-  let static_objs = ready_entities
-    .iter()
-    .filter(|(_, transform, _)| matches!(transform.get().opt, TransformOptimization::Static));
+  // Screen space objects are rendered within a separate overlay pass.
+  let (overlay_entities, world_entities): (Vec<_>, Vec<_>) = ready_entities
+    .into_iter()
+    .partition(|(_, _, _, screen_space)| *screen_space);
 
-  let static_groups = static_objs
-    .group_by(|(obj, _, _)| obj.static_entry.as_ref().unwrap().0.clone())
+  let build_static_groups = |entities: &[(&mut MeshRenderer, &Transform, f32, bool)],
+                             camera: &Uniform<CameraUniform>| {
+    group_adjacent_by(
+      entities
+        .iter()
+        .filter(|(obj, _, _, _)| obj.render_path == MeshRenderPath::Combined),
+      |(obj, _, _, _)| obj.static_entry.as_ref().unwrap().0.clone(),
+    )
     .into_iter()
     .map(|(group, entries)| StaticRenderDrawGroup {
       group,
       entries: entries
-        .map(|(obj, _, _)| obj.static_entry.as_ref().unwrap().1.clone())
+        .into_iter()
+        .map(|(obj, _, _, _)| obj.static_entry.as_ref().unwrap().1.clone())
         .collect_vec(),
-      system_uniforms: vec![main_cam_uniform.as_generic(), light_manager_uniform.clone()],
+      system_uniforms: vec![camera.as_generic(), light_manager_uniform.clone()],
     })
-    .collect_vec();
+    .collect_vec()
+  };
 
   // Query all dynamic meshes and put them into render graph node as dynamic nodes.
-  let dyn_objs = ready_entities
-    .iter()
-    .filter(|(_, transform, _)| matches!(transform.get().opt, TransformOptimization::Dynamic));
+  let build_dynamic_groups = |entities: &[(&mut MeshRenderer, &Transform, f32, bool)],
+                              camera: &Uniform<CameraUniform>| {
+    // Build logical grouping by material.
+    let material_grouped = entities
+      .iter()
+      .filter(|(obj, _, _, _)| obj.render_path == MeshRenderPath::Individual)
+      .into_group_map_by(|(obj, _, _, _)| obj.material.clone());
 
-  // Build logical grouping by material.
-  let material_grouped = dyn_objs.into_group_map_by(|(obj, _, _)| obj.material.clone());
+    material_grouped
+      .iter()
+      .map(|(material, objs)| RenderGroup {
+        pipeline: material.pbr_pipeline.clone(),
+        objects: objs
+          .iter()
+          .map(|(obj, transform, _distance, _)| SingleRenderObject {
+            index_format: obj.index_format,
+            vertex_buffer: obj.vertex_buffer.clone().unwrap(),
+            index_buffer: obj.index_buffer.clone().unwrap(),
+            indices: obj.indices,
+            uniforms: obj.get_bound_uniforms(
+              camera.as_generic(),
+              transform,
+              light_manager_uniform.clone(),
+            ),
+            bindings: obj.bindings.clone(),
+          })
+          .collect::<Vec<_>>(),
+      })
+      .collect::<Vec<_>>()
+  };
 
-  let render_groups = material_grouped
-    .iter()
-    .map(|(material, objs)| RenderGroup {
-      pipeline: material.pbr_pipeline.clone(),
-      objects: objs
-        .iter()
-        .map(|(obj, transform, _distance)| SingleRenderObject {
-          index_format: obj.index_format,
-          vertex_buffer: obj.vertex_buffer.clone().unwrap(),
-          index_buffer: obj.index_buffer.clone().unwrap(),
-          indices: obj.indices,
-          uniforms: vec![
-            main_cam_uniform.as_generic(),
-            transform.uniform.as_ref().unwrap().as_generic(),
-            light_manager_uniform.clone(),
-          ],
-          bindings: obj.bindings.clone(),
-        })
-        .collect::<Vec<_>>(),
+  let static_groups = build_static_groups(&world_entities, &main_cam_uniform);
+  let render_groups = build_dynamic_groups(&world_entities, &main_cam_uniform);
+
+  // Screen space objects are placed in pixels instead of being seen through the main camera.
+  let overlay_cam_uniform = PBR_OVERLAY_CAMERA.get().unwrap();
+  if !overlay_entities.is_empty() {
+    let size = Core::get_instance().get_swap_chain_size();
+    let overlay_camera = CameraUniform::screen_space(size, depth_mode);
+    if overlay_cam_uniform.get().projection != overlay_camera.projection {
+      *overlay_cam_uniform.get_mut() = overlay_camera;
+    }
+  }
+  let overlay_static_groups = build_static_groups(&overlay_entities, overlay_cam_uniform);
+  let overlay_groups = build_dynamic_groups(&overlay_entities, overlay_cam_uniform);
+  let has_overlay = !overlay_static_groups.is_empty() || !overlay_groups.is_empty();
+
+  // Query all instanced meshes, each one results in a single instanced draw.
+  let instanced_objects = <&InstancedMesh>::query()
+    .iter(world)
+    .filter_map(|mesh| {
+      let draw = mesh.draw_call()?;
+      Some(InstancedRenderObject {
+        pipeline: mesh.material.pbr_pipeline.clone(),
+        vertex_buffer: mesh.vertex_buffer.clone(),
+        index_buffer: mesh.index_buffer.clone(),
+        index_format: mesh.index_format,
+        instance_buffer: mesh.instance_buffer.get_accessor(),
+        uniforms: vec![
+          main_cam_uniform.as_generic(),
+          mesh.uniform.as_generic(),
+          light_manager_uniform.clone(),
+        ],
+        bindings: mesh.bindings.clone(),
+        draw,
+      })
     })
     .collect::<Vec<_>>();
 
@@ -276,13 +700,63 @@ pub fn create_pbr_frame_graph(world: &mut SubWorld) {
     PBRRenderGraphNode {
       dynamic_groups: render_groups,
       static_groups,
+      instanced_objects,
+      overlay_dynamic_groups: overlay_groups,
+      overlay_static_groups,
+      depth_mode,
     },
     "pbr_main_node",
   );
+
+  // Post effects are chained onto the scene before tonemapping.
+  let mut chain = PBRPostProcessChain {
+    graph: frame_graph,
+    color: (pbr_node, PBRRenderGraphNode::OUTPUT_COLOR),
+    depth: (pbr_node, PBRRenderGraphNode::OUTPUT_DEPTH),
+    bloom: None,
+  };
+  if let Some((main_cam, _)) = <(&Camera, &MainCameraTag)>::query().iter(world).next() {
+    for post_process in PBR_POST_PROCESSES.lock().iter() {
+      post_process(&mut chain, main_cam);
+    }
+  }
+  let (color_node, color_output) = chain.color;
+  let bloom = chain.bloom;
+
+  // HDR targets are tonemapped into an sRGB target before being presented.
+  let (present_node, present_output) = match PBR_MAIN_LDR.get() {
+    Some(ldr) => {
+      let pbr_main_ldr = frame_graph.add_node(ldr.create_node(), "pbr_main_ldr");
+      let tonemap = frame_graph.add_node(TonemapNode::new(), "pbr_tonemap");
+      frame_graph
+        .connect(
+          color_node,
+          color_output,
+          tonemap,
+          TonemapNode::INPUT_TEXTURE,
+        )
+        .unwrap();
+      if let Some((bloom_node, bloom_output)) = bloom {
+        frame_graph
+          .connect(bloom_node, bloom_output, tonemap, TonemapNode::INPUT_BLOOM)
+          .unwrap();
+      }
+      frame_graph
+        .connect(
+          pbr_main_ldr,
+          TextureGeneratorNode::OUTPUT_TEXTURE,
+          tonemap,
+          TonemapNode::INPUT_TARGET,
+        )
+        .unwrap();
+      (tonemap, TonemapNode::OUTPUT_TEXTURE)
+    }
+    None => (color_node, color_output),
+  };
   frame_graph
     .connect(
-      pbr_node,
-      PBRRenderGraphNode::OUTPUT_COLOR,
+      present_node,
+      present_output,
       frame_graph.get_end_node(),
       PresentToScreen::INPUT_TEXTURE,
     )
@@ -303,6 +777,20 @@ pub fn create_pbr_frame_graph(world: &mut SubWorld) {
       PBRRenderGraphNode::INPUT_DEPTH,
     )
     .unwrap();
+  if has_overlay {
+    let pbr_overlay_depth = frame_graph.add_node(
+      PBR_OVERLAY_DEPTH.get().unwrap().create_node(),
+      "pbr_overlay_depth",
+    );
+    frame_graph
+      .connect(
+        pbr_overlay_depth,
+        TextureGeneratorNode::OUTPUT_TEXTURE,
+        pbr_node,
+        PBRRenderGraphNode::INPUT_OVERLAY_DEPTH,
+      )
+      .unwrap();
+  }
 }
 struct CreatePBRFrameGraphSystem;
 impl SystemFactory for CreatePBRFrameGraphSystem {
@@ -314,6 +802,10 @@ impl SystemFactory for CreatePBRFrameGraphSystem {
 struct PBRRenderGraphNode {
   dynamic_groups: Vec<RenderGroup>,
   static_groups: Vec<StaticRenderDrawGroup>,
+  instanced_objects: Vec<InstancedRenderObject>,
+  overlay_dynamic_groups: Vec<RenderGroup>,
+  overlay_static_groups: Vec<StaticRenderDrawGroup>,
+  depth_mode: DepthMode,
 }
 
 struct StaticRenderDrawGroup {
@@ -335,68 +827,164 @@ struct SingleRenderObject {
   indices: u32,
 }
 
+struct InstancedRenderObject {
+  pipeline: ResourceRc<RenderPipeline>,
+  vertex_buffer: ResourceRc<Buffer>,
+  index_buffer: ResourceRc<Buffer>,
+  index_format: IndexFormat,
+  instance_buffer: StagedBufferAccessor,
+  uniforms: Vec<GenericUniform>,
+  bindings: Vec<ResourceRc<BindGroup>>,
+  draw: InstancedDrawCall,
+}
+
 impl PBRRenderGraphNode {
   pub const INPUT_COLOR: usize = 0;
   pub const INPUT_DEPTH: usize = 1;
+  /// Only connected while there are screen space objects.
+  pub const INPUT_OVERLAY_DEPTH: usize = 2;
   pub const OUTPUT_COLOR: usize = 0;
+  pub const OUTPUT_DEPTH: usize = 1;
+}
+
+fn access_static_uniforms<'a>(
+  groups: &'a [StaticRenderDrawGroup],
+  encoder: &mut CommandEncoder,
+) -> Vec<Vec<&'a PubUniformResources>> {
+  groups
+    .iter()
+    .map(|group| {
+      group
+        .system_uniforms
+        .iter()
+        .map(|uniform| uniform.get_resources(encoder))
+        .collect::<Vec<_>>()
+    })
+    .collect::<Vec<_>>()
+}
+
+fn access_dynamic_uniforms<'a>(
+  groups: &'a [RenderGroup],
+  encoder: &mut CommandEncoder,
+) -> Vec<Vec<Vec<&'a PubUniformResources>>> {
+  groups
+    .iter()
+    .map(|group| {
+      group
+        .objects
+        .iter()
+        .map(|obj| {
+          obj
+            .uniforms
+            .iter()
+            .map(|uniform| uniform.get_resources(encoder))
+            .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>()
+    })
+    .collect::<Vec<_>>()
+}
+
+fn render_static_groups(
+  rp: &mut RenderPassCommandEncoder,
+  groups: &[StaticRenderDrawGroup],
+  static_uniforms: &[Vec<&PubUniformResources>],
+) {
+  optick::event!("FrameGraph::PBR::RenderPassStatic");
+  let mesh_combiners = MERGED_MESH_GROUPS.lock();
+
+  for (group_index, group) in groups.iter().enumerate() {
+    // Prepare shared rendering.
+    let combiner = mesh_combiners.get(&group.group).unwrap();
+    rp.set_pipeline(group.group.material.pbr_pipeline.clone());
+
+    // Set bind groups.
+    let uniforms = &static_uniforms[group_index];
+    for (index, res) in uniforms.iter().enumerate() {
+      rp.set_bind_group(index as u32, res.bind_group.clone());
+    }
+    for (index, bind_group) in group.group.bindings.iter().enumerate() {
+      rp.set_bind_group(index as u32 + uniforms.len() as u32, bind_group.clone());
+    }
+
+    // Build optimized draw calls for static meshes.
+    combiner.merged_draw(&group.entries, rp);
+  }
+}
+
+fn render_dynamic_groups(
+  rp: &mut RenderPassCommandEncoder,
+  groups: &[RenderGroup],
+  uniforms: &[Vec<Vec<&PubUniformResources>>],
+) {
+  for (group_index, group) in groups.iter().enumerate() {
+    rp.set_pipeline(group.pipeline.clone());
+    for (object_index, object) in group.objects.iter().enumerate() {
+      // Do the rendering in order.
+      rp.set_vertex_buffer(object.vertex_buffer.clone());
+      rp.set_index_buffer(object.index_buffer.clone(), object.index_format);
+      for (index, _uniform) in object.uniforms.iter().enumerate() {
+        rp.set_bind_group(
+          index as u32,
+          uniforms[group_index][object_index][index]
+            .bind_group
+            .clone(),
+        );
+      }
+      for (index, bind_group) in object.bindings.iter().enumerate() {
+        rp.set_bind_group(
+          object.uniforms.len() as u32 + index as u32,
+          bind_group.clone(),
+        );
+      }
+      rp.render_indexed(0..object.indices);
+    }
+  }
 }
 
 impl FrameGraphNode for PBRRenderGraphNode {
   fn execute(
     &self,
     inputs: &[Option<FrameNodeValue>],
-    _outputs: &mut [Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
     encoder: &mut CommandEncoder,
   ) {
     optick::event!("FrameGraph::PBR");
 
     // Access uniforms
-    let uniforms = self
-      .dynamic_groups
-      .iter()
-      .map(|group| {
-        group
-          .objects
-          .iter()
-          .map(|obj| {
-            obj
-              .uniforms
-              .iter()
-              .map(|uniform| uniform.get_resources(encoder))
-              .collect::<Vec<_>>()
-          })
-          .collect::<Vec<_>>()
-      })
-      .collect::<Vec<_>>();
+    let uniforms = access_dynamic_uniforms(&self.dynamic_groups, encoder);
+    let static_uniforms = access_static_uniforms(&self.static_groups, encoder);
+    let overlay_uniforms = access_dynamic_uniforms(&self.overlay_dynamic_groups, encoder);
+    let overlay_static_uniforms = access_static_uniforms(&self.overlay_static_groups, encoder);
 
     // Create render pass.
+    let color = &inputs[Self::INPUT_COLOR]
+      .as_ref()
+      .unwrap()
+      .get_sampled_texture()
+      .view;
+    let depth = &inputs[Self::INPUT_DEPTH]
+      .as_ref()
+      .unwrap()
+      .get_sampled_texture()
+      .view;
     let mut rpb = RenderPassCommandEncoderBuilder::new("pbr_rp");
-    rpb.add_color_output(
-      &inputs[Self::INPUT_COLOR]
-        .as_ref()
-        .unwrap()
-        .get_sampled_texture()
-        .view,
-      Vector4::new(1.0, 1.0, 1.0, 1.0),
-    );
-    rpb.add_depth(
-      &inputs[Self::INPUT_DEPTH]
-        .as_ref()
-        .unwrap()
-        .get_sampled_texture()
-        .view,
-    );
+    rpb.add_color_output(color, Vector4::new(1.0, 1.0, 1.0, 1.0));
+    rpb.add_depth(depth);
+    rpb.set_depth_mode(self.depth_mode);
 
-    // Access uniforms
-    let static_uniforms = self
-      .static_groups
+    // Upload instance data and access uniforms of instanced meshes.
+    let instanced_resources = self
+      .instanced_objects
       .iter()
-      .map(|group| {
-        group
-          .system_uniforms
+      .map(|obj| {
+        let instance_buffer = obj.instance_buffer.get_resources(encoder).clone();
+        let uniforms = obj
+          .uniforms
           .iter()
           .map(|uniform| uniform.get_resources(encoder))
-          .collect::<Vec<_>>()
+          .collect::<Vec<_>>();
+        (instance_buffer, uniforms)
       })
       .collect::<Vec<_>>();
 
@@ -405,52 +993,77 @@ impl FrameGraphNode for PBRRenderGraphNode {
       let mut rp = encoder.create_render_pass_encoder(rpb);
 
       // Render static
+      render_static_groups(&mut rp, &self.static_groups, &static_uniforms);
+
+      // Render dynamic
+      render_dynamic_groups(&mut rp, &self.dynamic_groups, &uniforms);
+
+      // Render instanced
+      for (object, (instance_buffer, uniforms)) in self
+        .instanced_objects
+        .iter()
+        .zip(instanced_resources.iter())
       {
-        optick::event!("FrameGraph::PBR::RenderPassStatic");
-        let mesh_combiners = MERGED_MESH_GROUPS.lock();
-
-        for (group_index, group) in self.static_groups.iter().enumerate() {
-          // Prepare shared rendering.
-          let combiner = mesh_combiners.get(&group.group).unwrap();
-          rp.set_pipeline(group.group.material.pbr_pipeline.clone());
-
-          // Set bind groups.
-          let uniforms = &static_uniforms[group_index];
-          for (index, res) in uniforms.iter().enumerate() {
-            rp.set_bind_group(index as u32, res.bind_group.clone());
-          }
-          for (index, bind_group) in group.group.bindings.iter().enumerate() {
-            rp.set_bind_group(index as u32 + uniforms.len() as u32, bind_group.clone());
-          }
-
-          // Build optimized draw calls for static meshes.
-          combiner.merged_draw(&group.entries, &mut rp);
+        rp.set_pipeline(object.pipeline.clone());
+        rp.set_vertex_buffer(object.vertex_buffer.clone());
+        rp.set_vertex_buffer_slot(1, instance_buffer.clone());
+        rp.set_index_buffer(object.index_buffer.clone(), object.index_format);
+        for (index, res) in uniforms.iter().enumerate() {
+          rp.set_bind_group(index as u32, res.bind_group.clone());
         }
-      }
-
-      for (group_index, group) in self.dynamic_groups.iter().enumerate() {
-        rp.set_pipeline(group.pipeline.clone());
-        for (object_index, object) in group.objects.iter().enumerate() {
-          // Do the rendering in order.
-          rp.set_vertex_buffer(object.vertex_buffer.clone());
-          rp.set_index_buffer(object.index_buffer.clone(), object.index_format);
-          for (index, _uniform) in object.uniforms.iter().enumerate() {
-            rp.set_bind_group(
-              index as u32,
-              uniforms[group_index][object_index][index]
-                .bind_group
-                .clone(),
-            );
-          }
-          for (index, bind_group) in object.bindings.iter().enumerate() {
-            rp.set_bind_group(
-              object.uniforms.len() as u32 + index as u32,
-              bind_group.clone(),
-            );
-          }
-          rp.render_indexed(0..object.indices);
+        for (index, bind_group) in object.bindings.iter().enumerate() {
+          rp.set_bind_group(uniforms.len() as u32 + index as u32, bind_group.clone());
         }
+        rp.render_indexed_instanced(object.draw.indices.clone(), object.draw.instances.clone());
       }
     }
+
+    // Screen space objects are drawn on top of the scene using their own depth target,
+    // the scene depth stays untouched for later passes.
+    if !self.overlay_dynamic_groups.is_empty() || !self.overlay_static_groups.is_empty() {
+      optick::event!("FrameGraph::PBR::OverlayRenderPass");
+      let overlay_depth = &inputs[Self::INPUT_OVERLAY_DEPTH]
+        .as_ref()
+        .unwrap()
+        .get_sampled_texture()
+        .view;
+      let mut rpb = RenderPassCommandEncoderBuilder::new("pbr_overlay_rp");
+      rpb.add_color_output_preserved(color);
+      rpb.add_depth(overlay_depth);
+      rpb.set_depth_mode(self.depth_mode);
+
+      let mut rp = encoder.create_render_pass_encoder(rpb);
+      render_static_groups(
+        &mut rp,
+        &self.overlay_static_groups,
+        &overlay_static_uniforms,
+      );
+      render_dynamic_groups(&mut rp, &self.overlay_dynamic_groups, &overlay_uniforms);
+    }
+
+    // Post effects read the scene targets once this pass is done.
+    outputs[Self::OUTPUT_COLOR] = inputs[Self::INPUT_COLOR].clone();
+    outputs[Self::OUTPUT_DEPTH] = inputs[Self::INPUT_DEPTH].clone();
+  }
+}
+
+/// Marks an entity as screen space overlay, e.g. HUD meshes or gizmos.
+/// Such entities are never frustum culled and are drawn on top of the scene in a separate pass.
+/// Their transforms are in pixels starting at the top left of the screen.
+pub struct ScreenSpace;
+
+/// Whether an entity has to be rendered given the frustum planes of the main camera.
+/// Entities without bounding shape are only rendered in screen space.
+pub fn passes_frustum_culling(
+  bshape: Option<&BoundingShape>,
+  screen_space: bool,
+  frustum: &[Vector4<f32>; 6],
+) -> bool {
+  if screen_space {
+    return true;
   }
+
+  bshape
+    .map(|bshape| bshape.visible_in_frustum(frustum))
+    .unwrap_or(false)
 }
@@ -0,0 +1,137 @@
+use moonwave_common::*;
+use moonwave_core::{Core, ShaderKind, FULLSCREEN_TRIANGLE_VS};
+use moonwave_render::{CommandEncoder, RenderPassCommandEncoderBuilder};
+use moonwave_resources::{
+  BindGroup, BindGroupDescriptor, BindGroupLayout, BindGroupLayoutDescriptor,
+  BindGroupLayoutEntryType, PipelineLayoutDescriptor, RenderPipeline, RenderPipelineDescriptor,
+  ResourceRc, SampledTexture, Sampler, TextureView,
+};
+use moonwave_shader::{ShaderNode, UniformStruct, WorldPosFromDepthNode};
+use parking_lot::Mutex;
+
+use crate::{Uniform, MATERIAL_UNIFORM_LAYOUT};
+
+/// Line of a screen effect shader that is replaced by `world_from_depth`, see `screen_effect_shader`.
+pub const WORLD_FROM_DEPTH_INCLUDE: &str = "#include <world_from_depth>";
+
+/// Resolves the includes of a screen effect fragment shader.
+/// `WORLD_FROM_DEPTH_INCLUDE` becomes `vec3 world_from_depth(vec2 uv, float depth, mat4 inv_projection_view)`,
+/// generated through `WorldPosFromDepthNode`.
+pub fn screen_effect_shader(source: &str) -> String {
+  let mut body = String::new();
+  WorldPosFromDepthNode::new().generate(
+    &[
+      Some("depth".to_string()),
+      Some("uv".to_string()),
+      Some("inv_projection_view".to_string()),
+    ],
+    &[Some("world".to_string())],
+    &mut body,
+  );
+  let function = format!(
+    "vec3 world_from_depth(vec2 uv, float depth, mat4 inv_projection_view) {{\n{}return world;\n}}",
+    body
+  );
+  source.replace(WORLD_FROM_DEPTH_INCLUDE, &function)
+}
+
+/// Full screen pass of post effects reading several targets of the same frame, e.g. color and depth.
+/// The uniform is bound to set 0, the textures followed by an unfiltered sampler to set 1.
+/// The pipeline is shared while every node brings its own uniform, as all writes of a frame graph
+/// level are uploaded together and nodes of the same level would overwrite each others settings.
+pub(crate) struct ScreenEffectPass {
+  label: &'static str,
+  pipeline: ResourceRc<RenderPipeline>,
+  textures_layout: ResourceRc<BindGroupLayout>,
+  sampler: ResourceRc<Sampler>,
+  /// Bind group of the previous frame, reused as long as the targets stay the same.
+  textures: Mutex<Option<(Vec<ResourceRc<TextureView>>, ResourceRc<BindGroup>)>>,
+}
+
+impl ScreenEffectPass {
+  pub fn new(label: &'static str, fragment_shader: &str, textures: usize) -> Self {
+    let core = Core::get_instance();
+    let vs = core
+      .create_shader_from_glsl(
+        FULLSCREEN_TRIANGLE_VS,
+        &format!("{}VS", label),
+        ShaderKind::Vertex,
+      )
+      .unwrap();
+    let fs = core
+      .create_shader_from_glsl(
+        &screen_effect_shader(fragment_shader),
+        &format!("{}FS", label),
+        ShaderKind::Fragment,
+      )
+      .unwrap();
+
+    // Depth can't be filtered so all targets are sampled unfiltered.
+    let textures_layout = core.create_bind_group_layout(
+      (0..textures)
+        .fold(BindGroupLayoutDescriptor::new(), |desc, binding| {
+          desc.add_unfiltered_entry(binding as u32, BindGroupLayoutEntryType::SingleTexture)
+        })
+        .add_unfiltered_entry(textures as u32, BindGroupLayoutEntryType::Sampler),
+    );
+    let layout = core.create_pipeline_layout(
+      PipelineLayoutDescriptor::new()
+        .add_binding(MATERIAL_UNIFORM_LAYOUT.clone())
+        .add_binding(textures_layout.clone()),
+    );
+    let pipeline = core
+      .create_render_pipeline(
+        RenderPipelineDescriptor::new_without_vertices(layout, vs, fs)
+          .add_color_output(core.get_color_target_format()),
+      )
+      .unwrap();
+
+    Self {
+      label,
+      pipeline,
+      textures_layout,
+      sampler: core.create_sampler(),
+      textures: Mutex::new(None),
+    }
+  }
+
+  fn get_textures_bind_group(&self, views: Vec<ResourceRc<TextureView>>) -> ResourceRc<BindGroup> {
+    let mut cached = self.textures.lock();
+    if let Some((cached_views, bind_group)) = cached.as_ref() {
+      if *cached_views == views {
+        return bind_group.clone();
+      }
+    }
+
+    let desc = views.iter().enumerate().fold(
+      BindGroupDescriptor::new(self.textures_layout.clone()),
+      |desc, (binding, view)| desc.add_texture_binding(binding as u32, view.clone()),
+    );
+    let bind_group = Core::get_instance()
+      .create_bind_group(desc.add_sampler_binding(views.len() as u32, self.sampler.clone()));
+    *cached = Some((views, bind_group.clone()));
+    bind_group
+  }
+
+  /// Draws the effect of the given textures into the target.
+  pub fn render<U: UniformStruct + Send + Sync + 'static>(
+    &self,
+    encoder: &mut CommandEncoder,
+    uniform: &Uniform<U>,
+    views: Vec<ResourceRc<TextureView>>,
+    target: &SampledTexture,
+  ) {
+    let uniform = uniform.as_generic();
+    let uniform = uniform.get_resources(encoder);
+    let textures = self.get_textures_bind_group(views);
+
+    let mut rpb = RenderPassCommandEncoderBuilder::new(self.label);
+    rpb.add_color_output(&target.view, Vector4::new(0.0, 0.0, 0.0, 1.0));
+
+    let mut rp = encoder.create_render_pass_encoder(rpb);
+    rp.set_pipeline(self.pipeline.clone());
+    rp.set_bind_group(0, uniform.bind_group.clone());
+    rp.set_bind_group(1, textures);
+    rp.render(0..3);
+  }
+}
@@ -0,0 +1,400 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use legion::world::SubWorld;
+use legion::IntoQuery;
+use moonwave_common::*;
+use moonwave_core::{
+  optick, system, warn, Core, OnceCell, PresentToScreen, ShaderKind, SystemFactory, SystemStage,
+  TextureGeneratorHost, TextureGeneratorNode, TextureSize, WrappedSystem,
+};
+use moonwave_render::{
+  CommandEncoder, FrameGraphNode, FrameNodeValue, RenderPassCommandEncoderBuilder,
+};
+use moonwave_resources::{
+  BindGroup, BufferUsage, PipelineLayoutDescriptor, RenderPipeline, RenderPipelineDescriptor,
+  ResourceRc, SampledTexture, TextureFormat, VertexAttribute, VertexAttributeFormat, VertexBuffer,
+};
+use parking_lot::Mutex;
+
+use crate::{
+  Camera, GenericUniform, MainCameraTag, StagedBuffer, StagedBufferAccessor, TransformUniform,
+  Uniform, MATERIAL_UNIFORM_LAYOUT,
+};
+
+static REGISTERED_SYSTEM: std::sync::Once = std::sync::Once::new();
+static SPRITE_RESOURCES: OnceCell<SpriteResources> = OnceCell::new();
+
+/// A textured quad, positioned in world units with its origin at the bottom left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprite {
+  pub position: Vector2<f32>,
+  pub size: Vector2<f32>,
+  /// Texture coordinates of the top left corner followed by the bottom right corner.
+  pub uv_rect: Vector4<f32>,
+  pub tint: Vector4<f32>,
+}
+
+impl Sprite {
+  pub fn new(position: Vector2<f32>, size: Vector2<f32>) -> Self {
+    Self {
+      position,
+      size,
+      uv_rect: Vector4::new(0.0, 0.0, 1.0, 1.0),
+      tint: Vector4::new(1.0, 1.0, 1.0, 1.0),
+    }
+  }
+
+  /// Only samples the given part of the texture, e.g. a single frame of a sprite sheet.
+  pub fn with_uv_rect(mut self, min: Vector2<f32>, max: Vector2<f32>) -> Self {
+    self.uv_rect = Vector4::new(min.x, min.y, max.x, max.y);
+    self
+  }
+
+  pub fn with_tint(mut self, tint: Vector4<f32>) -> Self {
+    self.tint = tint;
+    self
+  }
+}
+
+/// Per-instance data of a sprite, the quad itself is generated within the vertex shader.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpriteInstance {
+  /// Bottom left corner followed by the size of the quad.
+  pub rect: [f32; 4],
+  pub uv_rect: [f32; 4],
+  pub tint: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for SpriteInstance {}
+unsafe impl bytemuck::Zeroable for SpriteInstance {}
+
+impl From<&Sprite> for SpriteInstance {
+  fn from(sprite: &Sprite) -> Self {
+    Self {
+      rect: [
+        sprite.position.x,
+        sprite.position.y,
+        sprite.size.x,
+        sprite.size.y,
+      ],
+      uv_rect: sprite.uv_rect.into(),
+      tint: sprite.tint.into(),
+    }
+  }
+}
+
+/// Vertices of the two triangles generated for every sprite instance.
+pub const SPRITE_VERTICES: Range<u32> = 0..6;
+
+/// Instance ranges of consecutive sprite groups with the given amount of sprites each.
+pub fn sprite_instance_ranges(group_sizes: impl IntoIterator<Item = usize>) -> Vec<Range<u32>> {
+  let mut start = 0;
+  group_sizes
+    .into_iter()
+    .map(|sprites| {
+      let end = start + sprites as u32;
+      let range = start..end;
+      start = end;
+      range
+    })
+    .collect()
+}
+
+/// Accumulates sprites and renders them with one draw call per texture.
+/// Sprites sharing a texture are drawn in the order they have been pushed, groups are drawn
+/// in the order their texture has first been used. Sprite batches are rendered with the
+/// projection of the main camera, see `Camera::with_orthographic` for 2D scenes.
+/// Every sprite is a single instance, its quad is generated within the vertex shader.
+pub struct SpriteBatch {
+  capacity: usize,
+  groups: Vec<(SampledTexture, Vec<Sprite>)>,
+  dirty: AtomicBool,
+  instance_buffer: StagedBuffer<SpriteInstance>,
+  draw_calls: Mutex<Vec<(ResourceRc<BindGroup>, Range<u32>)>>,
+}
+
+impl SpriteBatch {
+  /// Creates an empty batch able to hold up to `capacity` sprites.
+  pub fn new(capacity: usize) -> Self {
+    register_sprite_system();
+
+    let capacity = capacity.max(1);
+    Self {
+      capacity,
+      groups: Vec::new(),
+      dirty: AtomicBool::new(false),
+      instance_buffer: StagedBuffer::new(capacity as u64, BufferUsage::VERTEX),
+      draw_calls: Mutex::new(Vec::new()),
+    }
+  }
+
+  /// Adds a sprite sampling the given texture, returns false if the batch is full.
+  pub fn push(&mut self, texture: &SampledTexture, sprite: Sprite) -> bool {
+    if self.len() >= self.capacity {
+      warn!("Sprite batch is full, {} sprites at most", self.capacity);
+      return false;
+    }
+
+    match self
+      .groups
+      .iter_mut()
+      .find(|(group, _)| group.bind_group == texture.bind_group)
+    {
+      Some((_, sprites)) => sprites.push(sprite),
+      None => self.groups.push((texture.clone(), vec![sprite])),
+    }
+    self.dirty.store(true, Ordering::Relaxed);
+    true
+  }
+
+  /// Removes all sprites, e.g. to rebuild the batch every frame.
+  pub fn clear(&mut self) {
+    self.groups.clear();
+    self.dirty.store(true, Ordering::Relaxed);
+  }
+
+  pub fn len(&self) -> usize {
+    self.groups.iter().map(|(_, sprites)| sprites.len()).sum()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn get_capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// Amount of draw calls needed to render the batch.
+  pub fn len_draw_calls(&self) -> usize {
+    self.groups.len()
+  }
+
+  /// Rebuilds instances if sprites have changed since the last frame.
+  fn prepare(&self) -> StagedBufferAccessor {
+    if self.dirty.swap(false, Ordering::Relaxed) {
+      let mut instances = self.instance_buffer.get_mut();
+      instances.clear();
+      instances.extend(
+        self
+          .groups
+          .iter()
+          .flat_map(|(_, sprites)| sprites.iter().map(SpriteInstance::from)),
+      );
+
+      let ranges = sprite_instance_ranges(self.groups.iter().map(|(_, sprites)| sprites.len()));
+      *self.draw_calls.lock() = self
+        .groups
+        .iter()
+        .zip(ranges)
+        .map(|((texture, _), range)| (texture.bind_group.clone(), range))
+        .collect();
+    }
+
+    self.instance_buffer.get_accessor()
+  }
+}
+
+struct SpriteResources {
+  host: Arc<TextureGeneratorHost>,
+  pipeline: ResourceRc<RenderPipeline>,
+  uniform: Uniform<TransformUniform>,
+}
+
+impl SpriteResources {
+  fn new() -> Self {
+    let core = Core::get_instance();
+    let vs = core
+      .create_shader_from_glsl(
+        include_str!("./sprite.vert"),
+        "SpriteVS",
+        ShaderKind::Vertex,
+      )
+      .unwrap();
+    let fs = core
+      .create_shader_from_glsl(
+        include_str!("./sprite.frag"),
+        "SpriteFS",
+        ShaderKind::Fragment,
+      )
+      .unwrap();
+
+    let layout = core.create_pipeline_layout(
+      PipelineLayoutDescriptor::new()
+        .add_binding(MATERIAL_UNIFORM_LAYOUT.clone())
+        .add_binding(
+          core
+            .get_gp_resources()
+            .sampled_texture_bind_group_layout
+            .clone(),
+        ),
+    );
+    let instance_desc = VertexBuffer {
+      stride: std::mem::size_of::<SpriteInstance>() as u64,
+      attributes: vec![
+        VertexAttribute {
+          name: "rect".to_string(),
+          offset: 0,
+          location: 0,
+          format: VertexAttributeFormat::Float4,
+        },
+        VertexAttribute {
+          name: "uv_rect".to_string(),
+          offset: 4 * 4,
+          location: 1,
+          format: VertexAttributeFormat::Float4,
+        },
+        VertexAttribute {
+          name: "tint".to_string(),
+          offset: 4 * 8,
+          location: 2,
+          format: VertexAttributeFormat::Float4,
+        },
+      ],
+    };
+    let pipeline = core
+      .create_render_pipeline(
+        RenderPipelineDescriptor::new_without_vertices(layout, vs, fs)
+          .add_instance_buffer(instance_desc)
+          .add_color_output(TextureFormat::Bgra8UnormSrgb),
+      )
+      .unwrap();
+
+    Self {
+      host: TextureGeneratorHost::new(TextureSize::FullScreen, TextureFormat::Bgra8UnormSrgb),
+      pipeline,
+      uniform: Uniform::new(TransformUniform {
+        matrix: Matrix4::identity(),
+      }),
+    }
+  }
+}
+
+/// Lazily sets up the sprite frame graph system.
+fn register_sprite_system() {
+  REGISTERED_SYSTEM.call_once(|| {
+    Core::get_instance()
+      .get_world()
+      .add_system_to_stage(CreateSpriteFrameGraphSystem, SystemStage::Rendering);
+  });
+}
+
+#[system]
+#[read_component(Camera)]
+#[read_component(MainCameraTag)]
+#[read_component(SpriteBatch)]
+fn create_sprite_frame_graph(world: &mut SubWorld) {
+  optick::event!("moonwave_scene::create_sprite_frame_graph");
+
+  // Sprites are rendered from the main cameras point of view.
+  let projection_view = match <(&Camera, &MainCameraTag)>::query().iter(world).next() {
+    Some((camera, _)) => camera.uniform.get().projection_view,
+    None => return,
+  };
+
+  let batches = <&SpriteBatch>::query()
+    .iter(world)
+    .filter(|batch| !batch.is_empty())
+    .map(|batch| SpriteBatchDraw {
+      instances: batch.prepare(),
+      draw_calls: batch.draw_calls.lock().clone(),
+    })
+    .collect::<Vec<_>>();
+  if batches.is_empty() {
+    return;
+  }
+
+  let resources = SPRITE_RESOURCES.get_or_init(SpriteResources::new);
+  resources.uniform.get_mut().matrix = projection_view;
+
+  let graph = Core::get_instance().get_frame_graph();
+  let texture_in = graph.add_node(resources.host.create_node(), "SpriteTextureHost");
+  let texture_out = graph.add_node(
+    SpriteBatchNode {
+      batches,
+      pipeline: resources.pipeline.clone(),
+      transform: resources.uniform.as_generic(),
+    },
+    "SpriteBatches",
+  );
+  graph
+    .connect(
+      texture_in,
+      TextureGeneratorNode::OUTPUT_TEXTURE,
+      texture_out,
+      SpriteBatchNode::INPUT_TEXTURE,
+    )
+    .unwrap();
+  graph
+    .connect(
+      texture_out,
+      SpriteBatchNode::OUTPUT_TEXTURE,
+      graph.get_end_node(),
+      PresentToScreen::INPUT_TEXTURE_SPRITES,
+    )
+    .unwrap();
+}
+
+struct CreateSpriteFrameGraphSystem;
+impl SystemFactory for CreateSpriteFrameGraphSystem {
+  fn create_system(&self) -> WrappedSystem {
+    WrappedSystem(Box::new(create_sprite_frame_graph_system()))
+  }
+}
+
+struct SpriteBatchDraw {
+  instances: StagedBufferAccessor,
+  draw_calls: Vec<(ResourceRc<BindGroup>, Range<u32>)>,
+}
+
+struct SpriteBatchNode {
+  batches: Vec<SpriteBatchDraw>,
+  pipeline: ResourceRc<RenderPipeline>,
+  transform: GenericUniform,
+}
+
+impl SpriteBatchNode {
+  const INPUT_TEXTURE: usize = 0;
+  const OUTPUT_TEXTURE: usize = 0;
+}
+
+impl FrameGraphNode for SpriteBatchNode {
+  fn execute(
+    &self,
+    inputs: &[Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
+    encoder: &mut CommandEncoder,
+  ) {
+    optick::event!("FrameGraph::SpriteBatches");
+
+    let texture = inputs[Self::INPUT_TEXTURE].as_ref().unwrap();
+    let transform = self.transform.get_resources(encoder);
+    let buffers = self
+      .batches
+      .iter()
+      .map(|batch| batch.instances.get_resources(encoder).clone())
+      .collect::<Vec<_>>();
+
+    let mut rp_builder = RenderPassCommandEncoderBuilder::new("SpriteRenderPass");
+    rp_builder.add_color_output(
+      &texture.get_sampled_texture().view,
+      Vector4::new(0.0, 0.0, 0.0, 0.0),
+    );
+
+    let mut rp = encoder.create_render_pass_encoder(rp_builder);
+    rp.set_pipeline(self.pipeline.clone());
+    rp.set_bind_group(0, transform.bind_group.clone());
+    for (batch, instances) in self.batches.iter().zip(buffers) {
+      rp.set_vertex_buffer(instances);
+      for (texture, range) in batch.draw_calls.iter() {
+        rp.set_bind_group(1, texture.clone());
+        rp.render_instanced(SPRITE_VERTICES, range.clone());
+      }
+    }
+
+    outputs[Self::OUTPUT_TEXTURE] = Some(texture.clone());
+  }
+}
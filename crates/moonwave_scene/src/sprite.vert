@@ -0,0 +1,27 @@
+#version 450
+
+layout (location = 0) in vec4 i_rect;
+layout (location = 1) in vec4 i_uv_rect;
+layout (location = 2) in vec4 i_tint;
+
+layout (location = 0) out vec2 v_uv;
+layout (location = 1) out vec4 v_tint;
+
+layout (set = 0, binding = 0) uniform TransformUniform {
+  mat4 matrix;
+} transform;
+
+// Two counter clockwise triangles starting at the bottom left corner.
+const vec2 CORNERS[6] = vec2[6](
+  vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(1.0, 1.0),
+  vec2(0.0, 0.0), vec2(1.0, 1.0), vec2(0.0, 1.0)
+);
+
+void main() {
+  vec2 corner = CORNERS[gl_VertexIndex];
+
+  // Texture space points downwards, so the bottom of the quad samples the max uv row.
+  v_uv = vec2(mix(i_uv_rect.x, i_uv_rect.z, corner.x), mix(i_uv_rect.w, i_uv_rect.y, corner.y));
+  v_tint = i_tint;
+  gl_Position = transform.matrix * vec4(i_rect.xy + corner * i_rect.zw, 0.0, 1.0);
+}
@@ -0,0 +1,68 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform SsrUniform {
+  mat4 projection_view;
+  mat4 inv_projection_view;
+  vec3 camera_position;
+  float max_distance;
+  float thickness;
+  float far_depth;
+  uint steps;
+} u_ssr;
+
+// G-Buffer: lit color, depth, encoded normal and roughness (r) / metallic (g).
+layout(set = 1, binding = 0) uniform texture2D t_color;
+layout(set = 1, binding = 1) uniform texture2D t_depth;
+layout(set = 1, binding = 2) uniform texture2D t_normal;
+layout(set = 1, binding = 3) uniform texture2D t_material;
+layout(set = 1, binding = 4) uniform sampler s_gbuffer;
+
+layout(location=0) in vec2 v_uv;
+
+layout(location=0) out vec4 f_color;
+
+#include <world_from_depth>
+
+void main() {
+  vec4 base = texture(sampler2D(t_color, s_gbuffer), v_uv);
+  float depth = texture(sampler2D(t_depth, s_gbuffer), v_uv).r;
+  vec2 material = texture(sampler2D(t_material, s_gbuffer), v_uv).rg;
+  float reflectivity = material.g * (1.0 - material.r);
+
+  // Nothing to reflect on the sky or rough dielectrics.
+  if (depth == u_ssr.far_depth || reflectivity <= 0.0) {
+    f_color = base;
+    return;
+  }
+
+  vec3 position = world_from_depth(v_uv, depth, u_ssr.inv_projection_view);
+  vec3 normal = normalize(texture(sampler2D(t_normal, s_gbuffer), v_uv).xyz * 2.0 - 1.0);
+  vec3 ray = reflect(normalize(position - u_ssr.camera_position), normal);
+
+  // March along the reflected ray until it passes behind the depth buffer.
+  float step_size = u_ssr.max_distance / float(u_ssr.steps);
+  for (uint i = 1; i <= u_ssr.steps; i++) {
+    vec3 sample_position = position + ray * step_size * float(i);
+    vec4 clip = u_ssr.projection_view * vec4(sample_position, 1.0);
+    vec3 ndc = clip.xyz / clip.w;
+    vec2 uv = vec2(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+    if (clip.w <= 0.0 || any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0)))) {
+      break;
+    }
+
+    float scene_depth = texture(sampler2D(t_depth, s_gbuffer), uv).r;
+    vec3 scene_position = world_from_depth(uv, scene_depth, u_ssr.inv_projection_view);
+    float ray_distance = distance(u_ssr.camera_position, sample_position);
+    float scene_distance = distance(u_ssr.camera_position, scene_position);
+    if (ray_distance > scene_distance && ray_distance - scene_distance < u_ssr.thickness) {
+      // Fade out towards screen edges where the reflection would be cut off.
+      vec2 edge = min(uv, 1.0 - uv);
+      float fade = clamp(min(edge.x, edge.y) * 10.0, 0.0, 1.0);
+      vec4 reflection = texture(sampler2D(t_color, s_gbuffer), uv);
+      f_color = mix(base, reflection, reflectivity * fade);
+      return;
+    }
+  }
+
+  f_color = base;
+}
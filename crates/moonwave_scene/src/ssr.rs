@@ -0,0 +1,158 @@
+use moonwave_common::*;
+use moonwave_core::{optick, OnceCell};
+use moonwave_render::{CommandEncoder, FrameGraphNode, FrameNodeValue};
+use moonwave_resources::DepthMode;
+use moonwave_shader::uniform;
+
+use crate::{Camera, ScreenEffectPass, Uniform};
+
+/// Fragment shader performing the screen space ray march.
+/// Includes have to be resolved through `screen_effect_shader`.
+pub const SSR_FRAGMENT_SHADER: &str = include_str!("./ssr.frag");
+
+static SSR_PASS: OnceCell<ScreenEffectPass> = OnceCell::new();
+
+#[uniform]
+pub struct SsrUniform {
+  projection_view: Matrix4<f32>,
+  inv_projection_view: Matrix4<f32>,
+  camera_position: Vector3<f32>,
+  max_distance: f32,
+  thickness: f32,
+  far_depth: f32,
+  steps: u32,
+}
+
+/// Tweakables of the screen space reflections.
+#[derive(Debug, Clone, Copy)]
+pub struct SsrSettings {
+  /// Amount of samples taken along each reflected ray.
+  pub steps: u32,
+  /// World space distance a reflected ray travels at most.
+  pub max_distance: f32,
+  /// How far behind the depth buffer a sample may lie to still count as hit.
+  pub thickness: f32,
+}
+
+impl Default for SsrSettings {
+  fn default() -> Self {
+    Self {
+      steps: 32,
+      max_distance: 20.0,
+      thickness: 0.25,
+    }
+  }
+}
+
+impl SsrSettings {
+  pub fn build_uniform(
+    &self,
+    projection_view: Matrix4<f32>,
+    camera_position: Vector3<f32>,
+    depth_mode: DepthMode,
+  ) -> SsrUniform {
+    SsrUniform {
+      projection_view,
+      inv_projection_view: projection_view.invert().unwrap_or_else(Matrix4::identity),
+      camera_position,
+      max_distance: self.max_distance,
+      thickness: self.thickness,
+      far_depth: depth_mode.clear_value(),
+      steps: self.steps.max(1),
+    }
+  }
+}
+
+/// Screen space reflections post effect.
+/// Ray marches the depth buffer along reflected view rays and blends the hit color based on
+/// roughness (red) and metallic (green) of the material target.
+pub struct SsrNode {
+  settings: SsrSettings,
+  uniform: SsrUniform,
+  /// Created on the first execution, see `ScreenEffectPass`.
+  gpu_uniform: OnceCell<Uniform<SsrUniform>>,
+}
+
+impl SsrNode {
+  pub const INPUT_COLOR: usize = 0;
+  pub const INPUT_DEPTH: usize = 1;
+  pub const INPUT_NORMAL: usize = 2;
+  pub const INPUT_MATERIAL: usize = 3;
+  pub const INPUT_TARGET: usize = 4;
+  pub const OUTPUT_COLOR: usize = 0;
+
+  pub fn new(
+    projection_view: Matrix4<f32>,
+    camera_position: Vector3<f32>,
+    depth_mode: DepthMode,
+  ) -> Self {
+    let settings = SsrSettings::default();
+    Self {
+      uniform: settings.build_uniform(projection_view, camera_position, depth_mode),
+      settings,
+      gpu_uniform: OnceCell::new(),
+    }
+  }
+
+  pub fn from_camera(camera: &Camera) -> Self {
+    let uniform = camera.uniform.get();
+    Self::new(uniform.projection_view, camera.position, camera.depth_mode)
+  }
+
+  pub fn with_steps(mut self, steps: u32) -> Self {
+    self.settings.steps = steps;
+    self.uniform.steps = steps.max(1);
+    self
+  }
+
+  pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+    self.settings.max_distance = max_distance;
+    self.uniform.max_distance = max_distance;
+    self
+  }
+
+  pub fn with_thickness(mut self, thickness: f32) -> Self {
+    self.settings.thickness = thickness;
+    self.uniform.thickness = thickness;
+    self
+  }
+
+  pub fn get_settings(&self) -> &SsrSettings {
+    &self.settings
+  }
+}
+
+impl FrameGraphNode for SsrNode {
+  fn execute(
+    &self,
+    inputs: &[Option<FrameNodeValue>],
+    outputs: &mut [Option<FrameNodeValue>],
+    encoder: &mut CommandEncoder,
+  ) {
+    optick::event!("FrameGraph::SSR");
+    let pass = SSR_PASS.get_or_init(|| ScreenEffectPass::new("ssr", SSR_FRAGMENT_SHADER, 4));
+    let uniform = self.gpu_uniform.get_or_init(|| Uniform::new(self.uniform));
+
+    // G-Buffer in the order of the shader bindings.
+    let input = |index: usize| inputs[index].as_ref().unwrap().get_sampled_texture();
+    let target = input(Self::INPUT_TARGET).clone();
+    pass.render(
+      encoder,
+      uniform,
+      vec![
+        input(Self::INPUT_COLOR).view.clone(),
+        input(Self::INPUT_DEPTH).view.clone(),
+        input(Self::INPUT_NORMAL).view.clone(),
+        input(Self::INPUT_MATERIAL).view.clone(),
+      ],
+      &target,
+    );
+
+    outputs[Self::OUTPUT_COLOR] = Some(FrameNodeValue::SampledTexture(target));
+  }
+
+  /// Forwards the scene without reflections while disabled.
+  fn get_bypass(&self) -> Option<(usize, usize)> {
+    Some((Self::INPUT_COLOR, Self::OUTPUT_COLOR))
+  }
+}
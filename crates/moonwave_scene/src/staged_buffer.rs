@@ -1,8 +1,9 @@
 use moonwave_common::bytemuck::*;
 use moonwave_core::Core;
-use moonwave_render::{CommandEncoder, FrameGraphNode, FrameNodeValue};
+use moonwave_render::CommandEncoder;
 use moonwave_resources::{Buffer, BufferUsage, ResourceRc};
 use parking_lot::{RwLock, RwLockWriteGuard};
+use std::ops::Range;
 use std::sync::{
   atomic::{AtomicBool, Ordering},
   Arc,
@@ -12,77 +13,151 @@ use std::sync::{
 pub struct StagedBuffer<T: Sized> {
   content: Arc<RwLock<Vec<T>>>,
   is_dirty: Arc<AtomicBool>,
-  pub(crate) staging_buffer: ResourceRc<Buffer>,
-  pub(crate) buffer: ResourceRc<Buffer>,
+  dirty_ranges: Arc<RwLock<Vec<Range<usize>>>>,
+  usage: BufferUsage,
+  /// GPU buffer together with its size, replaced by a larger one once the content outgrows it.
+  buffer: Arc<RwLock<(ResourceRc<Buffer>, u64)>>,
 }
 
 impl<T: Sized + Pod> StagedBuffer<T> {
   pub fn new(length: u64, usage: BufferUsage) -> Self {
     let core = Core::get_instance();
     let size = (std::mem::size_of::<T>() * length as usize) as u64;
-    let staging_buffer = core.create_buffer(
-      size,
-      false,
-      BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
-      None,
-    );
-
     let buffer = core.create_buffer(size, false, usage | BufferUsage::COPY_DST, None);
 
     Self {
-      staging_buffer,
-      buffer,
+      buffer: Arc::new(RwLock::new((buffer, size))),
+      usage,
       content: Arc::new(RwLock::new(Vec::with_capacity(length as usize))),
       is_dirty: Arc::new(AtomicBool::new(false)),
+      dirty_ranges: Arc::new(RwLock::new(Vec::new())),
     }
   }
 
+  /// Gives access to the whole content, which is entirely re-uploaded afterwards.
   pub fn get_mut(&self) -> RwLockWriteGuard<Vec<T>> {
     self.is_dirty.store(true, Ordering::Relaxed);
     self.content.write()
   }
 
+  /// Amount of elements currently staged.
+  pub fn len(&self) -> usize {
+    self.content.read().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.content.read().is_empty()
+  }
+
+  /// Overwrites existing elements starting at `index`, only the changed range is uploaded.
+  pub fn patch(&self, index: usize, values: &[T]) {
+    self.content.write()[index..index + values.len()].copy_from_slice(values);
+    self.mark_range(index..index + values.len());
+  }
+
+  /// Appends elements to the end, only the new elements are uploaded.
+  /// Returns the index of the first appended element.
+  pub fn append(&self, values: &[T]) -> usize {
+    let mut content = self.content.write();
+    let index = content.len();
+    content.extend_from_slice(values);
+    self.mark_range(index..content.len());
+    index
+  }
+
+  fn mark_range(&self, range: Range<usize>) {
+    if !range.is_empty() {
+      self.dirty_ranges.write().push(range);
+    }
+  }
+
+  /// Size of the GPU buffer in bytes, it grows with the content.
+  pub fn capacity(&self) -> u64 {
+    self.buffer.read().1
+  }
+
   pub fn get_accessor(&self) -> StagedBufferAccessor {
-    let content = if self.is_dirty.swap(false, Ordering::Relaxed) {
-      let out = moonwave_common::bytemuck::cast_slice(&*self.content.read()).to_vec();
-      Some(out)
-    } else {
-      None
-    };
+    let stride = std::mem::size_of::<T>();
+    let mut dirty_ranges = std::mem::take(&mut *self.dirty_ranges.write());
+    let content = self.content.read();
+
+    // Content that outgrew the buffer is moved into a larger one, which has to be filled entirely.
+    let mut buffer = self.buffer.write();
+    let required = ((content.len() * stride + 3) / 4 * 4) as u64;
+    if required > buffer.1 {
+      let size = required.max(buffer.1 * 2);
+      buffer.0 =
+        Core::get_instance().create_buffer(size, false, self.usage | BufferUsage::COPY_DST, None);
+      buffer.1 = size;
+      self.is_dirty.store(true, Ordering::Relaxed);
+    }
+
+    // A full rewrite supersedes all patched ranges.
+    if self.is_dirty.swap(false, Ordering::Relaxed) {
+      dirty_ranges = vec![0..content.len()];
+    }
+
+    let raw = moonwave_common::bytemuck::cast_slice::<T, u8>(&*content);
+    let uploads = coalesce_ranges(dirty_ranges)
+      .into_iter()
+      .map(|range| {
+        // Copies have to be 4 byte aligned, pad with zeros if needed.
+        let start = range.start * stride / 4 * 4;
+        let end = ((range.end * stride + 3) / 4 * 4).min(buffer.1 as usize);
+        let mut data = raw[start..(end.min(raw.len()))].to_vec();
+        data.resize(end - start, 0);
+        (start as u64, data)
+      })
+      .filter(|(_, data)| !data.is_empty())
+      .collect();
 
     StagedBufferAccessor {
-      content,
-      buffer: self.buffer.clone(),
-      staging_buffer: self.staging_buffer.clone(),
+      uploads,
+      buffer: buffer.0.clone(),
     }
   }
 
-  pub fn partial_write_raw(&self, cmd: &mut CommandEncoder, offset: u64, new_data: &[u8]) {
-    cmd.write_buffer_offseted(&self.staging_buffer, &new_data, offset);
-    cmd.copy_buffer_to_buffer_offseted(
-      &self.staging_buffer,
-      offset,
-      &self.buffer,
-      offset,
-      new_data.len() as u64,
-    )
+  pub fn partial_write_raw(&self, _cmd: &mut CommandEncoder, offset: u64, new_data: &[u8]) {
+    Core::get_instance()
+      .get_upload_queue()
+      .write(&self.buffer.read().0, offset, new_data);
   }
 }
 
+/// Sorts the given ranges and merges all of them that overlap or touch each other.
+pub fn coalesce_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+  ranges.retain(|range| !range.is_empty());
+  ranges.sort_by_key(|range| range.start);
+
+  let mut coalesced: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+  for range in ranges {
+    match coalesced.last_mut() {
+      Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+      _ => coalesced.push(range),
+    }
+  }
+  coalesced
+}
+
 pub struct StagedBufferAccessor {
-  content: Option<Vec<u8>>,
-  staging_buffer: ResourceRc<Buffer>,
+  uploads: Vec<(u64, Vec<u8>)>,
   buffer: ResourceRc<Buffer>,
 }
 
 impl StagedBufferAccessor {
-  pub fn get_resources(&self, cmd: &mut CommandEncoder) -> &ResourceRc<Buffer> {
-    if let Some(data) = &self.content {
-      // Update staging buffer.
-      cmd.write_buffer(&self.staging_buffer, &data);
+  /// Byte ranges that are uploaded when the resources are accessed.
+  pub fn pending_uploads(&self) -> impl Iterator<Item = Range<u64>> + '_ {
+    self
+      .uploads
+      .iter()
+      .map(|(offset, data)| *offset..*offset + data.len() as u64)
+  }
 
-      // Update actual buffer
-      cmd.copy_buffer_to_buffer(&self.staging_buffer, &self.buffer, data.len() as u64)
+  /// Queues the pending uploads, they are uploaded together with all other writes of the frame graph level.
+  pub fn get_resources(&self, _cmd: &mut CommandEncoder) -> &ResourceRc<Buffer> {
+    let queue = Core::get_instance().get_upload_queue();
+    for (offset, data) in self.uploads.iter() {
+      queue.write(&self.buffer, *offset, data);
     }
 
     &self.buffer
@@ -4,9 +4,10 @@ use std::io::Cursor;
 use moonwave_common::Vector2;
 use moonwave_core::optick;
 use moonwave_core::{rayon::prelude::*, Core};
-use moonwave_resources::{SampledTexture, TextureFormat, TextureUsage};
+use moonwave_resources::{texture_byte_size, SampledTexture, TextureFormat, TextureUsage};
 use thiserror::Error;
 
+#[derive(Debug, Clone, Copy)]
 pub enum TextureCodec {
   DDS,
 }
@@ -55,22 +56,154 @@ pub fn create_raw_texture_data(
   })
 }
 
-pub fn create_static_texture(
+/// Block compressed texture data that is uploaded as is, including all mips stored in the file.
+pub struct CompressedTextureData {
+  pub format: TextureFormat,
+  pub size: Vector2<u32>,
+  /// Tightly packed block rows of every mip level, starting with the largest.
+  pub mips: Vec<Vec<u8>>,
+}
+
+impl CompressedTextureData {
+  pub fn mip_count(&self) -> u32 {
+    self.mips.len() as u32
+  }
+}
+
+/// Size of the `DDS ` magic and the header following it.
+const DDS_HEADER_SIZE: usize = 128;
+/// Size of the extended header following the DDS header if the fourcc is `DX10`.
+const DDS_DX10_HEADER_SIZE: usize = 20;
+
+fn dxgi_to_texture_format(dxgi_format: u32) -> Option<TextureFormat> {
+  Some(match dxgi_format {
+    71 => TextureFormat::Bc1RgbaUnorm,
+    72 => TextureFormat::Bc1RgbaUnormSrgb,
+    77 => TextureFormat::Bc3RgbaUnorm,
+    78 => TextureFormat::Bc3RgbaUnormSrgb,
+    98 => TextureFormat::Bc7RgbaUnorm,
+    99 => TextureFormat::Bc7RgbaUnormSrgb,
+    _ => return None,
+  })
+}
+
+/// Reads block compressed data without decoding it.
+/// Returns `None` if the file is not BC1, BC3 or BC7 compressed and has to be decoded instead.
+pub fn create_compressed_texture_data(
   decoder: TextureCodec,
   data: &[u8],
-) -> Result<SampledTexture, TextureReadError> {
-  optick::event!("scene::texture::create_static_texture");
+) -> Result<Option<CompressedTextureData>, TextureReadError> {
+  match decoder {
+    TextureCodec::DDS => {
+      if data.len() < DDS_HEADER_SIZE {
+        return Err(TextureReadError::UnexpectedData);
+      }
+      let header =
+        DDS::parse_header(&mut Cursor::new(data)).map_err(|_| TextureReadError::UnexpectedData)?;
 
-  let (width, height, buffer, format, row_size) = create_raw_texture_data(decoder, data)?;
+      let (format, offset) = match header.compression {
+        Compression::DXT1 => (TextureFormat::Bc1RgbaUnorm, DDS_HEADER_SIZE),
+        Compression::DXT5 => (TextureFormat::Bc3RgbaUnorm, DDS_HEADER_SIZE),
+        Compression::DX10 => {
+          let dxgi = data
+            .get(DDS_HEADER_SIZE..DDS_HEADER_SIZE + 4)
+            .ok_or(TextureReadError::UnexpectedData)?;
+          let dxgi = u32::from_le_bytes([dxgi[0], dxgi[1], dxgi[2], dxgi[3]]);
+          match dxgi_to_texture_format(dxgi) {
+            Some(format) => (format, DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE),
+            None => return Ok(None),
+          }
+        }
+        _ => return Ok(None),
+      };
+
+      // Split the payload into its mip levels.
+      let mip_count = header.mipmap_count.max(1);
+      let mut offset = offset;
+      let mut mips = Vec::with_capacity(mip_count as usize);
+      for mip in 0..mip_count {
+        let width = (header.width >> mip).max(1);
+        let height = (header.height >> mip).max(1);
+        let size = texture_byte_size(format, width, height, 1, 1) as usize;
+        let mip_data = data
+          .get(offset..offset + size)
+          .ok_or(TextureReadError::UnexpectedData)?;
+        mips.push(mip_data.to_vec());
+        offset += size;
+      }
+
+      Ok(Some(CompressedTextureData {
+        format,
+        size: Vector2::new(header.width, header.height),
+        mips,
+      }))
+    }
+  }
+}
+
+/// Texture data ready to be uploaded, see `create_texture_data`.
+pub enum TextureData {
+  Compressed(CompressedTextureData),
+  Raw {
+    size: Vector2<u32>,
+    buffer: Vec<u8>,
+    format: TextureFormat,
+    row_size: usize,
+  },
+}
 
-  let texture = Core::get_instance().create_inited_sampled_texture(
-    None,
-    TextureUsage::SAMPLED,
+/// Reads texture data for upload. Block compressed data is only kept as is if the device
+/// supports block compression, otherwise it is decoded into RGBA8.
+pub fn create_texture_data(
+  decoder: TextureCodec,
+  data: &[u8],
+  supports_compression: bool,
+) -> Result<TextureData, TextureReadError> {
+  if supports_compression {
+    if let Some(compressed) = create_compressed_texture_data(decoder, data)? {
+      return Ok(TextureData::Compressed(compressed));
+    }
+  }
+
+  let (width, height, buffer, format, row_size) = create_raw_texture_data(decoder, data)?;
+  Ok(TextureData::Raw {
+    size: Vector2::new(width, height),
+    buffer,
     format,
-    Vector2::new(width, height),
-    &buffer,
     row_size,
-  );
+  })
+}
+
+pub fn create_static_texture(
+  decoder: TextureCodec,
+  data: &[u8],
+) -> Result<SampledTexture, TextureReadError> {
+  optick::event!("scene::texture::create_static_texture");
+
+  let core = Core::get_instance();
+  let texture = match create_texture_data(decoder, data, core.supports_texture_compression_bc())? {
+    // Compressed data is uploaded directly, mips come from the file.
+    TextureData::Compressed(compressed) => core.create_compressed_sampled_texture(
+      None,
+      TextureUsage::SAMPLED,
+      compressed.format,
+      compressed.size,
+      &compressed.mips,
+    ),
+    TextureData::Raw {
+      size,
+      buffer,
+      format,
+      row_size,
+    } => core.create_inited_sampled_texture(
+      None,
+      TextureUsage::SAMPLED,
+      format,
+      size,
+      &buffer,
+      row_size,
+    ),
+  };
 
   Ok(texture)
 }
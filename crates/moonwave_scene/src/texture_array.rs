@@ -5,12 +5,29 @@ use std::{
 };
 
 use moonwave_common::*;
-use moonwave_core::Core;
+use moonwave_core::{warn, Core};
 use moonwave_resources::*;
 use parking_lot::Mutex;
+use thiserror::Error;
 
 use crate::{create_raw_texture_data, TextureCodec};
 
+#[derive(Error, Debug, PartialEq)]
+pub enum TextureArrayError {
+  #[error("Index {index} is out of bounds for a texture array of size {size}")]
+  IndexOutOfBounds { index: usize, size: usize },
+}
+
+/// Checks that `index` addresses one of the `size` textures of an array.
+/// Shaders sampling outside of an array cause undefined behaviour, so indices should be checked on the CPU.
+pub fn check_texture_array_index(index: usize, size: usize) -> Result<usize, TextureArrayError> {
+  if index < size {
+    Ok(index)
+  } else {
+    Err(TextureArrayError::IndexOutOfBounds { index, size })
+  }
+}
+
 pub struct DynamicTextureArray {
   textures: Vec<ResourceRc<Texture>>,
   texture_views: Vec<ResourceRc<TextureView>>,
@@ -66,11 +83,21 @@ impl DynamicTextureArray {
     }
   }
 
+  /// Amount of textures within the array.
+  pub fn size(&self) -> usize {
+    self.textures.len()
+  }
+
   pub fn reserve(&self) -> Option<usize> {
     self.free_list.lock().pop_front()
   }
 
   pub fn load_into_spot(&self, decoder: TextureCodec, data: &[u8], index: usize) -> Option<usize> {
+    if let Err(err) = check_texture_array_index(index, self.size()) {
+      warn!("Failed to load texture into array: {}", err);
+      return None;
+    }
+
     // Put into texture array
     let (width, height, buffer, format, row_size) = create_raw_texture_data(decoder, data).unwrap();
     Core::get_instance().upload_texture(
@@ -91,6 +118,10 @@ impl DynamicTextureArray {
   }
 
   pub fn remove(&self, index: usize) {
+    if let Err(err) = check_texture_array_index(index, self.size()) {
+      warn!("Failed to remove texture from array: {}", err);
+      return;
+    }
     self.free_list.lock().push_back(index);
   }
 }
@@ -1,9 +1,13 @@
-use std::{sync::atomic::AtomicBool, unimplemented};
+use std::{
+  sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+  unimplemented,
+};
 
 use legion::maybe_changed;
 use moonwave_common::*;
 use moonwave_core::*;
 use moonwave_shader::uniform;
+use parking_lot::RwLock;
 
 use crate::Uniform;
 
@@ -26,20 +30,14 @@ pub struct TransformInner {
 
 pub struct Transform {
   pub(crate) uniform: Option<Uniform<TransformUniform>>,
-  pub inner: TransformInner,
+  pub(crate) inner: TransformInner,
   dirty: AtomicBool,
+  matrix: RwLock<Option<Matrix4<f32>>>,
+  recomputations: AtomicUsize,
 }
 
 impl Transform {
   pub fn new_static(position: Vector3<f32>, rotation: Vector3<f32>, scale: Vector3<f32>) -> Self {
-    REGISTERED_SYSTEM.call_once(|| {
-      let core = Core::get_instance();
-      core.get_world().add_system_to_stage(
-        UpdateTransformUniformSystem,
-        SystemStage::RenderingPreperations,
-      )
-    });
-
     Self {
       uniform: None,
       inner: TransformInner {
@@ -50,18 +48,12 @@ impl Transform {
         scale,
       },
       dirty: AtomicBool::new(false),
+      matrix: RwLock::new(None),
+      recomputations: AtomicUsize::new(0),
     }
   }
 
   pub fn new_dynamic(position: Vector3<f32>, rotation: Vector3<f32>, scale: Vector3<f32>) -> Self {
-    REGISTERED_SYSTEM.call_once(|| {
-      let core = Core::get_instance();
-      core.get_world().add_system_to_stage(
-        UpdateTransformUniformSystem,
-        SystemStage::RenderingPreperations,
-      )
-    });
-
     Self {
       uniform: None,
       inner: TransformInner {
@@ -72,10 +64,14 @@ impl Transform {
         scale,
       },
       dirty: AtomicBool::new(true),
+      matrix: RwLock::new(None),
+      recomputations: AtomicUsize::new(0),
     }
   }
 
+  /// Creates a dynamic transform at the origin that is uploaded as uniform for rendering.
   pub fn new() -> Self {
+    // Only transforms with an uniform have to be updated by the system.
     REGISTERED_SYSTEM.call_once(|| {
       let core = Core::get_instance();
       core.get_world().add_system_to_stage(
@@ -96,10 +92,30 @@ impl Transform {
         scale: Vector3::new(1.0, 1.0, 1.0),
       },
       dirty: AtomicBool::new(true),
+      matrix: RwLock::new(None),
+      recomputations: AtomicUsize::new(0),
     }
   }
 
+  /// Returns the model matrix, it is cached until the transform is mutated again.
   pub fn calculate_transform_matrix(&self) -> Matrix4<f32> {
+    if let Some(matrix) = *self.matrix.read() {
+      return matrix;
+    }
+
+    let matrix = self.recompute_transform_matrix();
+    *self.matrix.write() = Some(matrix);
+    matrix
+  }
+
+  /// How many times the model matrix had to be recomputed since creation.
+  pub fn matrix_recomputations(&self) -> usize {
+    self.recomputations.load(Ordering::Relaxed)
+  }
+
+  fn recompute_transform_matrix(&self) -> Matrix4<f32> {
+    self.recomputations.fetch_add(1, Ordering::Relaxed);
+
     let translation = Matrix4::from_translation(self.inner.position);
     let rotation = Matrix4::from_angle_x(Rad(self.inner.rotation.x))
       * Matrix4::from_angle_y(Rad(self.inner.rotation.y))
@@ -121,7 +137,8 @@ impl Transform {
       "Tried to mutably access an static transform component."
     );
 
-    self.dirty.store(true, std::sync::atomic::Ordering::Relaxed);
+    self.dirty.store(true, Ordering::Relaxed);
+    *self.matrix.get_mut() = None;
     &mut self.inner
   }
 }
@@ -149,10 +166,7 @@ pub enum TransformOptimization {
 #[system(par_for_each)]
 #[filter(maybe_changed::<Transform>())]
 pub fn update_transform_uniforms(model: &Transform) {
-  if !model
-    .dirty
-    .swap(false, std::sync::atomic::Ordering::Relaxed)
-  {
+  if !model.dirty.swap(false, Ordering::Relaxed) {
     return;
   }
 
@@ -1,8 +1,12 @@
 use moonwave_core::Core;
 use moonwave_render::{CommandEncoder, FrameGraphNode, FrameNodeValue};
 use moonwave_resources::{BindGroup, BindGroupDescriptor, Buffer, BufferUsage, ResourceRc};
-use moonwave_shader::UniformStruct;
+use moonwave_shader::{
+  find_uniform_field, UniformField, UniformFieldError, UniformStruct, UniformValue,
+};
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::collections::HashMap;
+use std::ops::Range;
 use std::sync::{
   atomic::{AtomicBool, Ordering},
   Arc,
@@ -10,10 +14,17 @@ use std::sync::{
 
 use crate::MATERIAL_UNIFORM_LAYOUT;
 
+/// Value of a field set by name, together with the typed bytes it has been set over.
+struct FieldOverride {
+  typed: Vec<u8>,
+  value: Vec<u8>,
+}
+
 #[derive(Clone)]
 pub struct Uniform<T: UniformStruct> {
   content: Arc<RwLock<T>>,
-  staging_buffer: ResourceRc<Buffer>,
+  overrides: Arc<RwLock<HashMap<usize, FieldOverride>>>,
+  uploaded: Arc<RwLock<Vec<u8>>>,
   is_dirty: Arc<AtomicBool>,
   resources: Arc<PubUniformResources>,
 }
@@ -23,13 +34,6 @@ impl<T: UniformStruct + Send + Sync + 'static> Uniform<T> {
     let size = initial.generate_raw_u8().len() as u64;
 
     let core = Core::get_instance();
-    let staging_buffer = core.create_buffer(
-      size,
-      false,
-      BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
-      None,
-    );
-
     let buffer = core.create_buffer(
       size,
       false,
@@ -44,8 +48,9 @@ impl<T: UniformStruct + Send + Sync + 'static> Uniform<T> {
 
     Self {
       resources: Arc::new(PubUniformResources { buffer, bind_group }),
-      staging_buffer,
       content: Arc::new(RwLock::new(initial)),
+      overrides: Arc::new(RwLock::new(HashMap::new())),
+      uploaded: Arc::new(RwLock::new(Vec::new())),
       is_dirty: Arc::new(AtomicBool::new(true)),
     }
   }
@@ -63,18 +68,88 @@ impl<T: UniformStruct + Send + Sync + 'static> Uniform<T> {
     self.resources.bind_group.clone()
   }
 
+  /// Reflected fields of the uniform, useful for generic editors.
+  pub fn fields(&self) -> Vec<UniformField> {
+    T::generate_layout()
+  }
+
+  /// Reads the current value of a field by its name.
+  pub fn get_by_name(&self, name: &str) -> Result<UniformValue, UniformFieldError> {
+    let fields = T::generate_layout();
+    let field = find_uniform_field(&fields, name)?;
+    field.read(&self.generate_raw_u8())
+  }
+
+  /// Sets the value of a field by its name.
+  /// Values set by name take precedence over the typed content until the field is written
+  /// through `get_mut` or `clear_overrides` is called.
+  pub fn set_by_name(&self, name: &str, value: UniformValue) -> Result<(), UniformFieldError> {
+    let fields = T::generate_layout();
+    let field = find_uniform_field(&fields, name)?;
+
+    let mut data = self.generate_raw_u8();
+    field.write(&mut data, value)?;
+    let range = field.offset..field.offset + field.size().unwrap();
+    let typed = self.content.read().generate_raw_u8()[range.clone()].to_vec();
+    self.overrides.write().insert(
+      field.offset,
+      FieldOverride {
+        typed,
+        value: data[range].to_vec(),
+      },
+    );
+
+    self.is_dirty.store(true, Ordering::Relaxed);
+    Ok(())
+  }
+
+  /// Removes all values that have been set by name.
+  pub fn clear_overrides(&self) {
+    self.overrides.write().clear();
+    self.is_dirty.store(true, Ordering::Relaxed);
+  }
+
+  fn generate_raw_u8(&self) -> Vec<u8> {
+    let mut data = self.content.read().generate_raw_u8();
+    let mut overrides = self.overrides.write();
+    // Fields written through `get_mut` since they have been set by name are typed again.
+    overrides.retain(|offset, field| data[*offset..*offset + field.typed.len()] == field.typed[..]);
+    for (offset, field) in overrides.iter() {
+      data[*offset..*offset + field.value.len()].copy_from_slice(&field.value);
+    }
+    data
+  }
+
+  /// Uploads the content if it has been changed since the last upload, returns whether it did.
+  pub fn sync(&self, cmd: &mut CommandEncoder) -> bool {
+    let generic = self.as_generic();
+    generic.get_resources(cmd);
+    generic.content.is_some()
+  }
+
+  /// Only the byte range that actually changed since the last upload is written,
+  /// so changing a single element of a large array uniform doesn't upload the whole block.
   pub fn as_generic(&self) -> GenericUniform {
+    let mut offset = 0;
     let content = if self.is_dirty.swap(false, Ordering::Relaxed) {
-      Some(Arc::new(self.content.read().generate_raw_u8()))
+      let data = self.generate_raw_u8();
+      let mut uploaded = self.uploaded.write();
+      let range = dirty_byte_range(&uploaded, &data);
+      *uploaded = data;
+
+      range.map(|range| {
+        offset = range.start as u64;
+        Arc::new(uploaded[range].to_vec())
+      })
     } else {
       None
     };
 
     GenericUniform {
       content,
+      offset,
       written: Arc::new(AtomicBool::new(false)),
       resources: self.resources.clone(),
-      staging_buffer: self.staging_buffer.clone(),
     }
   }
 }
@@ -83,23 +158,22 @@ impl<T: UniformStruct + Send + Sync + 'static> Uniform<T> {
 pub struct GenericUniform {
   written: Arc<AtomicBool>,
   content: Option<Arc<Vec<u8>>>,
-  staging_buffer: ResourceRc<Buffer>,
+  offset: u64,
   resources: Arc<PubUniformResources>,
 }
 
 impl GenericUniform {
-  pub fn get_resources(&self, cmd: &mut CommandEncoder) -> &PubUniformResources {
+  pub fn get_bind_group(&self) -> ResourceRc<BindGroup> {
+    self.resources.bind_group.clone()
+  }
+
+  /// Queues the changed content, it is uploaded together with all other writes of the frame graph level.
+  pub fn get_resources(&self, _cmd: &mut CommandEncoder) -> &PubUniformResources {
     if let Some(data) = &self.content {
       if !self.written.swap(true, Ordering::Relaxed) {
-        // Update staging buffer.
-        cmd.write_buffer(&self.staging_buffer, &data);
-
-        // Update actual buffer
-        cmd.copy_buffer_to_buffer(
-          &self.staging_buffer,
-          &self.resources.buffer,
-          data.len() as u64,
-        )
+        Core::get_instance()
+          .get_upload_queue()
+          .write(&self.resources.buffer, self.offset, &data);
       }
     }
 
@@ -107,6 +181,22 @@ impl GenericUniform {
   }
 }
 
+/// Returns the 4 byte aligned range in which `new` differs from `old`, `None` if both are equal.
+/// Buffers of different sizes are considered entirely changed.
+pub fn dirty_byte_range(old: &[u8], new: &[u8]) -> Option<Range<usize>> {
+  if old.len() != new.len() {
+    return Some(0..new.len());
+  }
+
+  let changed = |(_, (a, b)): &(usize, (&u8, &u8))| a != b;
+  let first = old.iter().zip(new.iter()).enumerate().find(changed)?.0;
+  let last = old.iter().zip(new.iter()).enumerate().rfind(changed)?.0;
+
+  let start = first / 4 * 4;
+  let end = ((last + 4) / 4 * 4).min(new.len());
+  Some(start..end)
+}
+
 pub struct PubUniformResources {
   pub buffer: ResourceRc<Buffer>,
   pub bind_group: ResourceRc<BindGroup>,
@@ -0,0 +1,84 @@
+use moonwave_common::{Vector2, Vector3};
+use moonwave_core::{compile_glsl, ShaderKind};
+use moonwave_scene::{
+  bloom_bright_pass, bloom_level_sizes, bloom_passes, BloomNode, BloomPass, BLOOM_BLUR_WEIGHTS,
+  BLOOM_FRAGMENT_SHADER, MAX_BLOOM_ITERATIONS,
+};
+
+#[test]
+fn bloom_shader_compiles() {
+  compile_glsl(BLOOM_FRAGMENT_SHADER, "BloomFS", ShaderKind::Fragment).unwrap();
+}
+
+#[test]
+fn bloom_bright_pass_keeps_hue_above_threshold() {
+  assert_eq!(
+    bloom_bright_pass(Vector3::new(0.5, 0.8, 0.2), 1.0),
+    Vector3::new(0.0, 0.0, 0.0)
+  );
+
+  let bright = bloom_bright_pass(Vector3::new(4.0, 2.0, 0.0), 1.0);
+  assert!((bright.x - 3.0).abs() < 1e-5);
+  assert!((bright.y - 1.5).abs() < 1e-5);
+  assert_eq!(bright.z, 0.0);
+
+  // Without threshold everything glows.
+  let color = Vector3::new(0.1, 0.2, 0.3);
+  assert_eq!(bloom_bright_pass(color, 0.0), color);
+}
+
+#[test]
+fn bloom_blur_preserves_energy() {
+  let total = BLOOM_BLUR_WEIGHTS[0] + 2.0 * (BLOOM_BLUR_WEIGHTS[1] + BLOOM_BLUR_WEIGHTS[2]);
+  assert!((total - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn bloom_levels_halve_the_resolution() {
+  assert_eq!(
+    bloom_level_sizes(Vector2::new(1920, 1080), 3),
+    vec![
+      Vector2::new(960, 540),
+      Vector2::new(480, 270),
+      Vector2::new(240, 135)
+    ]
+  );
+
+  // Tiny targets never end up with empty levels and iterations are clamped.
+  let levels = bloom_level_sizes(Vector2::new(16, 4), 100);
+  assert_eq!(levels.len(), MAX_BLOOM_ITERATIONS as usize);
+  assert_eq!(*levels.last().unwrap(), Vector2::new(1, 1));
+  assert_eq!(bloom_level_sizes(Vector2::new(16, 4), 0).len(), 1);
+}
+
+#[test]
+fn bloom_passes_downsample_then_upsample() {
+  assert_eq!(
+    bloom_passes(3),
+    vec![
+      BloomPass::BrightPass,
+      BloomPass::BlurHorizontal(0),
+      BloomPass::BlurVertical(0),
+      BloomPass::Downsample(1),
+      BloomPass::BlurHorizontal(1),
+      BloomPass::BlurVertical(1),
+      BloomPass::Downsample(2),
+      BloomPass::BlurHorizontal(2),
+      BloomPass::BlurVertical(2),
+      BloomPass::Upsample(2),
+      BloomPass::Upsample(1),
+      BloomPass::Composite,
+    ]
+  );
+}
+
+#[test]
+fn bloom_node_settings() {
+  let node = BloomNode::new()
+    .with_threshold(1.5)
+    .with_intensity(0.2)
+    .with_iterations(4);
+  assert_eq!(node.get_settings().threshold, 1.5);
+  assert_eq!(node.get_settings().intensity, 0.2);
+  assert_eq!(node.get_settings().iterations, 4);
+}
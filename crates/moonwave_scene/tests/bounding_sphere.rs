@@ -0,0 +1,52 @@
+use moonwave_common::{Vector3, Vector4};
+use moonwave_scene::{BoundingShape, Mesh};
+use moonwave_shader::vertex;
+
+#[vertex]
+struct PositionVertex {
+  position: Vector3<f32>,
+}
+
+fn unit_cube() -> Mesh<PositionVertex, u16> {
+  let mut mesh = Mesh::new();
+  for i in 0..8 {
+    mesh.push_vertex(PositionVertex {
+      position: Vector3::new(
+        if i & 1 == 0 { -0.5 } else { 0.5 },
+        if i & 2 == 0 { -0.5 } else { 0.5 },
+        if i & 4 == 0 { -0.5 } else { 0.5 },
+      ),
+    });
+  }
+  mesh
+}
+
+#[test]
+fn unit_cube_bounding_sphere_test() {
+  let (center, radius) = unit_cube().compute_bounding_sphere();
+
+  assert!((radius - 3.0f32.sqrt() / 2.0).abs() < 1e-5);
+  assert!(center.x.abs() < 1e-5);
+  assert!(center.y.abs() < 1e-5);
+  assert!(center.z.abs() < 1e-5);
+}
+
+#[test]
+fn sphere_frustum_test() {
+  let frustum = [
+    Vector4::new(1.0, 0.0, 0.0, 1.0),
+    Vector4::new(-1.0, 0.0, 0.0, 1.0),
+    Vector4::new(0.0, 1.0, 0.0, 1.0),
+    Vector4::new(0.0, -1.0, 0.0, 1.0),
+    Vector4::new(0.0, 0.0, 1.0, 1.0),
+    Vector4::new(0.0, 0.0, -1.0, 1.0),
+  ];
+  let sphere = |x: f32| BoundingShape::Sphere {
+    center: Vector3::new(x, 0.0, 0.0),
+    radius: 0.5,
+  };
+
+  assert!(sphere(0.0).visible_in_frustum(&frustum));
+  assert!(sphere(1.4).visible_in_frustum(&frustum));
+  assert!(!sphere(1.6).visible_in_frustum(&frustum));
+}
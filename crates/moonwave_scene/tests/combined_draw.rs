@@ -0,0 +1,91 @@
+use moonwave_scene::opt::{coalesce_draw_ranges, CombinedDrawRange};
+
+const INDICES_PER_CHUNK: usize = 6;
+
+/// Places meshes into a simulated index buffer like the combiner does, padding with the last index.
+fn place(
+  buffer: &mut Vec<u32>,
+  generation: usize,
+  chunk_start: usize,
+  mesh: &[u32],
+) -> CombinedDrawRange {
+  let chunks = (mesh.len() + INDICES_PER_CHUNK - 1) / INDICES_PER_CHUNK;
+  let start = chunk_start * INDICES_PER_CHUNK;
+  let padded_end = (chunk_start + chunks) * INDICES_PER_CHUNK;
+  if buffer.len() < padded_end {
+    buffer.resize(padded_end, u32::MAX);
+  }
+  buffer[start..start + mesh.len()].copy_from_slice(mesh);
+  for index in start + mesh.len()..padded_end {
+    buffer[index] = *mesh.last().unwrap();
+  }
+
+  CombinedDrawRange {
+    generation,
+    start,
+    end: start + mesh.len(),
+    padded_end,
+  }
+}
+
+/// Triangles that actually produce pixels, degenerate ones are skipped by the rasterizer.
+fn visible_triangles(buffers: &[Vec<u32>], ranges: &[CombinedDrawRange]) -> Vec<(usize, [u32; 3])> {
+  let mut triangles = ranges
+    .iter()
+    .flat_map(|range| {
+      buffers[range.generation][range.start..range.end]
+        .chunks(3)
+        .map(move |tri| (range.generation, [tri[0], tri[1], tri[2]]))
+    })
+    .filter(|(_, tri)| tri[0] != tri[1] && tri[1] != tri[2] && tri[0] != tri[2])
+    .collect::<Vec<_>>();
+  triangles.sort_unstable();
+  triangles
+}
+
+#[test]
+fn coalesced_draws_match_per_entry_draws_test() {
+  let mut buffers = vec![Vec::new(), Vec::new()];
+  let ranges = vec![
+    // Three neighbouring meshes, the first one pads three indices.
+    place(&mut buffers[0], 0, 0, &[0, 1, 2, 2, 3, 0, 4, 5, 6]),
+    place(&mut buffers[0], 0, 2, &[7, 8, 9, 9, 10, 7]),
+    place(&mut buffers[0], 0, 3, &[11, 12, 13]),
+    // A gap to the next mesh.
+    place(&mut buffers[0], 0, 6, &[14, 15, 16]),
+    // Same chunk positions within another generation.
+    place(&mut buffers[1], 1, 0, &[0, 1, 2]),
+    place(&mut buffers[1], 1, 1, &[3, 4, 5]),
+  ];
+
+  let coalesced = coalesce_draw_ranges(ranges.iter().rev().copied().collect());
+  assert_eq!(coalesced.len(), 3);
+  assert_eq!((coalesced[0].start, coalesced[0].end), (0, 21));
+  assert_eq!((coalesced[1].start, coalesced[1].end), (36, 39));
+  assert_eq!((coalesced[2].generation, coalesced[2].end), (1, 9));
+
+  assert_eq!(
+    visible_triangles(&buffers, &coalesced),
+    visible_triangles(&buffers, &ranges)
+  );
+}
+
+#[test]
+fn misaligned_padding_is_not_merged_test() {
+  let ranges = vec![
+    CombinedDrawRange {
+      generation: 0,
+      start: 0,
+      end: 3,
+      padded_end: 4,
+    },
+    CombinedDrawRange {
+      generation: 0,
+      start: 4,
+      end: 7,
+      padded_end: 8,
+    },
+  ];
+
+  assert_eq!(coalesce_draw_ranges(ranges.clone()), ranges);
+}
@@ -0,0 +1,72 @@
+//! Helpers of tests that need a device, they are skipped on machines without a usable adapter.
+#![allow(dead_code)]
+
+use moonwave_common::{Vector2, Vector3};
+use moonwave_core::{Core, CoreConfig, OnceCell};
+use moonwave_scene::{Material, Mesh, PBRShaderNode};
+use moonwave_shader::{vertex, ShaderGraph};
+
+static HEADLESS: OnceCell<bool> = OnceCell::new();
+
+/// Initializes a small headless core once per test binary, `false` if there is no adapter.
+pub fn headless_core() -> bool {
+  headless_core_with(CoreConfig::new())
+}
+
+/// Same as `headless_core` but the config is only used by the first caller.
+pub fn headless_core_with(config: CoreConfig) -> bool {
+  *HEADLESS
+    .get_or_init(|| Core::try_initialize_headless(config.with_headless_size(Vector2::new(64, 64))))
+}
+
+#[vertex]
+pub struct TestVertex {
+  pub position: Vector3<f32>,
+  pub normal: Vector3<f32>,
+  pub tangent: Vector3<f32>,
+  pub bitangent: Vector3<f32>,
+}
+
+/// Unit quad within the xy plane facing +z.
+pub fn quad() -> Mesh<TestVertex, u16> {
+  let mut mesh = Mesh::new();
+  for (x, y) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].iter() {
+    mesh.push_vertex(TestVertex {
+      position: Vector3::new(*x, *y, 0.0),
+      normal: Vector3::new(0.0, 0.0, 1.0),
+      tangent: Vector3::new(1.0, 0.0, 0.0),
+      bitangent: Vector3::new(0.0, 1.0, 0.0),
+    });
+  }
+  for index in [0, 1, 2, 0, 2, 3].iter() {
+    mesh.push_index(*index);
+  }
+  mesh
+}
+
+/// PBR material reading `TestVertex`, the base color is left at its default.
+pub fn pbr_graph() -> (ShaderGraph, moonwave_shader::Index) {
+  let mut graph = ShaderGraph::new();
+  let (vertex_in, _) = graph.add_vertex_attributes::<TestVertex>();
+  let (pbr_graph, pbr_input) = PBRShaderNode::build_graph();
+  let (pbr, _) = graph.add_sub_graph(&pbr_graph, Some(pbr_input), None);
+  let pbr = pbr.unwrap();
+  for (output, input) in [
+    (TestVertex::OUTPUT_POSITION, PBRShaderNode::INPUT_POSITION),
+    (TestVertex::OUTPUT_NORMAL, PBRShaderNode::INPUT_VNORMAL),
+    (TestVertex::OUTPUT_TANGENT, PBRShaderNode::INPUT_VTANGENT),
+    (
+      TestVertex::OUTPUT_BITANGENT,
+      PBRShaderNode::INPUT_VBITANGENT,
+    ),
+  ]
+  .iter()
+  {
+    graph.connect(vertex_in, *output, pbr, *input).unwrap();
+  }
+  (graph, pbr)
+}
+
+pub fn pbr_material() -> Material {
+  Material::new(pbr_graph().0)
+}
@@ -0,0 +1,124 @@
+use moonwave_resources::{texture_bytes_per_row, TextureFormat};
+use moonwave_scene::{
+  create_compressed_texture_data, create_texture_data, TextureCodec, TextureData,
+};
+
+/// Builds a DDS file with the DX10 extended header and zeroed block data for all mips.
+fn build_dds(width: u32, height: u32, mips: u32, dxgi_format: u32, block_size: u32) -> Vec<u8> {
+  let mut header = vec![0u32; 31];
+  header[0] = 124; // size
+  header[1] = 0x1 | 0x2 | 0x4 | 0x1000 | 0x20000; // caps, height, width, pixel format, mip count
+  header[2] = height;
+  header[3] = width;
+  header[6] = mips;
+  header[18] = 32; // pixel format size
+  header[19] = 0x4; // fourcc flag
+  header[20] = u32::from_le_bytes(*b"DX10");
+  header[26] = 0x1000 | 0x400000 | 0x8; // texture, mipmap, complex
+
+  let mut data = b"DDS ".to_vec();
+  for value in header.iter().chain(&[dxgi_format, 3, 0, 1, 0]) {
+    data.extend_from_slice(&value.to_le_bytes());
+  }
+  for mip in 0..mips {
+    let blocks_x = ((width >> mip).max(1) + 3) / 4;
+    let blocks_y = ((height >> mip).max(1) + 3) / 4;
+    data.extend(vec![mip as u8; (blocks_x * blocks_y * block_size) as usize]);
+  }
+  data
+}
+
+#[test]
+fn bc7_embedded_mips_test() {
+  let dds = build_dds(64, 32, 7, 98, 16);
+  let texture = create_compressed_texture_data(TextureCodec::DDS, &dds)
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(texture.format, TextureFormat::Bc7RgbaUnorm);
+  assert_eq!((texture.size.x, texture.size.y), (64, 32));
+  assert_eq!(texture.mip_count(), 7);
+
+  // 16x8 blocks for the first mip down to a single block for the smallest ones.
+  let sizes = texture.mips.iter().map(|mip| mip.len()).collect::<Vec<_>>();
+  assert_eq!(sizes, vec![2048, 512, 128, 32, 16, 16, 16]);
+  assert!(texture.mips[3].iter().all(|byte| *byte == 3));
+}
+
+#[test]
+fn bc1_srgb_test() {
+  let dds = build_dds(16, 16, 1, 72, 8);
+  let texture = create_compressed_texture_data(TextureCodec::DDS, &dds)
+    .unwrap()
+    .unwrap();
+
+  assert_eq!(texture.format, TextureFormat::Bc1RgbaUnormSrgb);
+  assert_eq!(texture.mip_count(), 1);
+  assert_eq!(texture.mips[0].len(), 128);
+}
+
+#[test]
+fn truncated_compressed_data_test() {
+  let dds = build_dds(64, 64, 4, 98, 16);
+  assert!(create_compressed_texture_data(TextureCodec::DDS, &dds[..dds.len() - 1]).is_err());
+}
+
+#[test]
+fn compressed_row_pitch_test() {
+  assert_eq!(texture_bytes_per_row(TextureFormat::Bc7RgbaUnorm, 64), 256);
+  assert_eq!(texture_bytes_per_row(TextureFormat::Bc1RgbaUnorm, 64), 128);
+  assert_eq!(texture_bytes_per_row(TextureFormat::Bc7RgbaUnorm, 2), 16);
+  assert_eq!(texture_bytes_per_row(TextureFormat::Rgba8Unorm, 64), 256);
+}
+
+/// Builds a legacy DDS file using the `DXT1` fourcc without mips.
+fn build_dxt1_dds(width: u32, height: u32) -> Vec<u8> {
+  let mut header = vec![0u32; 31];
+  header[0] = 124; // size
+  header[1] = 0x1 | 0x2 | 0x4 | 0x1000; // caps, height, width, pixel format
+  header[2] = height;
+  header[3] = width;
+  header[18] = 32; // pixel format size
+  header[19] = 0x4; // fourcc flag
+  header[20] = u32::from_le_bytes(*b"DXT1");
+  header[26] = 0x1000; // texture
+
+  let mut data = b"DDS ".to_vec();
+  for value in header.iter() {
+    data.extend_from_slice(&value.to_le_bytes());
+  }
+  data.extend(vec![0u8; ((width / 4) * (height / 4) * 8) as usize]);
+  data
+}
+
+#[test]
+fn compressed_upload_when_supported_test() {
+  let dds = build_dxt1_dds(16, 16);
+  match create_texture_data(TextureCodec::DDS, &dds, true).unwrap() {
+    TextureData::Compressed(texture) => {
+      assert_eq!(texture.format, TextureFormat::Bc1RgbaUnorm);
+      assert_eq!(texture.mips[0].len(), 128);
+    }
+    TextureData::Raw { .. } => panic!("expected compressed texture data"),
+  }
+}
+
+#[test]
+fn decoded_fallback_without_compression_support_test() {
+  let dds = build_dxt1_dds(16, 16);
+  match create_texture_data(TextureCodec::DDS, &dds, false).unwrap() {
+    TextureData::Raw {
+      size,
+      buffer,
+      format,
+      row_size,
+    } => {
+      assert_eq!(format, TextureFormat::Rgba8Unorm);
+      assert_eq!((size.x, size.y), (16, 16));
+      // Rows are padded to the copy alignment.
+      assert_eq!(row_size, 256);
+      assert_eq!(buffer.len(), 16 * 256);
+    }
+    TextureData::Compressed(_) => panic!("expected decoded texture data"),
+  }
+}
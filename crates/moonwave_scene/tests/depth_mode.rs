@@ -0,0 +1,82 @@
+mod common;
+
+use common::headless_core;
+use moonwave_common::{look_at, perspective, Rad, Vector3, Vector4};
+use moonwave_render::RenderPassCommandEncoderBuilder;
+use moonwave_resources::{CompareFunction, DepthMode};
+use moonwave_scene::{depth_mode_projection, passes_frustum_culling, BoundingShape, Camera};
+
+#[test]
+fn reverse_z_test() {
+  // Standard depth is left untouched.
+  assert_eq!(DepthMode::Standard.clear_value(), 1.0);
+  assert_eq!(
+    DepthMode::Standard.compare(CompareFunction::Less),
+    CompareFunction::Less
+  );
+
+  // Reverse-Z flips compare function and clear value together.
+  assert_eq!(DepthMode::ReverseZ.clear_value(), 0.0);
+  assert_eq!(
+    DepthMode::ReverseZ.compare(CompareFunction::Less),
+    CompareFunction::Greater
+  );
+  assert_eq!(
+    DepthMode::ReverseZ.compare(CompareFunction::LessEqual),
+    CompareFunction::GreaterEqual
+  );
+  assert_eq!(
+    DepthMode::ReverseZ.compare(CompareFunction::Always),
+    CompareFunction::Always
+  );
+
+  // Render passes clear to the far plane of their mode.
+  let mut builder = RenderPassCommandEncoderBuilder::new("test");
+  assert_eq!(builder.get_depth_clear_value(), 1.0);
+  builder.set_depth_mode(DepthMode::ReverseZ);
+  assert_eq!(builder.get_depth_clear_value(), 0.0);
+}
+
+#[test]
+fn reverse_z_projection_test() {
+  let projection =
+    depth_mode_projection(DepthMode::ReverseZ, perspective(Rad(1.0), 1.0, 0.1, 100.0));
+  let depth = |z: f32| {
+    let clip = projection * Vector4::new(0.0, 0.0, -z, 1.0);
+    clip.z / clip.w
+  };
+
+  // Near plane ends up at 1 and far plane at 0.
+  assert!((depth(0.1) - 1.0).abs() < 1e-4);
+  assert!(depth(100.0).abs() < 1e-4);
+  assert!(depth(1.0) > depth(10.0));
+}
+
+#[test]
+fn reverse_z_frustum_culling_test() {
+  if !headless_core() {
+    return;
+  }
+
+  // Sphere of radius 1 at the given distance in front of a camera looking along +z.
+  let sphere = |distance: f32| BoundingShape::Sphere {
+    center: Vector3::new(0.0, 0.0, distance),
+    radius: 1.0,
+  };
+  for mode in [DepthMode::Standard, DepthMode::ReverseZ].iter() {
+    let mut camera = Camera::new();
+    camera.depth_mode = *mode;
+    let view = look_at(camera.position, camera.target, camera.up);
+    camera.uniform.get_mut().projection_view = camera.calculate_projection() * view;
+
+    let mut frustum = [Vector4::new(0.0, 0.0, 0.0, 0.0); 6];
+    camera.calculate_frustum_planes(&mut frustum);
+    let visible = |distance: f32| passes_frustum_culling(Some(&sphere(distance)), false, &frustum);
+
+    // The far plane is at 100 units, behind the camera nothing is visible.
+    assert!(visible(10.0), "{:?}", mode);
+    assert!(visible(100.5), "{:?}", mode);
+    assert!(!visible(200.0), "{:?}", mode);
+    assert!(!visible(-10.0), "{:?}", mode);
+  }
+}
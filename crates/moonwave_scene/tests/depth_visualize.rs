@@ -0,0 +1,33 @@
+use moonwave_common::{perspective, Rad, Vector4};
+use moonwave_core::{compile_glsl, ShaderKind};
+use moonwave_resources::DepthMode;
+use moonwave_scene::{depth_mode_projection, linearize_depth, DEPTH_VISUALIZE_FRAGMENT_SHADER};
+
+#[test]
+fn depth_visualize_shader_compiles() {
+  compile_glsl(
+    DEPTH_VISUALIZE_FRAGMENT_SHADER,
+    "DepthVisualizeFS",
+    ShaderKind::Fragment,
+  )
+  .unwrap();
+}
+
+#[test]
+fn linearized_depth_matches_view_distance() {
+  let (z_near, z_far) = (0.1, 50.0);
+  for mode in [DepthMode::Standard, DepthMode::ReverseZ].iter() {
+    let projection = depth_mode_projection(*mode, perspective(Rad(1.0), 1.0, z_near, z_far));
+    for distance in [0.5f32, 3.0, 20.0, 49.0].iter() {
+      let clip = projection * Vector4::new(0.0, 0.0, -distance, 1.0);
+      let linear = linearize_depth(clip.z / clip.w, z_near, z_far, *mode);
+      assert!(
+        (linear - distance).abs() < distance * 1e-3,
+        "{:?}: {} != {}",
+        mode,
+        linear,
+        distance
+      );
+    }
+  }
+}
@@ -0,0 +1,49 @@
+use moonwave_common::{Matrix4, SquareMatrix, Vector3};
+use moonwave_core::{compile_glsl, ShaderKind};
+use moonwave_resources::DepthMode;
+use moonwave_scene::{
+  circle_of_confusion, screen_effect_shader, DepthOfFieldNode, DOF_FRAGMENT_SHADER,
+};
+
+#[test]
+fn dof_shader_compiles() {
+  compile_glsl(
+    &screen_effect_shader(DOF_FRAGMENT_SHADER),
+    "DofFS",
+    ShaderKind::Fragment,
+  )
+  .unwrap();
+}
+
+#[test]
+fn dof_focus_plane_stays_sharp() {
+  assert_eq!(circle_of_confusion(10.0, 10.0, 0.5), 0.0);
+
+  // Blur grows with the distance to the focus plane in both directions.
+  let near = circle_of_confusion(5.0, 10.0, 0.5);
+  let far = circle_of_confusion(40.0, 10.0, 0.5);
+  assert!(near > 0.0 && far > 0.0);
+  assert!(circle_of_confusion(2.0, 10.0, 0.5) > near);
+  assert!(circle_of_confusion(80.0, 10.0, 0.5) > far);
+
+  // Wider apertures blur more but never above the maximum.
+  assert!(circle_of_confusion(40.0, 10.0, 1.0) > far);
+  assert_eq!(circle_of_confusion(0.1, 10.0, 10.0), 1.0);
+}
+
+#[test]
+fn dof_node_settings() {
+  let node = DepthOfFieldNode::new(
+    Matrix4::identity(),
+    Vector3::new(0.0, 0.0, 0.0),
+    DepthMode::Standard,
+    4.0,
+    0.8,
+  )
+  .with_max_radius(12.0)
+  .with_samples(32);
+  assert_eq!(node.get_settings().focus_distance, 4.0);
+  assert_eq!(node.get_settings().aperture, 0.8);
+  assert_eq!(node.get_settings().max_radius, 12.0);
+  assert_eq!(node.get_settings().samples, 32);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::{headless_core_with, pbr_graph, quad};
+use legion::SystemBuilder;
+use moonwave_common::{Vector3, Vector4};
+use moonwave_core::{Core, CoreConfig};
+use moonwave_resources::TextureFormat;
+use moonwave_scene::{
+  get_pbr_main_color_target, Camera, DirectionalLight, LightIntensity, LightManager, MainCameraTag,
+  Material, MeshRenderer, PBRShaderNode, Transform,
+};
+use moonwave_shader::Constant;
+
+/// Decodes a half float, denormals are flushed to zero.
+fn half_to_f32(bits: u16) -> f32 {
+  let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+  let exponent = ((bits >> 10) & 0x1f) as i32;
+  let mantissa = (bits & 0x3ff) as f32 / 1024.0;
+  match exponent {
+    0 => 0.0,
+    0x1f => sign * f32::INFINITY,
+    _ => sign * (1.0 + mantissa) * 2f32.powi(exponent - 15),
+  }
+}
+
+#[test]
+fn bright_light_survives_in_hdr_target_test() {
+  if !headless_core_with(CoreConfig::new().with_hdr(true)) {
+    return;
+  }
+  let core = Core::get_instance();
+  assert_eq!(core.get_color_target_format(), TextureFormat::Rgba16Float);
+
+  // White quad filling the whole view.
+  let (mut graph, pbr) = pbr_graph();
+  let white = graph.add_node(Constant::new(Vector4::new(1.0, 1.0, 1.0, 1.0)));
+  graph
+    .connect(white, 0, pbr, PBRShaderNode::INPUT_BASE_COLOR)
+    .unwrap();
+  let origin = Vector3::new(0.0, 0.0, 0.0);
+  let transform = Transform::new_static(origin, origin, Vector3::new(1.0, 1.0, 1.0));
+  let renderer =
+    MeshRenderer::new_uncombined(&Material::new(graph), &quad(), Vec::new(), &transform).unwrap();
+
+  let mut camera = Camera::new();
+  camera.position = Vector3::new(0.5, 0.5, 1.0);
+  camera.target = Vector3::new(0.5, 0.5, 0.0);
+
+  // Lit head-on by a light far brighter than the displayable range.
+  let mut light = DirectionalLight::new();
+  light.intensity = LightIntensity::Lumen(100_000.0);
+  light.direction = Vector3::new(0.0, 0.0, 1.0);
+
+  let mut scene = Some((renderer, transform, camera, light, LightManager::new()));
+  core
+    .get_world()
+    .add_temp_system(Box::new(SystemBuilder::new("spawn_hdr_scene").build(
+      move |cmd, _, _, _| {
+        if let Some((renderer, transform, camera, light, manager)) = scene.take() {
+          cmd.push((renderer, transform));
+          cmd.push((camera, MainCameraTag));
+          cmd.push((manager, light));
+        }
+      },
+    )));
+  for _ in 0..3 {
+    Core::run_headless_frame().unwrap();
+  }
+
+  // The scene target is read before tonemapping clamps it into the sRGB target.
+  let target = get_pbr_main_color_target().unwrap();
+  let size = core.get_swap_chain_size();
+  let pixels = core.read_texture(&target.texture, TextureFormat::Rgba16Float, size);
+  let center = ((size.y / 2 * size.x + size.x / 2) * 8) as usize;
+  for channel in 0..3 {
+    let offset = center + channel * 2;
+    let value = half_to_f32(u16::from_le_bytes([pixels[offset], pixels[offset + 1]]));
+    assert!(value > 1.0, "Channel {} is clamped to {}", channel, value);
+  }
+}
@@ -0,0 +1,48 @@
+use moonwave_common::{InnerSpace, Vector3, Vector4};
+use moonwave_scene::imd;
+
+#[test]
+fn aabb_edges_test() {
+  let edges = imd::aabb_edges(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+
+  // Every edge of a unit cube has the same length and is parallel to one axis.
+  for (from, to) in edges.iter() {
+    let delta = to - from;
+    assert!((delta.magnitude() - 2.0).abs() < 1e-6);
+    let axes = [delta.x, delta.y, delta.z]
+      .iter()
+      .filter(|v| v.abs() > 1e-6)
+      .count();
+    assert_eq!(axes, 1);
+  }
+}
+
+#[test]
+fn sphere_edges_test() {
+  let center = Vector3::new(1.0, 2.0, 3.0);
+  let edges = imd::sphere_edges(center, 2.0);
+  assert_eq!(edges.len(), imd::SPHERE_SEGMENTS * 3);
+
+  // All points lie on the sphere and each circle is closed.
+  for (from, to) in edges.iter() {
+    assert!(((from - center).magnitude() - 2.0).abs() < 1e-5);
+    assert!(((to - center).magnitude() - 2.0).abs() < 1e-5);
+  }
+  for circle in edges.chunks(imd::SPHERE_SEGMENTS) {
+    let (first, _) = circle[0];
+    let (_, last) = circle[imd::SPHERE_SEGMENTS - 1];
+    assert!((first - last).magnitude() < 1e-5);
+  }
+}
+
+#[test]
+fn accumulate_lines_test() {
+  let color = Vector4::new(1.0, 0.0, 0.0, 1.0);
+  let min = Vector3::new(0.0, 0.0, 0.0);
+  let max = Vector3::new(1.0, 1.0, 1.0);
+
+  imd::line(min, max, color);
+  imd::aabb(min, max, color);
+  imd::sphere(min, 1.0, color);
+  assert_eq!(imd::pending_lines(), 1 + 12 + imd::SPHERE_SEGMENTS * 3);
+}
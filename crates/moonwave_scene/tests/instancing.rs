@@ -0,0 +1,208 @@
+mod common;
+
+use common::{headless_core, pbr_graph, quad};
+use legion::SystemBuilder;
+use moonwave_common::{Matrix4, Vector3, Vector4};
+use moonwave_core::{compile_glsl, Core, ShaderKind};
+use moonwave_resources::{validate_vertex_buffers, InputStepMode, RenderPipelineVertexBuffer};
+use moonwave_scene::{
+  get_pbr_main_color_target, instance_matrix_location, instance_vertex_buffer, Camera,
+  InstanceTransforms, InstancedDrawCall, InstancedMesh, LightManager, MainCameraTag, Material,
+  PBRShaderNode,
+};
+use moonwave_shader::{vertex, Constant, ShaderGraph, VertexStruct};
+
+fn grid(count: usize) -> InstanceTransforms {
+  (0..count)
+    .map(|i| Matrix4::from_translation(Vector3::new(i as f32, 0.0, 0.0)))
+    .collect::<Vec<_>>()
+    .into()
+}
+
+#[test]
+fn instanced_draw_test() {
+  let transforms = grid(100);
+
+  // All instances end up in a single draw.
+  assert_eq!(
+    transforms.draw_call(36),
+    Some(InstancedDrawCall {
+      indices: 0..36,
+      instances: 0..100,
+    })
+  );
+  assert_eq!(transforms.build_instance_data().len(), 100);
+}
+
+#[test]
+fn instanced_toggle_test() {
+  let mut transforms = grid(100);
+  transforms.set_visible(10, false);
+
+  // Hidden instances are packed out so the draw stays a single range.
+  let data = transforms.build_instance_data();
+  assert_eq!(data.len(), 99);
+  assert_eq!(data[10][3][0], 11.0);
+  assert_eq!(transforms.draw_call(36).unwrap().instances, 0..99);
+
+  // Nothing visible results in no draw at all.
+  let mut transforms = grid(1);
+  transforms.set_visible(0, false);
+  assert_eq!(transforms.draw_call(36), None);
+}
+
+#[vertex]
+struct InstancedVertex {
+  position: Vector3<f32>,
+  normal: Vector3<f32>,
+  tangent: Vector3<f32>,
+  bitangent: Vector3<f32>,
+}
+
+#[test]
+fn instanced_material_reads_instance_matrix_test() {
+  let mut graph = ShaderGraph::new();
+  let (vertex_in, _) = graph.add_vertex_attributes::<InstancedVertex>();
+  let (pbr_graph, pbr_input) = PBRShaderNode::build_graph();
+  let (pbr, _) = graph.add_sub_graph(&pbr_graph, Some(pbr_input), None);
+  let pbr = pbr.unwrap();
+  for (output, input) in [
+    (
+      InstancedVertex::OUTPUT_POSITION,
+      PBRShaderNode::INPUT_POSITION,
+    ),
+    (InstancedVertex::OUTPUT_NORMAL, PBRShaderNode::INPUT_VNORMAL),
+    (
+      InstancedVertex::OUTPUT_TANGENT,
+      PBRShaderNode::INPUT_VTANGENT,
+    ),
+    (
+      InstancedVertex::OUTPUT_BITANGENT,
+      PBRShaderNode::INPUT_VBITANGENT,
+    ),
+  ]
+  .iter()
+  {
+    graph.connect(vertex_in, *output, pbr, *input).unwrap();
+  }
+
+  let material = Material::new(graph);
+  let (vs, fs) = material.dump_shaders(&InstancedMesh::build_params::<InstancedVertex>());
+
+  // The instance matrix follows directly after the four mesh attributes.
+  assert!(vs.contains("layout (location = 4) in mat4 a_instance_matrix;"));
+  assert!(vs.contains("a_instance_matrix * vec4("));
+  assert!(!fs.contains("a_instance_matrix"));
+  compile_glsl(&vs, "InstancedVS", ShaderKind::Vertex).unwrap();
+}
+
+#[test]
+fn instance_buffer_layout_test() {
+  let location = instance_matrix_location(&InstancedVertex::generate_buffer());
+  assert_eq!(location, 4);
+  let layout = instance_vertex_buffer(location);
+  assert_eq!(layout.stride, 64);
+  assert_eq!(
+    layout
+      .attributes
+      .iter()
+      .map(|attribute| attribute.location)
+      .collect::<Vec<_>>(),
+    (location..location + 4).collect::<Vec<_>>()
+  );
+
+  // Per-instance attributes don't collide with the mesh attributes.
+  validate_vertex_buffers(&[
+    RenderPipelineVertexBuffer {
+      layout: InstancedVertex::generate_buffer(),
+      step_mode: InputStepMode::Vertex,
+    },
+    RenderPipelineVertexBuffer {
+      layout,
+      step_mode: InputStepMode::Instance,
+    },
+  ])
+  .unwrap();
+}
+
+#[test]
+fn instanced_mesh_renders_in_single_draw_test() {
+  if !headless_core() {
+    return;
+  }
+  let core = Core::get_instance();
+
+  // 10x10 black quads tiling the whole view, the one at column 5 and row 5 is hidden.
+  let (mut graph, pbr) = pbr_graph();
+  let black = graph.add_node(Constant::new(Vector4::new(0.0, 0.0, 0.0, 1.0)));
+  graph
+    .connect(black, 0, pbr, PBRShaderNode::INPUT_BASE_COLOR)
+    .unwrap();
+  let mut transforms: InstanceTransforms = (0..100)
+    .map(|i| {
+      let offset = Vector3::new(
+        (i % 10) as f32 * 0.1 - 0.5,
+        (i / 10) as f32 * 0.1 - 0.5,
+        0.0,
+      );
+      Matrix4::from_translation(offset) * Matrix4::from_nonuniform_scale(0.1, 0.1, 1.0)
+    })
+    .collect::<Vec<_>>()
+    .into();
+  transforms.set_visible(55, false);
+  let mesh = InstancedMesh::new(&Material::new(graph), &quad(), Vec::new(), transforms).unwrap();
+  assert_eq!(
+    mesh.draw_call(),
+    Some(InstancedDrawCall {
+      indices: 0..6,
+      instances: 0..99,
+    })
+  );
+
+  // At this distance the 45 degree field of view covers exactly the grid.
+  let mut camera = Camera::new();
+  camera.position = Vector3::new(0.0, 0.0, 0.5 / (std::f32::consts::FRAC_PI_8).tan());
+  camera.target = Vector3::new(0.0, 0.0, 0.0);
+
+  let mut scene = Some((mesh, camera, LightManager::new()));
+  core
+    .get_world()
+    .add_temp_system(Box::new(SystemBuilder::new("spawn_instanced_scene").build(
+      move |cmd, _, _, _| {
+        if let Some((mesh, camera, manager)) = scene.take() {
+          cmd.push((mesh,));
+          cmd.push((camera, MainCameraTag));
+          cmd.push((manager,));
+        }
+      },
+    )));
+  for _ in 0..3 {
+    Core::run_headless_frame().unwrap();
+  }
+
+  let target = get_pbr_main_color_target().unwrap();
+  let size = core.get_swap_chain_size();
+  let pixels = core.read_texture(&target.texture, core.get_color_target_format(), size);
+  let brightness = |column: u32, row: u32| {
+    // Center of a grid cell, rows go up in the world but down in the image.
+    let cell = size.x as f32 / 10.0;
+    let x = (column as f32 + 0.5) * cell;
+    let y = size.y as f32 - (row as f32 + 0.5) * cell;
+    let offset = ((y as u32 * size.x + x as u32) * 4) as usize;
+    pixels[offset..offset + 3]
+      .iter()
+      .map(|c| *c as u32)
+      .sum::<u32>()
+  };
+
+  // Every visible instance is drawn, the hidden one keeps the white clear color.
+  for (column, row) in [(0, 0), (9, 9), (4, 5), (6, 5), (5, 4), (5, 6)].iter() {
+    assert!(
+      brightness(*column, *row) < 64,
+      "Instance at {}x{} was not drawn",
+      column,
+      row
+    );
+  }
+  assert!(brightness(5, 5) > 700);
+}
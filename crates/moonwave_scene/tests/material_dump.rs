@@ -0,0 +1,36 @@
+use moonwave_common::Vector3;
+use moonwave_scene::Material;
+use moonwave_shader::{vertex, Constant, ShaderBuildParams, ShaderGraph, ShaderType};
+
+#[vertex]
+struct DumpVertex {
+  position: Vector3<f32>,
+}
+
+#[test]
+fn dump_material_shaders_test() {
+  let mut graph = ShaderGraph::new();
+  let (_, vertex_out) = graph.add_vertex_attributes::<DumpVertex>();
+  let color = graph.add_color_output("color", ShaderType::Float4);
+  let red = graph.add_node(Constant::with_type(
+    ShaderType::Float4,
+    &[1.0, 0.0, 0.0, 1.0],
+  ));
+  let position = graph.add_node(Constant::with_type(
+    ShaderType::Float4,
+    &[0.0, 0.0, 0.0, 1.0],
+  ));
+  graph
+    .connect(position, Constant::OUTPUT, vertex_out, 0)
+    .unwrap();
+  graph.connect(red, Constant::OUTPUT, color, 0).unwrap();
+
+  let material = Material::new(graph);
+  let (vs, fs) = material.dump_shaders(&ShaderBuildParams::new());
+  assert!(vs.starts_with("#version 450"));
+  assert!(fs.starts_with("#version 450"));
+  assert!(fs.contains("vec4(1.0000000,0.0000000,0.0000000,1.0000000)"));
+
+  // Dumping doesn't change the graph, so it yields the same source again.
+  assert_eq!(material.dump_shaders(&ShaderBuildParams::new()), (vs, fs));
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::{headless_core, pbr_graph, pbr_material, quad};
+use moonwave_common::Vector3;
+use moonwave_scene::{
+  Material, MaterialError, MeshRenderPath, MeshRenderer, PBRShaderNode, Transform,
+};
+use moonwave_shader::{ShaderBuildParams, ShaderNode, ShaderType};
+use std::sync::Arc;
+
+/// Custom node referencing a variable that doesn't exist, like a typo within an editor.
+#[derive(Debug)]
+struct BrokenNode;
+impl ShaderNode for BrokenNode {
+  fn get_outputs(&self) -> Vec<ShaderType> {
+    vec![ShaderType::Float4]
+  }
+
+  fn generate(&self, _inputs: &[Option<String>], outputs: &[Option<String>], output: &mut String) {
+    *output += format!("vec4 {} = undefined_value;\n", outputs[0].as_ref().unwrap()).as_str();
+  }
+}
+
+fn broken_material() -> Material {
+  let (mut graph, pbr) = pbr_graph();
+  let broken = graph.add_node(BrokenNode);
+  graph
+    .connect(broken, 0, pbr, PBRShaderNode::INPUT_BASE_COLOR)
+    .unwrap();
+  Material::new(graph)
+}
+
+fn transform() -> Transform {
+  let origin = Vector3::new(0.0, 0.0, 0.0);
+  Transform::new_static(origin, origin, Vector3::new(1.0, 1.0, 1.0))
+}
+
+#[test]
+fn broken_material_build_test() {
+  if !headless_core() {
+    return;
+  }
+
+  let err = match broken_material().build(&ShaderBuildParams::new()) {
+    Err(MaterialError::Shader(err)) => err,
+    _ => panic!("broken material didn't fail to compile"),
+  };
+  assert!(err.message().contains("undefined_value"));
+  assert!(err.source_code().contains("undefined_value"));
+
+  // Renderers fail instead of panicking.
+  assert!(MeshRenderer::new(&broken_material(), &quad(), Vec::new(), &transform()).is_err());
+}
+
+#[test]
+fn set_broken_material_keeps_previous_test() {
+  if !headless_core() {
+    return;
+  }
+
+  let mesh = quad();
+  let transform = transform();
+  let mut renderer = MeshRenderer::new(&pbr_material(), &mesh, Vec::new(), &transform).unwrap();
+  let previous = renderer.get_material().clone();
+
+  let params = ShaderBuildParams::new();
+  assert!(renderer
+    .set_material(&broken_material(), &params, &mesh, &transform)
+    .is_err());
+  assert!(Arc::ptr_eq(renderer.get_material(), &previous));
+  assert_eq!(renderer.get_render_path(), MeshRenderPath::Combined);
+}
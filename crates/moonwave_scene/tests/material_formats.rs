@@ -0,0 +1,68 @@
+mod common;
+
+use common::{headless_core, pbr_material};
+use moonwave_core::Core;
+use moonwave_resources::{Face, FrontFace, TextureFormat};
+use moonwave_scene::Material;
+use moonwave_shader::{ShaderBuildParams, ShaderGraph};
+
+#[test]
+fn default_material_formats_test() {
+  let material = Material::new(ShaderGraph::new());
+  assert_eq!(material.get_color_format(), None);
+  assert_eq!(material.get_depth_format(), None);
+  assert_eq!(material.get_depth_mode(), None);
+}
+
+#[test]
+fn hdr_material_formats_test() {
+  let material = Material::new(ShaderGraph::new())
+    .with_color_format(TextureFormat::Rgba16Float)
+    .with_depth_format(TextureFormat::Depth24Plus);
+  assert_eq!(
+    material.get_color_format(),
+    Some(TextureFormat::Rgba16Float)
+  );
+  assert_eq!(
+    material.get_depth_format(),
+    Some(TextureFormat::Depth24Plus)
+  );
+}
+
+#[test]
+fn material_pipeline_formats_test() {
+  if !headless_core() {
+    return;
+  }
+  let params = ShaderBuildParams::new();
+
+  // Without a declared format the pipeline renders into the main color target.
+  let built = pbr_material().build(&params).unwrap();
+  assert_eq!(
+    built.pbr_pipeline.get_formats(),
+    Some(vec![Core::get_instance().get_color_target_format()])
+  );
+
+  let built = pbr_material()
+    .with_color_format(TextureFormat::Rgba16Float)
+    .build(&params)
+    .unwrap();
+  assert_eq!(
+    built.pbr_pipeline.get_formats(),
+    Some(vec![TextureFormat::Rgba16Float])
+  );
+}
+
+#[test]
+fn material_culling_test() {
+  let material = Material::new(ShaderGraph::new());
+  assert_eq!(material.get_front_face(), FrontFace::Ccw);
+  assert_eq!(material.get_cull_mode(), Some(Face::Back));
+
+  // Two sided foliage of a mesh with clockwise winding.
+  let material = Material::new(ShaderGraph::new())
+    .with_front_face(FrontFace::Cw)
+    .with_cull_mode(None);
+  assert_eq!(material.get_front_face(), FrontFace::Cw);
+  assert_eq!(material.get_cull_mode(), None);
+}
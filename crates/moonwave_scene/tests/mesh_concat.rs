@@ -0,0 +1,87 @@
+use moonwave_common::{Deg, InnerSpace, Matrix4, SquareMatrix, Vector3};
+use moonwave_scene::Mesh;
+use moonwave_shader::vertex;
+
+#[vertex]
+struct PositionVertex {
+  position: Vector3<f32>,
+  normal: Vector3<f32>,
+  tangent: Vector3<f32>,
+  bitangent: Vector3<f32>,
+}
+
+fn vertex(x: f32, y: f32) -> PositionVertex {
+  PositionVertex {
+    position: Vector3::new(x, y, 0.0),
+    normal: Vector3::new(0.0, 0.0, 1.0),
+    tangent: Vector3::new(1.0, 0.0, 0.0),
+    bitangent: Vector3::new(0.0, 1.0, 0.0),
+  }
+}
+
+fn triangle() -> Mesh<PositionVertex, u16> {
+  let mut mesh = Mesh::new();
+  mesh.push_vertex(vertex(0.0, 0.0));
+  mesh.push_vertex(vertex(1.0, 0.0));
+  mesh.push_vertex(vertex(0.0, 1.0));
+  mesh.push_index(0);
+  mesh.push_index(1);
+  mesh.push_index(2);
+  mesh
+}
+
+#[test]
+fn concat_triangles_test() {
+  let merged = Mesh::concat(&[
+    (triangle(), Matrix4::identity()),
+    (
+      triangle(),
+      Matrix4::from_translation(Vector3::new(0.0, 0.0, 5.0)),
+    ),
+  ]);
+
+  assert_eq!(merged.len_vertices(), 6);
+  assert_eq!(
+    merged.iter_indices().copied().collect::<Vec<_>>(),
+    vec![0, 1, 2, 3, 4, 5]
+  );
+
+  // Second triangle has its transform baked in.
+  let positions = merged
+    .iter_vertices()
+    .map(|vertex| vertex.position)
+    .collect::<Vec<_>>();
+  assert_eq!(positions[1], Vector3::new(1.0, 0.0, 0.0));
+  assert_eq!(positions[4], Vector3::new(1.0, 0.0, 5.0));
+}
+
+#[test]
+fn concat_transforms_normals_test() {
+  // Rotated by 90 degrees around y and stretched along x.
+  let matrix = Matrix4::from_nonuniform_scale(4.0, 1.0, 1.0) * Matrix4::from_angle_y(Deg(90.0));
+  let merged = Mesh::concat(&[(triangle(), matrix)]);
+
+  for vertex in merged.iter_vertices() {
+    assert!((vertex.normal - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+    assert!((vertex.tangent - Vector3::new(0.0, 0.0, -1.0)).magnitude() < 1e-5);
+    assert!((vertex.normal.magnitude() - 1.0).abs() < 1e-5);
+  }
+}
+
+#[test]
+#[should_panic(expected = "can't be addressed by index format")]
+fn concat_overflowing_u16_indices_test() {
+  // 2 * 32768 + 3 vertices exceed the 65536 addressable ones.
+  let large = || {
+    let mut mesh = Mesh::<PositionVertex, u16>::new();
+    for _ in 0..32768 {
+      mesh.push_vertex(vertex(0.0, 0.0));
+    }
+    mesh
+  };
+  Mesh::concat(&[
+    (large(), Matrix4::identity()),
+    (large(), Matrix4::identity()),
+    (triangle(), Matrix4::identity()),
+  ]);
+}
@@ -0,0 +1,42 @@
+use moonwave_common::Vector3;
+use moonwave_resources::IndexFormat;
+use moonwave_scene::{Mesh, MeshIndex};
+use moonwave_shader::vertex;
+
+#[vertex]
+struct PositionVertex {
+  position: Vector3<f32>,
+}
+
+fn mesh_with_vertices<I: MeshIndex>(count: usize) -> Mesh<PositionVertex, I> {
+  let mut mesh = Mesh::with_capacity(count, 0);
+  for _ in 0..count {
+    mesh.push_vertex(PositionVertex {
+      position: Vector3::new(0.0, 0.0, 0.0),
+    });
+  }
+  mesh
+}
+
+#[test]
+fn optimal_index_format_switches_at_u16_limit_test() {
+  // Index 65535 is the last one a u16 can address.
+  assert_eq!(
+    mesh_with_vertices::<u32>(65536).optimal_index_format(),
+    IndexFormat::Uint16
+  );
+  assert_eq!(
+    mesh_with_vertices::<u32>(65537).optimal_index_format(),
+    IndexFormat::Uint32
+  );
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn index_beyond_vertices_panics_test() {
+  let mut mesh = mesh_with_vertices::<u16>(3);
+  mesh.push_index(0);
+  mesh.push_index(1);
+  mesh.push_index(3);
+  mesh.build_index_buffer();
+}
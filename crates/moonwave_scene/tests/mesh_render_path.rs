@@ -0,0 +1,101 @@
+mod common;
+
+use common::{headless_core, pbr_material, quad, TestVertex};
+use moonwave_common::Vector3;
+use moonwave_scene::opt::StaticMeshCombinerConfig;
+use moonwave_scene::{Mesh, MeshRenderPath, MeshRenderer, Transform};
+
+fn origin() -> Vector3<f32> {
+  Vector3::new(0.0, 0.0, 0.0)
+}
+
+#[test]
+fn static_meshes_are_combined_by_default() {
+  let transform = Transform::new_static(origin(), origin(), Vector3::new(1.0, 1.0, 1.0));
+  assert_eq!(
+    MeshRenderPath::select(&transform, true),
+    MeshRenderPath::Combined
+  );
+}
+
+#[test]
+fn uncombined_static_meshes_are_drawn_individually() {
+  let transform = Transform::new_static(origin(), origin(), Vector3::new(1.0, 1.0, 1.0));
+  assert_eq!(
+    MeshRenderPath::select(&transform, false),
+    MeshRenderPath::Individual
+  );
+
+  // Dynamic meshes never end up in a combiner.
+  let transform = Transform::new_dynamic(origin(), origin(), Vector3::new(1.0, 1.0, 1.0));
+  assert_eq!(
+    MeshRenderPath::select(&transform, true),
+    MeshRenderPath::Individual
+  );
+}
+
+#[test]
+fn uncombined_renderer_owns_buffers_test() {
+  if !headless_core() {
+    return;
+  }
+
+  let mesh = quad();
+  let material = pbr_material();
+  let transform = Transform::new_static(origin(), origin(), Vector3::new(1.0, 1.0, 1.0));
+  let renderer = MeshRenderer::new_uncombined(&material, &mesh, Vec::new(), &transform).unwrap();
+  assert_eq!(renderer.get_render_path(), MeshRenderPath::Individual);
+  assert!(renderer.get_vertex_buffer().is_some());
+  assert!(renderer.get_index_buffer().is_some());
+
+  // The same static mesh is merged into a combiner by default.
+  let combined = MeshRenderer::new(&material, &mesh, Vec::new(), &transform).unwrap();
+  assert_eq!(combined.get_render_path(), MeshRenderPath::Combined);
+  assert!(combined.get_vertex_buffer().is_none());
+  assert!(combined.get_index_buffer().is_none());
+}
+
+/// Row of unit quads along the x axis.
+fn quads(count: u16) -> Mesh<TestVertex, u16> {
+  let mut mesh = Mesh::new();
+  for quad in 0..count {
+    for (x, y) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].iter() {
+      mesh.push_vertex(TestVertex {
+        position: Vector3::new(quad as f32 + x, *y, 0.0),
+        normal: Vector3::new(0.0, 0.0, 1.0),
+        tangent: Vector3::new(1.0, 0.0, 0.0),
+        bitangent: Vector3::new(0.0, 1.0, 0.0),
+      });
+    }
+    for index in [0, 1, 2, 0, 2, 3].iter() {
+      mesh.push_index(quad * 4 + *index);
+    }
+  }
+  mesh
+}
+
+#[test]
+fn mesh_exceeding_existing_combiner_is_drawn_individually_test() {
+  if !headless_core() {
+    return;
+  }
+
+  // The combiner of the material is created with a config holding at most 4 quads.
+  let material = pbr_material();
+  let transform = Transform::new_static(origin(), origin(), Vector3::new(1.0, 1.0, 1.0));
+  StaticMeshCombinerConfig::set_global(
+    StaticMeshCombinerConfig::new()
+      .with_vertices_per_chunk(4)
+      .with_indices_per_chunk(6)
+      .with_max_vertex_chunks(4)
+      .with_max_index_chunks(4),
+  );
+  let small = MeshRenderer::new(&material, &quad(), Vec::new(), &transform).unwrap();
+  StaticMeshCombinerConfig::set_global(StaticMeshCombinerConfig::default());
+  assert_eq!(small.get_render_path(), MeshRenderPath::Combined);
+
+  // Fits the global config but not the combiner it would be merged into.
+  let large = MeshRenderer::new(&material, &quads(5), Vec::new(), &transform).unwrap();
+  assert_eq!(large.get_render_path(), MeshRenderPath::Individual);
+  assert!(large.get_vertex_buffer().is_some());
+}
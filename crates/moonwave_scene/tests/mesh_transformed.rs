@@ -0,0 +1,105 @@
+use moonwave_common::{InnerSpace, Matrix4, Vector2, Vector3};
+use moonwave_scene::{normal_matrix, Mesh, Transform};
+use moonwave_shader::vertex;
+
+#[vertex]
+struct NormalVertex {
+  position: Vector3<f32>,
+  uv: Vector2<f32>,
+  normal: Vector3<f32>,
+  tangent: Vector3<f32>,
+  bitangent: Vector3<f32>,
+}
+
+/// Single triangle lying within the plane `x + y = 1`.
+fn slanted_triangle() -> Mesh<NormalVertex, u16> {
+  let normal = Vector3::new(1.0, 1.0, 0.0).normalize();
+  let mut mesh = Mesh::new();
+  for position in [(1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (1.0, 0.0, 1.0)].iter() {
+    mesh.push_vertex(NormalVertex {
+      position: (*position).into(),
+      uv: Vector2::new(0.0, 0.0),
+      normal,
+      tangent: Vector3::new(0.0, 0.0, 1.0),
+      bitangent: normal.cross(Vector3::new(0.0, 0.0, 1.0)),
+    });
+  }
+  for index in [0, 1, 2].iter() {
+    mesh.push_index(*index);
+  }
+  mesh
+}
+
+fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+  assert!((a - b).magnitude() < 1e-5, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn transformed_mesh_positions_test() {
+  let transform = Transform::new_dynamic(
+    Vector3::new(0.0, 5.0, 0.0),
+    Vector3::new(0.0, 0.0, 0.0),
+    Vector3::new(1.0, 1.0, 1.0),
+  );
+  let mesh = slanted_triangle();
+  let transformed = mesh.transformed(&transform);
+
+  assert_eq!(transformed.len_vertices(), 3);
+  assert_eq!(
+    transformed.iter_indices().copied().collect::<Vec<_>>(),
+    vec![0, 1, 2]
+  );
+  let vertex = transformed.iter_vertices().next().unwrap();
+  assert_close(vertex.position, Vector3::new(1.0, 5.0, 0.0));
+
+  // Translation leaves directions untouched.
+  assert_close(vertex.normal, mesh.iter_vertices().next().unwrap().normal);
+}
+
+#[test]
+fn transformed_mesh_normals_test() {
+  let scale = Matrix4::from_nonuniform_scale(2.0, 1.0, 1.0);
+  let transformed = slanted_triangle().transformed_by_matrix(&scale);
+  let vertices = transformed.iter_vertices().collect::<Vec<_>>();
+
+  // Normals stay perpendicular to the stretched surface and normalized.
+  let edge = vertices[1].position - vertices[0].position;
+  let normal = vertices[0].normal;
+  assert!(normal.dot(edge).abs() < 1e-5);
+  assert_close(normal, Vector3::new(0.5, 1.0, 0.0).normalize());
+
+  // Tangent and bitangent are stretched with the surface and stay within it.
+  assert_close(vertices[0].tangent, Vector3::new(0.0, 0.0, 1.0));
+  assert_close(
+    vertices[0].bitangent,
+    Vector3::new(2.0, -1.0, 0.0).normalize(),
+  );
+  assert!(vertices[0].bitangent.dot(normal).abs() < 1e-5);
+  assert!(vertices[0].bitangent.cross(edge).magnitude() < 1e-5);
+
+  // Uniform scales only shrink the normal matrix, normalizing restores the directions.
+  let matrix = normal_matrix(&Matrix4::from_scale(4.0));
+  assert!((matrix.x.x - 0.25).abs() < 1e-6);
+  assert_eq!(matrix.x.y, 0.0);
+}
+
+#[test]
+fn static_transform_normals_test() {
+  // Static meshes are baked the same way by the static mesh combiner.
+  let transform = Transform::new_static(
+    Vector3::new(3.0, 0.0, -2.0),
+    Vector3::new(0.3, 1.2, 0.0),
+    Vector3::new(1.0, 4.0, 0.5),
+  );
+  let transformed = slanted_triangle().transformed(&transform);
+  let vertices = transformed.iter_vertices().collect::<Vec<_>>();
+
+  let normal = vertices[0].normal;
+  assert!((normal.magnitude() - 1.0).abs() < 1e-5);
+  for other in vertices.iter().skip(1) {
+    let edge = other.position - vertices[0].position;
+    assert!(normal.dot(edge).abs() < 1e-4, "{:?}", normal.dot(edge));
+  }
+  assert!(normal.dot(vertices[0].tangent).abs() < 1e-4);
+  assert!(normal.dot(vertices[0].bitangent).abs() < 1e-4);
+}
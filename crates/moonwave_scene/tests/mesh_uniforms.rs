@@ -0,0 +1,105 @@
+mod common;
+
+use common::{headless_core, pbr_graph, pbr_material, quad};
+use moonwave_common::{Vector3, Vector4};
+use moonwave_scene::{
+  Material, MeshRenderer, MeshRendererError, PBRShaderNode, Transform, Uniform,
+};
+use moonwave_shader::uniform;
+
+#[uniform]
+struct TintUniform {
+  color: Vector4<f32>,
+}
+
+fn tint_uniform() -> Uniform<TintUniform> {
+  Uniform::new(TintUniform {
+    color: Vector4::new(1.0, 0.0, 0.0, 1.0),
+  })
+}
+
+/// PBR material whose base color is read from an extra tint uniform.
+fn tinted_material() -> Material {
+  let (mut graph, pbr) = pbr_graph();
+  let (_, tint_in) = graph.add_uniform::<TintUniform>("tint");
+  graph
+    .connect(
+      tint_in,
+      TintUniform::OUTPUT_COLOR,
+      pbr,
+      PBRShaderNode::INPUT_BASE_COLOR,
+    )
+    .unwrap();
+  Material::new(graph)
+}
+
+fn transform() -> Transform {
+  let origin = Vector3::new(0.0, 0.0, 0.0);
+  Transform::new_static(origin, origin, Vector3::new(1.0, 1.0, 1.0))
+}
+
+#[test]
+fn extra_uniforms_are_bound_last_test() {
+  if !headless_core() {
+    return;
+  }
+
+  let tint = tint_uniform();
+  let transform = transform();
+  let renderer = MeshRenderer::new_with_uniforms(
+    &tinted_material(),
+    &quad(),
+    Vec::new(),
+    vec![tint.as_generic()],
+    &transform,
+  )
+  .unwrap();
+  assert_eq!(renderer.get_material().uniform_count(), 4);
+
+  // Camera, transform and lights keep their bindings, the tint follows them.
+  let camera = tint_uniform();
+  let lights = tint_uniform();
+  let bound = renderer.get_bound_uniforms(camera.as_generic(), &transform, lights.as_generic());
+  let bound = bound
+    .iter()
+    .map(|uniform| uniform.get_bind_group())
+    .collect::<Vec<_>>();
+  assert_eq!(bound.len(), 4);
+  assert!(bound[0] == camera.get_bind_group());
+  assert!(bound[1] != camera.get_bind_group() && bound[1] != lights.get_bind_group());
+  assert!(bound[2] == lights.get_bind_group());
+  assert!(bound[3] == tint.get_bind_group());
+}
+
+#[test]
+fn mismatching_extra_uniforms_test() {
+  if !headless_core() {
+    return;
+  }
+
+  // Missing tint of the material.
+  let result = MeshRenderer::new_with_uniforms(
+    &tinted_material(),
+    &quad(),
+    Vec::new(),
+    Vec::new(),
+    &transform(),
+  );
+  assert!(matches!(
+    result,
+    Err(MeshRendererError::ExtraUniformMismatch {
+      expected: 1,
+      passed: 0
+    })
+  ));
+
+  // Tint the material doesn't know about.
+  let result = MeshRenderer::new_with_uniforms(
+    &pbr_material(),
+    &quad(),
+    Vec::new(),
+    vec![tint_uniform().as_generic()],
+    &transform(),
+  );
+  assert!(result.is_err());
+}
@@ -0,0 +1,58 @@
+use moonwave_common::{Half2, Unorm8x4, Vector2, Vector3, Vector4};
+use moonwave_resources::{VertexAttributeFormat, VertexFormat};
+use moonwave_shader::{vertex, ShaderType, VertexStruct};
+
+#[vertex]
+struct PackedVertex {
+  position: Vector3<f32>,
+  index: Vector2<i32>,
+  color: Unorm8x4,
+  uv: Half2,
+}
+
+#[test]
+fn packed_vertex_layout_test() {
+  let attributes = PackedVertex::generate_attributes();
+  let offsets = attributes.iter().map(|a| a.offset).collect::<Vec<_>>();
+  assert_eq!(offsets, vec![0, 12, 20, 24]);
+  assert_eq!(
+    PackedVertex::generate_buffer().stride as usize,
+    std::mem::size_of::<PackedVertex>()
+  );
+
+  let formats = attributes
+    .iter()
+    .map(|a| a.format.to_wgpu())
+    .collect::<Vec<_>>();
+  assert_eq!(
+    formats,
+    vec![
+      VertexFormat::Float32x3,
+      VertexFormat::Sint32x2,
+      VertexFormat::Unorm8x4,
+      VertexFormat::Float16x2,
+    ]
+  );
+
+  // Packed attributes are read as floats, signed ones as integers.
+  assert!(matches!(
+    ShaderType::from(VertexAttributeFormat::Unorm8x4),
+    ShaderType::Float4
+  ));
+  assert!(matches!(
+    ShaderType::from(VertexAttributeFormat::Half2),
+    ShaderType::Float2
+  ));
+  assert_eq!(
+    ShaderType::from(attributes[1].format).get_glsl_type(),
+    "ivec2"
+  );
+
+  let vertex = PackedVertex {
+    position: Vector3::new(0.0, 0.0, 0.0),
+    index: Vector2::new(-1, 2),
+    color: Unorm8x4::from_vector(Vector4::new(1.0, 0.0, 0.0, 1.0)),
+    uv: Half2::from_vector(Vector2::new(0.5, 1.0)),
+  };
+  assert_eq!(PackedVertex::generate_raw_u8(&[vertex]).len(), 28);
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::{headless_core_with, pbr_material, quad};
+use legion::SystemBuilder;
+use moonwave_common::Vector3;
+use moonwave_core::{Core, CoreConfig, TextureGeneratorHost, TextureGeneratorNode, TextureSize};
+use moonwave_resources::TextureFormat;
+use moonwave_scene::{
+  add_pbr_post_process, BloomNode, Camera, DepthOfFieldNode, DepthVisualizeNode, LightManager,
+  MainCameraTag, MeshRenderer, SsrNode, Transform,
+};
+
+#[test]
+fn post_effects_run_in_pbr_graph_test() {
+  if !headless_core_with(CoreConfig::new().with_hdr(true)) {
+    return;
+  }
+  let core = Core::get_instance();
+  let hdr = core.get_color_target_format();
+  let target = |format: TextureFormat| TextureGeneratorHost::new(TextureSize::FullScreen, format);
+  let (dof_target, ssr_target, normal, material) =
+    (target(hdr), target(hdr), target(hdr), target(hdr));
+  let bloom_target = target(TextureFormat::Rgba16Float);
+  let depth_target = target(TextureFormat::Bgra8UnormSrgb);
+
+  // pbr -> depth of field -> ssr -> depth visualize -> tonemap, bloom of the ssr output.
+  add_pbr_post_process(move |chain, camera| {
+    let graph = chain.get_graph();
+    let texture = |host: &Arc<TextureGeneratorHost>, name: &str| {
+      (
+        graph.add_node(host.create_node(), name),
+        TextureGeneratorNode::OUTPUT_TEXTURE,
+      )
+    };
+
+    let dof = graph.add_node(DepthOfFieldNode::from_camera(camera, 2.0, 0.5), "dof");
+    chain.connect(chain.get_color(), dof, DepthOfFieldNode::INPUT_COLOR);
+    chain.connect(chain.get_depth(), dof, DepthOfFieldNode::INPUT_DEPTH);
+    chain.connect(
+      texture(&dof_target, "dof_target"),
+      dof,
+      DepthOfFieldNode::INPUT_TARGET,
+    );
+    chain.set_color(dof, DepthOfFieldNode::OUTPUT_COLOR);
+
+    let ssr = graph.add_node(SsrNode::from_camera(camera).with_steps(8), "ssr");
+    chain.connect(chain.get_color(), ssr, SsrNode::INPUT_COLOR);
+    chain.connect(chain.get_depth(), ssr, SsrNode::INPUT_DEPTH);
+    chain.connect(texture(&normal, "normal"), ssr, SsrNode::INPUT_NORMAL);
+    chain.connect(texture(&material, "material"), ssr, SsrNode::INPUT_MATERIAL);
+    chain.connect(
+      texture(&ssr_target, "ssr_target"),
+      ssr,
+      SsrNode::INPUT_TARGET,
+    );
+    chain.set_color(ssr, SsrNode::OUTPUT_COLOR);
+
+    let bloom = graph.add_node(BloomNode::new().with_iterations(2), "bloom");
+    chain.connect(chain.get_color(), bloom, BloomNode::INPUT_COLOR);
+    chain.connect(
+      texture(&bloom_target, "bloom_target"),
+      bloom,
+      BloomNode::INPUT_TARGET,
+    );
+    chain.set_bloom(bloom, BloomNode::OUTPUT_BLOOM);
+
+    let depth = graph.add_node(DepthVisualizeNode::from_camera(camera), "depth_visualize");
+    chain.connect(chain.get_depth(), depth, DepthVisualizeNode::INPUT_DEPTH);
+    chain.connect(chain.get_color(), depth, DepthVisualizeNode::INPUT_COLOR);
+    chain.connect(
+      texture(&depth_target, "depth_target"),
+      depth,
+      DepthVisualizeNode::INPUT_TARGET,
+    );
+    chain.set_color(depth, DepthVisualizeNode::OUTPUT_COLOR);
+  });
+
+  // A quad in front of the camera, so every target holds some of the scene.
+  let origin = Vector3::new(0.0, 0.0, 0.0);
+  let transform = Transform::new_static(origin, origin, Vector3::new(1.0, 1.0, 1.0));
+  let renderer =
+    MeshRenderer::new_uncombined(&pbr_material(), &quad(), Vec::new(), &transform).unwrap();
+  let mut camera = Camera::new();
+  camera.position = Vector3::new(0.5, 0.5, 2.0);
+  camera.target = Vector3::new(0.5, 0.5, 0.0);
+
+  let mut scene = Some((renderer, transform, camera, LightManager::new()));
+  core.get_world().add_temp_system(Box::new(
+    SystemBuilder::new("spawn_post_process_scene").build(move |cmd, _, _, _| {
+      if let Some((renderer, transform, camera, manager)) = scene.take() {
+        cmd.push((renderer, transform));
+        cmd.push((camera, MainCameraTag));
+        cmd.push((manager,));
+      }
+    }),
+  ));
+  for _ in 0..3 {
+    Core::run_headless_frame().unwrap();
+  }
+
+  // Only nodes connected to the presented image are executed.
+  let timings = core.get_frame_graph().get_node_timings();
+  for name in [
+    "pbr_main_node",
+    "dof",
+    "ssr",
+    "bloom",
+    "depth_visualize",
+    "pbr_tonemap",
+  ]
+  .iter()
+  {
+    assert!(timings.contains_key(*name), "{} did not run", name);
+  }
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{headless_core, pbr_material};
+use moonwave_common::{Unorm8x4, Vector3, Vector4};
+use moonwave_scene::{Material, PBRShaderNode};
+use moonwave_shader::{vertex, ShaderBuildParams, ShaderGraph, VertexStruct};
+
+#[vertex]
+struct FloatColorVertex {
+  position: Vector3<f32>,
+  normal: Vector3<f32>,
+  tangent: Vector3<f32>,
+  bitangent: Vector3<f32>,
+  color: Vector4<f32>,
+}
+
+#[vertex]
+struct PackedColorVertex {
+  position: Vector3<f32>,
+  normal: Vector3<f32>,
+  tangent: Vector3<f32>,
+  bitangent: Vector3<f32>,
+  color: Unorm8x4,
+}
+
+/// Same graph as `common::pbr_graph` for any vertex with the test vertex attribute order.
+fn pbr_material_for<T: VertexStruct + 'static>() -> Material {
+  let mut graph = ShaderGraph::new();
+  let (vertex_in, _) = graph.add_vertex_attributes::<T>();
+  let (pbr_graph, pbr_input) = PBRShaderNode::build_graph();
+  let (pbr, _) = graph.add_sub_graph(&pbr_graph, Some(pbr_input), None);
+  let pbr = pbr.unwrap();
+  for (output, input) in [
+    (0, PBRShaderNode::INPUT_POSITION),
+    (1, PBRShaderNode::INPUT_VNORMAL),
+    (2, PBRShaderNode::INPUT_VTANGENT),
+    (3, PBRShaderNode::INPUT_VBITANGENT),
+    (4, PBRShaderNode::INPUT_VERTEX_COLOR),
+  ]
+  .iter()
+  {
+    graph.connect(vertex_in, *output, pbr, *input).unwrap();
+  }
+  Material::new(graph)
+}
+
+#[test]
+fn pipelines_are_keyed_by_vertex_layout_test() {
+  if !headless_core() {
+    return;
+  }
+  let params = ShaderBuildParams::new();
+
+  // Both colors are read as `vec4`, only the vertex layout tells them apart.
+  let float = pbr_material_for::<FloatColorVertex>()
+    .build(&params)
+    .unwrap();
+  let packed = pbr_material_for::<PackedColorVertex>()
+    .build(&params)
+    .unwrap();
+  assert_eq!(float.shader.vs, packed.shader.vs);
+  assert!(float.pbr_pipeline != packed.pbr_pipeline);
+
+  // Materials with identical sources and layouts still share their pipeline.
+  let first = pbr_material().build(&params).unwrap();
+  let second = pbr_material().build(&params).unwrap();
+  assert!(first.pbr_pipeline == second.pbr_pipeline);
+}
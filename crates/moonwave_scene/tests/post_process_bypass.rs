@@ -0,0 +1,111 @@
+mod common;
+
+use common::headless_core;
+use moonwave_common::{Matrix4, SquareMatrix, Vector2, Vector3};
+use moonwave_core::{Core, TextureGeneratorHost, TextureGeneratorNode, TextureSize, TonemapNode};
+use moonwave_render::{CommandEncoder, FrameGraph, FrameGraphNode, FrameNodeValue, FrameTarget};
+use moonwave_resources::{DepthMode, SampledTexture, TextureFormat};
+use moonwave_scene::DepthOfFieldNode;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Records the texture that arrived at its first input.
+#[derive(Clone, Default)]
+struct RecordingEndNode(Arc<Mutex<Option<SampledTexture>>>);
+impl FrameGraphNode for RecordingEndNode {
+  fn execute(
+    &self,
+    inputs: &[Option<FrameNodeValue>],
+    _outputs: &mut [Option<FrameNodeValue>],
+    _encoder: &mut CommandEncoder,
+  ) {
+    *self.0.lock() = FrameNodeValue::optional_input(inputs, 0)
+      .and_then(FrameNodeValue::try_get_sampled_texture)
+      .cloned();
+  }
+}
+
+#[test]
+fn disabled_post_process_forwards_scene_test() {
+  if !headless_core() {
+    return;
+  }
+  let core = Core::get_instance();
+  let size = TextureSize::Custom(Vector2::new(16, 16));
+  let color = TextureGeneratorHost::new(size, TextureFormat::Rgba16Float);
+  let depth = TextureGeneratorHost::new(size, core.get_depth_target_format());
+  let blurred = TextureGeneratorHost::new(size, core.get_color_target_format());
+  let mapped = TextureGeneratorHost::new(size, TextureFormat::Bgra8UnormSrgb);
+
+  // color -> depth of field -> tonemap -> end
+  let end = RecordingEndNode::default();
+  let mut graph = FrameGraph::new(end.clone());
+  let color_node = graph.add_node(color.create_node(), "color");
+  let depth_node = graph.add_node(depth.create_node(), "depth");
+  let blurred_node = graph.add_node(blurred.create_node(), "blurred");
+  let mapped_node = graph.add_node(mapped.create_node(), "mapped");
+  let dof = graph.add_node(
+    DepthOfFieldNode::new(
+      Matrix4::identity(),
+      Vector3::new(0.0, 0.0, 0.0),
+      DepthMode::Standard,
+      4.0,
+      0.8,
+    ),
+    "dof",
+  );
+  let tonemap = graph.add_node(TonemapNode::new(), "tonemap");
+  let output = TextureGeneratorNode::OUTPUT_TEXTURE;
+  for (source, target, input) in [
+    (color_node, dof, DepthOfFieldNode::INPUT_COLOR),
+    (depth_node, dof, DepthOfFieldNode::INPUT_DEPTH),
+    (blurred_node, dof, DepthOfFieldNode::INPUT_TARGET),
+    (mapped_node, tonemap, TonemapNode::INPUT_TARGET),
+  ]
+  .iter()
+  {
+    graph.connect(*source, output, *target, *input).unwrap();
+  }
+  graph
+    .connect(
+      dof,
+      DepthOfFieldNode::OUTPUT_COLOR,
+      tonemap,
+      TonemapNode::INPUT_TEXTURE,
+    )
+    .unwrap();
+  graph
+    .connect(
+      tonemap,
+      TonemapNode::OUTPUT_TEXTURE,
+      graph.get_end_node(),
+      0,
+    )
+    .unwrap();
+
+  let pool = moonwave_core::rayon::ThreadPoolBuilder::new()
+    .num_threads(2)
+    .build()
+    .unwrap();
+  let target = Arc::new(FrameTarget::Offscreen(mapped.get_texture().view));
+  let mut run_frame = |graph: &mut FrameGraph| {
+    graph.execute(target.clone(), core, &pool);
+    end
+      .0
+      .lock()
+      .take()
+      .expect("Nothing arrived at the end node")
+  };
+
+  // The tonemapper still receives the scene while the effect in front of it is disabled.
+  graph.set_node_enabled("dof", false);
+  assert!(run_frame(&mut graph).texture == mapped.get_texture().texture);
+
+  // Disabling the tonemapper as well presents the raw scene.
+  graph.set_node_enabled("tonemap", false);
+  assert!(run_frame(&mut graph).texture == color.get_texture().texture);
+
+  graph.set_node_enabled("dof", true);
+  graph.set_node_enabled("tonemap", true);
+  assert!(run_frame(&mut graph).texture == mapped.get_texture().texture);
+}
@@ -0,0 +1,94 @@
+use moonwave_common::{look_at, perspective, InnerSpace, Matrix4, Rad, SquareMatrix, Vector3};
+use moonwave_resources::DepthMode;
+use moonwave_scene::{depth_mode_projection, unproject_screen_ray, BoundingShape};
+
+fn unit_box() -> BoundingShape {
+  BoundingShape::AABB {
+    min: Vector3::new(-0.5, -0.5, -0.5),
+    max: Vector3::new(0.5, 0.5, 0.5),
+  }
+}
+
+#[test]
+fn aabb_ray_intersection_test() {
+  let aabb = unit_box();
+
+  // Straight hit onto the front face.
+  let hit = aabb.intersect_ray(Vector3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+  assert!((hit.unwrap() - 4.5).abs() < 1e-5);
+
+  // Unnormalized directions scale the distance.
+  let hit = aabb.intersect_ray(Vector3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 2.0));
+  assert!((hit.unwrap() - 2.25).abs() < 1e-5);
+
+  // Diagonal hit onto the corner.
+  let hit = aabb.intersect_ray(Vector3::new(-1.5, -1.5, -1.5), Vector3::new(1.0, 1.0, 1.0));
+  assert!((hit.unwrap() - 1.0).abs() < 1e-5);
+
+  // Origin inside the box.
+  assert_eq!(
+    aabb.intersect_ray(Vector3::new(0.1, 0.2, 0.3), Vector3::new(1.0, 0.0, 0.0)),
+    Some(0.0)
+  );
+
+  // Misses: pointing away, parallel next to the box and passing by.
+  assert_eq!(
+    aabb.intersect_ray(Vector3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, -1.0)),
+    None
+  );
+  assert_eq!(
+    aabb.intersect_ray(Vector3::new(0.0, 1.0, -5.0), Vector3::new(0.0, 0.0, 1.0)),
+    None
+  );
+  assert_eq!(
+    aabb.intersect_ray(Vector3::new(-5.0, 0.0, -5.0), Vector3::new(1.0, 0.0, 0.2)),
+    None
+  );
+}
+
+#[test]
+fn sphere_ray_intersection_test() {
+  let sphere = BoundingShape::Sphere {
+    center: Vector3::new(0.0, 0.0, 0.0),
+    radius: 1.0,
+  };
+
+  let hit = sphere.intersect_ray(Vector3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+  assert!((hit.unwrap() - 4.0).abs() < 1e-5);
+  assert_eq!(
+    sphere.intersect_ray(Vector3::new(0.0, 2.0, -5.0), Vector3::new(0.0, 0.0, 1.0)),
+    None
+  );
+}
+
+#[test]
+fn screen_ray_picking_test() {
+  let eye = Vector3::new(0.0, 0.0, -5.0);
+  let view = look_at(
+    eye,
+    Vector3::new(0.0, 0.0, 0.0),
+    Vector3::new(0.0, 1.0, 0.0),
+  );
+
+  for mode in [DepthMode::Standard, DepthMode::ReverseZ].iter() {
+    let projection = depth_mode_projection(*mode, perspective(Rad(1.0), 1.0, 0.1, 100.0));
+    let inverse = (projection * view).invert().unwrap();
+
+    // Center of the screen looks straight at the box.
+    let (origin, direction) = unproject_screen_ray(inverse, *mode, 0.5, 0.5);
+    assert!((direction - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-4);
+    assert!((origin - eye).magnitude() < 0.2);
+    assert!(unit_box().intersect_ray(origin, direction).is_some());
+
+    // Corners of the screen miss it.
+    let (origin, direction) = unproject_screen_ray(inverse, *mode, 0.0, 0.0);
+    assert!(direction.y > 0.0);
+    assert!(unit_box().intersect_ray(origin, direction).is_none());
+  }
+
+  // Identity leaves clip space as is.
+  let (origin, direction) =
+    unproject_screen_ray(Matrix4::identity(), DepthMode::Standard, 1.0, 0.0);
+  assert!((origin - Vector3::new(1.0, 1.0, -1.0)).magnitude() < 1e-5);
+  assert!((direction - Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-5);
+}
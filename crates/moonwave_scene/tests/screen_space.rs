@@ -0,0 +1,103 @@
+mod common;
+
+use common::{headless_core, pbr_graph, quad};
+use legion::SystemBuilder;
+use moonwave_common::{Vector3, Vector4};
+use moonwave_core::Core;
+use moonwave_scene::{
+  get_pbr_main_color_target, passes_frustum_culling, BoundingShape, Camera, LightManager,
+  MainCameraTag, Material, MeshRenderer, PBRShaderNode, ScreenSpace, Transform,
+};
+use moonwave_shader::Constant;
+
+/// Frustum of an axis aligned box from -1 to 1 on every axis.
+fn unit_frustum() -> [Vector4<f32>; 6] {
+  [
+    Vector4::new(1.0, 0.0, 0.0, 1.0),
+    Vector4::new(-1.0, 0.0, 0.0, 1.0),
+    Vector4::new(0.0, 1.0, 0.0, 1.0),
+    Vector4::new(0.0, -1.0, 0.0, 1.0),
+    Vector4::new(0.0, 0.0, 1.0, 1.0),
+    Vector4::new(0.0, 0.0, -1.0, 1.0),
+  ]
+}
+
+#[test]
+fn screen_space_culling_test() {
+  let frustum = unit_frustum();
+  let inside = BoundingShape::AABB {
+    min: Vector3::new(-0.5, -0.5, -0.5),
+    max: Vector3::new(0.5, 0.5, 0.5),
+  };
+  let outside = BoundingShape::AABB {
+    min: Vector3::new(10.0, 10.0, 10.0),
+    max: Vector3::new(11.0, 11.0, 11.0),
+  };
+
+  // World space objects are culled by their bounds.
+  assert!(passes_frustum_culling(Some(&inside), false, &frustum));
+  assert!(!passes_frustum_culling(Some(&outside), false, &frustum));
+  assert!(!passes_frustum_culling(None, false, &frustum));
+
+  // Screen space objects are always drawn.
+  assert!(passes_frustum_culling(Some(&outside), true, &frustum));
+  assert!(passes_frustum_culling(None, true, &frustum));
+}
+
+#[test]
+fn screen_space_overlay_pass_test() {
+  if !headless_core() {
+    return;
+  }
+  let core = Core::get_instance();
+
+  // Black quad covering the top left 16x16 pixels, both sides are drawn as the y axis points down.
+  let (mut graph, pbr) = pbr_graph();
+  let black = graph.add_node(Constant::new(Vector4::new(0.0, 0.0, 0.0, 1.0)));
+  graph
+    .connect(black, 0, pbr, PBRShaderNode::INPUT_BASE_COLOR)
+    .unwrap();
+  let material = Material::new(graph).with_cull_mode(None);
+  let transform = Transform::new_dynamic(
+    Vector3::new(0.0, 0.0, 0.0),
+    Vector3::new(0.0, 0.0, 0.0),
+    Vector3::new(16.0, 16.0, 1.0),
+  );
+  let renderer = MeshRenderer::new_uncombined(&material, &quad(), Vec::new(), &transform).unwrap();
+
+  // The main camera looks away from the quad, it would be invisible within the world.
+  let mut camera = Camera::new();
+  camera.position = Vector3::new(0.0, 0.0, 10.0);
+  camera.target = Vector3::new(0.0, 0.0, 20.0);
+
+  let mut scene = Some((renderer, transform, camera, LightManager::new()));
+  core
+    .get_world()
+    .add_temp_system(Box::new(SystemBuilder::new("spawn_overlay_scene").build(
+      move |cmd, _, _, _| {
+        if let Some((renderer, transform, camera, manager)) = scene.take() {
+          cmd.push((renderer, transform, ScreenSpace));
+          cmd.push((camera, MainCameraTag));
+          cmd.push((manager,));
+        }
+      },
+    )));
+  for _ in 0..3 {
+    Core::run_headless_frame().unwrap();
+  }
+
+  let target = get_pbr_main_color_target().unwrap();
+  let size = core.get_swap_chain_size();
+  let pixels = core.read_texture(&target.texture, core.get_color_target_format(), size);
+  let brightness = |x: u32, y: u32| {
+    let offset = ((y * size.x + x) * 4) as usize;
+    pixels[offset..offset + 3]
+      .iter()
+      .map(|c| *c as u32)
+      .sum::<u32>()
+  };
+
+  // Only the top left corner is covered, the rest keeps the white clear color.
+  assert!(brightness(8, 8) < 64, "Overlay was not drawn");
+  assert!(brightness(size.x - 8, size.y - 8) > 700);
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::{headless_core, pbr_material, quad};
+use moonwave_common::Vector3;
+use moonwave_scene::{MeshRenderPath, MeshRenderer, Transform};
+use moonwave_shader::ShaderBuildParams;
+use std::sync::Arc;
+
+fn origin() -> Vector3<f32> {
+  Vector3::new(0.0, 0.0, 0.0)
+}
+
+#[test]
+fn set_material_moves_combined_mesh_test() {
+  if !headless_core() {
+    return;
+  }
+
+  let mesh = quad();
+  let transform = Transform::new_static(origin(), origin(), Vector3::new(1.0, 1.0, 1.0));
+  let first = pbr_material();
+  let mut renderer = MeshRenderer::new(&first, &mesh, Vec::new(), &transform).unwrap();
+  assert_eq!(renderer.get_render_path(), MeshRenderPath::Combined);
+  let previous = renderer.get_material().clone();
+
+  // The mesh is moved into the group of the new material and back again.
+  let second = pbr_material().with_cull_mode(None);
+  let params = ShaderBuildParams::new();
+  renderer
+    .set_material(&second, &params, &mesh, &transform)
+    .unwrap();
+  assert!(!Arc::ptr_eq(renderer.get_material(), &previous));
+  assert_eq!(renderer.get_render_path(), MeshRenderPath::Combined);
+
+  renderer
+    .set_material(&first, &params, &mesh, &transform)
+    .unwrap();
+  assert!(Arc::ptr_eq(renderer.get_material(), &previous));
+}
@@ -0,0 +1,24 @@
+use moonwave_common::{Vector2, Vector4};
+use moonwave_scene::{sprite_instance_ranges, Sprite, SpriteInstance};
+
+#[test]
+fn sprite_instance_test() {
+  let sprite = Sprite::new(Vector2::new(1.0, 2.0), Vector2::new(3.0, 4.0))
+    .with_uv_rect(Vector2::new(0.25, 0.5), Vector2::new(0.5, 1.0))
+    .with_tint(Vector4::new(1.0, 0.0, 0.0, 0.5));
+  let instance = SpriteInstance::from(&sprite);
+
+  // Bottom left corner followed by the size, the quad is generated on the GPU.
+  assert_eq!(instance.rect, [1.0, 2.0, 3.0, 4.0]);
+  assert_eq!(instance.uv_rect, [0.25, 0.5, 0.5, 1.0]);
+  assert_eq!(instance.tint, [1.0, 0.0, 0.0, 0.5]);
+}
+
+#[test]
+fn sprite_instance_ranges_test() {
+  assert_eq!(
+    sprite_instance_ranges(vec![2, 1, 3]),
+    vec![0..2, 2..3, 3..6]
+  );
+  assert!(sprite_instance_ranges(Vec::new()).is_empty());
+}
@@ -0,0 +1,28 @@
+use moonwave_common::{Matrix4, SquareMatrix, Vector3};
+use moonwave_core::{compile_glsl, ShaderKind, FULLSCREEN_TRIANGLE_VS};
+use moonwave_resources::DepthMode;
+use moonwave_scene::{screen_effect_shader, SsrNode, SSR_FRAGMENT_SHADER};
+
+#[test]
+fn ssr_shaders_compile() {
+  compile_glsl(FULLSCREEN_TRIANGLE_VS, "SsrVS", ShaderKind::Vertex).unwrap();
+  compile_glsl(
+    &screen_effect_shader(SSR_FRAGMENT_SHADER),
+    "SsrFS",
+    ShaderKind::Fragment,
+  )
+  .unwrap();
+}
+
+#[test]
+fn ssr_node_settings() {
+  let node = SsrNode::new(
+    Matrix4::identity(),
+    Vector3::new(0.0, 0.0, 0.0),
+    DepthMode::Standard,
+  )
+  .with_steps(64)
+  .with_max_distance(50.0);
+  assert_eq!(node.get_settings().steps, 64);
+  assert_eq!(node.get_settings().max_distance, 50.0);
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::headless_core;
+use moonwave_resources::BufferUsage;
+use moonwave_scene::{coalesce_ranges, StagedBuffer};
+
+#[test]
+fn coalesce_ranges_test() {
+  // Overlapping and touching ranges are merged, order doesn't matter.
+  assert_eq!(
+    coalesce_ranges(vec![10..12, 0..4, 2..6, 6..8, 20..24]),
+    vec![0..8, 10..12, 20..24]
+  );
+
+  // Contained and empty ranges vanish.
+  assert_eq!(coalesce_ranges(vec![0..10, 2..4, 5..5]), vec![0..10]);
+  assert!(coalesce_ranges(vec![]).is_empty());
+}
+
+#[test]
+fn content_beyond_capacity_grows_buffer_test() {
+  if !headless_core() {
+    return;
+  }
+
+  let buffer = StagedBuffer::<u32>::new(4, BufferUsage::VERTEX);
+  buffer.append(&[1, 2, 3]);
+  let accessor = buffer.get_accessor();
+  assert_eq!(accessor.pending_uploads().collect::<Vec<_>>(), vec![0..12]);
+  assert_eq!(buffer.capacity(), 16);
+
+  // Appending past the end moves everything into a larger buffer, including older elements.
+  buffer.append(&[4, 5, 6, 7, 8]);
+  let accessor = buffer.get_accessor();
+  assert_eq!(accessor.pending_uploads().collect::<Vec<_>>(), vec![0..32]);
+  assert_eq!(buffer.capacity(), 32);
+
+  // Patches within the grown buffer upload only their range again.
+  buffer.patch(7, &[9]);
+  let accessor = buffer.get_accessor();
+  assert_eq!(accessor.pending_uploads().collect::<Vec<_>>(), vec![28..32]);
+}
@@ -0,0 +1,64 @@
+use moonwave_scene::opt::StaticMeshCombinerConfig;
+
+#[test]
+fn default_config_test() {
+  let config = StaticMeshCombinerConfig::default();
+
+  // A generation holds roughly three indices per vertex.
+  let vertices = config.vertices_per_chunk * config.max_vertex_chunks;
+  let indices = config.indices_per_chunk * config.max_index_chunks;
+  assert_eq!(vertices, 512 * 1024);
+  assert_eq!(indices, 3 * vertices);
+}
+
+#[test]
+fn chunks_for_mesh_test() {
+  let config = StaticMeshCombinerConfig::new()
+    .with_vertices_per_chunk(1000)
+    .with_indices_per_chunk(100)
+    .with_max_vertex_chunks(4)
+    .with_max_index_chunks(10);
+
+  assert_eq!(config.chunks_for(1, 1), (1, 1));
+  assert_eq!(config.chunks_for(1000, 100), (1, 1));
+  assert_eq!(config.chunks_for(1001, 250), (2, 3));
+  assert!(config.fits::<u32>(4000, 1000));
+  assert!(!config.fits::<u32>(4001, 1000));
+  assert!(!config.fits::<u32>(10, 1001));
+}
+
+#[test]
+fn global_config_test() {
+  let config = StaticMeshCombinerConfig::new().with_vertices_per_chunk(8192);
+  StaticMeshCombinerConfig::set_global(config);
+  assert_eq!(StaticMeshCombinerConfig::global(), config);
+
+  StaticMeshCombinerConfig::set_global(StaticMeshCombinerConfig::default());
+  assert_eq!(
+    StaticMeshCombinerConfig::global(),
+    StaticMeshCombinerConfig::default()
+  );
+}
+
+#[test]
+fn u16_indices_clamp_generation_test() {
+  let config = StaticMeshCombinerConfig::default();
+
+  // 16 bit indices can't address vertices beyond 65535 within a generation.
+  let clamped = config.for_index::<u16>();
+  assert_eq!(clamped.max_vertex_chunks, 64);
+  assert_eq!(
+    clamped.vertices_per_chunk * clamped.max_vertex_chunks,
+    u16::MAX as usize + 1
+  );
+  assert_eq!(config.for_index::<u32>(), config);
+
+  assert!(config.fits::<u16>(65536, 3));
+  assert!(!config.fits::<u16>(65537, 3));
+  assert!(config.fits::<u32>(65537, 3));
+
+  // Even a single chunk is too large if it exceeds the index range.
+  let huge_chunks = StaticMeshCombinerConfig::new().with_vertices_per_chunk(100_000);
+  assert_eq!(huge_chunks.for_index::<u16>().max_vertex_chunks, 1);
+  assert!(!huge_chunks.fits::<u16>(70_000, 3));
+}
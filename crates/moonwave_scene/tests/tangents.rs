@@ -0,0 +1,71 @@
+use moonwave_common::{InnerSpace, Vector2, Vector3};
+use moonwave_scene::{Mesh, MeshVertexNormal};
+use moonwave_shader::vertex;
+
+#[vertex]
+struct TangentVertex {
+  position: Vector3<f32>,
+  uv: Vector2<f32>,
+  normal: Vector3<f32>,
+  tangent: Vector3<f32>,
+  bitangent: Vector3<f32>,
+}
+
+/// Quad within the xy plane facing +z, `u_direction` flips the u axis of the uvs.
+fn quad(u_direction: f32) -> Mesh<TangentVertex, u16> {
+  let mut mesh = Mesh::new();
+  for (x, y) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].iter() {
+    mesh.push_vertex(TangentVertex {
+      position: Vector3::new(*x, *y, 0.0),
+      uv: Vector2::new(x * u_direction, *y),
+      normal: Vector3::new(0.0, 0.0, 1.0),
+      tangent: Vector3::new(0.0, 0.0, 0.0),
+      bitangent: Vector3::new(0.0, 0.0, 0.0),
+    });
+  }
+  for index in [0, 1, 2, 0, 2, 3].iter() {
+    mesh.push_index(*index);
+  }
+  mesh
+}
+
+fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+  assert!((a - b).magnitude() < 1e-5, "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn generate_tangents_test() {
+  let mut mesh = quad(1.0);
+  mesh.generate_tangents();
+
+  for vertex in mesh.iter_vertices() {
+    assert_close(*vertex.get_tangent(), Vector3::new(1.0, 0.0, 0.0));
+    assert_close(*vertex.get_bitangent(), Vector3::new(0.0, 1.0, 0.0));
+  }
+}
+
+#[test]
+fn generate_tangents_mirrored_test() {
+  let mut mesh = quad(-1.0);
+  mesh.generate_tangents();
+
+  // Mirrored uvs flip the tangent while the bitangent keeps following v.
+  for vertex in mesh.iter_vertices() {
+    assert_close(*vertex.get_tangent(), Vector3::new(-1.0, 0.0, 0.0));
+    assert_close(*vertex.get_bitangent(), Vector3::new(0.0, 1.0, 0.0));
+  }
+}
+
+#[test]
+fn generate_tangents_degenerate_uvs_test() {
+  let mut mesh = quad(0.0);
+  mesh.generate_tangents();
+
+  // Without uv gradients an arbitrary but valid tangent frame is chosen.
+  for vertex in mesh.iter_vertices() {
+    let tangent = *vertex.get_tangent();
+    assert!((tangent.magnitude() - 1.0).abs() < 1e-5);
+    assert!(tangent.dot(*vertex.get_normal()).abs() < 1e-5);
+    assert!((vertex.get_bitangent().magnitude() - 1.0).abs() < 1e-5);
+  }
+}
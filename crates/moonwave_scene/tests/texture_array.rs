@@ -0,0 +1,12 @@
+use moonwave_scene::texture_array::{check_texture_array_index, TextureArrayError};
+
+#[test]
+fn texture_array_index_bounds_test() {
+  assert_eq!(check_texture_array_index(0, 4), Ok(0));
+  assert_eq!(check_texture_array_index(3, 4), Ok(3));
+  assert_eq!(
+    check_texture_array_index(4, 4),
+    Err(TextureArrayError::IndexOutOfBounds { index: 4, size: 4 })
+  );
+  assert!(check_texture_array_index(0, 0).is_err());
+}
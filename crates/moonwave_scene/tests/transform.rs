@@ -0,0 +1,30 @@
+use moonwave_common::{Matrix4, Vector3};
+use moonwave_scene::Transform;
+
+#[test]
+fn cached_transform_matrix_test() {
+  let mut transform = Transform::new_dynamic(
+    Vector3::new(1.0, 2.0, 3.0),
+    Vector3::new(0.0, 0.0, 0.0),
+    Vector3::new(1.0, 1.0, 1.0),
+  );
+  assert_eq!(transform.matrix_recomputations(), 0);
+
+  // Consecutive calls without mutation are served from the cache.
+  let first = transform.calculate_transform_matrix();
+  let second = transform.calculate_transform_matrix();
+  assert_eq!(first, second);
+  assert_eq!(
+    first,
+    Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0))
+  );
+  assert_eq!(transform.matrix_recomputations(), 1);
+
+  // Mutation invalidates the cached matrix.
+  transform.get_mut().position = Vector3::new(0.0, 0.0, 0.0);
+  assert_eq!(
+    transform.calculate_transform_matrix(),
+    Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.0))
+  );
+  assert_eq!(transform.matrix_recomputations(), 2);
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use common::headless_core;
+use moonwave_common::Vector4;
+use moonwave_scene::{dirty_byte_range, Uniform};
+use moonwave_shader::{uniform, UniformValue};
+
+#[uniform]
+struct TintUniform {
+  color: Vector4<f32>,
+  strength: f32,
+}
+
+#[test]
+fn dirty_byte_range_test() {
+  let old = vec![0u8; 64];
+
+  // Nothing changed, nothing to upload.
+  assert_eq!(dirty_byte_range(&old, &old), None);
+
+  // A single changed byte uploads the surrounding 4 byte word.
+  let mut new = old.clone();
+  new[33] = 1;
+  assert_eq!(dirty_byte_range(&old, &new), Some(32..36));
+
+  // One element in the middle of an array.
+  let mut new = old.clone();
+  new[16..32].copy_from_slice(&[1; 16]);
+  assert_eq!(dirty_byte_range(&old, &new), Some(16..32));
+
+  // First upload or resized content is uploaded entirely.
+  assert_eq!(dirty_byte_range(&[], &old), Some(0..64));
+}
+
+#[test]
+fn typed_writes_replace_values_set_by_name_test() {
+  if !headless_core() {
+    return;
+  }
+  let uniform = Uniform::new(TintUniform {
+    color: Vector4::new(1.0, 0.0, 0.0, 1.0),
+    strength: 1.0,
+  });
+
+  uniform
+    .set_by_name("color", UniformValue::Float4([0.0, 1.0, 0.0, 1.0]))
+    .unwrap();
+  uniform
+    .set_by_name("strength", UniformValue::Float(2.0))
+    .unwrap();
+  assert_eq!(
+    uniform.get_by_name("color").unwrap(),
+    UniformValue::Float4([0.0, 1.0, 0.0, 1.0])
+  );
+
+  // Writing a field through `get_mut` wins over the value set by name, others are kept.
+  uniform.get_mut().color = Vector4::new(0.0, 0.0, 1.0, 1.0);
+  assert_eq!(
+    uniform.get_by_name("color").unwrap(),
+    UniformValue::Float4([0.0, 0.0, 1.0, 1.0])
+  );
+  assert_eq!(
+    uniform.get_by_name("strength").unwrap(),
+    UniformValue::Float(2.0)
+  );
+
+  // Setting it by name again overrides the new typed value.
+  uniform
+    .set_by_name("color", UniformValue::Float4([1.0, 1.0, 1.0, 1.0]))
+    .unwrap();
+  assert_eq!(
+    uniform.get_by_name("color").unwrap(),
+    UniformValue::Float4([1.0, 1.0, 1.0, 1.0])
+  );
+}
@@ -0,0 +1,44 @@
+use moonwave_common::{Vector3, Vector4};
+use moonwave_scene::{Mesh, MeshVertexColor, PBRShaderNode};
+use moonwave_shader::{vertex, ShaderGraph};
+
+#[vertex]
+struct ColoredVertex {
+  position: Vector3<f32>,
+  color: Vector4<f32>,
+}
+
+#[test]
+fn vertex_color_accessors_test() {
+  let mut mesh = Mesh::<ColoredVertex, u16>::new();
+  mesh.push_vertex(ColoredVertex {
+    position: Vector3::new(0.0, 0.0, 0.0),
+    color: Vector4::new(1.0, 0.0, 0.0, 1.0),
+  });
+
+  let vertex = mesh.iter_vertices().next().unwrap();
+  assert_eq!(*vertex.get_color(), Vector4::new(1.0, 0.0, 0.0, 1.0));
+
+  let mut vertex = *vertex;
+  vertex.get_color_mut().y = 0.5;
+  assert_eq!(vertex.color.y, 0.5);
+  assert_eq!(ColoredVertex::OUTPUT_COLOR, 1);
+}
+
+#[test]
+fn connect_vertex_color_test() {
+  let (mut graph, pbr): (ShaderGraph, _) = PBRShaderNode::build_graph();
+  let (vertex_in, _) = graph.add_vertex_attributes::<ColoredVertex>();
+
+  PBRShaderNode::connect_vertex_color(&mut graph, vertex_in, ColoredVertex::OUTPUT_COLOR, pbr)
+    .unwrap();
+
+  // The input can only be connected once.
+  assert!(PBRShaderNode::connect_vertex_color(
+    &mut graph,
+    vertex_in,
+    ColoredVertex::OUTPUT_COLOR,
+    pbr
+  )
+  .is_err());
+}
@@ -2,7 +2,10 @@ use heck::{ShoutySnakeCase, SnakeCase};
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{format_ident, quote};
-use syn::{parse_macro_input, Expr, GenericArgument, ItemStruct, Path, PathArguments, Type};
+use syn::{
+  parse_macro_input, AttributeArgs, Expr, GenericArgument, ItemStruct, Lit, Meta, NestedMeta, Path,
+  PathArguments, Type,
+};
 use uuid::Uuid;
 
 fn path_to_string(path: &Path) -> String {
@@ -91,6 +94,49 @@ fn path_to_glsl_type(ty: &Type) -> Option<GlslType> {
           size: 4,
           ..Default::default()
         }),
+        "Vector4<i32>" => Some(GlslType {
+          enum_type: "Int4".to_string(),
+          glsl_type: "ivec4".to_string(),
+          size: 4 * 4,
+          ..Default::default()
+        }),
+        "Vector3<i32>" => Some(GlslType {
+          enum_type: "Int3".to_string(),
+          glsl_type: "ivec3".to_string(),
+          size: 4 * 3,
+          ..Default::default()
+        }),
+        "Vector2<i32>" => Some(GlslType {
+          enum_type: "Int2".to_string(),
+          glsl_type: "ivec2".to_string(),
+          size: 4 * 2,
+          ..Default::default()
+        }),
+        "i32" => Some(GlslType {
+          enum_type: "Int".to_string(),
+          glsl_type: "int".to_string(),
+          size: 4,
+          ..Default::default()
+        }),
+        // Packed types are only available as vertex attributes.
+        "Unorm8x4" => Some(GlslType {
+          enum_type: "Unorm8x4".to_string(),
+          glsl_type: "vec4".to_string(),
+          size: 4,
+          ..Default::default()
+        }),
+        "Half2" => Some(GlslType {
+          enum_type: "Half2".to_string(),
+          glsl_type: "vec2".to_string(),
+          size: 2 * 2,
+          ..Default::default()
+        }),
+        "Half4" => Some(GlslType {
+          enum_type: "Half4".to_string(),
+          glsl_type: "vec4".to_string(),
+          size: 2 * 4,
+          ..Default::default()
+        }),
         _ => Some(GlslType {
           enum_type: "Struct".to_string(),
           glsl_type: full_path.clone(),
@@ -114,6 +160,10 @@ fn path_to_glsl_type(ty: &Type) -> Option<GlslType> {
   }
 }
 
+/// Implements vertex buffer layout and shader attributes for a vertex struct.
+/// Fields named `uv: Vector2<f32>` and `color: Vector4<f32>` additionally implement `MeshVertexUV` and `MeshVertexColor`,
+/// packed types like `Half2` or `Unorm8x4` save memory but skip those implementations.
+/// Normal support requires `tangent` and `bitangent` fields next to `normal`, see `moonwave_shader/tests/ui`.
 #[proc_macro_attribute]
 pub fn vertex(_attr: TokenStream, item: TokenStream) -> TokenStream {
   // Parse basic structure.
@@ -126,9 +176,11 @@ pub fn vertex(_attr: TokenStream, item: TokenStream) -> TokenStream {
   let mut shader_outputs = Vec::with_capacity(item.fields.len());
   let mut shader_outputs_constants = Vec::with_capacity(item.fields.len());
   let mut has_uvs = false;
+  let mut has_color = false;
   let mut has_normal = false;
   let mut has_tangent = false;
   let mut has_bitangent = false;
+  let mut normal_ident = None;
 
   for (index, attr) in item.fields.iter().enumerate() {
     let name = attr
@@ -137,17 +189,21 @@ pub fn vertex(_attr: TokenStream, item: TokenStream) -> TokenStream {
       .unwrap_or_else(|| panic!("All vertex struct fields must be named"));
     let name_str = name.to_string();
 
+    let ty = path_to_glsl_type(&attr.ty)
+      .unwrap_or_else(|| panic!("Unknown types can't be used within a vertex struct"));
+
     match name_str.as_str() {
-      "uv" => has_uvs = true,
-      "normal" => has_normal = true,
+      "uv" => has_uvs = ty.enum_type == "Float2",
+      "color" => has_color = ty.enum_type == "Float4",
+      "normal" => {
+        has_normal = true;
+        normal_ident = Some(name.clone());
+      }
       "tangent" => has_tangent = true,
       "bitangent" => has_bitangent = true,
       _ => {}
     }
 
-    let ty = path_to_glsl_type(&attr.ty)
-      .unwrap_or_else(|| panic!("Unknown types can't be used within a vertex struct"));
-
     // Attribute desc
     let attribute_ty = format_ident!("{}", ty.enum_type);
     attribute_descs.push(quote! {
@@ -189,6 +245,34 @@ pub fn vertex(_attr: TokenStream, item: TokenStream) -> TokenStream {
     TokenStream2::new()
   };
 
+  // Has vertex color support
+  let color_support = if has_color {
+    quote! {
+      impl moonwave_scene::MeshVertexColor for #struct_ident {
+        fn get_color(&self) -> &Vector4<f32> {
+          &self.color
+        }
+        fn get_color_mut(&mut self) -> &mut Vector4<f32> {
+          &mut self.color
+        }
+      }
+    }
+  } else {
+    TokenStream2::new()
+  };
+
+  // Normals without tangent space would silently lose normal support.
+  if has_normal && !(has_tangent && has_bitangent) {
+    return TokenStream::from(
+      syn::Error::new_spanned(
+        normal_ident.unwrap(),
+        "Vertex structs with a `normal` field also need `tangent` and `bitangent` fields, \
+         they can be generated at runtime with `Mesh::generate_tangents`",
+      )
+      .to_compile_error(),
+    );
+  }
+
   // Has normal support
   let normal_support = if has_normal && has_tangent && has_bitangent {
     quote! {
@@ -261,6 +345,7 @@ pub fn vertex(_attr: TokenStream, item: TokenStream) -> TokenStream {
     }
 
     #uv_support
+    #color_support
     #normal_support
   })
 }
@@ -279,27 +364,132 @@ fn struct_copy(vec: &mut Vec<TokenStream2>, ty: &GlslType) {
   }
 }
 
+/// Id of a uniform struct depending on the arguments of the `uniform` attribute.
+fn uniform_id(args: &[NestedMeta], item: &ItemStruct) -> syn::Result<Uuid> {
+  let mut id = None;
+  for arg in args {
+    id = Some(match arg {
+      NestedMeta::Meta(Meta::NameValue(value)) if value.path.is_ident("id") => match &value.lit {
+        Lit::Str(lit) => Uuid::parse_str(&lit.value()).map_err(|err| {
+          syn::Error::new_spanned(
+            lit,
+            format!("Invalid uniform id '{}': {}", lit.value(), err),
+          )
+        })?,
+        lit => {
+          return Err(syn::Error::new_spanned(
+            lit,
+            "Uniform ids must be string literals",
+          ))
+        }
+      },
+      NestedMeta::Meta(Meta::Path(path)) if path.is_ident("stable") => {
+        // Name and type of every field, so layout changes result in a different id.
+        let layout = item
+          .fields
+          .iter()
+          .map(|field| {
+            let name = field.ident.as_ref().map(|name| name.to_string());
+            let ty = &field.ty;
+            format!("{}:{}", name.unwrap_or_default(), quote!(#ty))
+          })
+          .collect::<Vec<_>>()
+          .join(",");
+        let name = format!("{}{{{}}}", item.ident, layout);
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes())
+      }
+      arg => {
+        return Err(syn::Error::new_spanned(
+          arg,
+          "Unknown uniform argument, expected `id = \"…\"` or `stable`",
+        ))
+      }
+    });
+  }
+  Ok(id.unwrap_or_else(Uuid::new_v4))
+}
+
+/// Generates the std140 layout and shader reflection of a uniform struct.
+///
+/// Fields can be annotated with `#[default(expr)]` which generates a `Default` implementation,
+/// fields without an annotation fall back to their own `Default`. Use it for values that must
+/// not be zero when a material doesn't set them, e.g. a base color.
+///
+/// The id returned by `get_id` is random for every compilation unless it is fixed with
+/// `#[uniform(id = "…")]` or derived from the struct name and field layout with `#[uniform(stable)]`.
 #[proc_macro_attribute]
-pub fn uniform(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn uniform(attr: TokenStream, item: TokenStream) -> TokenStream {
   // Parse basic structure.
-  let item = parse_macro_input!(item as ItemStruct);
+  let args = parse_macro_input!(attr as AttributeArgs);
+  let mut item = parse_macro_input!(item as ItemStruct);
   let struct_ident = item.ident.clone();
   let struct_name_snakecase = item.ident.to_string().to_snake_case();
 
+  // Field defaults, the attributes are removed as the compiler doesn't know them.
+  let mut defaults = Vec::with_capacity(item.fields.len());
+  let mut has_defaults = false;
+  for field in item.fields.iter_mut() {
+    let mut default = None;
+    let mut error = None;
+    field.attrs.retain(|attr| {
+      if !attr.path.is_ident("default") {
+        return true;
+      }
+      match attr.parse_args::<Expr>() {
+        Ok(expr) => default = Some(expr),
+        Err(_) => {
+          error = Some(syn::Error::new_spanned(
+            attr,
+            "Default values of uniform fields must be expressions",
+          ))
+        }
+      }
+      false
+    });
+    if let Some(error) = error {
+      return TokenStream::from(error.to_compile_error());
+    }
+
+    let name = match field.ident.clone() {
+      Some(name) => name,
+      None => {
+        return TokenStream::from(
+          syn::Error::new_spanned(&*field, "All uniform struct fields must be named")
+            .to_compile_error(),
+        )
+      }
+    };
+    defaults.push(match default {
+      Some(expr) => {
+        has_defaults = true;
+        quote! { #name: #expr }
+      }
+      None => quote! { #name: Default::default() },
+    });
+  }
+
   // Structure attribute parsing
   let mut attribute_descs = Vec::with_capacity(item.fields.len());
   let mut shader_outputs_constants = Vec::with_capacity(item.fields.len());
   let mut struct_dependencies = Vec::new();
 
   for (index, attr) in item.fields.iter().enumerate() {
-    let name = attr
-      .ident
-      .clone()
-      .unwrap_or_else(|| panic!("All vertex struct fields must be named"));
+    // Fields are known to be named from collecting the defaults above.
+    let name = attr.ident.clone().unwrap();
     let name_str = name.to_string();
 
-    let ty = path_to_glsl_type(&attr.ty)
-      .unwrap_or_else(|| panic!("Unknown types can't be used within a vertex struct"));
+    let ty = match path_to_glsl_type(&attr.ty) {
+      Some(ty) => ty,
+      None => {
+        return TokenStream::from(
+          syn::Error::new_spanned(
+            &attr.ty,
+            "Unknown types can't be used within a uniform struct",
+          )
+          .to_compile_error(),
+        )
+      }
+    };
 
     // Attribute desc
     struct_copy(&mut struct_dependencies, &ty);
@@ -316,6 +506,15 @@ pub fn uniform(_attr: TokenStream, item: TokenStream) -> TokenStream {
           Array(#name, #len)
         }
       }
+      "Unorm8x4" | "Half2" | "Half4" => {
+        return TokenStream::from(
+          syn::Error::new_spanned(
+            &attr.ty,
+            "Packed types can't be used within a uniform struct",
+          )
+          .to_compile_error(),
+        );
+      }
       _ => {
         let ident = format_ident!("{}", ty.enum_type);
         quote! { #ident }
@@ -333,13 +532,33 @@ pub fn uniform(_attr: TokenStream, item: TokenStream) -> TokenStream {
     });
   }
 
-  let uuid = Uuid::new_v4().to_u128_le();
+  let uuid = match uniform_id(&args, &item) {
+    Ok(id) => id.to_u128_le(),
+    Err(err) => return TokenStream::from(err.to_compile_error()),
+  };
+
+  // Only structs using default values get a default implementation, others might derive it.
+  let default_impl = if has_defaults {
+    quote! {
+      impl Default for #struct_ident {
+        fn default() -> Self {
+          Self {
+            #(#defaults),*
+          }
+        }
+      }
+    }
+  } else {
+    quote! {}
+  };
 
   TokenStream::from(quote! {
     #[repr(C)]
     #[derive(Copy, Clone, Debug, moonwave_shader::std140::AsStd140)]
     #item
 
+    #default_impl
+
     impl #struct_ident {
       #(#shader_outputs_constants)*
     }
@@ -1,23 +1,64 @@
 use crate::*;
 
+/// Literal value, e.g. to feed a fixed roughness into a node instead of relying on input defaults.
 #[derive(Debug)]
-pub struct Constant(Vec<f32>, ShaderType);
+pub struct Constant(Vec<String>, ShaderType);
 impl Constant {
   pub const OUTPUT: usize = 0;
 
   pub fn new<V: std::ops::Index<std::ops::RangeFull, Output = [f32]>>(value: V) -> Self {
-    let values = value.index(..).to_vec();
+    let values = value.index(..);
     let ty = match values.len() {
       4 => ShaderType::Float4,
       3 => ShaderType::Float3,
       2 => ShaderType::Float2,
       _ => ShaderType::Float,
     };
-    Self(values, ty)
+    Self::with_type(ty, values)
   }
 
   pub fn new_scalar(value: f32) -> Self {
-    Self(vec![value], ShaderType::Float)
+    Self::with_type(ShaderType::Float, &[value])
+  }
+
+  pub fn new_uint(value: u32) -> Self {
+    Self(vec![format!("{}u", value)], ShaderType::UInt)
+  }
+
+  /// Constant of an explicit type, values of integer types are truncated.
+  /// Panics if the amount of values doesn't match the components of the type.
+  pub fn with_type(ty: ShaderType, values: &[f32]) -> Self {
+    let float = |x: f32| format!("{:.7}", x);
+    let uint = |x: f32| format!("{}u", x.max(0.0) as u32);
+    let int = |x: f32| format!("{}", x as i32);
+    let (components, literal): (usize, &dyn Fn(f32) -> String) = match ty {
+      ShaderType::Matrix4 => (16, &float),
+      ShaderType::Float4 => (4, &float),
+      ShaderType::Float3 => (3, &float),
+      ShaderType::Float2 => (2, &float),
+      ShaderType::Float => (1, &float),
+      ShaderType::UInt4 => (4, &uint),
+      ShaderType::UInt3 => (3, &uint),
+      ShaderType::UInt2 => (2, &uint),
+      ShaderType::UInt => (1, &uint),
+      ShaderType::Int4 => (4, &int),
+      ShaderType::Int3 => (3, &int),
+      ShaderType::Int2 => (2, &int),
+      ShaderType::Int => (1, &int),
+      ShaderType::Struct(_) | ShaderType::Array(..) => {
+        panic!("Constants of type {:?} are not supported", ty)
+      }
+    };
+    assert_eq!(
+      values.len(),
+      components,
+      "Constant of type {:?} needs {} values",
+      ty,
+      components
+    );
+
+    let literals = values.iter().map(|x| literal(*x)).collect();
+    Self(literals, ty)
   }
 }
 impl ShaderNode for Constant {
@@ -31,12 +72,7 @@ impl ShaderNode for Constant {
       self.1.get_glsl_type(),
       outputs[Self::OUTPUT].as_ref().unwrap(),
       self.1.get_glsl_type(),
-      self
-        .0
-        .iter()
-        .map(|x| format!("{:.7}", x))
-        .collect::<Vec<_>>()
-        .join(",")
+      self.0.join(",")
     )
     .as_str();
   }
@@ -253,3 +289,165 @@ impl ShaderNode for MemberAccess {
     .as_str();
   }
 }
+
+/// Reconstructs the world position of a fragment from its depth and screen uv.
+/// Expects depth in the 0..1 range and uv with the origin in the top left corner.
+#[derive(Debug, Default)]
+pub struct WorldPosFromDepthNode;
+impl WorldPosFromDepthNode {
+  pub const INPUT_DEPTH: usize = 0;
+  pub const INPUT_UV: usize = 1;
+  pub const INPUT_INV_VIEW_PROJECTION: usize = 2;
+  pub const OUTPUT: usize = 0;
+
+  pub fn new() -> Self {
+    Self {}
+  }
+}
+
+impl ShaderNode for WorldPosFromDepthNode {
+  fn get_type_expectation(&self, index: usize) -> Option<ShaderType> {
+    match index {
+      Self::INPUT_DEPTH => Some(ShaderType::Float),
+      Self::INPUT_UV => Some(ShaderType::Float2),
+      Self::INPUT_INV_VIEW_PROJECTION => Some(ShaderType::Matrix4),
+      _ => None,
+    }
+  }
+
+  fn get_outputs(&self) -> Vec<ShaderType> {
+    vec![ShaderType::Float3]
+  }
+
+  fn generate(&self, inputs: &[Option<String>], outputs: &[Option<String>], output: &mut String) {
+    let output_name = outputs[Self::OUTPUT].as_ref().unwrap();
+    let uv = inputs[Self::INPUT_UV].as_ref().unwrap();
+
+    // Uv y points down while clip space y points up.
+    *output += format!(
+      "vec4 {}_clip = vec4({}.x * 2.0 - 1.0, 1.0 - {}.y * 2.0, {}, 1.0);\n",
+      output_name,
+      uv,
+      uv,
+      inputs[Self::INPUT_DEPTH].as_ref().unwrap(),
+    )
+    .as_str();
+    *output += format!(
+      "vec4 {}_world = {} * {}_clip;\n",
+      output_name,
+      inputs[Self::INPUT_INV_VIEW_PROJECTION].as_ref().unwrap(),
+      output_name,
+    )
+    .as_str();
+    *output += format!(
+      "vec3 {} = {}_world.xyz / {}_world.w;\n",
+      output_name, output_name, output_name,
+    )
+    .as_str();
+  }
+}
+
+/// Converts a sampled tangent space normal map color from [0, 1] into a normal within [-1, 1].
+#[derive(Debug)]
+pub struct NormalMapUnpackNode;
+impl NormalMapUnpackNode {
+  pub const INPUT_COLOR: usize = 0;
+  pub const OUTPUT_NORMAL: usize = 0;
+}
+
+impl ShaderNode for NormalMapUnpackNode {
+  fn get_type_expectation(&self, index: usize) -> Option<ShaderType> {
+    match index {
+      Self::INPUT_COLOR => Some(ShaderType::Float4),
+      _ => None,
+    }
+  }
+
+  fn get_outputs(&self) -> Vec<ShaderType> {
+    vec![ShaderType::Float3]
+  }
+
+  fn generate(&self, inputs: &[Option<String>], outputs: &[Option<String>], output: &mut String) {
+    *output += format!(
+      "vec3 {} = normalize({}.rgb * 2.0 - 1.0);\n",
+      outputs[Self::OUTPUT_NORMAL].as_ref().unwrap(),
+      inputs[Self::INPUT_COLOR].as_ref().unwrap(),
+    )
+    .as_str();
+  }
+}
+
+/// Maps linear HDR color into `0..=1` with the fitted ACES filmic curve of the tonemap pass.
+#[derive(Debug)]
+pub struct AcesTonemapNode;
+impl AcesTonemapNode {
+  pub const INPUT_COLOR: usize = 0;
+  pub const OUTPUT_COLOR: usize = 0;
+}
+
+impl ShaderNode for AcesTonemapNode {
+  fn get_type_expectation(&self, index: usize) -> Option<ShaderType> {
+    match index {
+      Self::INPUT_COLOR => Some(ShaderType::Float3),
+      _ => None,
+    }
+  }
+
+  fn get_outputs(&self) -> Vec<ShaderType> {
+    vec![ShaderType::Float3]
+  }
+
+  fn generate(&self, inputs: &[Option<String>], outputs: &[Option<String>], output: &mut String) {
+    let output_name = outputs[Self::OUTPUT_COLOR].as_ref().unwrap();
+    *output += format!(
+      "vec3 {}_hdr = max({}, vec3(0.0));\n",
+      output_name,
+      inputs[Self::INPUT_COLOR].as_ref().unwrap(),
+    )
+    .as_str();
+    *output += format!(
+      "vec3 {0} = clamp(({0}_hdr * (2.51 * {0}_hdr + 0.03)) / ({0}_hdr * (2.43 * {0}_hdr + 0.59) + 0.14), 0.0, 1.0);\n",
+      output_name,
+    )
+    .as_str();
+  }
+}
+
+/// Lambert diffuse term of a single directional light, the light direction points away from the light.
+#[derive(Debug)]
+pub struct DiffuseLightingNode;
+impl DiffuseLightingNode {
+  pub const INPUT_ALBEDO: usize = 0;
+  pub const INPUT_NORMAL: usize = 1;
+  pub const INPUT_LIGHT_DIRECTION: usize = 2;
+  pub const INPUT_LIGHT_COLOR: usize = 3;
+  pub const OUTPUT_COLOR: usize = 0;
+}
+
+impl ShaderNode for DiffuseLightingNode {
+  fn get_type_expectation(&self, index: usize) -> Option<ShaderType> {
+    match index {
+      Self::INPUT_ALBEDO
+      | Self::INPUT_NORMAL
+      | Self::INPUT_LIGHT_DIRECTION
+      | Self::INPUT_LIGHT_COLOR => Some(ShaderType::Float3),
+      _ => None,
+    }
+  }
+
+  fn get_outputs(&self) -> Vec<ShaderType> {
+    vec![ShaderType::Float3]
+  }
+
+  fn generate(&self, inputs: &[Option<String>], outputs: &[Option<String>], output: &mut String) {
+    *output += format!(
+      "vec3 {} = {} * {} * max(dot(normalize({}), -normalize({})), 0.0);\n",
+      outputs[Self::OUTPUT_COLOR].as_ref().unwrap(),
+      inputs[Self::INPUT_ALBEDO].as_ref().unwrap(),
+      inputs[Self::INPUT_LIGHT_COLOR].as_ref().unwrap(),
+      inputs[Self::INPUT_NORMAL].as_ref().unwrap(),
+      inputs[Self::INPUT_LIGHT_DIRECTION].as_ref().unwrap(),
+    )
+    .as_str();
+  }
+}
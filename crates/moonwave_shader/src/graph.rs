@@ -1,6 +1,7 @@
 use std::any::Any;
 use std::any::TypeId;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::hash::Hasher;
@@ -8,6 +9,7 @@ use std::sync::Arc;
 
 use generational_arena::Arena;
 use moonwave_resources::{VertexAttribute, VertexBuffer};
+use moonwave_util::dedup_preserving_order;
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -68,6 +70,7 @@ impl ShaderGraph {
     let string = name.to_string();
     let index = self.add_node(ColorOutputNode {
       name: string.clone(),
+      ty: format,
     });
     self.color_outputs.push((string, format, index));
     index
@@ -109,6 +112,7 @@ impl ShaderGraph {
     let id = Uuid::new_v4();
     let node = TextureArrayNode {
       name: name.to_string(),
+      size,
     };
     let node_index = self.add_node(node);
     self.texture_arrays.push(TextureArray {
@@ -241,6 +245,7 @@ impl ShaderGraph {
   }
 
   /// Connects one nodes output to another nodes input.
+  /// Fails if the output type differs from the type the input expects.
   pub fn connect(
     &mut self,
     source: Index,
@@ -256,11 +261,33 @@ impl ShaderGraph {
       return Err(GraphConnectError::MaximumInputsReached);
     };
 
+    let source_node = self
+      .nodes
+      .get(source)
+      .ok_or(GraphConnectError::InvalidSource)?
+      .node
+      .clone();
     let destination_node = self
       .nodes
       .get_mut(destination)
       .ok_or(GraphConnectError::InvalidDestination)?;
 
+    // Types are only known for nodes that declare them.
+    let provided = source_node.get_outputs().get(source_output).copied();
+    let expected = destination_node
+      .node
+      .get_type_expectation(destination_input);
+    if let (Some(provided), Some(expected)) = (provided, expected) {
+      if provided != expected {
+        return Err(GraphConnectError::TypeMismatch {
+          source_node: format!("{:?}", source_node),
+          provided,
+          destination_node: format!("{:?}", destination_node.node),
+          expected,
+        });
+      }
+    }
+
     // Target input is already connected.
     if destination_node.inputs[destination_input].is_some() {
       return Err(GraphConnectError::AlreadyConnected);
@@ -290,7 +317,7 @@ impl ShaderGraph {
       optick::event!("ShaderGraph::traverse_vertex_shader");
       let mut nodes = Vec::with_capacity(MAX_NODES);
       self.traverse(self.vertex_output_node.unwrap(), &mut nodes, params);
-      Self::dedup_unordered(&nodes)
+      dedup_preserving_order(&nodes)
     };
 
     // Traverse from the starting point of each color output.
@@ -300,7 +327,7 @@ impl ShaderGraph {
       for node in outputs {
         self.traverse(*node, &mut nodes, params);
       }
-      Self::dedup_unordered(&nodes)
+      dedup_preserving_order(&nodes)
     };
 
     // Find shared subtrees to optimze fragment shader with vertex shader calculations.
@@ -317,7 +344,7 @@ impl ShaderGraph {
           *node,
         );
       }
-      Self::dedup_unordered(&nodes)
+      dedup_preserving_order(&nodes)
     };
 
     // Build vertex / frag shared variables.
@@ -496,7 +523,14 @@ impl ShaderGraph {
       for (index, (ty, name)) in shared_attributes.iter().enumerate() {
         let is_flat = matches!(
           ty,
-          ShaderType::UInt | ShaderType::UInt2 | ShaderType::UInt3 | ShaderType::UInt4
+          ShaderType::UInt
+            | ShaderType::UInt2
+            | ShaderType::UInt3
+            | ShaderType::UInt4
+            | ShaderType::Int
+            | ShaderType::Int2
+            | ShaderType::Int3
+            | ShaderType::Int4
         );
 
         fragment_shader_code += format!(
@@ -669,25 +703,15 @@ impl ShaderGraph {
         .map(|i| Some(format!("var_{}_{}", node_index.into_raw_parts().0, i)))
         .collect::<Vec<_>>();
 
-      node.node.generate_global_code(&inputs, &outputs, global);
+      node
+        .node
+        .generate_global_code_with_params(&inputs, &outputs, global, params);
       node
         .node
         .generate_with_params(&inputs, &outputs, output, params);
     }
   }
 
-  fn dedup_unordered(list: &[Index]) -> Vec<Index> {
-    let mut deduped = Vec::new();
-    for item in list.iter().rev() {
-      if deduped.contains(item) {
-        continue;
-      }
-      deduped.push(*item);
-    }
-    deduped.reverse();
-    deduped
-  }
-
   fn traverse_subtree(
     &self,
     nodes_source: &[Index],
@@ -732,13 +756,12 @@ impl ShaderGraph {
     let node = self.nodes.get(index).unwrap().clone();
     for (index, input) in node.inputs.iter().enumerate() {
       if let Some(input) = input {
-        // Is target node a passthrough
+        // Is target node a passthrough, unconnected inputs stay to use the passthrough default value.
         let target_node = self.nodes.get(input.owner_node_index).unwrap();
-        if let Some(_) = target_node.node.as_passthrough() {
-          changes.push((
-            index,
-            target_node.inputs[input.owner_node_output].clone().unwrap(),
-          ));
+        if target_node.node.as_passthrough().is_some() {
+          if let Some(passthrough_input) = target_node.inputs[input.owner_node_output] {
+            changes.push((index, passthrough_input));
+          }
         }
 
         self.cleanup_passthrough(input.owner_node_index);
@@ -788,8 +811,13 @@ struct TextureArray {
   node_index: Index,
 }
 
+/// Cheap to clone, values are shared between clones.
+#[derive(Clone)]
 pub struct ShaderBuildParams {
-  params: HashMap<std::any::TypeId, Box<dyn Any>>,
+  params: HashMap<std::any::TypeId, Arc<dyn Any>>,
+  params_hash: u64,
+  defines: BTreeMap<String, bool>,
+  default_overrides: BTreeMap<(String, usize), String>,
   pub hash: u64,
 }
 
@@ -797,17 +825,21 @@ impl ShaderBuildParams {
   pub fn new() -> Self {
     Self {
       params: HashMap::new(),
+      params_hash: 0,
+      defines: BTreeMap::new(),
+      default_overrides: BTreeMap::new(),
       hash: 0,
     }
   }
 
   pub fn add<T: Any + Hash>(&mut self, value: T) {
     let mut hasher = DefaultHasher::default();
-    hasher.write_u64(self.hash);
+    hasher.write_u64(self.params_hash);
     value.hash(&mut hasher);
-    self.hash = hasher.finish();
+    self.params_hash = hasher.finish();
+    self.update_hash();
 
-    self.params.insert(value.type_id(), Box::new(value));
+    self.params.insert(value.type_id(), Arc::new(value));
   }
 
   pub fn get<T: Any>(&self) -> &T {
@@ -818,6 +850,47 @@ impl ShaderBuildParams {
       .downcast_ref()
       .unwrap()
   }
+
+  /// Sets a named flag nodes can query to specialize the generated shader, similar to `#define`.
+  pub fn define(&mut self, name: &str, value: bool) {
+    self.defines.insert(name.to_string(), value);
+    self.update_hash();
+  }
+
+  /// Value of a named flag, `None` if it has never been defined.
+  pub fn get_define(&self, name: &str) -> Option<bool> {
+    self.defines.get(name).copied()
+  }
+
+  /// Whether a named flag has been defined and set to true.
+  pub fn is_defined(&self, name: &str) -> bool {
+    self.get_define(name).unwrap_or(false)
+  }
+
+  /// Replaces the default GLSL literal of an unconnected input of the passthrough node with the given name.
+  pub fn override_default(&mut self, passthrough: &str, input: usize, literal: &str) {
+    self
+      .default_overrides
+      .insert((passthrough.to_string(), input), literal.to_string());
+    self.update_hash();
+  }
+
+  /// Overridden default literal of a passthrough input, `None` if the default isn't overridden.
+  pub fn get_default_override(&self, passthrough: &str, input: usize) -> Option<&str> {
+    self
+      .default_overrides
+      .get(&(passthrough.to_string(), input))
+      .map(|literal| literal.as_str())
+  }
+
+  fn update_hash(&mut self) {
+    // Defines are sorted by name so their order doesn't change the hash.
+    let mut hasher = DefaultHasher::default();
+    hasher.write_u64(self.params_hash);
+    self.defines.hash(&mut hasher);
+    self.default_overrides.hash(&mut hasher);
+    self.hash = hasher.finish();
+  }
 }
 
 pub trait ShaderNode: std::fmt::Debug + Send + Sync + 'static {
@@ -867,6 +940,18 @@ pub trait ShaderNode: std::fmt::Debug + Send + Sync + 'static {
     _output: &mut String,
   ) {
   }
+
+  /// Same as `generate_global_code` for declarations that depend on the build params,
+  /// e.g. additional vertex inputs.
+  fn generate_global_code_with_params(
+    &self,
+    inputs: &[Option<String>],
+    outputs: &[Option<String>],
+    output: &mut String,
+    _params: &ShaderBuildParams,
+  ) {
+    self.generate_global_code(inputs, outputs, output)
+  }
 }
 
 #[derive(Clone, Debug)]
@@ -920,6 +1005,7 @@ impl ShaderNode for VertexShaderOutputNode {
 #[derive(Debug)]
 struct ColorOutputNode {
   name: String,
+  ty: ShaderType,
 }
 
 impl ShaderNode for ColorOutputNode {
@@ -927,7 +1013,7 @@ impl ShaderNode for ColorOutputNode {
     (false, true)
   }
   fn get_type_expectation(&self, _index: usize) -> Option<ShaderType> {
-    Some(ShaderType::Float4)
+    Some(self.ty)
   }
   fn generate(&self, inputs: &[Option<String>], _outputs: &[Option<String>], output: &mut String) {
     *output += format!("f_{} = {};\n", self.name, inputs[0].as_ref().unwrap()).as_str();
@@ -1044,6 +1130,7 @@ impl ShaderNode for TextureSampleNode {
 #[derive(Debug)]
 struct TextureArrayNode {
   name: String,
+  size: u32,
 }
 impl ShaderNode for TextureArrayNode {
   fn get_outputs(&self) -> Vec<ShaderType> {
@@ -1066,11 +1153,14 @@ impl ShaderNode for TextureArrayNode {
   ) {
     *output += format!(
       r#"
+      const uint size_fn_arr_{} = {};
       vec4 sample_fn_arr_{}(vec2 uv, uint index) {{
         return texture(sampler2D(t_{}[index], s_{}), uv);
       }}
       "#,
       outputs[0].as_ref().unwrap(),
+      self.size,
+      outputs[0].as_ref().unwrap(),
       &self.name,
       &self.name,
     )
@@ -1087,12 +1177,40 @@ impl TextureArraySampleNode {
   pub const INPUT_INDEX: usize = 2;
   pub const OUTPUT_COLOR: usize = 0;
 
+  /// Define that makes sampling with an index outside of the array return magenta instead.
+  pub const DEFINE_VALIDATE_INDEX: &str = "VALIDATE_TEXTURE_ARRAY_INDEX";
+
   pub fn new() -> Self {
     Self
   }
 }
 
 impl ShaderNode for TextureArraySampleNode {
+  fn generate_with_params(
+    &self,
+    inputs: &[Option<String>],
+    outputs: &[Option<String>],
+    output: &mut String,
+    params: &ShaderBuildParams,
+  ) {
+    if !params.is_defined(Self::DEFINE_VALIDATE_INDEX) {
+      return self.generate(inputs, outputs, output);
+    }
+
+    let texture = inputs[Self::INPUT_TEXTURE].as_ref().unwrap();
+    let index = inputs[Self::INPUT_INDEX].as_ref().unwrap();
+    *output += format!(
+      "vec4 {} = {} < size_fn_arr_{} ? sample_fn_arr_{}({}, {}) : vec4(1.0, 0.0, 1.0, 1.0);\n",
+      outputs[Self::OUTPUT_COLOR].as_ref().unwrap(),
+      index,
+      texture,
+      texture,
+      inputs[Self::INPUT_UV].as_ref().unwrap(),
+      index
+    )
+    .as_str();
+  }
+
   fn generate(&self, inputs: &[Option<String>], outputs: &[Option<String>], output: &mut String) {
     *output += format!(
       "vec4 {} = sample_fn_arr_{}({}, {});\n",
@@ -1105,20 +1223,42 @@ impl ShaderNode for TextureArraySampleNode {
   }
 }
 
+/// Forwards its inputs to the nodes connected to it, used as the entry point of sub graphs.
+/// Unconnected inputs fall back to a default GLSL literal which can be overridden per build
+/// through `ShaderBuildParams::override_default` if the node has a name.
 #[derive(Debug)]
 pub struct InputPassthroughNode {
+  name: Option<String>,
   inputs: Vec<(ShaderType, String)>,
 }
 
 impl InputPassthroughNode {
   pub fn new() -> Self {
-    Self { inputs: Vec::new() }
+    Self {
+      name: None,
+      inputs: Vec::new(),
+    }
+  }
+
+  /// Name used to look up default overrides within the build params.
+  pub fn with_name(mut self, name: &str) -> Self {
+    self.name = Some(name.to_string());
+    self
   }
 
   pub fn add_input(mut self, ty: ShaderType, default: &str) -> Self {
     self.inputs.push((ty, default.to_string()));
     self
   }
+
+  /// Default literal used for the given input if it is not connected.
+  pub fn get_default<'a>(&'a self, index: usize, params: &'a ShaderBuildParams) -> &'a str {
+    self
+      .name
+      .as_ref()
+      .and_then(|name| params.get_default_override(name, index))
+      .unwrap_or_else(|| self.inputs[index].1.as_str())
+  }
 }
 
 impl ShaderNode for InputPassthroughNode {
@@ -1126,13 +1266,41 @@ impl ShaderNode for InputPassthroughNode {
     Some(self)
   }
 
-  fn generate(
+  fn optimize_input(&self, _index: usize, _params: &ShaderBuildParams) -> bool {
+    // Connected inputs are rewired to their source by the graph pre-processor.
+    false
+  }
+
+  fn get_outputs(&self) -> Vec<ShaderType> {
+    self.inputs.iter().map(|(ty, _)| *ty).collect()
+  }
+
+  fn generate_with_params(
     &self,
-    _inputs: &[Option<String>],
-    _outputs: &[Option<String>],
-    _output: &mut String,
+    inputs: &[Option<String>],
+    outputs: &[Option<String>],
+    output: &mut String,
+    params: &ShaderBuildParams,
   ) {
-    panic!("Passthrough node must be elimated in graph pre-processor");
+    // Connected inputs have been rewired to their source, only defaults are declared.
+    for (index, (ty, _)) in self.inputs.iter().enumerate() {
+      if inputs.get(index).map_or(false, Option::is_some) {
+        continue;
+      }
+      if let Some(Some(name)) = outputs.get(index) {
+        *output += format!(
+          "{} {} = {};\n",
+          ty.get_glsl_type(),
+          name,
+          self.get_default(index, params)
+        )
+        .as_str();
+      }
+    }
+  }
+
+  fn generate(&self, inputs: &[Option<String>], outputs: &[Option<String>], output: &mut String) {
+    self.generate_with_params(inputs, outputs, output, &ShaderBuildParams::new());
   }
 }
 
@@ -1189,6 +1357,15 @@ pub enum GraphConnectError {
   MaximumOutputsReached,
   #[error("The target node does not exist")]
   InvalidDestination,
+  #[error("The source node does not exist")]
+  InvalidSource,
+  #[error("{source_node} provides {provided:?} but {destination_node} expects {expected:?}")]
+  TypeMismatch {
+    source_node: String,
+    provided: ShaderType,
+    destination_node: String,
+    expected: ShaderType,
+  },
   #[error("The target nodes input is already connected")]
   AlreadyConnected,
 }
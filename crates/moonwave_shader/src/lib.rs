@@ -11,16 +11,20 @@ pub use moonwave_shader_macro::{uniform, vertex};
 
 mod base;
 mod graph;
+mod library;
+mod reflection;
 
 pub use base::*;
 pub use graph::*;
+pub use library::*;
+pub use reflection::*;
 pub use uuid::Uuid;
 
 #[cfg(test)]
 mod test;
 
 /// Describes a type available within shaders.
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
 pub enum ShaderType {
   Matrix4,
   Float4,
@@ -31,6 +35,10 @@ pub enum ShaderType {
   UInt3,
   UInt2,
   UInt,
+  Int4,
+  Int3,
+  Int2,
+  Int,
   Struct(&'static str),
   Array(&'static str, usize),
 }
@@ -47,6 +55,10 @@ impl ShaderType {
       ShaderType::UInt3 => "uvec3".to_string(),
       ShaderType::UInt2 => "uvec2".to_string(),
       ShaderType::UInt => "uint".to_string(),
+      ShaderType::Int4 => "ivec4".to_string(),
+      ShaderType::Int3 => "ivec3".to_string(),
+      ShaderType::Int2 => "ivec2".to_string(),
+      ShaderType::Int => "int".to_string(),
       ShaderType::Struct(name) => name.to_string(),
       ShaderType::Array(name, _size) => name.to_string(),
     }
@@ -70,6 +82,13 @@ impl From<VertexAttributeFormat> for ShaderType {
       VertexAttributeFormat::UInt3 => ShaderType::UInt3,
       VertexAttributeFormat::UInt2 => ShaderType::UInt2,
       VertexAttributeFormat::UInt => ShaderType::UInt,
+      VertexAttributeFormat::Int4 => ShaderType::Int4,
+      VertexAttributeFormat::Int3 => ShaderType::Int3,
+      VertexAttributeFormat::Int2 => ShaderType::Int2,
+      VertexAttributeFormat::Int => ShaderType::Int,
+      // Packed formats are converted to floats when fetched.
+      VertexAttributeFormat::Unorm8x4 | VertexAttributeFormat::Half4 => ShaderType::Float4,
+      VertexAttributeFormat::Half2 => ShaderType::Float2,
     }
   }
 }
@@ -88,4 +107,19 @@ pub trait UniformStruct: Sized {
   fn generate_name() -> String;
   fn generate_dependencies() -> Vec<(String, Vec<(String, ShaderType)>)>;
   fn generate_attributes() -> Vec<(String, ShaderType)>;
+
+  /// Fields together with their std140 offsets within the data of `generate_raw_u8`.
+  fn generate_layout() -> Vec<UniformField> {
+    std140_layout(&Self::generate_attributes(), &Self::generate_dependencies())
+  }
+
+  /// Padding std140 inserts between fields, useful to spot layouts not matching the GLSL side.
+  fn generate_padding() -> Vec<UniformPadding> {
+    std140_padding(&Self::generate_attributes(), &Self::generate_dependencies())
+  }
+
+  /// Listing of all fields with their offsets and padding.
+  fn layout_report() -> String {
+    std140_report(&Self::generate_attributes(), &Self::generate_dependencies())
+  }
 }
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+use thiserror::Error;
+
+use crate::{
+  AcesTonemapNode, DiffuseLightingNode, Index, InputPassthroughNode, NormalMapUnpackNode,
+  ShaderGraph, ShaderNode, ShaderType,
+};
+
+/// Built-in sub graph lighting albedo by a directional light.
+/// Inputs follow `DiffuseLightingNode`, the output is the lit `Float3` color.
+pub const SUBGRAPH_LIGHTING: &str = "lighting";
+/// Built-in sub graph mapping a linear HDR `Float3` color into `0..=1`, see `AcesTonemapNode`.
+pub const SUBGRAPH_TONEMAPPING: &str = "tonemapping";
+/// Built-in sub graph unpacking a sampled `Float4` normal map color into a `Float3` normal.
+pub const SUBGRAPH_NORMAL_MAPPING: &str = "normal_mapping";
+
+static SUB_GRAPH_LIBRARY: Lazy<RwLock<HashMap<String, Arc<LibrarySubGraph>>>> =
+  Lazy::new(|| RwLock::new(builtin_subgraphs()));
+
+/// A reusable sub graph stored in the global sub graph library.
+pub struct LibrarySubGraph {
+  pub graph: ShaderGraph,
+  pub input_node: Option<Index>,
+  pub output_node: Option<Index>,
+}
+
+/// Registers a reusable sub graph under the given name, replacing any previous registration.
+pub fn register_named_subgraph(
+  name: &str,
+  graph: ShaderGraph,
+  input_node: Option<Index>,
+  output_node: Option<Index>,
+) {
+  let mut library = SUB_GRAPH_LIBRARY.write().unwrap();
+  library.insert(
+    name.to_string(),
+    Arc::new(LibrarySubGraph {
+      graph,
+      input_node,
+      output_node,
+    }),
+  );
+}
+
+/// Wraps a single node into a sub graph whose inputs are fed through a passthrough node.
+fn single_node_subgraph<T: ShaderNode>(node: T, inputs: &[(ShaderType, &str)]) -> LibrarySubGraph {
+  let mut graph = ShaderGraph::new();
+  let input = graph.add_node(
+    inputs
+      .iter()
+      .fold(InputPassthroughNode::new(), |passthrough, (ty, default)| {
+        passthrough.add_input(*ty, default)
+      }),
+  );
+  let output = graph.add_node(node);
+  for index in 0..inputs.len() {
+    graph.connect(input, index, output, index).unwrap();
+  }
+
+  LibrarySubGraph {
+    graph,
+    input_node: Some(input),
+    output_node: Some(output),
+  }
+}
+
+/// Sub graphs every library starts with.
+fn builtin_subgraphs() -> HashMap<String, Arc<LibrarySubGraph>> {
+  let lighting = single_node_subgraph(
+    DiffuseLightingNode,
+    &[
+      (ShaderType::Float3, "vec3(1.0)"),
+      (ShaderType::Float3, "vec3(0.0, 0.0, 1.0)"),
+      (ShaderType::Float3, "vec3(0.0, 0.0, -1.0)"),
+      (ShaderType::Float3, "vec3(1.0)"),
+    ],
+  );
+  let tonemapping = single_node_subgraph(AcesTonemapNode, &[(ShaderType::Float3, "vec3(0.0)")]);
+  let normal_mapping = single_node_subgraph(
+    NormalMapUnpackNode,
+    &[(ShaderType::Float4, "vec4(0.5, 0.5, 1.0, 1.0)")],
+  );
+
+  vec![
+    (SUBGRAPH_LIGHTING, lighting),
+    (SUBGRAPH_TONEMAPPING, tonemapping),
+    (SUBGRAPH_NORMAL_MAPPING, normal_mapping),
+  ]
+  .into_iter()
+  .map(|(name, sub_graph)| (name.to_string(), Arc::new(sub_graph)))
+  .collect()
+}
+
+/// Returns the sub graph registered under the given name.
+pub fn get_named_subgraph(name: &str) -> Option<Arc<LibrarySubGraph>> {
+  let library = SUB_GRAPH_LIBRARY.read().unwrap();
+  library.get(name).cloned()
+}
+
+impl ShaderGraph {
+  /// Splices a sub graph from the global library into the current graph.
+  pub fn add_named_subgraph(
+    &mut self,
+    name: &str,
+  ) -> Result<(Option<Index>, Option<Index>), SubGraphLibraryError> {
+    let sub_graph =
+      get_named_subgraph(name).ok_or_else(|| SubGraphLibraryError::NotFound(name.to_string()))?;
+    Ok(self.add_sub_graph(
+      &sub_graph.graph,
+      sub_graph.input_node,
+      sub_graph.output_node,
+    ))
+  }
+}
+
+#[derive(Error, Debug)]
+pub enum SubGraphLibraryError {
+  #[error("No sub graph registered with name {0}")]
+  NotFound(String),
+}
@@ -0,0 +1,327 @@
+use thiserror::Error;
+
+use crate::ShaderType;
+
+/// A single field of a uniform struct together with its std140 byte offset.
+#[derive(Clone, Debug)]
+pub struct UniformField {
+  pub name: String,
+  pub ty: ShaderType,
+  pub offset: usize,
+}
+
+impl UniformField {
+  /// Size in bytes of primitive fields, `None` for structs and arrays.
+  pub fn size(&self) -> Option<usize> {
+    UniformValue::size_of(self.ty)
+  }
+
+  /// Reads the value of this field from raw std140 uniform data.
+  pub fn read(&self, data: &[u8]) -> Result<UniformValue, UniformFieldError> {
+    let size = self
+      .size()
+      .ok_or_else(|| UniformFieldError::Unsupported(self.name.clone()))?;
+    let bytes = data
+      .get(self.offset..self.offset + size)
+      .ok_or_else(|| UniformFieldError::OutOfBounds(self.name.clone()))?;
+    Ok(UniformValue::from_bytes(self.ty, bytes).unwrap())
+  }
+
+  /// Writes the given value into raw std140 uniform data.
+  pub fn write(&self, data: &mut [u8], value: UniformValue) -> Result<(), UniformFieldError> {
+    if std::mem::discriminant(&value.get_type()) != std::mem::discriminant(&self.ty) {
+      return Err(UniformFieldError::TypeMismatch(self.name.clone()));
+    }
+
+    let bytes = value.to_bytes();
+    let target = data
+      .get_mut(self.offset..self.offset + bytes.len())
+      .ok_or_else(|| UniformFieldError::OutOfBounds(self.name.clone()))?;
+    target.copy_from_slice(&bytes);
+    Ok(())
+  }
+}
+
+/// Finds a field by its name.
+pub fn find_uniform_field<'a>(
+  fields: &'a [UniformField],
+  name: &str,
+) -> Result<&'a UniformField, UniformFieldError> {
+  fields
+    .iter()
+    .find(|field| field.name == name)
+    .ok_or_else(|| UniformFieldError::UnknownField(name.to_string()))
+}
+
+/// Runtime value of a primitive uniform field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniformValue {
+  Matrix4([[f32; 4]; 4]),
+  Float4([f32; 4]),
+  Float3([f32; 3]),
+  Float2([f32; 2]),
+  Float(f32),
+  UInt4([u32; 4]),
+  UInt3([u32; 3]),
+  UInt2([u32; 2]),
+  UInt(u32),
+  Int4([i32; 4]),
+  Int3([i32; 3]),
+  Int2([i32; 2]),
+  Int(i32),
+}
+
+impl UniformValue {
+  pub fn get_type(&self) -> ShaderType {
+    match self {
+      UniformValue::Matrix4(_) => ShaderType::Matrix4,
+      UniformValue::Float4(_) => ShaderType::Float4,
+      UniformValue::Float3(_) => ShaderType::Float3,
+      UniformValue::Float2(_) => ShaderType::Float2,
+      UniformValue::Float(_) => ShaderType::Float,
+      UniformValue::UInt4(_) => ShaderType::UInt4,
+      UniformValue::UInt3(_) => ShaderType::UInt3,
+      UniformValue::UInt2(_) => ShaderType::UInt2,
+      UniformValue::UInt(_) => ShaderType::UInt,
+      UniformValue::Int4(_) => ShaderType::Int4,
+      UniformValue::Int3(_) => ShaderType::Int3,
+      UniformValue::Int2(_) => ShaderType::Int2,
+      UniformValue::Int(_) => ShaderType::Int,
+    }
+  }
+
+  fn size_of(ty: ShaderType) -> Option<usize> {
+    match ty {
+      ShaderType::Struct(_) | ShaderType::Array(..) => None,
+      ty => std140_size_align(ty, &[]).map(|(size, _)| size),
+    }
+  }
+
+  fn to_bytes(&self) -> Vec<u8> {
+    fn floats(values: &[f32]) -> Vec<u8> {
+      values
+        .iter()
+        .flat_map(|v| v.to_le_bytes().to_vec())
+        .collect()
+    }
+    fn uints(values: &[u32]) -> Vec<u8> {
+      values
+        .iter()
+        .flat_map(|v| v.to_le_bytes().to_vec())
+        .collect()
+    }
+    fn ints(values: &[i32]) -> Vec<u8> {
+      values
+        .iter()
+        .flat_map(|v| v.to_le_bytes().to_vec())
+        .collect()
+    }
+
+    match self {
+      UniformValue::Matrix4(m) => floats(&m.concat()),
+      UniformValue::Float4(v) => floats(&v[..]),
+      UniformValue::Float3(v) => floats(&v[..]),
+      UniformValue::Float2(v) => floats(&v[..]),
+      UniformValue::Float(v) => floats(&[*v]),
+      UniformValue::UInt4(v) => uints(&v[..]),
+      UniformValue::UInt3(v) => uints(&v[..]),
+      UniformValue::UInt2(v) => uints(&v[..]),
+      UniformValue::UInt(v) => uints(&[*v]),
+      UniformValue::Int4(v) => ints(&v[..]),
+      UniformValue::Int3(v) => ints(&v[..]),
+      UniformValue::Int2(v) => ints(&v[..]),
+      UniformValue::Int(v) => ints(&[*v]),
+    }
+  }
+
+  fn from_bytes(ty: ShaderType, bytes: &[u8]) -> Option<Self> {
+    let word = |i: usize| {
+      let mut raw = [0u8; 4];
+      raw.copy_from_slice(&bytes[i * 4..i * 4 + 4]);
+      raw
+    };
+    let float = |i: usize| f32::from_le_bytes(word(i));
+    let uint = |i: usize| u32::from_le_bytes(word(i));
+    let int = |i: usize| i32::from_le_bytes(word(i));
+
+    Some(match ty {
+      ShaderType::Matrix4 => {
+        let mut m = [[0.0; 4]; 4];
+        for (c, column) in m.iter_mut().enumerate() {
+          for (r, value) in column.iter_mut().enumerate() {
+            *value = float(c * 4 + r);
+          }
+        }
+        UniformValue::Matrix4(m)
+      }
+      ShaderType::Float4 => UniformValue::Float4([float(0), float(1), float(2), float(3)]),
+      ShaderType::Float3 => UniformValue::Float3([float(0), float(1), float(2)]),
+      ShaderType::Float2 => UniformValue::Float2([float(0), float(1)]),
+      ShaderType::Float => UniformValue::Float(float(0)),
+      ShaderType::UInt4 => UniformValue::UInt4([uint(0), uint(1), uint(2), uint(3)]),
+      ShaderType::UInt3 => UniformValue::UInt3([uint(0), uint(1), uint(2)]),
+      ShaderType::UInt2 => UniformValue::UInt2([uint(0), uint(1)]),
+      ShaderType::UInt => UniformValue::UInt(uint(0)),
+      ShaderType::Int4 => UniformValue::Int4([int(0), int(1), int(2), int(3)]),
+      ShaderType::Int3 => UniformValue::Int3([int(0), int(1), int(2)]),
+      ShaderType::Int2 => UniformValue::Int2([int(0), int(1)]),
+      ShaderType::Int => UniformValue::Int(int(0)),
+      ShaderType::Struct(_) | ShaderType::Array(..) => return None,
+    })
+  }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum UniformFieldError {
+  #[error("The uniform has no field named '{0}'")]
+  UnknownField(String),
+  #[error("The value does not match the type of field '{0}'")]
+  TypeMismatch(String),
+  #[error("Field '{0}' is not a primitive and can't be accessed by value")]
+  Unsupported(String),
+  #[error("Field '{0}' lies outside of the uniform data")]
+  OutOfBounds(String),
+}
+
+fn round_up(value: usize, align: usize) -> usize {
+  (value + align - 1) / align * align
+}
+
+fn glsl_name_to_type(name: &'static str) -> ShaderType {
+  match name {
+    "mat4" => ShaderType::Matrix4,
+    "vec4" => ShaderType::Float4,
+    "vec3" => ShaderType::Float3,
+    "vec2" => ShaderType::Float2,
+    "float" => ShaderType::Float,
+    "uvec4" => ShaderType::UInt4,
+    "uvec3" => ShaderType::UInt3,
+    "uvec2" => ShaderType::UInt2,
+    "uint" | "u32" => ShaderType::UInt,
+    "ivec4" => ShaderType::Int4,
+    "ivec3" => ShaderType::Int3,
+    "ivec2" => ShaderType::Int2,
+    "int" => ShaderType::Int,
+    name => ShaderType::Struct(name),
+  }
+}
+
+/// Returns size and alignment of a type following the std140 layout rules.
+fn std140_size_align(
+  ty: ShaderType,
+  dependencies: &[(String, Vec<(String, ShaderType)>)],
+) -> Option<(usize, usize)> {
+  Some(match ty {
+    ShaderType::Float | ShaderType::UInt | ShaderType::Int => (4, 4),
+    ShaderType::Float2 | ShaderType::UInt2 | ShaderType::Int2 => (8, 8),
+    ShaderType::Float3 | ShaderType::UInt3 | ShaderType::Int3 => (12, 16),
+    ShaderType::Float4 | ShaderType::UInt4 | ShaderType::Int4 => (16, 16),
+    ShaderType::Matrix4 => (64, 16),
+    ShaderType::Struct(name) => {
+      let (_, attributes) = dependencies.iter().find(|(dep, _)| dep == name)?;
+      let mut end = 0;
+      let mut align = 16;
+      for (_, ty) in attributes {
+        let (size, field_align) = std140_size_align(*ty, dependencies)?;
+        end = round_up(end, field_align) + size;
+        align = align.max(field_align);
+      }
+      (round_up(end, align), align)
+    }
+    ShaderType::Array(name, len) => {
+      let (size, align) = std140_size_align(glsl_name_to_type(name), dependencies)?;
+      let align = round_up(align, 16);
+      (round_up(size, align) * len, align)
+    }
+  })
+}
+
+/// Calculates the std140 offsets of all fields, stops at the first field of unknown size.
+pub fn std140_layout(
+  attributes: &[(String, ShaderType)],
+  dependencies: &[(String, Vec<(String, ShaderType)>)],
+) -> Vec<UniformField> {
+  let mut fields = Vec::with_capacity(attributes.len());
+  let mut end = 0;
+  for (name, ty) in attributes {
+    let (size, align) = match std140_size_align(*ty, dependencies) {
+      Some(size_align) => size_align,
+      None => break,
+    };
+    let offset = round_up(end, align);
+    fields.push(UniformField {
+      name: name.clone(),
+      ty: *ty,
+      offset,
+    });
+    end = offset + size;
+  }
+  fields
+}
+
+/// Bytes std140 leaves unused in front of a field, or at the end of the struct if `field` is `None`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UniformPadding {
+  pub offset: usize,
+  pub size: usize,
+  pub field: Option<String>,
+}
+
+/// Finds all padding std140 inserts between fields and at the end of the struct.
+pub fn std140_padding(
+  attributes: &[(String, ShaderType)],
+  dependencies: &[(String, Vec<(String, ShaderType)>)],
+) -> Vec<UniformPadding> {
+  let mut paddings = Vec::new();
+  let mut end = 0;
+  for (name, ty) in attributes {
+    let (size, align) = match std140_size_align(*ty, dependencies) {
+      Some(size_align) => size_align,
+      None => return paddings,
+    };
+    let offset = round_up(end, align);
+    if offset > end {
+      paddings.push(UniformPadding {
+        offset: end,
+        size: offset - end,
+        field: Some(name.clone()),
+      });
+    }
+    end = offset + size;
+  }
+
+  // Structs are always padded to a multiple of 16 bytes.
+  if round_up(end, 16) > end {
+    paddings.push(UniformPadding {
+      offset: end,
+      size: round_up(end, 16) - end,
+      field: None,
+    });
+  }
+  paddings
+}
+
+/// Human readable listing of all fields with offsets and padding, e.g. to compare with GLSL.
+pub fn std140_report(
+  attributes: &[(String, ShaderType)],
+  dependencies: &[(String, Vec<(String, ShaderType)>)],
+) -> String {
+  let paddings = std140_padding(attributes, dependencies);
+  let padding_line =
+    |padding: &UniformPadding| format!("{:>5}  padding ({} bytes)\n", padding.offset, padding.size);
+
+  let mut report = String::new();
+  for field in std140_layout(attributes, dependencies) {
+    let padding = paddings
+      .iter()
+      .find(|padding| padding.field.as_ref() == Some(&field.name));
+    if let Some(padding) = padding {
+      report += padding_line(padding).as_str();
+    }
+    report += format!("{:>5}  {}: {:?}\n", field.offset, field.name, field.ty).as_str();
+  }
+  if let Some(padding) = paddings.iter().find(|padding| padding.field.is_none()) {
+    report += padding_line(padding).as_str();
+  }
+  report
+}
@@ -1,5 +1,5 @@
 use crate::*;
-use moonwave_common::{Vector2, Vector3, Vector4};
+use moonwave_common::{Matrix4, SquareMatrix, Vector2, Vector3, Vector4};
 
 mod moonwave_shader {
   pub use crate::*;
@@ -179,3 +179,437 @@ fn test_uniform_shader() {
   let built = shader.build(&[color]);
   insta::assert_debug_snapshot!("ub_full", built);
 }
+
+fn build_library_material() -> BuiltShaderGraph {
+  let mut shader = ShaderGraph::new();
+  let (_, vertex_out) = shader.add_vertex_attributes::<SampleVertex>();
+  let color = shader.add_color_output("color", ShaderType::Float4);
+  let source = shader.add_node(Constant::new(Vector4::new(1.0, 0.5, 0.25, 1.0)));
+
+  let (input, output) = shader.add_named_subgraph("test_scale").unwrap();
+  shader
+    .connect(source, Constant::OUTPUT, input.unwrap(), 0)
+    .unwrap();
+  shader
+    .connect(output.unwrap(), Multiply::OUTPUT, vertex_out, 0)
+    .unwrap();
+  shader
+    .connect(output.unwrap(), Multiply::OUTPUT, color, 0)
+    .unwrap();
+
+  shader.build(&[color], &ShaderBuildParams::new())
+}
+
+#[test]
+fn test_named_subgraph() {
+  // Register library sub graph
+  let mut sub = ShaderGraph::new();
+  let input = sub.add_node(InputPassthroughNode::new().add_input(ShaderType::Float4, "vec4(0.0)"));
+  let scale = sub.add_node(Constant::new(Vector4::new(2.0, 2.0, 2.0, 1.0)));
+  let multiply = sub.add_node(Multiply::new(ShaderType::Float4));
+  sub.connect(input, 0, multiply, Multiply::INPUT_A).unwrap();
+  sub
+    .connect(scale, Constant::OUTPUT, multiply, Multiply::INPUT_B)
+    .unwrap();
+  register_named_subgraph("test_scale", sub, Some(input), Some(multiply));
+
+  // Both materials must end up with the same wiring.
+  let a = build_library_material();
+  let b = build_library_material();
+  assert_eq!(a.vs, b.vs);
+  assert_eq!(a.fs, b.fs);
+  assert!(ShaderGraph::new().add_named_subgraph("unknown").is_err());
+}
+
+#[test]
+fn test_builtin_subgraphs() {
+  // Normal map -> lighting -> tonemapping, composed from library pieces only.
+  let mut shader = ShaderGraph::new();
+  let (_, vertex_out) = shader.add_vertex_attributes::<SampleVertex>();
+  let color = shader.add_color_output("color", ShaderType::Float4);
+  let (_, normal) = shader.add_named_subgraph(SUBGRAPH_NORMAL_MAPPING).unwrap();
+  let (lighting_in, lit) = shader.add_named_subgraph(SUBGRAPH_LIGHTING).unwrap();
+  let (tonemap_in, tonemapped) = shader.add_named_subgraph(SUBGRAPH_TONEMAPPING).unwrap();
+  let upgrade = shader.add_node(Vector3Upgrade);
+  let position = shader.add_node(Constant::new(Vector4::new(0.0, 0.0, 0.0, 1.0)));
+
+  shader
+    .connect(
+      normal.unwrap(),
+      NormalMapUnpackNode::OUTPUT_NORMAL,
+      lighting_in.unwrap(),
+      DiffuseLightingNode::INPUT_NORMAL,
+    )
+    .unwrap();
+  shader
+    .connect(
+      lit.unwrap(),
+      DiffuseLightingNode::OUTPUT_COLOR,
+      tonemap_in.unwrap(),
+      AcesTonemapNode::INPUT_COLOR,
+    )
+    .unwrap();
+  shader
+    .connect(
+      tonemapped.unwrap(),
+      AcesTonemapNode::OUTPUT_COLOR,
+      upgrade,
+      Vector3Upgrade::INPUT,
+    )
+    .unwrap();
+  shader
+    .connect(position, Constant::OUTPUT, vertex_out, 0)
+    .unwrap();
+  shader
+    .connect(upgrade, Vector3Upgrade::OUTPUT, color, 0)
+    .unwrap();
+
+  let built = shader.build(&[color], &ShaderBuildParams::new());
+  assert!(built.fs.contains("* 2.0 - 1.0"));
+  assert!(built.fs.contains("max(dot("));
+  assert!(built.fs.contains("2.51"));
+}
+
+#[cfg(test)]
+#[uniform]
+struct SampleUniformReflection {
+  matrix: Matrix4<f32>,
+  tint: Vector3<f32>,
+  roughness: f32,
+}
+
+#[test]
+fn test_uniform_reflection() {
+  let layout = SampleUniformReflection::generate_layout();
+  let offsets = layout
+    .iter()
+    .map(|field| (field.name.as_str(), field.offset))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    offsets,
+    vec![("matrix", 0), ("tint", 64), ("roughness", 76)]
+  );
+
+  let uniform = |roughness| SampleUniformReflection {
+    matrix: Matrix4::identity(),
+    tint: Vector3::new(1.0, 0.5, 0.25),
+    roughness,
+  };
+  let mut data = uniform(0.0).generate_raw_u8();
+  let field = find_uniform_field(&layout, "roughness").unwrap();
+  field.write(&mut data, UniformValue::Float(0.5)).unwrap();
+  assert_eq!(data, uniform(0.5).generate_raw_u8());
+  assert_eq!(field.read(&data), Ok(UniformValue::Float(0.5)));
+
+  assert_eq!(
+    field.write(&mut data, UniformValue::UInt(1)),
+    Err(UniformFieldError::TypeMismatch("roughness".to_string()))
+  );
+  assert!(find_uniform_field(&layout, "unknown").is_err());
+}
+
+#[test]
+fn test_build_params_defines() {
+  let mut a = ShaderBuildParams::new();
+  a.define("USE_NORMAL_MAP", true);
+  a.define("USE_FOG", false);
+  assert!(a.is_defined("USE_NORMAL_MAP"));
+  assert!(!a.is_defined("USE_FOG"));
+  assert!(!a.is_defined("UNKNOWN"));
+  assert_eq!(a.get_define("USE_FOG"), Some(false));
+  assert_eq!(a.get_define("UNKNOWN"), None);
+
+  // Order of definition doesn't matter for caching but values do.
+  let mut b = ShaderBuildParams::new();
+  b.define("USE_FOG", false);
+  b.define("USE_NORMAL_MAP", true);
+  assert_eq!(a.hash, b.hash);
+
+  b.define("USE_FOG", true);
+  assert_ne!(a.hash, b.hash);
+  assert_ne!(a.hash, ShaderBuildParams::new().hash);
+}
+
+#[test]
+fn test_world_pos_from_depth() {
+  let node = WorldPosFromDepthNode::new();
+  assert!(matches!(node.get_outputs()[..], [ShaderType::Float3]));
+  assert!(matches!(
+    node.get_type_expectation(WorldPosFromDepthNode::INPUT_INV_VIEW_PROJECTION),
+    Some(ShaderType::Matrix4)
+  ));
+
+  let mut glsl = String::new();
+  node.generate(
+    &[
+      Some("depth".to_string()),
+      Some("uv".to_string()),
+      Some("inv_vp".to_string()),
+    ],
+    &[Some("world_pos".to_string())],
+    &mut glsl,
+  );
+
+  // Clip space reconstruction followed by the perspective divide.
+  assert!(
+    glsl.contains("vec4 world_pos_clip = vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);")
+  );
+  assert!(glsl.contains("vec4 world_pos_world = inv_vp * world_pos_clip;"));
+  assert!(glsl.contains("vec3 world_pos = world_pos_world.xyz / world_pos_world.w;"));
+}
+
+#[test]
+fn test_texture_array_index_validation() {
+  let node = TextureArraySampleNode::new();
+  let inputs = [
+    Some("arr".to_string()),
+    Some("uv".to_string()),
+    Some("index".to_string()),
+  ];
+  let outputs = [Some("color".to_string())];
+
+  // Without the define the index is used as is.
+  let mut glsl = String::new();
+  node.generate_with_params(&inputs, &outputs, &mut glsl, &ShaderBuildParams::new());
+  assert_eq!(glsl, "vec4 color = sample_fn_arr_arr(uv, index);\n");
+
+  // Indices outside of the array are replaced with a debug color.
+  let mut params = ShaderBuildParams::new();
+  params.define(TextureArraySampleNode::DEFINE_VALIDATE_INDEX, true);
+  let mut glsl = String::new();
+  node.generate_with_params(&inputs, &outputs, &mut glsl, &params);
+  assert!(glsl.contains("index < size_fn_arr_arr ? sample_fn_arr_arr(uv, index)"));
+  assert!(glsl.contains("vec4(1.0, 0.0, 1.0, 1.0)"));
+}
+
+#[test]
+fn test_passthrough_default_override() {
+  let mut shader = ShaderGraph::new();
+  let (_, vertex_out) = shader.add_vertex_attributes::<SampleVertex>();
+  let color = shader.add_color_output("color", ShaderType::Float4);
+  let input = shader.add_node(
+    InputPassthroughNode::new()
+      .with_name("tint")
+      .add_input(ShaderType::Float4, "vec4(1.0)")
+      .add_input(ShaderType::Float4, "vec4(0.0)"),
+  );
+  let source = shader.add_node(Constant::new(Vector4::new(0.0, 0.0, 0.0, 1.0)));
+  shader.connect(source, Constant::OUTPUT, input, 1).unwrap();
+  shader.connect(input, 1, vertex_out, 0).unwrap();
+  shader.connect(input, 0, color, 0).unwrap();
+
+  // Unconnected inputs fall back to their default.
+  let built = shader.build(&[color], &ShaderBuildParams::new());
+  assert!(built.fs.contains("= vec4(1.0);"));
+
+  // The default can be replaced per build without touching the graph.
+  let mut params = ShaderBuildParams::new();
+  params.override_default("tint", 0, "vec4(0.5, 0.5, 0.5, 1.0)");
+  assert_eq!(
+    params.get_default_override("tint", 0),
+    Some("vec4(0.5, 0.5, 0.5, 1.0)")
+  );
+  assert_eq!(params.get_default_override("tint", 1), None);
+  assert_ne!(params.hash, ShaderBuildParams::new().hash);
+
+  let built = shader.build(&[color], &params);
+  assert!(built.fs.contains("= vec4(0.5, 0.5, 0.5, 1.0);"));
+  assert!(!built.fs.contains("= vec4(1.0);"));
+}
+
+#[test]
+fn test_passthrough_only_declares_defaults() {
+  let node = InputPassthroughNode::new()
+    .with_name("tint")
+    .add_input(ShaderType::Float4, "vec4(1.0)")
+    .add_input(ShaderType::Float4, "vec4(0.0)")
+    .add_input(ShaderType::Float, "0.5");
+  let mut params = ShaderBuildParams::new();
+  params.override_default("tint", 2, "0.25");
+
+  // The first input is connected, the last output isn't used by anyone.
+  let inputs = vec![Some("var_source".to_string()), None, None];
+  let outputs = vec![
+    Some("var_tint_0".to_string()),
+    Some("var_tint_1".to_string()),
+    None,
+  ];
+  let mut glsl = String::new();
+  node.generate_with_params(&inputs, &outputs, &mut glsl, &params);
+  assert_eq!(glsl, "vec4 var_tint_1 = vec4(0.0);\n");
+
+  // Overridden defaults are declared as well.
+  let outputs = vec![None, None, Some("var_tint_2".to_string())];
+  let mut glsl = String::new();
+  node.generate_with_params(&inputs, &outputs, &mut glsl, &params);
+  assert_eq!(glsl, "float var_tint_2 = 0.25;\n");
+}
+
+#[cfg(test)]
+#[uniform]
+struct SampleUniformPadding {
+  strength: f32,
+  direction: Vector3<f32>,
+  range: f32,
+  offset: Vector2<f32>,
+}
+
+#[test]
+fn test_uniform_padding() {
+  let padding = SampleUniformPadding::generate_padding();
+  assert_eq!(
+    padding,
+    vec![
+      UniformPadding {
+        offset: 4,
+        size: 12,
+        field: Some("direction".to_string()),
+      },
+      UniformPadding {
+        offset: 40,
+        size: 8,
+        field: None,
+      },
+    ]
+  );
+
+  let report = SampleUniformPadding::layout_report();
+  let lines = report.lines().map(str::trim).collect::<Vec<_>>();
+  assert_eq!(
+    lines,
+    vec![
+      "0  strength: Float",
+      "4  padding (12 bytes)",
+      "16  direction: Float3",
+      "28  range: Float",
+      "32  offset: Float2",
+      "40  padding (8 bytes)",
+    ]
+  );
+}
+
+#[cfg(test)]
+#[uniform]
+struct SampleUniformDefaults {
+  #[default(Vector4::new(1.0, 1.0, 1.0, 1.0))]
+  base_color: Vector4<f32>,
+  #[default(0.5)]
+  roughness: f32,
+  metallic: f32,
+}
+
+#[test]
+fn test_uniform_defaults() {
+  let uniform = SampleUniformDefaults::default();
+  assert_eq!(uniform.base_color, Vector4::new(1.0, 1.0, 1.0, 1.0));
+  assert_eq!(uniform.roughness, 0.5);
+
+  // Fields without a default value fall back to their type default.
+  assert_eq!(uniform.metallic, 0.0);
+}
+
+#[test]
+fn test_typed_constants() {
+  let generate = |node: Constant| {
+    let mut code = String::new();
+    node.generate(&[], &[Some("c".to_string())], &mut code);
+    (node.get_outputs(), code)
+  };
+
+  let (outputs, code) = generate(Constant::new_scalar(0.5));
+  assert!(matches!(outputs[..], [ShaderType::Float]));
+  assert_eq!(code, "float c = float(0.5000000);\n");
+
+  let (outputs, code) = generate(Constant::new_uint(3));
+  assert!(matches!(outputs[..], [ShaderType::UInt]));
+  assert_eq!(code, "uint c = uint(3u);\n");
+
+  let (outputs, code) = generate(Constant::with_type(ShaderType::UInt2, &[1.0, 2.0]));
+  assert!(matches!(outputs[..], [ShaderType::UInt2]));
+  assert_eq!(code, "uvec2 c = uvec2(1u,2u);\n");
+
+  let identity = Matrix4::<f32>::identity();
+  let identity: &[f32; 16] = identity.as_ref();
+  let (outputs, code) = generate(Constant::with_type(ShaderType::Matrix4, identity));
+  assert!(matches!(outputs[..], [ShaderType::Matrix4]));
+  assert!(code.starts_with("mat4 c = mat4(1.0000000,0.0000000,"));
+}
+
+#[test]
+#[should_panic]
+fn test_constant_component_mismatch() {
+  Constant::with_type(ShaderType::Float3, &[1.0, 2.0]);
+}
+
+#[cfg(test)]
+#[uniform(id = "5b0c3f62-8a52-4f0e-9d3c-2f4a6f0b1c7e")]
+struct SampleUniformFixedId {
+  sample: f32,
+}
+#[cfg(test)]
+#[uniform(stable)]
+struct SampleUniformStableA {
+  sample: f32,
+}
+#[cfg(test)]
+#[uniform(stable)]
+struct SampleUniformStableB {
+  sample: f32,
+  position: Vector3<f32>,
+}
+
+#[test]
+fn test_uniform_ids() {
+  assert_eq!(
+    SampleUniformFixedId::get_id(),
+    Uuid::parse_str("5b0c3f62-8a52-4f0e-9d3c-2f4a6f0b1c7e").unwrap()
+  );
+
+  // Stable ids are derived from name and layout.
+  let expected = Uuid::new_v5(&Uuid::NAMESPACE_OID, b"SampleUniformStableA{sample:f32}");
+  assert_eq!(SampleUniformStableA::get_id(), expected);
+  assert_ne!(
+    SampleUniformStableA::get_id(),
+    SampleUniformStableB::get_id()
+  );
+}
+
+#[test]
+fn test_connect_type_mismatch() {
+  let mut shader = ShaderGraph::new();
+  let (_, vertex_out) = shader.add_vertex_attributes::<SampleVertex>();
+  let color = shader.add_color_output("color", ShaderType::Float4);
+  let source = shader.add_node(Constant::new(Vector3::new(1.0, 0.5, 0.25)));
+
+  let error = shader
+    .connect(source, Constant::OUTPUT, color, 0)
+    .unwrap_err();
+  assert!(matches!(
+    error,
+    GraphConnectError::TypeMismatch {
+      provided: ShaderType::Float3,
+      expected: ShaderType::Float4,
+      ..
+    }
+  ));
+  let message = error.to_string();
+  assert!(message.contains("Constant"), "{}", message);
+  assert!(message.contains("ColorOutputNode"), "{}", message);
+
+  // The failed connection is not recorded.
+  let upgrade = shader.add_node(Vector3Upgrade {});
+  shader
+    .connect(source, Constant::OUTPUT, upgrade, Vector3Upgrade::INPUT)
+    .unwrap();
+  shader
+    .connect(upgrade, Vector3Upgrade::OUTPUT, color, 0)
+    .unwrap();
+  shader
+    .connect(upgrade, Vector3Upgrade::OUTPUT, vertex_out, 0)
+    .unwrap();
+
+  // Color outputs expect the type they have been declared with.
+  let velocity = shader.add_color_output("velocity", ShaderType::Float2);
+  let uv = shader.add_node(Constant::new(Vector2::new(0.0, 1.0)));
+  shader.connect(uv, Constant::OUTPUT, velocity, 0).unwrap();
+}
@@ -0,0 +1,8 @@
+use moonwave_shader::uniform;
+
+#[uniform(id = 4)]
+struct FixedUniform {
+  value: f32,
+}
+
+fn main() {}
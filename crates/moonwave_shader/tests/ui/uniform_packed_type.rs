@@ -0,0 +1,8 @@
+use moonwave_shader::uniform;
+
+#[uniform]
+struct PackedUniform {
+  color: Unorm8x4,
+}
+
+fn main() {}
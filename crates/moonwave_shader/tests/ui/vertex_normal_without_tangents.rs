@@ -0,0 +1,9 @@
+use moonwave_shader::vertex;
+
+#[vertex]
+struct NormalOnlyVertex {
+  position: Vector3<f32>,
+  normal: Vector3<f32>,
+}
+
+fn main() {}
@@ -0,0 +1,5 @@
+#[test]
+fn uniform_ui_test() {
+  let cases = trybuild::TestCases::new();
+  cases.compile_fail("tests/ui/uniform_*.rs");
+}
@@ -0,0 +1,5 @@
+#[test]
+fn vertex_ui_test() {
+  let cases = trybuild::TestCases::new();
+  cases.compile_fail("tests/ui/vertex_*.rs");
+}
@@ -97,8 +97,7 @@ impl RenderMethod {
         }
 
         fn mount(&mut self, size: (f32, f32), position: (f32, f32)) {
-          let mut root = std::cell::RefCell::borrow_mut(self.storage.stmt_0.as_ref().unwrap());
-          root.component.mount(size, position);
+          moonwave_ui::mount_component(self.storage.stmt_0.as_ref().unwrap(), size, position);
         }
       }
     }
@@ -0,0 +1,171 @@
+use std::cell::RefCell;
+
+use generational_arena::Index;
+
+use crate::{
+  build_rounded_rect, mount_component, Allocator, ChildrenProxy, Component, HostedComponentRc,
+  LayoutProps, PointerEvent, PointerEventKind, Text, UpdateList, SHAPE_MANAGER,
+};
+
+/// Space between the label and the border of a button.
+const BUTTON_PADDING: (f32, f32) = (12.0, 6.0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ButtonState {
+  Normal,
+  Hovered,
+  Pressed,
+}
+
+/// Clickable component with a text label on a colored background.
+/// The click callback is invoked when the pointer is released within the button after pressing it.
+pub struct Button {
+  label: String,
+  on_click: Option<Box<dyn Fn()>>,
+  state: ButtonState,
+  border_radius: f32,
+  background_color: (f32, f32, f32, f32),
+  hovered_color: (f32, f32, f32, f32),
+  pressed_color: (f32, f32, f32, f32),
+  layout: LayoutProps,
+  text: Option<HostedComponentRc>,
+  shape: Option<Index>,
+}
+
+impl Button {
+  pub fn new(label: &str) -> Self {
+    Self {
+      label: label.to_string(),
+      on_click: None,
+      state: ButtonState::Normal,
+      border_radius: 4.0,
+      background_color: (0.2, 0.2, 0.25, 1.0),
+      hovered_color: (0.3, 0.3, 0.38, 1.0),
+      pressed_color: (0.12, 0.12, 0.16, 1.0),
+      layout: Default::default(),
+      text: None,
+      shape: None,
+    }
+  }
+
+  pub fn on_click<F: Fn() + 'static>(mut self, on_click: F) -> Self {
+    self.on_click = Some(Box::new(on_click));
+    self
+  }
+
+  pub fn border_radius(mut self, radius: f32) -> Self {
+    self.border_radius = radius;
+    self
+  }
+
+  pub fn background(mut self, color: (f32, f32, f32, f32)) -> Self {
+    self.background_color = color;
+    self
+  }
+
+  pub fn hovered_background(mut self, color: (f32, f32, f32, f32)) -> Self {
+    self.hovered_color = color;
+    self
+  }
+
+  pub fn pressed_background(mut self, color: (f32, f32, f32, f32)) -> Self {
+    self.pressed_color = color;
+    self
+  }
+
+  pub fn get_label(&self) -> &str {
+    &self.label
+  }
+
+  pub fn get_state(&self) -> ButtonState {
+    self.state
+  }
+
+  fn current_color(&self) -> (f32, f32, f32, f32) {
+    match self.state {
+      ButtonState::Normal => self.background_color,
+      ButtonState::Hovered => self.hovered_color,
+      ButtonState::Pressed => self.pressed_color,
+    }
+  }
+
+  fn set_state(&mut self, state: ButtonState) {
+    if self.state == state {
+      return;
+    }
+    self.state = state;
+    if let Some(shape) = self.shape {
+      SHAPE_MANAGER.set_colored_shape_color(shape, self.current_color().into());
+    }
+  }
+}
+
+impl Component for Button {
+  fn get_layout_props(&self) -> &LayoutProps {
+    &self.layout
+  }
+  fn get_layout_props_mut(&mut self) -> &mut LayoutProps {
+    &mut self.layout
+  }
+  fn create(&mut self, alloc: &mut Allocator) -> Option<ChildrenProxy> {
+    self.text = Some(alloc.alloc(Text::new(self.label.as_str())));
+    None
+  }
+  fn update(&mut self, _updates: Box<dyn UpdateList>) {}
+  fn offer_layout(&self, size: (f32, f32)) -> (f32, f32) {
+    if let Some(frame) = self.layout.frame {
+      return frame;
+    }
+    let label = match &self.text {
+      Some(text) => RefCell::borrow(text).component.offer_layout(size),
+      None => (0.0, 0.0),
+    };
+    (
+      label.0 + BUTTON_PADDING.0 * 2.0,
+      label.1 + BUTTON_PADDING.1 * 2.0,
+    )
+  }
+  fn mount(&mut self, size: (f32, f32), position: (f32, f32)) {
+    // Background
+    if let Some(shape) = self.shape.take() {
+      SHAPE_MANAGER.remove_colored_shape(shape);
+    }
+    let radius = self.border_radius;
+    let geometry = build_rounded_rect(size, position, (radius, radius, radius, radius));
+    self.shape =
+      Some(SHAPE_MANAGER.add_mounted_colored_shape(self.current_color().into(), geometry));
+
+    // Centered label
+    let text = self.text.as_ref().unwrap();
+    let label = RefCell::borrow(text).component.offer_layout(size);
+    mount_component(
+      text,
+      label,
+      (
+        position.0 + (size.0 - label.0) / 2.0,
+        position.1 + (size.1 - label.1) / 2.0,
+      ),
+    );
+  }
+  fn on_pointer(&mut self, event: &PointerEvent) -> bool {
+    match event.kind {
+      PointerEventKind::Moved if self.state == ButtonState::Normal => {
+        self.set_state(ButtonState::Hovered)
+      }
+      PointerEventKind::Pressed => self.set_state(ButtonState::Pressed),
+      PointerEventKind::Released => {
+        if self.state == ButtonState::Pressed {
+          if let Some(on_click) = &self.on_click {
+            on_click();
+          }
+        }
+        self.set_state(ButtonState::Hovered);
+      }
+      PointerEventKind::Left => self.set_state(ButtonState::Normal),
+      PointerEventKind::Moved => {}
+      // Scrolling bubbles up, e.g. into a scroll view around the button.
+      PointerEventKind::Scrolled(..) => return false,
+    }
+    true
+  }
+}
@@ -0,0 +1,114 @@
+use std::{cell::RefCell, rc::Rc};
+
+use moonwave_core::KeyboardEvent;
+
+use crate::HostedComponentRc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointerEventKind {
+  Moved,
+  Pressed,
+  Released,
+  /// The pointer moved away from a component that consumed the previous move.
+  Left,
+  /// Mouse wheel or touchpad scrolling in pixels, positive values scroll up and left.
+  Scrolled(f32, f32),
+}
+
+/// Pointer input in pixels starting at the top left of the UI.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerEvent {
+  pub position: (f32, f32),
+  pub kind: PointerEventKind,
+}
+
+impl PointerEvent {
+  pub fn new(kind: PointerEventKind, position: (f32, f32)) -> Self {
+    Self { position, kind }
+  }
+}
+
+/// Walks the mounted component tree and returns all components under the point,
+/// starting at the root and ending with the topmost one. Siblings with a higher z-index or
+/// mounted later are on top.
+pub fn hit_test(root: &HostedComponentRc, point: (f32, f32)) -> Vec<HostedComponentRc> {
+  let hosted = RefCell::borrow(root);
+  let visible = hosted
+    .mounted_clip
+    .map_or(true, |clip| clip.contains(point));
+  let mut path = match hosted.mounted {
+    Some(rect) if visible && rect.contains(point) => vec![root.clone()],
+    _ => return Vec::new(),
+  };
+
+  // Children on top are tested first, siblings with the same z-index in reverse mount order.
+  let mut children = hosted.mounted_children.iter().collect::<Vec<_>>();
+  children.sort_by_key(|child| RefCell::borrow(child).mounted_z_index);
+  for child in children.into_iter().rev() {
+    let child_path = hit_test(child, point);
+    if !child_path.is_empty() {
+      path.extend(child_path);
+      break;
+    }
+  }
+  path
+}
+
+/// Hover and focus state used to route input into a mounted component tree.
+pub struct UIInputState {
+  hovered: RefCell<Option<HostedComponentRc>>,
+  focused: RefCell<Option<HostedComponentRc>>,
+}
+
+impl UIInputState {
+  pub fn new() -> Self {
+    Self {
+      hovered: RefCell::new(None),
+      focused: RefCell::new(None),
+    }
+  }
+
+  /// Dispatches the event to the topmost component under the pointer and bubbles it up until consumed.
+  /// The component consuming a press gains keyboard focus, the one consuming a move is notified
+  /// with `PointerEventKind::Left` once another component consumes a move.
+  pub fn dispatch_pointer(&self, root: &HostedComponentRc, event: PointerEvent) -> bool {
+    let handler = hit_test(root, event.position)
+      .into_iter()
+      .rev()
+      .find(|component| RefCell::borrow_mut(component).component.on_pointer(&event));
+
+    match event.kind {
+      PointerEventKind::Moved => {
+        let previous = self.hovered.replace(handler.clone());
+        if let Some(previous) = previous {
+          let same = matches!(&handler, Some(handler) if Rc::ptr_eq(handler, &previous));
+          if !same {
+            let left = PointerEvent::new(PointerEventKind::Left, event.position);
+            RefCell::borrow_mut(&previous).component.on_pointer(&left);
+          }
+        }
+      }
+      PointerEventKind::Pressed => {
+        *self.focused.borrow_mut() = handler.clone();
+      }
+      _ => {}
+    }
+    handler.is_some()
+  }
+
+  /// Dispatches the event to the focused component.
+  pub fn dispatch_keyboard(&self, event: &KeyboardEvent) -> bool {
+    match &*self.focused.borrow() {
+      Some(focused) => RefCell::borrow_mut(focused).component.on_keyboard(event),
+      None => false,
+    }
+  }
+
+  pub fn get_focused(&self) -> Option<HostedComponentRc> {
+    self.focused.borrow().clone()
+  }
+
+  pub fn get_hovered(&self) -> Option<HostedComponentRc> {
+    self.hovered.borrow().clone()
+  }
+}
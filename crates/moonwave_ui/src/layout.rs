@@ -13,6 +13,8 @@ pub struct LayoutProps {
   pub frame: Option<(f32, f32)>,
   pub spacing: (f32, f32),
   pub alignment: Alignment,
+  /// Draw order relative to the parent, higher values are drawn and hit-tested on top.
+  pub z_index: i32,
 }
 
 impl Default for LayoutProps {
@@ -22,10 +24,66 @@ impl Default for LayoutProps {
       frame: None,
       spacing: (0.0, 0.0),
       alignment: Alignment::Center,
+      z_index: 0,
     }
   }
 }
 
+/// Area a component has been mounted into, in pixels starting at the top left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutRect {
+  pub position: (f32, f32),
+  pub size: (f32, f32),
+}
+
+impl LayoutRect {
+  pub fn new(position: (f32, f32), size: (f32, f32)) -> Self {
+    Self { position, size }
+  }
+
+  /// Overlapping area of both rects, empty if they don't overlap.
+  pub fn intersect(&self, other: &LayoutRect) -> LayoutRect {
+    let min = (
+      self.position.0.max(other.position.0),
+      self.position.1.max(other.position.1),
+    );
+    let max = (
+      (self.position.0 + self.size.0).min(other.position.0 + other.size.0),
+      (self.position.1 + self.size.1).min(other.position.1 + other.size.1),
+    );
+    LayoutRect::new(min, ((max.0 - min.0).max(0.0), (max.1 - min.1).max(0.0)))
+  }
+
+  /// Whether the other rect lies completely within this one.
+  pub fn contains_rect(&self, other: &LayoutRect) -> bool {
+    other.position.0 >= self.position.0
+      && other.position.1 >= self.position.1
+      && other.position.0 + other.size.0 <= self.position.0 + self.size.0
+      && other.position.1 + other.size.1 <= self.position.1 + self.size.1
+  }
+
+  /// Pixels of a target with the given size covered by the rect as `(x, y, width, height)`,
+  /// `None` if nothing is covered.
+  pub fn to_scissor(&self, target: (u32, u32)) -> Option<(u32, u32, u32, u32)> {
+    let clamp = |value: f32, max: u32| (value.max(0.0) as u32).min(max);
+    let x = clamp(self.position.0.floor(), target.0);
+    let y = clamp(self.position.1.floor(), target.1);
+    let right = clamp((self.position.0 + self.size.0).ceil(), target.0);
+    let bottom = clamp((self.position.1 + self.size.1).ceil(), target.1);
+    if right <= x || bottom <= y {
+      return None;
+    }
+    Some((x, y, right - x, bottom - y))
+  }
+
+  pub fn contains(&self, point: (f32, f32)) -> bool {
+    point.0 >= self.position.0
+      && point.1 >= self.position.1
+      && point.0 < self.position.0 + self.size.0
+      && point.1 < self.position.1 + self.size.1
+  }
+}
+
 pub struct DefaultLayouter {
   root: HostedComponentRc,
 }
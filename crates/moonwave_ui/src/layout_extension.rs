@@ -10,6 +10,11 @@ pub trait LayoutExtension: Component + Sized {
     self.get_layout_props_mut().spacing = (spacing, spacing);
     self
   }
+
+  fn z_index(mut self, z_index: i32) -> Self {
+    self.get_layout_props_mut().z_index = z_index;
+    self
+  }
 }
 
 impl<T: Component + Sized> LayoutExtension for T {}
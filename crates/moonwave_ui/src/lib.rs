@@ -2,15 +2,25 @@
 
 use std::{cell::RefCell, rc::Rc};
 
+use moonwave_core::KeyboardEvent;
+
+mod button;
+mod input;
 mod layout;
 mod layout_extension;
 mod render;
+mod scroll;
 mod stacks;
+mod text;
 mod view;
+pub use button::*;
+pub use input::*;
 pub use layout::*;
 pub use layout_extension::*;
 pub use render::*;
+pub use scroll::*;
 pub use stacks::*;
+pub use text::*;
 pub use view::*;
 
 pub use moonwave_ui_macros::*;
@@ -29,7 +39,19 @@ pub trait Component {
   fn update(&mut self, updates: Box<dyn UpdateList>);
 
   /// Mounts and renders the actual component.
+  /// Children have to be mounted through `mount_component` so that they can receive input.
   fn mount(&mut self, size: (f32, f32), position: (f32, f32));
+
+  /// Handles a pointer event within the bounds of the component, returns whether it has been consumed.
+  /// Unconsumed events bubble up to the parent components.
+  fn on_pointer(&mut self, _event: &PointerEvent) -> bool {
+    false
+  }
+
+  /// Handles a keyboard event while the component is focused, returns whether it has been consumed.
+  fn on_keyboard(&mut self, _event: &KeyboardEvent) -> bool {
+    false
+  }
 }
 
 pub trait UpdateList {}
@@ -49,6 +71,10 @@ impl Allocator {
       component: boxed,
       children: Vec::new(),
       children_proxy,
+      mounted: None,
+      mounted_children: Vec::new(),
+      mounted_z_index: 0,
+      mounted_clip: None,
     }))
   }
 }
@@ -59,6 +85,104 @@ pub struct HostedComponent {
   pub component: Box<dyn Component>,
   pub children: Vec<HostedComponentRc>,
   children_proxy: Option<ChildrenProxy>,
+  /// Area of the last mount, `None` until mounted.
+  pub mounted: Option<LayoutRect>,
+  /// Components mounted by this component during its last mount in drawing order.
+  pub mounted_children: Vec<HostedComponentRc>,
+  /// Z-index of the last mount including the ones of all parents.
+  pub mounted_z_index: i32,
+  /// Area the component has been clipped to during its last mount.
+  pub mounted_clip: Option<LayoutRect>,
+}
+
+/// Placement a component inherits from its parents while being mounted.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MountContext {
+  /// Sum of the z-indices of the component and all its parents.
+  pub z_index: i32,
+  /// Area everything outside of is clipped, `None` if nothing is clipped.
+  pub clip: Option<LayoutRect>,
+}
+
+/// Component that is currently being mounted.
+struct MountFrame {
+  context: MountContext,
+  children: Vec<HostedComponentRc>,
+}
+
+thread_local! {
+  /// Children collected for each component that is currently being mounted.
+  static MOUNTING: RefCell<Vec<MountFrame>> = RefCell::new(Vec::new());
+}
+
+/// Context of the component that is currently being mounted.
+/// Shapes registered during `Component::mount` should be drawn with it.
+pub fn mounting_context() -> MountContext {
+  MOUNTING.with(|mounting| {
+    mounting
+      .borrow()
+      .last()
+      .map(|frame| frame.context)
+      .unwrap_or_default()
+  })
+}
+
+/// Z-index of the component that is currently being mounted, the z-indices of its parents are added up.
+pub fn mounting_z_index() -> i32 {
+  mounting_context().z_index
+}
+
+/// Clipping area of the component that is currently being mounted.
+pub fn mounting_clip() -> Option<LayoutRect> {
+  mounting_context().clip
+}
+
+/// Mounts a hosted component and records its area and mounted children for hit-testing.
+pub fn mount_component(component: &HostedComponentRc, size: (f32, f32), position: (f32, f32)) {
+  mount_component_in(mounting_context(), component, size, position);
+}
+
+/// Same as `mount_component` with an explicit parent context,
+/// e.g. to clip children or to mount them again outside of `Component::mount`.
+pub fn mount_component_in(
+  parent: MountContext,
+  component: &HostedComponentRc,
+  size: (f32, f32),
+  position: (f32, f32),
+) {
+  let context = MountContext {
+    z_index: parent.z_index
+      + RefCell::borrow(component)
+        .component
+        .get_layout_props()
+        .z_index,
+    clip: parent.clip,
+  };
+  MOUNTING.with(|mounting| {
+    mounting.borrow_mut().push(MountFrame {
+      context,
+      children: Vec::new(),
+    })
+  });
+  RefCell::borrow_mut(component)
+    .component
+    .mount(size, position);
+  let frame = MOUNTING.with(|mounting| mounting.borrow_mut().pop().unwrap());
+
+  {
+    let mut hosted = RefCell::borrow_mut(component);
+    hosted.mounted = Some(LayoutRect::new(position, size));
+    hosted.mounted_children = frame.children;
+    hosted.mounted_z_index = context.z_index;
+    hosted.mounted_clip = context.clip;
+  }
+
+  // Register with the component that is mounting this one.
+  MOUNTING.with(|mounting| {
+    if let Some(parent) = mounting.borrow_mut().last_mut() {
+      parent.children.push(component.clone());
+    }
+  });
 }
 
 pub struct ChildrenProxy {
@@ -130,15 +254,18 @@ impl Component for AppRoot {
     if proxy.children.len() != 1 {
       panic!("AppRoot component must have exactly one child");
     }
-    let mut child = RefCell::borrow_mut(&proxy.children[0]);
-    let wanted = child.component.offer_layout(self.layout.frame.unwrap());
-    child.component.mount(wanted, (0.0, 0.0));
+    let child = &proxy.children[0];
+    let wanted = RefCell::borrow(child)
+      .component
+      .offer_layout(self.layout.frame.unwrap());
+    mount_component(child, wanted, (0.0, 0.0));
   }
 }
 
 pub struct UIRenderer {
   allocator: Allocator,
   root: HostedComponentRc,
+  input: UIInputState,
 }
 
 impl UIRenderer {
@@ -146,16 +273,35 @@ impl UIRenderer {
     let mut allocator = Allocator::new();
     let root = allocator.alloc(component);
 
-    Self { root, allocator }
+    Self {
+      root,
+      allocator,
+      input: UIInputState::new(),
+    }
   }
 
   pub fn mount(&self) {
     // Layouting phase
-    let mut root = RefCell::borrow_mut(&self.root);
-    let root_layout = root.component.offer_layout((0.0, 0.0));
+    let root_layout = RefCell::borrow(&self.root)
+      .component
+      .offer_layout((0.0, 0.0));
 
     // Mounting phase
-    root.component.mount(root_layout, (0.0, 0.0));
+    mount_component(&self.root, root_layout, (0.0, 0.0));
+  }
+
+  pub fn get_root(&self) -> &HostedComponentRc {
+    &self.root
+  }
+
+  /// Dispatches a pointer event to the topmost component under the pointer, see `UIInputState`.
+  pub fn dispatch_pointer(&self, event: PointerEvent) -> bool {
+    self.input.dispatch_pointer(&self.root, event)
+  }
+
+  /// Dispatches a keyboard event to the focused component.
+  pub fn dispatch_keyboard(&self, event: &KeyboardEvent) -> bool {
+    self.input.dispatch_keyboard(event)
   }
 }
 
@@ -11,12 +11,15 @@ use moonwave_scene::{
 use moonwave_shader::*;
 use parking_lot::Mutex;
 use send_wrapper::SendWrapper;
-use std::sync::{
-  atomic::{AtomicBool, Ordering},
-  Arc,
+use std::{
+  ops::Range,
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc,
+  },
 };
 
-use crate::{Component, UIRenderer};
+use crate::{mounting_context, Component, LayoutRect, PointerEvent, PointerEventKind, UIRenderer};
 
 #[uniform]
 struct TransformUniform {
@@ -38,6 +41,13 @@ struct RenderResources {
   pipeline_colored_shape: ResourceRc<RenderPipeline>,
   ui_texture: Arc<TextureGeneratorHost>,
   active_indices: u16,
+  /// Glyph quads of all labels, rebuilt whenever labels or the screen size change.
+  label_vertices: Option<ResourceRc<Buffer>>,
+  label_draws: Vec<UIDraw>,
+  label_screen_size: Vector2<u32>,
+  text_pipeline: ResourceRc<RenderPipeline>,
+  text_atlas: ResourceRc<BindGroup>,
+  batches: Vec<DrawBatch>,
 }
 
 impl RenderResources {
@@ -86,19 +96,23 @@ impl RenderResources {
       graph.connect(mul, Multiply::OUTPUT, vertex_out, 0).unwrap();
 
       // Build shader
-      Material::new(graph).build(&ShaderBuildParams::new())
+      Material::new(graph)
+        .build(&ShaderBuildParams::new())
+        .unwrap()
     };
 
     // Build pipeline
-    let pipeline_colored_shape = Core::get_instance().create_render_pipeline(
-      RenderPipelineDescriptor::new(
-        shader_colored_shape.layout.clone(),
-        ColoredShapeVertex::generate_buffer(),
-        shader_colored_shape.vertex_shader.clone(),
-        shader_colored_shape.fragment_shader.clone(),
+    let pipeline_colored_shape = Core::get_instance()
+      .create_render_pipeline(
+        RenderPipelineDescriptor::new(
+          shader_colored_shape.layout.clone(),
+          ColoredShapeVertex::generate_buffer(),
+          shader_colored_shape.vertex_shader.clone(),
+          shader_colored_shape.fragment_shader.clone(),
+        )
+        .add_color_output(TextureFormat::Bgra8UnormSrgb),
       )
-      .add_color_output(TextureFormat::Bgra8UnormSrgb),
-    );
+      .unwrap();
 
     // Build and reserve buffers
     let vertex_buffer = StagedBuffer::new(2048, BufferUsage::VERTEX);
@@ -108,6 +122,9 @@ impl RenderResources {
     let ui_texture =
       TextureGeneratorHost::new(TextureSize::FullScreen, TextureFormat::Bgra8UnormSrgb);
 
+    // Labels are drawn with the glyphs of the debug text font.
+    let (text_pipeline, text_atlas) = debug_text_pipeline();
+
     Self {
       transform,
       vs_transform,
@@ -117,13 +134,21 @@ impl RenderResources {
       index_buffer,
       ui_texture,
       active_indices: 0,
+      label_vertices: None,
+      label_draws: Vec::new(),
+      label_screen_size: Vector2::new(0, 0),
+      text_pipeline,
+      text_atlas,
+      batches: Vec::new(),
     }
   }
 }
 
 pub struct UIExtension {
   resources: Mutex<Option<RenderResources>>,
-  _renderer: SendWrapper<UIRenderer>,
+  renderer: SendWrapper<UIRenderer>,
+  /// Last known cursor position, button and wheel input happens at this position.
+  cursor: (f32, f32),
 }
 
 impl UIExtension {
@@ -133,12 +158,47 @@ impl UIExtension {
 
     Self {
       resources: Mutex::new(None),
-      _renderer: SendWrapper::new(renderer),
+      renderer: SendWrapper::new(renderer),
+      cursor: (0.0, 0.0),
     }
   }
+
+  /// Routes pointer input into the mounted components, has to be called from the main thread.
+  pub fn dispatch_pointer(&self, event: PointerEvent) -> bool {
+    self.renderer.dispatch_pointer(event)
+  }
+
+  /// Routes keyboard input to the focused component, has to be called from the main thread.
+  pub fn dispatch_keyboard(&self, event: &KeyboardEvent) -> bool {
+    self.renderer.dispatch_keyboard(event)
+  }
 }
 
 impl Extension for UIExtension {
+  fn on_input(&mut self, input: &WindowInput) -> bool {
+    let kind = match input {
+      WindowInput::CursorMoved(x, y) => {
+        self.cursor = (*x, *y);
+        PointerEventKind::Moved
+      }
+      // Moving outside of every component notifies the hovered one that it has been left.
+      WindowInput::CursorLeft => {
+        self.cursor = (f32::MIN, f32::MIN);
+        PointerEventKind::Moved
+      }
+      WindowInput::MouseButton(MouseButton::Left, ElementState::Pressed) => {
+        PointerEventKind::Pressed
+      }
+      WindowInput::MouseButton(MouseButton::Left, ElementState::Released) => {
+        PointerEventKind::Released
+      }
+      WindowInput::MouseButton(..) => return false,
+      WindowInput::Scrolled(x, y) => PointerEventKind::Scrolled(*x, *y),
+      WindowInput::Keyboard(event) => return self.dispatch_keyboard(event),
+    };
+    self.dispatch_pointer(PointerEvent::new(kind, self.cursor))
+  }
+
   fn before_tick(&mut self) {
     optick::event!("moonwave_ui::UIExtension::before_frame");
 
@@ -150,46 +210,109 @@ impl Extension for UIExtension {
     });
 
     // Update geometry
+    let shapes_changed = SHAPE_MANAGER.dirty.swap(false, Ordering::Relaxed);
     {
       optick::event!("moonwave_ui::UIExtension::update_geometry");
-      let dirty = SHAPE_MANAGER.dirty.load(Ordering::Relaxed);
-      if dirty {
-        // Write to staging buffer
-        let mut vertex_buffer = resources.vertex_buffer.get_mut();
-        vertex_buffer.clear();
-        let mut index_buffer = resources.index_buffer.get_mut();
-        index_buffer.clear();
-
-        // Build colored geometry
-        let mut offset = 0;
-        let shapes = SHAPE_MANAGER.colored_shapes.lock();
-        for (_, shape) in shapes.iter() {
-          // Vertices
-          let vertices = shape.geometry.vertices.iter().map(|v| ColoredShapeVertex {
-            position: Vector3::new(v.x, v.y, 0.0),
-            color: shape.color,
-          });
-          vertex_buffer.extend(vertices);
-
-          // Indices
-          let indices = shape.geometry.indices.iter().map(move |i| *i + offset);
-          offset += shape.geometry.vertices.len() as u16;
-          index_buffer.extend(indices);
+      if shapes_changed {
+        let mut shapes = SHAPE_MANAGER.colored_shapes.lock();
+
+        // Removed shapes leave holes behind, so everything is rebuilt from scratch.
+        if SHAPE_MANAGER.rebuild.swap(false, Ordering::Relaxed) {
+          resources.vertex_buffer.get_mut().clear();
+          resources.index_buffer.get_mut().clear();
+          for (_, shape) in shapes.iter_mut() {
+            shape.vertex_offset = None;
+            shape.index_range = None;
+            shape.dirty = true;
+          }
+        }
+
+        // Only upload shapes that have been changed or added, new ones are appended in draw order.
+        for index in draw_order(&shapes) {
+          let shape = shapes.get_mut(index).unwrap();
+          if !shape.dirty {
+            continue;
+          }
+          let vertices = shape
+            .geometry
+            .vertices
+            .iter()
+            .map(|v| ColoredShapeVertex {
+              position: Vector3::new(v.x, v.y, 0.0),
+              color: shape.color,
+            })
+            .collect::<Vec<_>>();
+
+          match shape.vertex_offset {
+            Some(offset) => resources.vertex_buffer.patch(offset, &vertices),
+            None => {
+              let offset = resources.vertex_buffer.append(&vertices);
+              let indices = shape
+                .geometry
+                .indices
+                .iter()
+                .map(|i| *i + offset as u16)
+                .collect::<Vec<_>>();
+              let first = resources.index_buffer.append(&indices) as u32;
+              shape.index_range = Some(first..first + indices.len() as u32);
+              shape.vertex_offset = Some(offset);
+            }
+          }
+          shape.dirty = false;
         }
 
-        resources.active_indices = index_buffer.len() as u16;
+        resources.active_indices = resources.index_buffer.len() as u16;
       }
     }
 
+    // Glyph quads are in clip space, so they are also rebuilt when the screen size changes.
+    let size = Core::get_instance().get_swap_chain_size();
+    let labels_changed =
+      TEXT_MANAGER.dirty.swap(false, Ordering::Relaxed) || resources.label_screen_size != size;
+    if labels_changed {
+      optick::event!("moonwave_ui::UIExtension::update_labels");
+      let (vertices, draws) = TEXT_MANAGER.build_vertices(size);
+      resources.label_vertices = if vertices.is_empty() {
+        None
+      } else {
+        Some(Core::get_instance().create_inited_buffer(
+          bytemuck::cast_slice(&vertices).to_vec().into_boxed_slice(),
+          BufferUsage::VERTEX,
+          Some("UILabels"),
+        ))
+      };
+      resources.label_draws = draws;
+      resources.label_screen_size = size;
+    }
+
+    // Shapes and labels are drawn together in the order of their z-index.
+    if shapes_changed || labels_changed {
+      let shapes = SHAPE_MANAGER.colored_shapes.lock();
+      let mut draws = draw_order(&shapes)
+        .into_iter()
+        .filter_map(|index| {
+          let shape = &shapes[index];
+          Some(UIDraw {
+            kind: DrawBatchKind::Shapes,
+            z_index: shape.z_index,
+            sequence: shape.sequence,
+            clip: shape.clip,
+            range: shape.index_range.clone()?,
+          })
+        })
+        .collect::<Vec<_>>();
+      draws.extend(resources.label_draws.iter().cloned());
+      resources.batches = build_draw_batches(draws);
+    }
+
     // Update transform uniform
     {
-      let size = Core::get_instance().get_swap_chain_size();
       let mut transform = resources.transform.get_mut();
       transform.view = ortho(0.0, size.x as f32, size.y as f32, 0.0, -100.0, 100.0);
     }
 
     // Build frame graph
-    if resources.active_indices > 0 {
+    if !resources.batches.is_empty() {
       optick::event!("moonwave_ui::UIExtension::build_frame");
 
       let graph = Core::get_instance().get_frame_graph();
@@ -201,6 +324,11 @@ impl Extension for UIExtension {
           ib: resources.index_buffer.get_accessor(),
           transform: resources.transform.as_generic(),
           pipeline: resources.pipeline_colored_shape.clone(),
+          label_vertices: resources.label_vertices.clone(),
+          text_pipeline: resources.text_pipeline.clone(),
+          text_atlas: resources.text_atlas.clone(),
+          batches: resources.batches.clone(),
+          target_size: size,
         },
         "UIColoredShape",
       );
@@ -231,6 +359,11 @@ struct ColoredShapeRenderNode {
   ib: StagedBufferAccessor,
   pipeline: ResourceRc<RenderPipeline>,
   transform: GenericUniform,
+  label_vertices: Option<ResourceRc<Buffer>>,
+  text_pipeline: ResourceRc<RenderPipeline>,
+  text_atlas: ResourceRc<BindGroup>,
+  batches: Vec<DrawBatch>,
+  target_size: Vector2<u32>,
 }
 
 impl ColoredShapeRenderNode {
@@ -247,22 +380,54 @@ impl FrameGraphNode for ColoredShapeRenderNode {
   ) {
     let texture = inputs[Self::INPUT_TEXTURE].as_ref().unwrap();
 
-    let vb = self.vb.get_resources(encoder);
-    let ib = self.ib.get_resources(encoder);
+    // Shape buffers only exist once the first shape has been uploaded.
+    let shapes = if self.indices > 0 {
+      Some((
+        self.vb.get_resources(encoder).clone(),
+        self.ib.get_resources(encoder).clone(),
+      ))
+    } else {
+      None
+    };
     let transform = self.transform.get_resources(encoder);
 
     let mut rp_builder = RenderPassCommandEncoderBuilder::new("UIRenderPassColoredShape");
     rp_builder.add_color_output(
       &texture.get_sampled_texture().view,
-      Vector4::new(0.5, 0.0, 0.0, 0.0),
+      srgb_to_linear(Vector4::new(0.5, 0.0, 0.0, 0.0)),
     );
 
     let mut rp = encoder.create_render_pass_encoder(rp_builder);
-    rp.set_vertex_buffer(vb.clone());
-    rp.set_index_buffer(ib.clone(), IndexFormat::Uint16);
-    rp.set_bind_group(0, transform.bind_group.clone());
-    rp.set_pipeline(self.pipeline.clone());
-    rp.render_indexed(0..self.indices as u32);
+    let target = (self.target_size.x, self.target_size.y);
+    for batch in &self.batches {
+      // Clipped shapes and labels are cut off at the edges of their clip.
+      let scissor = match batch.clip {
+        Some(clip) => clip.to_scissor(target),
+        None => Some((0, 0, target.0, target.1)),
+      };
+      let (x, y, width, height) = match scissor {
+        Some(scissor) => scissor,
+        None => continue,
+      };
+      rp.set_scissor_rect(x, y, width, height);
+
+      match (batch.kind, &shapes, &self.label_vertices) {
+        (DrawBatchKind::Shapes, Some((vb, ib)), _) => {
+          rp.set_pipeline(self.pipeline.clone());
+          rp.set_vertex_buffer(vb.clone());
+          rp.set_index_buffer(ib.clone(), IndexFormat::Uint16);
+          rp.set_bind_group(0, transform.bind_group.clone());
+          rp.render_indexed(batch.range.clone());
+        }
+        (DrawBatchKind::Labels, _, Some(vertices)) => {
+          rp.set_pipeline(self.text_pipeline.clone());
+          rp.set_vertex_buffer(vertices.clone());
+          rp.set_bind_group(0, self.text_atlas.clone());
+          rp.render(batch.range.clone());
+        }
+        _ => {}
+      }
+    }
 
     outputs[Self::OUTPUT_TEXTURE] = Some(texture.clone());
   }
@@ -270,6 +435,8 @@ impl FrameGraphNode for ColoredShapeRenderNode {
 
 pub struct ShapeManager {
   dirty: AtomicBool,
+  rebuild: AtomicBool,
+  sequence: AtomicU64,
   colored_shapes: Mutex<Arena<ColoredShape>>,
 }
 
@@ -277,23 +444,253 @@ pub type ColoredShapeGeometry = VertexBuffers<Point, u16>;
 pub struct ColoredShape {
   color: Vector4<f32>,
   geometry: ColoredShapeGeometry,
+  /// Shapes are drawn by ascending z-index, equal ones in the order they have been added.
+  z_index: i32,
+  sequence: u64,
+  /// Area outside of which the shape is cut off.
+  clip: Option<LayoutRect>,
+  /// Whether the shape has to be uploaded again.
+  dirty: bool,
+  /// Position of the first vertex within the vertex buffer once uploaded.
+  vertex_offset: Option<usize>,
+  /// Indices of the shape within the index buffer once uploaded.
+  index_range: Option<Range<u32>>,
+}
+
+/// Whether a batch draws shapes through the index buffer or labels through their glyph vertices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrawBatchKind {
+  Shapes,
+  Labels,
+}
+
+/// A shape or label within the UI pass, `range` are its indices or glyph vertices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UIDraw {
+  pub kind: DrawBatchKind,
+  pub z_index: i32,
+  pub sequence: u64,
+  pub clip: Option<LayoutRect>,
+  pub range: Range<u32>,
+}
+
+/// Consecutive indices or vertices of the same kind drawn with the same clipping.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawBatch {
+  pub kind: DrawBatchKind,
+  pub clip: Option<LayoutRect>,
+  pub range: Range<u32>,
+}
+
+/// Indices of all shapes sorted by ascending z-index, equal ones in the order they have been added.
+fn draw_order(shapes: &Arena<ColoredShape>) -> Vec<Index> {
+  let mut order = shapes
+    .iter()
+    .map(|(index, shape)| (shape.z_index, shape.sequence, index))
+    .collect::<Vec<_>>();
+  order.sort_unstable_by_key(|(z_index, sequence, _)| (*z_index, *sequence));
+  order.into_iter().map(|(_, _, index)| index).collect()
+}
+
+/// Sorts shapes and labels by ascending z-index, equal ones in the order they have been added,
+/// and merges them into as few batches as possible.
+pub fn build_draw_batches(mut draws: Vec<UIDraw>) -> Vec<DrawBatch> {
+  draws.sort_by_key(|draw| (draw.z_index, draw.sequence));
+
+  let mut batches: Vec<DrawBatch> = Vec::new();
+  for draw in draws {
+    match batches.last_mut() {
+      Some(last)
+        if last.kind == draw.kind
+          && last.clip == draw.clip
+          && last.range.end == draw.range.start =>
+      {
+        last.range.end = draw.range.end;
+      }
+      _ => batches.push(DrawBatch {
+        kind: draw.kind,
+        clip: draw.clip,
+        range: draw.range,
+      }),
+    }
+  }
+  batches
 }
 
 impl ShapeManager {
-  fn new() -> Self {
+  /// Shapes of mounted components are registered with the shared manager of the UI extension,
+  /// separate managers are only useful on their own, e.g. to check the draw order.
+  pub fn new() -> Self {
     ShapeManager {
       colored_shapes: Mutex::new(Arena::new()),
       dirty: AtomicBool::new(false),
+      rebuild: AtomicBool::new(false),
+      sequence: AtomicU64::new(0),
     }
   }
 
+  /// Position within the draw order of shapes and labels sharing the same z-index.
+  fn next_sequence(&self) -> u64 {
+    self.sequence.fetch_add(1, Ordering::Relaxed)
+  }
+
   pub fn add_colored_shape(&self, color: Vector4<f32>, geometry: ColoredShapeGeometry) -> Index {
+    self.add_colored_shape_with_z_index(color, geometry, 0)
+  }
+
+  /// Adds a shape drawn with the z-index and clipping of the component that is currently being mounted.
+  pub fn add_mounted_colored_shape(
+    &self,
+    color: Vector4<f32>,
+    geometry: ColoredShapeGeometry,
+  ) -> Index {
+    let context = mounting_context();
+    let index = self.add_colored_shape_with_z_index(color, geometry, context.z_index);
+    if let Some(shape) = self.colored_shapes.lock().get_mut(index) {
+      shape.clip = context.clip;
+    }
+    index
+  }
+
+  /// Adds a shape drawn above all shapes with a lower z-index.
+  pub fn add_colored_shape_with_z_index(
+    &self,
+    color: Vector4<f32>,
+    geometry: ColoredShapeGeometry,
+    z_index: i32,
+  ) -> Index {
     let mut shapes = self.colored_shapes.lock();
     self.dirty.store(true, Ordering::Relaxed);
-    shapes.insert(ColoredShape { color, geometry })
+
+    // Shapes are appended to the buffers, anything below existing shapes requires a rebuild.
+    if shapes.iter().any(|(_, shape)| shape.z_index > z_index) {
+      self.rebuild.store(true, Ordering::Relaxed);
+    }
+
+    shapes.insert(ColoredShape {
+      color,
+      geometry,
+      z_index,
+      sequence: self.next_sequence(),
+      clip: None,
+      dirty: true,
+      vertex_offset: None,
+      index_range: None,
+    })
+  }
+
+  /// Moves a shape within the draw order, all shapes are uploaded again.
+  pub fn set_colored_shape_z_index(&self, index: Index, z_index: i32) {
+    let mut shapes = self.colored_shapes.lock();
+    if let Some(shape) = shapes.get_mut(index) {
+      if shape.z_index != z_index {
+        shape.z_index = z_index;
+        self.rebuild.store(true, Ordering::Relaxed);
+        self.dirty.store(true, Ordering::Relaxed);
+      }
+    }
+  }
+
+  /// Indices of all shapes in the order they are drawn.
+  pub fn get_draw_order(&self) -> Vec<Index> {
+    draw_order(&self.colored_shapes.lock())
+  }
+
+  /// Changes the color of a shape, only its own vertices are uploaded again.
+  pub fn set_colored_shape_color(&self, index: Index, color: Vector4<f32>) {
+    let mut shapes = self.colored_shapes.lock();
+    if let Some(shape) = shapes.get_mut(index) {
+      shape.color = color;
+      shape.dirty = true;
+      self.dirty.store(true, Ordering::Relaxed);
+    }
+  }
+
+  pub fn remove_colored_shape(&self, index: Index) {
+    let mut shapes = self.colored_shapes.lock();
+    if shapes.remove(index).is_some() {
+      self.rebuild.store(true, Ordering::Relaxed);
+      self.dirty.store(true, Ordering::Relaxed);
+    }
+  }
+}
+
+/// Label of a mounted component, drawn like a shape with the same z-index and clipping.
+struct Label {
+  text: DebugText,
+  z_index: i32,
+  sequence: u64,
+  clip: Option<LayoutRect>,
+}
+
+/// Texts of mounted components, drawn within the UI pass.
+pub struct TextManager {
+  dirty: AtomicBool,
+  labels: Mutex<Arena<Label>>,
+}
+
+impl TextManager {
+  fn new() -> Self {
+    TextManager {
+      dirty: AtomicBool::new(false),
+      labels: Mutex::new(Arena::new()),
+    }
+  }
+
+  /// Adds a label drawn with the z-index and clipping of the component that is currently being mounted.
+  pub fn add_mounted_label(
+    &self,
+    text: &str,
+    position: (f32, f32),
+    color: (f32, f32, f32, f32),
+  ) -> Index {
+    let context = mounting_context();
+    let label = Label {
+      text: DebugText {
+        text: text.to_string(),
+        position: Vector2::new(position.0, position.1),
+        color: color.into(),
+      },
+      z_index: context.z_index,
+      sequence: SHAPE_MANAGER.next_sequence(),
+      clip: context.clip,
+    };
+    self.dirty.store(true, Ordering::Relaxed);
+    self.labels.lock().insert(label)
+  }
+
+  pub fn remove_label(&self, index: Index) {
+    if self.labels.lock().remove(index).is_some() {
+      self.dirty.store(true, Ordering::Relaxed);
+    }
+  }
+
+  /// Glyph quads of all labels for a screen of the given size and the vertex range of each label.
+  fn build_vertices(&self, screen_size: Vector2<u32>) -> (Vec<DebugTextVertex>, Vec<UIDraw>) {
+    let screen_size = screen_size.cast::<f32>().unwrap();
+    let mut vertices = Vec::new();
+    let mut draws = Vec::new();
+    for (_, label) in self.labels.lock().iter() {
+      let start = vertices.len() as u32;
+      vertices.extend(build_debug_text_vertices(
+        std::slice::from_ref(&label.text),
+        screen_size,
+      ));
+      if vertices.len() as u32 > start {
+        draws.push(UIDraw {
+          kind: DrawBatchKind::Labels,
+          z_index: label.z_index,
+          sequence: label.sequence,
+          clip: label.clip,
+          range: start..vertices.len() as u32,
+        });
+      }
+    }
+    (vertices, draws)
   }
 }
 
 lazy_static! {
   pub(crate) static ref SHAPE_MANAGER: ShapeManager = ShapeManager::new();
+  pub(crate) static ref TEXT_MANAGER: TextManager = TextManager::new();
 }
@@ -0,0 +1,125 @@
+use std::cell::RefCell;
+
+use crate::{
+  mount_component_in, mounting_context, Allocator, ChildrenCollectionProxy, ChildrenProxy,
+  Component, HostedComponentRc, LayoutProps, LayoutRect, MountContext, PointerEvent,
+  PointerEventKind, UpdateList,
+};
+
+/// Stacks its children vertically and lets them overflow its frame.
+/// Everything outside of the frame is clipped and the content is scrolled with the mouse wheel.
+pub struct ScrollView {
+  layout: LayoutProps,
+  proxy: Option<HostedComponentRc>,
+  offset: f32,
+  content_height: f32,
+  /// Area of the last mount.
+  viewport: LayoutRect,
+  /// Context the children have been mounted in, used to remount them when scrolling.
+  context: MountContext,
+}
+
+impl ScrollView {
+  pub fn new() -> Self {
+    Self {
+      layout: Default::default(),
+      proxy: None,
+      offset: 0.0,
+      content_height: 0.0,
+      viewport: LayoutRect::new((0.0, 0.0), (0.0, 0.0)),
+      context: Default::default(),
+    }
+  }
+
+  /// Distance in pixels the content has been scrolled down.
+  pub fn get_offset(&self) -> f32 {
+    self.offset
+  }
+
+  /// Height of all children together.
+  pub fn get_content_height(&self) -> f32 {
+    self.content_height
+  }
+
+  fn max_offset(&self) -> f32 {
+    (self.content_height - self.viewport.size.1).max(0.0)
+  }
+
+  /// Measures and mounts all children below each other, shifted by the scroll offset.
+  /// Children are offered unbounded height, those filling all of it get the height of the frame.
+  fn mount_children(&mut self) {
+    let proxy = RefCell::borrow(self.proxy.as_ref().unwrap());
+    let offered = (self.viewport.size.0, f32::INFINITY);
+    let sizes = proxy
+      .children
+      .iter()
+      .map(|child| {
+        let (width, height) = RefCell::borrow(child).component.offer_layout(offered);
+        if height.is_finite() {
+          (width, height)
+        } else {
+          (width, self.viewport.size.1)
+        }
+      })
+      .collect::<Vec<_>>();
+    self.content_height = sizes.iter().map(|size| size.1).sum();
+    self.offset = self.offset.min(self.max_offset());
+
+    let mut current_y = self.viewport.position.1 - self.offset;
+    for (child, size) in proxy.children.iter().zip(sizes) {
+      mount_component_in(
+        self.context,
+        child,
+        size,
+        (self.viewport.position.0, current_y),
+      );
+      current_y += size.1;
+    }
+  }
+}
+
+impl Component for ScrollView {
+  fn get_layout_props(&self) -> &LayoutProps {
+    &self.layout
+  }
+  fn get_layout_props_mut(&mut self) -> &mut LayoutProps {
+    &mut self.layout
+  }
+  fn create(&mut self, alloc: &mut Allocator) -> Option<ChildrenProxy> {
+    let proxy = alloc.alloc(ChildrenCollectionProxy {});
+    self.proxy = Some(proxy.clone());
+    Some(ChildrenProxy { component: proxy })
+  }
+  fn update(&mut self, _updates: Box<dyn UpdateList>) {}
+  fn offer_layout(&self, size: (f32, f32)) -> (f32, f32) {
+    self.layout.frame.unwrap_or(size)
+  }
+  fn mount(&mut self, size: (f32, f32), position: (f32, f32)) {
+    self.viewport = LayoutRect::new(position, size);
+
+    let parent = mounting_context();
+    self.context = MountContext {
+      z_index: parent.z_index,
+      clip: Some(match parent.clip {
+        Some(clip) => clip.intersect(&self.viewport),
+        None => self.viewport,
+      }),
+    };
+
+    self.mount_children();
+  }
+  fn on_pointer(&mut self, event: &PointerEvent) -> bool {
+    let delta = match event.kind {
+      PointerEventKind::Scrolled(_, delta) => delta,
+      _ => return false,
+    };
+
+    let offset = (self.offset - delta).max(0.0).min(self.max_offset());
+    if (offset - self.offset).abs() < f32::EPSILON {
+      return false;
+    }
+    self.offset = offset;
+    self.mount_children();
+    true
+  }
+}
@@ -1,8 +1,8 @@
 use std::cell::RefCell;
 
 use crate::{
-  Allocator, ChildrenCollectionProxy, ChildrenProxy, Component, HostedComponentRc, LayoutProps,
-  UpdateList,
+  mount_component, Allocator, ChildrenCollectionProxy, ChildrenProxy, Component, HostedComponentRc,
+  LayoutProps, UpdateList,
 };
 
 pub struct HStack {
@@ -56,8 +56,8 @@ impl Component for HStack {
     // Mount
     let mut current_x = position.0;
     for (child, size) in proxy.children.iter().zip(spaces) {
-      let mut child = RefCell::borrow_mut(child);
-      child.component.mount(
+      mount_component(
+        child,
         size,
         (
           current_x + self.layout_props.spacing.0,
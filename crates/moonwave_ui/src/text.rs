@@ -0,0 +1,63 @@
+use moonwave_core::{DEBUG_TEXT_GLYPH_HEIGHT, DEBUG_TEXT_GLYPH_WIDTH};
+
+use crate::{Allocator, ChildrenProxy, Component, LayoutProps, UpdateList, TEXT_MANAGER};
+
+/// Single line of text drawn with the monospace debug text font within the UI pass,
+/// ordered and clipped like the shapes of the component mounting it.
+pub struct Text {
+  text: String,
+  color: (f32, f32, f32, f32),
+  layout: LayoutProps,
+  label: Option<generational_arena::Index>,
+}
+
+impl Text {
+  pub fn new<S: Into<String>>(text: S) -> Self {
+    Self {
+      text: text.into(),
+      color: (1.0, 1.0, 1.0, 1.0),
+      layout: Default::default(),
+      label: None,
+    }
+  }
+
+  pub fn color(mut self, color: (f32, f32, f32, f32)) -> Self {
+    self.color = color;
+    self
+  }
+
+  pub fn get_text(&self) -> &str {
+    &self.text
+  }
+
+  /// Size in pixels the text covers.
+  pub fn measure(&self) -> (f32, f32) {
+    let columns = self.text.chars().count() as u32;
+    (
+      (columns * DEBUG_TEXT_GLYPH_WIDTH) as f32,
+      DEBUG_TEXT_GLYPH_HEIGHT as f32,
+    )
+  }
+}
+
+impl Component for Text {
+  fn get_layout_props(&self) -> &LayoutProps {
+    &self.layout
+  }
+  fn get_layout_props_mut(&mut self) -> &mut LayoutProps {
+    &mut self.layout
+  }
+  fn create(&mut self, _alloc: &mut Allocator) -> Option<ChildrenProxy> {
+    None
+  }
+  fn update(&mut self, _updates: Box<dyn UpdateList>) {}
+  fn offer_layout(&self, _size: (f32, f32)) -> (f32, f32) {
+    self.layout.frame.unwrap_or_else(|| self.measure())
+  }
+  fn mount(&mut self, _size: (f32, f32), position: (f32, f32)) {
+    if let Some(label) = self.label.take() {
+      TEXT_MANAGER.remove_label(label);
+    }
+    self.label = Some(TEXT_MANAGER.add_mounted_label(&self.text, position, self.color));
+  }
+}
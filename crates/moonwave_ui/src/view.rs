@@ -9,6 +9,8 @@ use lyon::{
   path::{builder::BorderRadii, traits::PathBuilder},
 };
 
+use generational_arena::Index;
+
 use crate::{
   Allocator, ChildrenCollectionProxy, ChildrenProxy, ColoredShapeGeometry, Component,
   HostedComponentRc, LayoutProps, UpdateList, SHAPE_MANAGER,
@@ -21,6 +23,7 @@ pub struct View {
   opacity: f32,
   proxy: Option<HostedComponentRc>,
   layout: LayoutProps,
+  shape: Option<Index>,
 }
 
 impl View {
@@ -32,6 +35,7 @@ impl View {
       opacity: 1.0,
       layout: Default::default(),
       proxy: None,
+      shape: None,
     }
   }
 
@@ -68,31 +72,45 @@ impl Component for View {
     size
   }
   fn mount(&mut self, size: (f32, f32), position: (f32, f32)) {
-    // Prepare lyon geometry
-    let mut geometry = ColoredShapeGeometry::new();
-    let mut geometry_builder = simple_builder(&mut geometry);
-
-    let mut tesselator = FillTessellator::new();
-    let options = FillOptions::tolerance(0.1);
-    let mut builder = tesselator.builder(&options, &mut geometry_builder);
-
-    // Build geometry
-    builder.add_rounded_rectangle(
-      &Rect::new(
-        Point2D::new(position.0, position.1),
-        Size2D::new(size.0, size.1),
-      ),
-      &BorderRadii {
-        top_left: self.border_radius.0,
-        top_right: self.border_radius.1,
-        bottom_left: self.border_radius.2,
-        bottom_right: self.border_radius.3,
-      },
-      lyon::path::Winding::Negative,
-    );
-    builder.build().unwrap();
+    if let Some(shape) = self.shape.take() {
+      SHAPE_MANAGER.remove_colored_shape(shape);
+    }
+    let geometry = build_rounded_rect(size, position, self.border_radius);
 
     // Register shape with renderer.
-    SHAPE_MANAGER.add_colored_shape(self.background_color.into(), geometry);
+    self.shape =
+      Some(SHAPE_MANAGER.add_mounted_colored_shape(self.background_color.into(), geometry));
   }
 }
+
+/// Tessellates a rectangle with the given corner radii, ordered top left, top right, bottom left, bottom right.
+pub(crate) fn build_rounded_rect(
+  size: (f32, f32),
+  position: (f32, f32),
+  border_radius: (f32, f32, f32, f32),
+) -> ColoredShapeGeometry {
+  // Prepare lyon geometry
+  let mut geometry = ColoredShapeGeometry::new();
+  let mut geometry_builder = simple_builder(&mut geometry);
+
+  let mut tesselator = FillTessellator::new();
+  let options = FillOptions::tolerance(0.1);
+  let mut builder = tesselator.builder(&options, &mut geometry_builder);
+
+  // Build geometry
+  builder.add_rounded_rectangle(
+    &Rect::new(
+      Point2D::new(position.0, position.1),
+      Size2D::new(size.0, size.1),
+    ),
+    &BorderRadii {
+      top_left: border_radius.0,
+      top_right: border_radius.1,
+      bottom_left: border_radius.2,
+      bottom_right: border_radius.3,
+    },
+    lyon::path::Winding::Negative,
+  );
+  builder.build().unwrap();
+  geometry
+}
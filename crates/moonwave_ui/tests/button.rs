@@ -0,0 +1,63 @@
+use std::{cell::Cell, rc::Rc};
+
+use moonwave_core::{DEBUG_TEXT_GLYPH_HEIGHT, DEBUG_TEXT_GLYPH_WIDTH};
+use moonwave_ui::*;
+
+fn counting_button() -> (Button, Rc<Cell<u32>>) {
+  let clicks = Rc::new(Cell::new(0));
+  let counter = clicks.clone();
+  let button = Button::new("Play").on_click(move || counter.set(counter.get() + 1));
+  (button, clicks)
+}
+
+fn pointer(button: &mut Button, kind: PointerEventKind) {
+  assert!(button.on_pointer(&PointerEvent::new(kind, (0.0, 0.0))));
+}
+
+#[test]
+fn button_clicks_on_release_after_press() {
+  let (mut button, clicks) = counting_button();
+
+  pointer(&mut button, PointerEventKind::Moved);
+  assert_eq!(button.get_state(), ButtonState::Hovered);
+  pointer(&mut button, PointerEventKind::Pressed);
+  assert_eq!(button.get_state(), ButtonState::Pressed);
+  pointer(&mut button, PointerEventKind::Released);
+  assert_eq!(button.get_state(), ButtonState::Hovered);
+  assert_eq!(clicks.get(), 1);
+
+  // Releasing without a press does nothing.
+  pointer(&mut button, PointerEventKind::Released);
+  assert_eq!(clicks.get(), 1);
+}
+
+#[test]
+fn button_leaving_cancels_click() {
+  let (mut button, clicks) = counting_button();
+
+  pointer(&mut button, PointerEventKind::Pressed);
+  pointer(&mut button, PointerEventKind::Left);
+  assert_eq!(button.get_state(), ButtonState::Normal);
+  pointer(&mut button, PointerEventKind::Released);
+  assert_eq!(clicks.get(), 0);
+}
+
+#[test]
+fn button_ignores_scrolling() {
+  let (mut button, _) = counting_button();
+  let scrolled = PointerEvent::new(PointerEventKind::Scrolled(0.0, 10.0), (0.0, 0.0));
+  assert!(!button.on_pointer(&scrolled));
+}
+
+#[test]
+fn text_measures_monospace_glyphs() {
+  let text = Text::new("Play");
+  assert_eq!(
+    text.measure(),
+    (
+      (4 * DEBUG_TEXT_GLYPH_WIDTH) as f32,
+      DEBUG_TEXT_GLYPH_HEIGHT as f32
+    )
+  );
+  assert_eq!(text.offer_layout((100.0, 100.0)), text.measure());
+}
@@ -0,0 +1,48 @@
+use std::{cell::Cell, rc::Rc};
+
+use moonwave_common::Vector2;
+use moonwave_core::{Core, CoreConfig, ElementState, MouseButton, WindowInput};
+use moonwave_ui::*;
+
+#[test]
+fn window_input_reaches_registered_extension_test() {
+  if !Core::try_initialize_headless(CoreConfig::new().with_headless_size(Vector2::new(64, 64))) {
+    return;
+  }
+  let core = Core::get_instance();
+
+  let clicks = Rc::new(Cell::new(0));
+  let counter = clicks.clone();
+  let button = Button::new("Play").on_click(move || counter.set(counter.get() + 1));
+  core.add_extension(UIExtension::new(button));
+
+  // Clicking the button through the same path window events take.
+  assert!(core.dispatch_input(&WindowInput::CursorMoved(2.0, 2.0)));
+  assert!(core.dispatch_input(&WindowInput::MouseButton(
+    MouseButton::Left,
+    ElementState::Pressed
+  )));
+  assert!(core.dispatch_input(&WindowInput::MouseButton(
+    MouseButton::Left,
+    ElementState::Released
+  )));
+  assert_eq!(clicks.get(), 1);
+
+  // Other buttons are left to the application.
+  assert!(!core.dispatch_input(&WindowInput::MouseButton(
+    MouseButton::Right,
+    ElementState::Pressed
+  )));
+
+  // Nothing is hit outside of the button, and the button is no longer hovered.
+  assert!(!core.dispatch_input(&WindowInput::CursorLeft));
+  assert!(!core.dispatch_input(&WindowInput::MouseButton(
+    MouseButton::Left,
+    ElementState::Pressed
+  )));
+  assert!(!core.dispatch_input(&WindowInput::MouseButton(
+    MouseButton::Left,
+    ElementState::Released
+  )));
+  assert_eq!(clicks.get(), 1);
+}
@@ -0,0 +1,162 @@
+use std::{cell::RefCell, rc::Rc};
+
+use moonwave_ui::*;
+
+type EventLog = Rc<RefCell<Vec<(&'static str, PointerEventKind)>>>;
+
+struct Leaf {
+  name: &'static str,
+  consume: bool,
+  log: EventLog,
+  layout: LayoutProps,
+}
+
+impl Component for Leaf {
+  fn get_layout_props(&self) -> &LayoutProps {
+    &self.layout
+  }
+  fn get_layout_props_mut(&mut self) -> &mut LayoutProps {
+    &mut self.layout
+  }
+  fn offer_layout(&self, size: (f32, f32)) -> (f32, f32) {
+    size
+  }
+  fn create(&mut self, _alloc: &mut Allocator) -> Option<ChildrenProxy> {
+    None
+  }
+  fn update(&mut self, _updates: Box<dyn UpdateList>) {}
+  fn mount(&mut self, _size: (f32, f32), _position: (f32, f32)) {}
+  fn on_pointer(&mut self, event: &PointerEvent) -> bool {
+    if self.consume {
+      self.log.borrow_mut().push((self.name, event.kind));
+    }
+    self.consume
+  }
+}
+
+struct Root {
+  log: EventLog,
+  /// Moves the first leaf on top of the second one.
+  raise_first: bool,
+  stack: Option<HostedComponentRc>,
+  layout: LayoutProps,
+}
+
+impl Component for Root {
+  fn get_layout_props(&self) -> &LayoutProps {
+    &self.layout
+  }
+  fn get_layout_props_mut(&mut self) -> &mut LayoutProps {
+    &mut self.layout
+  }
+  fn offer_layout(&self, _size: (f32, f32)) -> (f32, f32) {
+    (200.0, 100.0)
+  }
+  fn create(&mut self, alloc: &mut Allocator) -> Option<ChildrenProxy> {
+    let stack = alloc.alloc(HStack::new());
+    for (name, consume) in [("a", false), ("b", true)].iter() {
+      let mut leaf = Leaf {
+        name: *name,
+        consume: *consume,
+        log: self.log.clone(),
+        layout: Default::default(),
+      };
+      if *name == "a" && self.raise_first {
+        leaf = leaf.z_index(2);
+      }
+      let leaf = alloc.alloc(leaf);
+      RefCell::borrow_mut(&stack).add_child(leaf);
+    }
+    self.stack = Some(stack);
+    None
+  }
+  fn update(&mut self, _updates: Box<dyn UpdateList>) {}
+  fn mount(&mut self, size: (f32, f32), position: (f32, f32)) {
+    mount_component(self.stack.as_ref().unwrap(), size, position);
+  }
+  fn on_pointer(&mut self, event: &PointerEvent) -> bool {
+    self.log.borrow_mut().push(("root", event.kind));
+    true
+  }
+}
+
+fn mounted_renderer() -> (UIRenderer, EventLog) {
+  mounted_renderer_with(false)
+}
+
+fn mounted_renderer_with(raise_first: bool) -> (UIRenderer, EventLog) {
+  let log = EventLog::default();
+  let renderer = UIRenderer::new(Root {
+    log: log.clone(),
+    raise_first,
+    stack: None,
+    layout: Default::default(),
+  });
+  renderer.mount();
+  (renderer, log)
+}
+
+#[test]
+fn hit_test_finds_topmost_component() {
+  let (renderer, _) = mounted_renderer();
+
+  let path = hit_test(renderer.get_root(), (150.0, 50.0));
+  assert_eq!(path.len(), 3);
+  assert_eq!(
+    RefCell::borrow(&path[2]).mounted,
+    Some(LayoutRect::new((100.0, 0.0), (100.0, 100.0)))
+  );
+
+  assert!(hit_test(renderer.get_root(), (250.0, 50.0)).is_empty());
+}
+
+#[test]
+fn pointer_events_bubble_until_consumed() {
+  let (renderer, log) = mounted_renderer();
+
+  assert!(renderer.dispatch_pointer(PointerEvent::new(PointerEventKind::Pressed, (150.0, 50.0))));
+  assert!(renderer.dispatch_pointer(PointerEvent::new(PointerEventKind::Pressed, (50.0, 50.0))));
+  assert!(!renderer.dispatch_pointer(PointerEvent::new(PointerEventKind::Pressed, (250.0, 50.0))));
+
+  assert_eq!(
+    *log.borrow(),
+    vec![
+      ("b", PointerEventKind::Pressed),
+      ("root", PointerEventKind::Pressed)
+    ]
+  );
+}
+
+#[test]
+fn leaving_a_component_is_reported() {
+  let (renderer, log) = mounted_renderer();
+
+  renderer.dispatch_pointer(PointerEvent::new(PointerEventKind::Moved, (150.0, 50.0)));
+  renderer.dispatch_pointer(PointerEvent::new(PointerEventKind::Moved, (160.0, 50.0)));
+  renderer.dispatch_pointer(PointerEvent::new(PointerEventKind::Moved, (50.0, 50.0)));
+
+  assert_eq!(
+    *log.borrow(),
+    vec![
+      ("b", PointerEventKind::Moved),
+      ("b", PointerEventKind::Moved),
+      ("root", PointerEventKind::Moved),
+      ("b", PointerEventKind::Left),
+    ]
+  );
+}
+
+#[test]
+fn z_index_is_recorded_on_mount() {
+  let (renderer, _) = mounted_renderer_with(true);
+
+  let root = RefCell::borrow(renderer.get_root());
+  let stack = RefCell::borrow(&root.mounted_children[0]);
+  let z_indices = stack
+    .mounted_children
+    .iter()
+    .map(|child| RefCell::borrow(child).mounted_z_index)
+    .collect::<Vec<_>>();
+  assert_eq!(z_indices, vec![2, 0]);
+  assert_eq!(mounting_z_index(), 0);
+}
@@ -0,0 +1,163 @@
+use std::cell::RefCell;
+
+use moonwave_ui::*;
+
+struct Row {
+  layout: LayoutProps,
+  height: f32,
+}
+
+impl Component for Row {
+  fn get_layout_props(&self) -> &LayoutProps {
+    &self.layout
+  }
+  fn get_layout_props_mut(&mut self) -> &mut LayoutProps {
+    &mut self.layout
+  }
+  fn offer_layout(&self, size: (f32, f32)) -> (f32, f32) {
+    (size.0, self.height.min(size.1))
+  }
+  fn create(&mut self, _alloc: &mut Allocator) -> Option<ChildrenProxy> {
+    None
+  }
+  fn update(&mut self, _updates: Box<dyn UpdateList>) {}
+  fn mount(&mut self, _size: (f32, f32), _position: (f32, f32)) {}
+}
+
+struct Root {
+  scroll: Option<HostedComponentRc>,
+  layout: LayoutProps,
+  rows: Vec<f32>,
+}
+
+impl Component for Root {
+  fn get_layout_props(&self) -> &LayoutProps {
+    &self.layout
+  }
+  fn get_layout_props_mut(&mut self) -> &mut LayoutProps {
+    &mut self.layout
+  }
+  fn offer_layout(&self, _size: (f32, f32)) -> (f32, f32) {
+    (200.0, 200.0)
+  }
+  fn create(&mut self, alloc: &mut Allocator) -> Option<ChildrenProxy> {
+    let scroll = alloc.alloc(ScrollView::new().frame((100.0, 50.0)));
+    for height in self.rows.iter() {
+      let row = alloc.alloc(Row {
+        layout: Default::default(),
+        height: *height,
+      });
+      RefCell::borrow_mut(&scroll).add_child(row);
+    }
+    self.scroll = Some(scroll);
+    None
+  }
+  fn update(&mut self, _updates: Box<dyn UpdateList>) {}
+  fn mount(&mut self, size: (f32, f32), position: (f32, f32)) {
+    let scroll = self.scroll.as_ref().unwrap();
+    let wanted = RefCell::borrow(scroll).component.offer_layout(size);
+    mount_component(scroll, wanted, position);
+  }
+}
+
+fn mounted_renderer() -> UIRenderer {
+  mounted_renderer_with(vec![40.0, 40.0, 40.0])
+}
+
+fn mounted_renderer_with(rows: Vec<f32>) -> UIRenderer {
+  let renderer = UIRenderer::new(Root {
+    scroll: None,
+    layout: Default::default(),
+    rows,
+  });
+  renderer.mount();
+  renderer
+}
+
+fn row_positions(renderer: &UIRenderer) -> Vec<f32> {
+  let root = RefCell::borrow(renderer.get_root());
+  let scroll = RefCell::borrow(&root.mounted_children[0]);
+  scroll
+    .mounted_children
+    .iter()
+    .map(|row| RefCell::borrow(row).mounted.unwrap().position.1)
+    .collect()
+}
+
+#[test]
+fn layout_rect_intersection_and_scissor() {
+  let a = LayoutRect::new((0.0, 0.0), (100.0, 50.0));
+  let b = LayoutRect::new((50.0, 25.0), (100.0, 100.0));
+  assert_eq!(a.intersect(&b), LayoutRect::new((50.0, 25.0), (50.0, 25.0)));
+  assert_eq!(
+    a.intersect(&LayoutRect::new((200.0, 0.0), (10.0, 10.0)))
+      .size,
+    (0.0, 0.0)
+  );
+  assert!(b.contains_rect(&LayoutRect::new((60.0, 30.0), (10.0, 10.0))));
+  assert!(!a.contains_rect(&b));
+
+  assert_eq!(b.to_scissor((120, 80)), Some((50, 25, 70, 55)));
+  assert_eq!(
+    LayoutRect::new((-10.5, 0.0), (20.0, 10.0)).to_scissor((120, 80)),
+    Some((0, 0, 10, 10))
+  );
+  assert_eq!(b.to_scissor((40, 20)), None);
+}
+
+#[test]
+fn scroll_view_reports_its_frame_and_clips_children() {
+  let renderer = mounted_renderer();
+
+  let root = RefCell::borrow(renderer.get_root());
+  let scroll = RefCell::borrow(&root.mounted_children[0]);
+  let viewport = LayoutRect::new((0.0, 0.0), (100.0, 50.0));
+  assert_eq!(scroll.mounted, Some(viewport));
+  assert_eq!(scroll.mounted_children.len(), 3);
+  for row in scroll.mounted_children.iter() {
+    assert_eq!(RefCell::borrow(row).mounted_clip, Some(viewport));
+  }
+  drop(scroll);
+  drop(root);
+
+  // The second row overflows the frame and can only be hit within it.
+  assert_eq!(hit_test(renderer.get_root(), (50.0, 45.0)).len(), 3);
+  assert_eq!(hit_test(renderer.get_root(), (50.0, 60.0)).len(), 1);
+  assert_eq!(row_positions(&renderer), vec![0.0, 40.0, 80.0]);
+}
+
+#[test]
+fn scrolling_moves_children_within_bounds() {
+  let renderer = mounted_renderer();
+  let scroll = |delta: f32| {
+    renderer.dispatch_pointer(PointerEvent::new(
+      PointerEventKind::Scrolled(0.0, delta),
+      (50.0, 25.0),
+    ))
+  };
+
+  assert!(!scroll(10.0));
+  assert!(scroll(-30.0));
+  assert_eq!(row_positions(&renderer), vec![-30.0, 10.0, 50.0]);
+
+  // Content is 120 pixels high, so it can't be scrolled further than 70 pixels.
+  assert!(scroll(-100.0));
+  assert_eq!(row_positions(&renderer), vec![-70.0, -30.0, 10.0]);
+  assert!(!scroll(-10.0));
+}
+
+#[test]
+fn children_taller_than_the_frame_keep_their_height() {
+  // A row filling all offered height gets the height of the frame instead.
+  let renderer = mounted_renderer_with(vec![80.0, f32::INFINITY, 40.0]);
+  assert_eq!(row_positions(&renderer), vec![0.0, 80.0, 130.0]);
+
+  let root = RefCell::borrow(renderer.get_root());
+  let scroll = RefCell::borrow(&root.mounted_children[0]);
+  let heights = scroll
+    .mounted_children
+    .iter()
+    .map(|row| RefCell::borrow(row).mounted.unwrap().size.1)
+    .collect::<Vec<_>>();
+  assert_eq!(heights, vec![80.0, 50.0, 40.0]);
+}
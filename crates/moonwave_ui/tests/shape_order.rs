@@ -0,0 +1,85 @@
+use moonwave_common::Vector4;
+use moonwave_ui::*;
+
+fn add_shape(manager: &ShapeManager, z_index: i32) -> generational_arena::Index {
+  manager.add_colored_shape_with_z_index(
+    Vector4::new(1.0, 1.0, 1.0, 1.0),
+    ColoredShapeGeometry::new(),
+    z_index,
+  )
+}
+
+#[test]
+fn shapes_are_drawn_by_z_index_test() {
+  let manager = ShapeManager::new();
+  let overlay = add_shape(&manager, 2);
+  let first = add_shape(&manager, 0);
+  let below = add_shape(&manager, -1);
+  let second = add_shape(&manager, 0);
+
+  // Equal z-indices keep the order in which they have been added.
+  assert_eq!(
+    manager.get_draw_order(),
+    vec![below, first, second, overlay]
+  );
+
+  // Raising a shape moves it behind all shapes up to its new z-index.
+  manager.set_colored_shape_z_index(first, 2);
+  assert_eq!(
+    manager.get_draw_order(),
+    vec![below, second, overlay, first]
+  );
+
+  manager.set_colored_shape_z_index(overlay, -2);
+  assert_eq!(
+    manager.get_draw_order(),
+    vec![overlay, below, second, first]
+  );
+
+  manager.remove_colored_shape(below);
+  assert_eq!(manager.get_draw_order(), vec![overlay, second, first]);
+}
+
+fn draw(kind: DrawBatchKind, z_index: i32, sequence: u64, range: std::ops::Range<u32>) -> UIDraw {
+  UIDraw {
+    kind,
+    z_index,
+    sequence,
+    clip: None,
+    range,
+  }
+}
+
+#[test]
+fn labels_are_drawn_between_shapes_test() {
+  // A button background with its label, followed by a popup above it with its own label.
+  let draws = vec![
+    draw(DrawBatchKind::Shapes, 0, 0, 0..6),
+    draw(DrawBatchKind::Shapes, 1, 2, 6..12),
+    draw(DrawBatchKind::Labels, 1, 3, 24..48),
+    draw(DrawBatchKind::Labels, 0, 1, 0..24),
+  ];
+
+  let batches = build_draw_batches(draws);
+  let order = batches
+    .iter()
+    .map(|batch| (batch.kind, batch.range.clone()))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    order,
+    vec![
+      (DrawBatchKind::Shapes, 0..6),
+      (DrawBatchKind::Labels, 0..24),
+      (DrawBatchKind::Shapes, 6..12),
+      (DrawBatchKind::Labels, 24..48),
+    ]
+  );
+
+  // Consecutive draws of the same kind share a batch.
+  let merged = build_draw_batches(vec![
+    draw(DrawBatchKind::Labels, 0, 0, 0..24),
+    draw(DrawBatchKind::Labels, 0, 1, 24..30),
+  ]);
+  assert_eq!(merged.len(), 1);
+  assert_eq!(merged[0].range, 0..30);
+}
@@ -1,3 +1,52 @@
 pub fn invert_option_result<T, E>(opt: Option<Result<T, E>>) -> Result<Option<T>, E> {
   opt.map_or(Ok(None), |v| v.map(Some))
 }
+
+/// Removes duplicates while keeping the relative order of the remaining items.
+/// The last occurrence of each item is kept, so items stay behind everything that was pushed before them.
+pub fn dedup_preserving_order<T: PartialEq + Clone>(list: &[T]) -> Vec<T> {
+  let mut deduped: Vec<T> = Vec::with_capacity(list.len());
+  for item in list.iter().rev() {
+    if deduped.contains(item) {
+      continue;
+    }
+    deduped.push(item.clone());
+  }
+  deduped.reverse();
+  deduped
+}
+
+/// Groups consecutive items that share the same key, equal keys that are not adjacent form separate groups.
+pub fn group_adjacent_by<T, K, F>(
+  items: impl IntoIterator<Item = T>,
+  mut key: F,
+) -> Vec<(K, Vec<T>)>
+where
+  K: PartialEq,
+  F: FnMut(&T) -> K,
+{
+  let mut groups: Vec<(K, Vec<T>)> = Vec::new();
+  for item in items {
+    let item_key = key(&item);
+    match groups.last_mut() {
+      Some((last_key, group)) if *last_key == item_key => group.push(item),
+      _ => groups.push((item_key, vec![item])),
+    }
+  }
+  groups
+}
+
+/// Like `Vec::retain` but the predicate may modify the items it keeps.
+pub fn retain_mut<T, F>(vec: &mut Vec<T>, mut f: F)
+where
+  F: FnMut(&mut T) -> bool,
+{
+  let mut kept = 0;
+  for index in 0..vec.len() {
+    if f(&mut vec[index]) {
+      vec.swap(kept, index);
+      kept += 1;
+    }
+  }
+  vec.truncate(kept);
+}
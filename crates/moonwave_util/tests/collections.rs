@@ -0,0 +1,29 @@
+use moonwave_util::{dedup_preserving_order, group_adjacent_by, retain_mut};
+
+#[test]
+fn dedup_preserving_order_keeps_last_occurrence_test() {
+  assert_eq!(
+    dedup_preserving_order(&[1, 2, 3, 2, 4, 1]),
+    vec![3, 2, 4, 1]
+  );
+  assert_eq!(dedup_preserving_order::<u32>(&[]), Vec::<u32>::new());
+}
+
+#[test]
+fn group_adjacent_by_test() {
+  let groups = group_adjacent_by(vec![1, 3, 2, 4, 5, 6], |v| v % 2);
+  assert_eq!(
+    groups,
+    vec![(1, vec![1, 3]), (0, vec![2, 4]), (1, vec![5]), (0, vec![6])]
+  );
+}
+
+#[test]
+fn retain_mut_test() {
+  let mut values = vec![1, 2, 3, 4, 5];
+  retain_mut(&mut values, |v| {
+    *v *= 10;
+    *v != 20 && *v != 40
+  });
+  assert_eq!(values, vec![10, 30, 50]);
+}